@@ -1,15 +1,22 @@
 use axum::extract::FromRef;
 use axum_extra::extract::cookie::Key;
 use common::cratesio_prefetch_msg::CratesioPrefetchMsg;
+use common::prewarm::PrewarmJobs;
+use common::ttl_cache::TtlCache;
 use db::DbProvider;
 use flume::Sender;
+use jsonwebtoken::jwk::JwkSet;
+use rate_limit::RateLimiter;
 use settings::Settings;
 use std::sync::Arc;
+use std::time::Duration;
 use storage::{
     cached_crate_storage::DynStorage, cratesio_crate_storage::CratesIoCrateStorage,
     fs_storage::FSStorage, kellnr_crate_storage::KellnrCrateStorage,
 };
 
+pub mod rate_limit;
+
 pub type AppState = axum::extract::State<AppStateData>;
 
 // Substates
@@ -19,6 +26,39 @@ pub type CrateStorageState = axum::extract::State<Arc<KellnrCrateStorage>>;
 pub type CrateIoStorageState = axum::extract::State<Arc<CratesIoCrateStorage>>;
 pub type SigningKeyState = axum::extract::State<Key>;
 pub type CratesIoPrefetchSenderState = axum::extract::State<Sender<CratesioPrefetchMsg>>;
+pub type RateLimiterState = axum::extract::State<Arc<RateLimiter>>;
+
+/// Key sets of the trusted publishing issuers with the URL they were loaded from, by issuer.
+pub type JwksCache = TtlCache<String, (String, JwkSet)>;
+
+/// Key sets are loaded again after a while, such that rotated keys are picked up.
+const JWKS_CACHE_TTL: Duration = Duration::from_mins(10);
+const MAX_CACHED_JWKS: usize = 100;
+
+pub fn jwks_cache() -> JwksCache {
+    TtlCache::new(JWKS_CACHE_TTL, MAX_CACHED_JWKS)
+}
+
+/// Users authenticated by the header of a trusted proxy with the groups they
+/// were synchronized with, by name.
+pub type SyncedUsers = TtlCache<String, String>;
+
+/// Users are synchronized at most once per interval, unless their groups change.
+const HEADER_AUTH_SYNC_INTERVAL: Duration = Duration::from_mins(1);
+const MAX_SYNCED_USERS: usize = 10_000;
+
+pub fn synced_users() -> SyncedUsers {
+    TtlCache::new(HEADER_AUTH_SYNC_INTERVAL, MAX_SYNCED_USERS)
+}
+
+/// Number of finished and running pre-warm jobs whose progress is kept.
+const MAX_PREWARM_JOBS: usize = 100;
+/// Number of crate files downloaded by all pre-warm jobs at once.
+const MAX_PREWARM_DOWNLOADS: usize = 8;
+
+pub fn prewarm_jobs() -> PrewarmJobs {
+    PrewarmJobs::new(MAX_PREWARM_JOBS, MAX_PREWARM_DOWNLOADS)
+}
 
 #[derive(Clone, FromRef)]
 pub struct AppStateData {
@@ -29,6 +69,10 @@ pub struct AppStateData {
     pub crate_storage: Arc<KellnrCrateStorage>,
     pub cratesio_storage: Arc<CratesIoCrateStorage>,
     pub cratesio_prefetch_sender: Sender<CratesioPrefetchMsg>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub jwks_cache: Arc<JwksCache>,
+    pub synced_users: Arc<SyncedUsers>,
+    pub prewarm_jobs: Arc<PrewarmJobs>,
 }
 
 pub fn test_state() -> AppStateData {
@@ -46,6 +90,10 @@ pub fn test_state() -> AppStateData {
         ),
     );
     let (cratesio_prefetch_sender, _) = flume::unbounded();
+    let rate_limiter = Arc::new(RateLimiter::new(settings.rate_limit.clone()));
+    let jwks_cache = Arc::new(jwks_cache());
+    let synced_users = Arc::new(synced_users());
+    let prewarm_jobs = Arc::new(prewarm_jobs());
     AppStateData {
         db,
         signing_key,
@@ -53,5 +101,9 @@ pub fn test_state() -> AppStateData {
         crate_storage,
         cratesio_storage,
         cratesio_prefetch_sender,
+        rate_limiter,
+        jwks_cache,
+        synced_users,
+        prewarm_jobs,
     }
 }
@@ -0,0 +1,559 @@
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Duration, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use settings::RateLimit;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tracing::warn;
+
+/// Maximum number of IP addresses and accounts with failed logins, such that
+/// failed logins from many addresses cannot exhaust the memory.
+const MAX_TRACKED_FAILURES: usize = 100_000;
+
+/// Maximum number of tokens with a quota window.
+const MAX_TRACKED_QUOTAS: usize = 100_000;
+
+/// Maximum wait of an account after failed logins from any address.
+const MAX_ACCOUNT_BACKOFF: Duration = Duration::minutes(1);
+
+/// Failed logins are counted per client IP address and per account and IP address,
+/// such that failed logins from other addresses cannot lock out the owner of an account.
+/// IPv6 addresses are grouped by their /64 network, which usually belongs to one client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockoutKind {
+    Ip,
+    Account,
+    /// Failed logins of an account from all addresses. They only lead to a short backoff,
+    /// such that guessing from many addresses is slowed down without locking out the owner.
+    User,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Lockout {
+    pub kind: LockoutKind,
+    pub key: String,
+    pub failed_logins: u32,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quota {
+    Publish,
+    Download,
+}
+
+/// A request was rejected by the rate limiter and can be retried after the given duration.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Throttled {
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Too many requests, retry after {}s",
+            self.retry_after_secs()
+        )
+    }
+}
+
+impl Throttled {
+    fn retry_after_secs(&self) -> i64 {
+        // Round up, such that a client retrying after the given seconds is not rejected again
+        (self.retry_after.num_milliseconds() + 999)
+            .div_euclid(1000)
+            .max(1)
+    }
+}
+
+impl IntoResponse for Throttled {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, self.retry_after_secs().to_string())],
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Failures {
+    count: u32,
+    last: DateTime<Utc>,
+    blocked_until: Option<DateTime<Utc>>,
+}
+
+/// Requests of a token in the current one minute window
+#[derive(Debug, Clone)]
+struct Window {
+    start: DateTime<Utc>,
+    requests: u32,
+}
+
+/// In-memory brute-force protection for logins and quotas for cargo tokens.
+pub struct RateLimiter {
+    settings: RateLimit,
+    failures: Mutex<HashMap<(LockoutKind, String), Failures>>,
+    quotas: Mutex<HashMap<(Quota, String), Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimit) -> Self {
+        Self {
+            settings,
+            failures: Mutex::new(HashMap::new()),
+            quotas: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rejects a login from an IP address or for an account, which has
+    /// to wait after failed logins or is locked out.
+    pub fn check_login(&self, ip: Option<IpAddr>, account: Option<&str>) -> Result<(), Throttled> {
+        if !self.settings.enabled {
+            return Ok(());
+        }
+        let now = Utc::now();
+        let failures = self.failures();
+        let retry_after = subjects(ip, account)
+            .filter_map(|subject| failures.get(&subject))
+            .filter_map(|f| f.blocked_until)
+            .filter(|until| *until > now)
+            .max()
+            .map(|until| until - now);
+
+        match retry_after {
+            Some(retry_after) => Err(Throttled { retry_after }),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a failed login. After `max_failed_logins` failures, the next login has to wait
+    /// exponentially longer, after `lockout_threshold` failures the subject is locked out.
+    /// An account is only locked out for the IP address, from all addresses the wait is
+    /// limited to `MAX_ACCOUNT_BACKOFF`.
+    pub fn login_failed(&self, ip: Option<IpAddr>, account: Option<&str>) {
+        if !self.settings.enabled {
+            return;
+        }
+        let now = Utc::now();
+        let forget_after = self.lockout_duration();
+        let mut failures = self.failures();
+        failures.retain(|_, f| now - f.last < forget_after);
+
+        for subject in subjects(ip, account) {
+            let kind = subject.0;
+            if failures.len() >= MAX_TRACKED_FAILURES
+                && !failures.contains_key(&subject)
+                && let Some(oldest) = failures
+                    .iter()
+                    .min_by_key(|(_, f)| f.last)
+                    .map(|(s, _)| s.clone())
+            {
+                failures.remove(&oldest);
+            }
+            let f = failures.entry(subject).or_insert(Failures {
+                count: 0,
+                last: now,
+                blocked_until: None,
+            });
+            f.count += 1;
+            f.last = now;
+            f.blocked_until = self.backoff(kind, f.count).map(|backoff| now + backoff);
+        }
+    }
+
+    /// Resets the failed logins of an account from the IP address. Failures of the IP address
+    /// are kept, such that logins to an own account cannot be used to guess other passwords.
+    /// Failures of the account from all addresses are kept as well, such that a login of the
+    /// owner does not allow further guesses from other addresses.
+    pub fn login_succeeded(&self, ip: Option<IpAddr>, account: &str) {
+        self.failures().remove(&account_subject(ip, account));
+    }
+
+    /// Address of the client. For requests from a trusted reverse proxy, this is the
+    /// last address in the client IP header, which was added by the proxy.
+    pub fn client_ip(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let peer = peer?;
+        if !is_trusted_proxy(&self.settings.trusted_proxies, peer) {
+            return Some(peer);
+        }
+        // Earlier addresses were sent by the client and can be forged
+        headers
+            .get_all(self.settings.client_ip_header.as_str())
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .next_back()
+            .and_then(|ip| ip.trim().parse().ok())
+            .or(Some(peer))
+    }
+
+    pub fn lockouts(&self) -> Vec<Lockout> {
+        let now = Utc::now();
+        let mut lockouts: Vec<Lockout> = self
+            .failures()
+            .iter()
+            .filter(|((kind, _), f)| {
+                *kind != LockoutKind::User && f.count >= self.settings.lockout_threshold
+            })
+            .filter_map(|((kind, key), f)| {
+                f.blocked_until
+                    .filter(|until| *until > now)
+                    .map(|until| Lockout {
+                        kind: *kind,
+                        key: key.clone(),
+                        failed_logins: f.count,
+                        until,
+                    })
+            })
+            .collect();
+        lockouts.sort_by_key(|l| l.until);
+        lockouts
+    }
+
+    /// Removes the lockout and the failed logins. Returns `false` if there were none.
+    /// Clearing the lockout of an account also removes its backoff from all addresses.
+    pub fn clear_lockout(&self, kind: LockoutKind, key: &str) -> bool {
+        let mut failures = self.failures();
+        if kind == LockoutKind::Account
+            && let Some((account, _)) = key.rsplit_once('@')
+        {
+            failures.remove(&(LockoutKind::User, account.to_string()));
+        }
+        failures.remove(&(kind, key.to_string())).is_some()
+    }
+
+    /// Counts a request of a validated cargo token and rejects it, if the quota per minute
+    /// is exceeded. The token is identified by a key, e.g. the id of the token.
+    pub fn check_quota(&self, quota: Quota, token: &str) -> Result<(), Throttled> {
+        let limit = match quota {
+            Quota::Publish => self.settings.publish_per_minute,
+            Quota::Download => self.settings.download_per_minute,
+        };
+        if !self.settings.enabled || limit == 0 {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let length = Duration::minutes(1);
+        let mut quotas = self.quotas.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (quota, token.to_string());
+        if quotas.len() >= MAX_TRACKED_QUOTAS && !quotas.contains_key(&key) {
+            quotas.retain(|_, window| now - window.start < length);
+            if quotas.len() >= MAX_TRACKED_QUOTAS
+                && let Some(oldest) = quotas
+                    .iter()
+                    .min_by_key(|(_, window)| window.start)
+                    .map(|(k, _)| k.clone())
+            {
+                quotas.remove(&oldest);
+            }
+        }
+
+        let window = quotas.entry(key).or_insert(Window {
+            start: now,
+            requests: 0,
+        });
+        if now - window.start >= length {
+            *window = Window {
+                start: now,
+                requests: 0,
+            };
+        }
+        if window.requests >= limit {
+            return Err(Throttled {
+                retry_after: window.start + length - now,
+            });
+        }
+        window.requests += 1;
+        Ok(())
+    }
+
+    fn failures(&self) -> MutexGuard<'_, HashMap<(LockoutKind, String), Failures>> {
+        self.failures.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lockout_duration(&self) -> Duration {
+        Duration::minutes(i64::try_from(self.settings.lockout_minutes).unwrap_or(i64::MAX / 60_000))
+    }
+
+    fn backoff(&self, kind: LockoutKind, failed_logins: u32) -> Option<Duration> {
+        let lockout = self.lockout_duration();
+        let max_backoff = match kind {
+            LockoutKind::User => MAX_ACCOUNT_BACKOFF.min(lockout),
+            LockoutKind::Ip | LockoutKind::Account => lockout,
+        };
+        if kind != LockoutKind::User && failed_logins >= self.settings.lockout_threshold {
+            Some(lockout)
+        } else if failed_logins >= self.settings.max_failed_logins {
+            let exponent = (failed_logins - self.settings.max_failed_logins + 1).min(30);
+            Some(Duration::seconds(1 << exponent).min(max_backoff))
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks if the peer is one of the trusted proxies, which are
+/// given as single addresses or CIDR ranges.
+pub fn is_trusted_proxy(trusted_proxies: &[String], ip: IpAddr) -> bool {
+    let ip = ip.to_canonical();
+    trusted_proxies.iter().any(|proxy| {
+        if let Ok(net) = proxy.parse::<IpNet>() {
+            net.contains(&ip)
+        } else if let Ok(addr) = proxy.parse::<IpAddr>() {
+            addr == ip
+        } else {
+            warn!("Invalid trusted proxy address: {proxy}");
+            false
+        }
+    })
+}
+
+fn subjects(
+    ip: Option<IpAddr>,
+    account: Option<&str>,
+) -> impl Iterator<Item = (LockoutKind, String)> {
+    ip.map(|ip| (LockoutKind::Ip, ip_key(ip)))
+        .into_iter()
+        .chain(account.map(|a| account_subject(ip, a)))
+        .chain(account.map(|a| (LockoutKind::User, a.to_string())))
+}
+
+fn account_subject(ip: Option<IpAddr>, account: &str) -> (LockoutKind, String) {
+    let key = match ip {
+        Some(ip) => format!("{account}@{}", ip_key(ip)),
+        None => account.to_string(),
+    };
+    (LockoutKind::Account, key)
+}
+
+/// IPv6 clients usually get a whole /64 network, so the address is reduced to the network.
+fn ip_key(ip: IpAddr) -> String {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => {
+            let network = Ipv6Addr::from(u128::from(ip) & (u128::MAX << 64));
+            format!("{network}/64")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            max_failed_logins: 2,
+            lockout_threshold: 4,
+            publish_per_minute: 2,
+            ..RateLimit::default()
+        })
+    }
+
+    const IP: Option<IpAddr> = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+
+    #[test]
+    fn failed_logins_lead_to_backoff() {
+        let limiter = limiter();
+
+        limiter.login_failed(IP, Some("user"));
+        assert_eq!(Ok(()), limiter.check_login(IP, Some("user")));
+
+        limiter.login_failed(IP, Some("user"));
+        let throttled = limiter.check_login(IP, Some("user")).unwrap_err();
+        assert!(throttled.retry_after <= Duration::seconds(2));
+        assert!(limiter.check_login(IP, None).is_err());
+        assert_eq!(Ok(()), limiter.check_login(None, Some("other")));
+        assert!(limiter.lockouts().is_empty());
+    }
+
+    #[test]
+    fn failed_logins_lead_to_lockout() {
+        let limiter = limiter();
+
+        for _ in 0..4 {
+            limiter.login_failed(IP, Some("user"));
+        }
+
+        let throttled = limiter.check_login(IP, Some("user")).unwrap_err();
+        assert!(throttled.retry_after > Duration::minutes(14));
+        let lockouts = limiter.lockouts();
+        assert_eq!(2, lockouts.len());
+        assert!(lockouts.iter().all(|l| l.failed_logins == 4));
+        assert!(
+            lockouts
+                .iter()
+                .any(|l| l.kind == LockoutKind::Ip && l.key == "10.0.0.1")
+        );
+        assert!(
+            lockouts
+                .iter()
+                .any(|l| l.kind == LockoutKind::Account && l.key == "user@10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn lockout_of_account_does_not_apply_to_other_addresses() {
+        let limiter = limiter();
+        let other_ip = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        for _ in 0..4 {
+            limiter.login_failed(IP, Some("user"));
+        }
+
+        let locked_out = limiter.check_login(IP, Some("user")).unwrap_err();
+        let backoff = limiter.check_login(other_ip, Some("user")).unwrap_err();
+        assert!(locked_out.retry_after > Duration::minutes(14));
+        assert!(backoff.retry_after <= MAX_ACCOUNT_BACKOFF);
+    }
+
+    #[test]
+    fn failed_logins_from_many_addresses_lead_to_account_backoff_only() {
+        let limiter = limiter();
+
+        for i in 1..=8 {
+            let ip = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 1, i)));
+            limiter.login_failed(ip, Some("user"));
+        }
+
+        let other_ip = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 2, 1)));
+        let throttled = limiter.check_login(other_ip, Some("user")).unwrap_err();
+        assert!(throttled.retry_after <= MAX_ACCOUNT_BACKOFF);
+        assert_eq!(Ok(()), limiter.check_login(other_ip, Some("other")));
+        assert!(limiter.lockouts().is_empty());
+    }
+
+    #[test]
+    fn ipv6_addresses_are_grouped_by_network() {
+        let limiter = limiter();
+        let ip = Some("2001:db8:1:2::1".parse().unwrap());
+        let same_network = Some("2001:db8:1:2:ffff::2".parse().unwrap());
+        let other_network = Some("2001:db8:1:3::1".parse().unwrap());
+
+        for _ in 0..4 {
+            limiter.login_failed(ip, None);
+        }
+
+        assert!(limiter.check_login(same_network, None).is_err());
+        assert_eq!(Ok(()), limiter.check_login(other_network, None));
+        assert_eq!("2001:db8:1:2::/64", limiter.lockouts()[0].key);
+    }
+
+    #[test]
+    fn client_ip_of_trusted_proxy_is_taken_from_header() {
+        let limiter = RateLimiter::new(RateLimit {
+            trusted_proxies: vec!["10.0.0.0/8".to_string()],
+            ..RateLimit::default()
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Forwarded-For",
+            "192.168.0.1, 172.16.0.1".parse().unwrap(),
+        );
+        let untrusted = Some(IpAddr::V4(Ipv4Addr::new(172, 16, 0, 2)));
+
+        assert_eq!(
+            Some(IpAddr::V4(Ipv4Addr::new(172, 16, 0, 1))),
+            limiter.client_ip(IP, &headers)
+        );
+        assert_eq!(untrusted, limiter.client_ip(untrusted, &headers));
+        assert_eq!(IP, limiter.client_ip(IP, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn trusted_proxies_are_addresses_or_ranges() {
+        let trusted = vec![
+            "10.0.0.0/8".to_string(),
+            "192.168.1.10".to_string(),
+            "fd00::/8".to_string(),
+            "invalid".to_string(),
+        ];
+
+        assert!(is_trusted_proxy(&trusted, "10.1.2.3".parse().unwrap()));
+        assert!(is_trusted_proxy(&trusted, "192.168.1.10".parse().unwrap()));
+        assert!(is_trusted_proxy(&trusted, "fd00::1".parse().unwrap()));
+        assert!(is_trusted_proxy(
+            &trusted,
+            "::ffff:10.1.2.3".parse().unwrap()
+        ));
+        assert!(!is_trusted_proxy(&trusted, "192.168.1.11".parse().unwrap()));
+        assert!(!is_trusted_proxy(&[], "127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn cleared_lockouts_allow_logins() {
+        let limiter = limiter();
+        for _ in 0..4 {
+            limiter.login_failed(IP, Some("user"));
+        }
+
+        assert!(limiter.clear_lockout(LockoutKind::Ip, "10.0.0.1"));
+        assert!(limiter.clear_lockout(LockoutKind::Account, "user@10.0.0.1"));
+        assert!(!limiter.clear_lockout(LockoutKind::Account, "user@10.0.0.1"));
+
+        assert_eq!(Ok(()), limiter.check_login(IP, Some("user")));
+        assert!(limiter.lockouts().is_empty());
+    }
+
+    #[test]
+    fn successful_login_resets_account_only() {
+        let limiter = limiter();
+        limiter.login_failed(IP, Some("user"));
+        limiter.login_failed(IP, Some("user"));
+
+        limiter.login_succeeded(IP, "user");
+
+        assert!(limiter.check_login(IP, None).is_err());
+        assert!(!limiter.clear_lockout(LockoutKind::Account, "user@10.0.0.1"));
+    }
+
+    #[test]
+    fn disabled_rate_limit_allows_everything() {
+        let limiter = RateLimiter::new(RateLimit {
+            enabled: false,
+            max_failed_logins: 1,
+            publish_per_minute: 1,
+            ..RateLimit::default()
+        });
+
+        limiter.login_failed(IP, Some("user"));
+        limiter.login_failed(IP, Some("user"));
+
+        assert_eq!(Ok(()), limiter.check_login(IP, Some("user")));
+        assert_eq!(Ok(()), limiter.check_quota(Quota::Publish, "token"));
+        assert_eq!(Ok(()), limiter.check_quota(Quota::Publish, "token"));
+    }
+
+    #[test]
+    fn quota_is_counted_per_token() {
+        let limiter = limiter();
+
+        assert_eq!(Ok(()), limiter.check_quota(Quota::Publish, "token"));
+        assert_eq!(Ok(()), limiter.check_quota(Quota::Publish, "token"));
+        let throttled = limiter.check_quota(Quota::Publish, "token").unwrap_err();
+
+        assert!(throttled.retry_after <= Duration::minutes(1));
+        assert_eq!(Ok(()), limiter.check_quota(Quota::Publish, "other token"));
+        // No download quota configured
+        assert_eq!(Ok(()), limiter.check_quota(Quota::Download, "token"));
+    }
+
+    #[test]
+    fn throttled_response_has_retry_after_header() {
+        let response = Throttled {
+            retry_after: Duration::milliseconds(1500),
+        }
+        .into_response();
+
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert_eq!("2", response.headers()[header::RETRY_AFTER]);
+    }
+}
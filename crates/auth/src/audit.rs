@@ -0,0 +1,10 @@
+use db::{AuditEvent, DbProvider};
+use tracing::error;
+
+/// Appends the event to the audit log. The action was already performed,
+/// so a failure is logged instead of failing the request.
+pub async fn record(db: &dyn DbProvider, event: AuditEvent) {
+    if let Err(e) = db.add_audit_entry(&event).await {
+        error!("Failed to write audit log entry {event:?}: {e}");
+    }
+}
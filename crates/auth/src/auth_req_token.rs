@@ -1,8 +1,7 @@
 use crate::token::Token;
 use axum::extract::{Request, State};
-use axum::http::StatusCode;
 use axum::middleware::Next;
-use axum::response::Response;
+use axum::response::{IntoResponse, Response};
 use tracing::warn;
 
 /// Middleware that checks if a cargo token is provided when `settings.registry.auth_required` is `true`.
@@ -12,19 +11,19 @@ pub async fn cargo_auth_when_required(
     State(state): State<appstate::AppStateData>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Response {
     if !state.settings.registry.auth_required {
         // If auth_required is not true, pass through.
-        return Ok(next.run(request).await);
+        return next.run(request).await;
     }
 
-    let token = Token::from_header(request.headers(), &state.db).await;
+    let token = Token::from_request(request.headers(), request.extensions(), &state).await;
 
     match token {
-        Ok(_) => Ok(next.run(request).await),
-        Err(status) => {
-            warn!("Authentication required, but failed: {status}");
-            Err(status)
+        Ok(_) => next.run(request).await,
+        Err(e) => {
+            warn!("Authentication required, but failed: {e}");
+            e.into_response()
         }
     }
 }
@@ -37,9 +36,9 @@ mod test {
     use axum::http::{Request, StatusCode, header};
     use axum::routing::get;
     use axum::{Router, middleware};
-    use db::User;
     use db::error::DbError;
     use db::mock::MockDb;
+    use db::{AuthToken, User};
     use mockall::predicate::*;
     use settings::Settings;
     use std::sync::Arc;
@@ -137,15 +136,23 @@ mod test {
             .expect_get_user_from_token()
             .with(eq("token"))
             .returning(move |_| {
-                Ok(User {
-                    id: 0,
-                    name: "user".to_string(),
-                    pwd: String::new(),
-                    salt: String::new(),
-                    is_admin: false,
-                    is_read_only: false,
-                })
+                Ok((
+                    User {
+                        id: 0,
+                        name: "user".to_string(),
+                        pwd: String::new(),
+                        salt: String::new(),
+                        is_admin: false,
+                        is_read_only: false,
+                        totp_enabled: false,
+                        is_service_account: false,
+                    },
+                    AuthToken::default(),
+                ))
             });
+        mock_db
+            .expect_update_auth_token_usage()
+            .returning(|_, _, _| Ok(()));
         mock_db
             .expect_get_user_from_token()
             .with(eq("wrong_token"))
@@ -170,10 +177,14 @@ mod test {
 mod auth_middleware_tests {
     use super::*;
     use appstate::AppStateData;
+    use appstate::rate_limit::RateLimiter;
     use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::StatusCode;
     use axum::middleware::from_fn_with_state;
     use axum::{Router, routing::get};
     use db::DbProvider;
+    use db::{AuthToken, User};
     use db::{error::DbError, mock::MockDb};
     use hyper::{Request, header};
     use mockall::predicate::*;
@@ -237,6 +248,118 @@ mod auth_middleware_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn guarded_route_with_invalid_tokens_is_throttled() -> Result {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_user_from_token()
+            .with(eq("1234"))
+            .times(3)
+            .returning(|_st| Err(DbError::UserNotFound("1234".to_owned())));
+        mock_db
+            .expect_get_user_from_token()
+            .with(eq("valid"))
+            .returning(|_st| Ok((User::default(), AuthToken::default())));
+        mock_db
+            .expect_update_auth_token_usage()
+            .returning(|_, _, _| Ok(()));
+        let rate_limit = settings::RateLimit {
+            max_failed_logins: 2,
+            ..settings::RateLimit::default()
+        };
+        let state = AppStateData {
+            db: Arc::new(mock_db),
+            settings: Arc::new(Settings {
+                registry: settings::Registry {
+                    auth_required: true,
+                    ..settings::Registry::default()
+                },
+                ..Settings::default()
+            }),
+            rate_limiter: Arc::new(RateLimiter::new(rate_limit)),
+            ..appstate::test_state()
+        };
+        let app = Router::new()
+            .route("/guarded", get(StatusCode::OK))
+            .route_layer(from_fn_with_state(state.clone(), cargo_auth_when_required))
+            .with_state(state);
+        let request = |token: &str| {
+            Request::get("/guarded")
+                .header(header::AUTHORIZATION, token)
+                .extension(ConnectInfo(std::net::SocketAddr::from((
+                    [10, 0, 0, 1],
+                    1234,
+                ))))
+                .body(Body::empty())
+        };
+
+        for _ in 0..2 {
+            let r = app.clone().oneshot(request("1234")?).await?;
+            assert_eq!(r.status(), StatusCode::FORBIDDEN);
+        }
+        let r = app.clone().oneshot(request("1234")?).await?;
+        assert_eq!(r.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(r.headers()[header::RETRY_AFTER], "2");
+
+        // Valid tokens from the address are not throttled
+        let r = app.oneshot(request("valid")?).await?;
+        assert_eq!(r.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_tokens_behind_trusted_proxy_are_throttled_per_client() -> Result {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_user_from_token()
+            .with(eq("1234"))
+            .times(4)
+            .returning(|_st| Err(DbError::UserNotFound("1234".to_owned())));
+        let rate_limit = settings::RateLimit {
+            max_failed_logins: 2,
+            trusted_proxies: vec!["10.0.0.0/8".to_string()],
+            ..settings::RateLimit::default()
+        };
+        let state = AppStateData {
+            db: Arc::new(mock_db),
+            settings: Arc::new(Settings {
+                registry: settings::Registry {
+                    auth_required: true,
+                    ..settings::Registry::default()
+                },
+                ..Settings::default()
+            }),
+            rate_limiter: Arc::new(RateLimiter::new(rate_limit)),
+            ..appstate::test_state()
+        };
+        let app = Router::new()
+            .route("/guarded", get(StatusCode::OK))
+            .route_layer(from_fn_with_state(state.clone(), cargo_auth_when_required))
+            .with_state(state);
+        let request = |client: &str| {
+            Request::get("/guarded")
+                .header(header::AUTHORIZATION, "1234")
+                .header("X-Forwarded-For", client)
+                .extension(ConnectInfo(std::net::SocketAddr::from((
+                    [10, 0, 0, 1],
+                    1234,
+                ))))
+                .body(Body::empty())
+        };
+
+        for _ in 0..2 {
+            let r = app.clone().oneshot(request("192.168.0.1")?).await?;
+            assert_eq!(r.status(), StatusCode::FORBIDDEN);
+        }
+        let r = app.clone().oneshot(request("192.168.0.1")?).await?;
+        assert_eq!(r.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Other clients of the proxy are not throttled
+        let r = app.oneshot(request("192.168.0.2")?).await?;
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn guarded_route_without_token() -> Result {
         let mock_db = MockDb::new();
@@ -0,0 +1,421 @@
+use async_trait::async_trait;
+use db::error::DbError;
+use db::provider::DbResult;
+use db::{AuthProvider, ExternalUser};
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope, SearchEntry, dn_escape, ldap_escape};
+use settings::Ldap;
+use std::time::Duration;
+
+/// LDAP result code for a failed bind with wrong credentials or an unknown DN
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Authenticates users with a bind against an LDAP server or Active Directory.
+pub struct LdapAuthProvider {
+    settings: Ldap,
+}
+
+impl LdapAuthProvider {
+    pub fn new(settings: Ldap) -> Self {
+        Self { settings }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for LdapAuthProvider {
+    async fn authenticate(&self, name: &str, pwd: &str) -> DbResult<Option<ExternalUser>> {
+        let conn_settings = LdapConnSettings::new()
+            .set_starttls(self.settings.start_tls)
+            .set_conn_timeout(Duration::from_secs(10));
+        let (conn, ldap) = LdapConnAsync::with_settings(conn_settings, &self.settings.url)
+            .await
+            .map_err(provider_error)?;
+        ldap3::drive!(conn);
+
+        let mut directory = LdapDirectory(ldap);
+        let result = authenticate(&mut directory, &self.settings, name, pwd).await;
+        // The connection is closed anyway, if the unbind fails
+        let _ = directory.0.unbind().await;
+        result
+    }
+}
+
+/// Operations on the directory, which are needed for the authentication.
+#[async_trait]
+trait Directory: Send {
+    /// Returns `false` if the credentials are invalid.
+    async fn bind(&mut self, dn: &str, pwd: &str) -> DbResult<bool>;
+    async fn search(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: &[&str],
+    ) -> DbResult<Vec<SearchEntry>>;
+}
+
+struct LdapDirectory(ldap3::Ldap);
+
+#[async_trait]
+impl Directory for LdapDirectory {
+    async fn bind(&mut self, dn: &str, pwd: &str) -> DbResult<bool> {
+        let result = self.0.simple_bind(dn, pwd).await.map_err(provider_error)?;
+        match result.rc {
+            0 => Ok(true),
+            INVALID_CREDENTIALS => Ok(false),
+            _ => Err(provider_error(result)),
+        }
+    }
+
+    async fn search(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: &[&str],
+    ) -> DbResult<Vec<SearchEntry>> {
+        let (entries, _) = self
+            .0
+            .search(base, scope, filter, attrs.to_vec())
+            .await
+            .and_then(ldap3::SearchResult::success)
+            .map_err(provider_error)?;
+        Ok(entries.into_iter().map(SearchEntry::construct).collect())
+    }
+}
+
+async fn authenticate(
+    directory: &mut dyn Directory,
+    settings: &Ldap,
+    name: &str,
+    pwd: &str,
+) -> DbResult<Option<ExternalUser>> {
+    // A bind without password is an anonymous bind, which always succeeds
+    if pwd.is_empty() {
+        return Err(DbError::PasswordMismatch);
+    }
+
+    let searched = settings.user_dn_template.is_empty();
+    let user_dn = if searched {
+        match find_user(directory, settings, name).await? {
+            Some(dn) => dn,
+            None => return Ok(None),
+        }
+    } else {
+        settings
+            .user_dn_template
+            .replace("{username}", &dn_escape(name))
+    };
+
+    if !directory.bind(&user_dn, pwd).await? {
+        // With a DN template, unknown users cannot be told apart from wrong passwords
+        return if searched {
+            Err(DbError::PasswordMismatch)
+        } else {
+            Ok(None)
+        };
+    }
+
+    let groups = user_groups(directory, settings, &user_dn).await?;
+    Ok(Some(ExternalUser::from_groups(
+        name.to_string(),
+        None,
+        groups,
+        &settings.admin_groups,
+        &settings.read_only_groups,
+        settings.sync_groups,
+    )))
+}
+
+/// Searches the DN of the user with the service account.
+async fn find_user(
+    directory: &mut dyn Directory,
+    settings: &Ldap,
+    name: &str,
+) -> DbResult<Option<String>> {
+    if !settings.bind_dn.is_empty()
+        && !directory
+            .bind(&settings.bind_dn, &settings.bind_pwd)
+            .await?
+    {
+        return Err(provider_error("Bind with the service account failed"));
+    }
+
+    let filter = settings
+        .search_filter
+        .replace("{username}", &ldap_escape(name));
+    // "1.1" requests no attributes, as only the DN is needed
+    let entries = directory
+        .search(&settings.search_base, Scope::Subtree, &filter, &["1.1"])
+        .await?;
+    match entries.as_slice() {
+        [] => Ok(None),
+        [entry] => Ok(Some(entry.dn.clone())),
+        _ => Err(provider_error(format!("Multiple users found for {name}"))),
+    }
+}
+
+/// Names of the groups of the user, which are read with the permissions of the user.
+async fn user_groups(
+    directory: &mut dyn Directory,
+    settings: &Ldap,
+    user_dn: &str,
+) -> DbResult<Vec<String>> {
+    let entries = directory
+        .search(
+            user_dn,
+            Scope::Base,
+            "(objectClass=*)",
+            &[&settings.group_attribute],
+        )
+        .await?;
+
+    Ok(entries
+        .into_iter()
+        .flat_map(|mut entry| {
+            entry
+                .attrs
+                .remove(&settings.group_attribute)
+                .unwrap_or_default()
+        })
+        .map(|dn| group_name(&dn))
+        .collect())
+}
+
+/// The CN of a group DN like `cn=developers,ou=groups,dc=example,dc=org`,
+/// or the value itself, if it is not a DN.
+fn group_name(group_dn: &str) -> String {
+    let rdn = group_dn.split(',').next().unwrap_or_default();
+    match rdn.split_once('=') {
+        Some((attr, value)) if attr.trim().eq_ignore_ascii_case("cn") => value.trim().to_string(),
+        _ => group_dn.to_string(),
+    }
+}
+
+fn provider_error(e: impl std::fmt::Display) -> DbError {
+    DbError::AuthProviderError(format!("LDAP: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct StubUser {
+        dn: &'static str,
+        uid: &'static str,
+        pwd: &'static str,
+        groups: Vec<&'static str>,
+    }
+
+    /// In-process directory with a service account and two users.
+    struct StubDirectory {
+        users: Vec<StubUser>,
+        filters: Vec<String>,
+    }
+
+    impl StubDirectory {
+        fn new() -> Self {
+            Self {
+                users: vec![
+                    StubUser {
+                        dn: "uid=alice,ou=people,dc=example,dc=org",
+                        uid: "alice",
+                        pwd: "secret",
+                        groups: vec![
+                            "cn=Admins,ou=groups,dc=example,dc=org",
+                            "cn=developers,ou=groups,dc=example,dc=org",
+                        ],
+                    },
+                    StubUser {
+                        dn: "uid=bob,ou=people,dc=example,dc=org",
+                        uid: "bob",
+                        pwd: "pwd",
+                        groups: vec!["cn=readers,ou=groups,dc=example,dc=org"],
+                    },
+                ],
+                filters: Vec::new(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Directory for StubDirectory {
+        async fn bind(&mut self, dn: &str, pwd: &str) -> DbResult<bool> {
+            if dn == "cn=kellnr,dc=example,dc=org" {
+                return Ok(pwd == "service");
+            }
+            Ok(self.users.iter().any(|u| u.dn == dn && u.pwd == pwd))
+        }
+
+        async fn search(
+            &mut self,
+            base: &str,
+            scope: Scope,
+            filter: &str,
+            attrs: &[&str],
+        ) -> DbResult<Vec<SearchEntry>> {
+            self.filters.push(filter.to_string());
+            let entry = |u: &StubUser| SearchEntry {
+                dn: u.dn.to_string(),
+                attrs: attrs
+                    .iter()
+                    .filter(|a| **a == "memberOf")
+                    .map(|a| {
+                        let groups = u.groups.iter().map(ToString::to_string).collect();
+                        ((*a).to_string(), groups)
+                    })
+                    .collect(),
+                bin_attrs: HashMap::new(),
+            };
+
+            Ok(match scope {
+                Scope::Base => self
+                    .users
+                    .iter()
+                    .filter(|u| u.dn == base)
+                    .map(entry)
+                    .collect(),
+                _ => self
+                    .users
+                    .iter()
+                    .filter(|u| filter == format!("(uid={})", u.uid))
+                    .map(entry)
+                    .collect(),
+            })
+        }
+    }
+
+    fn template_settings() -> Ldap {
+        Ldap {
+            enabled: true,
+            user_dn_template: "uid={username},ou=people,dc=example,dc=org".to_string(),
+            admin_groups: vec!["admins".to_string()],
+            read_only_groups: vec!["readers".to_string()],
+            sync_groups: true,
+            ..Ldap::default()
+        }
+    }
+
+    fn search_settings() -> Ldap {
+        Ldap {
+            enabled: true,
+            bind_dn: "cn=kellnr,dc=example,dc=org".to_string(),
+            bind_pwd: "service".to_string(),
+            search_base: "ou=people,dc=example,dc=org".to_string(),
+            ..Ldap::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn bind_with_dn_template_maps_groups() {
+        let mut directory = StubDirectory::new();
+
+        let user = authenticate(&mut directory, &template_settings(), "alice", "secret")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            Some(ExternalUser {
+                name: "alice".to_string(),
+                identity: None,
+                is_admin: Some(true),
+                is_read_only: Some(false),
+                groups: Some(vec!["Admins".to_string(), "developers".to_string()]),
+            }),
+            user
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_password_with_dn_template_falls_back_to_kellnr() {
+        let mut directory = StubDirectory::new();
+
+        let user = authenticate(&mut directory, &template_settings(), "alice", "wrong")
+            .await
+            .unwrap();
+
+        assert_eq!(None, user);
+    }
+
+    #[tokio::test]
+    async fn search_finds_user_and_keeps_unmapped_roles() {
+        let mut directory = StubDirectory::new();
+
+        let user = authenticate(&mut directory, &search_settings(), "bob", "pwd")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            Some(ExternalUser {
+                name: "bob".to_string(),
+                ..ExternalUser::default()
+            }),
+            user
+        );
+    }
+
+    #[tokio::test]
+    async fn search_with_wrong_password_is_rejected() {
+        let mut directory = StubDirectory::new();
+
+        let result = authenticate(&mut directory, &search_settings(), "bob", "wrong").await;
+
+        assert!(matches!(result, Err(DbError::PasswordMismatch)));
+    }
+
+    #[tokio::test]
+    async fn search_for_unknown_user_falls_back_to_kellnr() {
+        let mut directory = StubDirectory::new();
+
+        let user = authenticate(&mut directory, &search_settings(), "admin", "123")
+            .await
+            .unwrap();
+
+        assert_eq!(None, user);
+    }
+
+    #[tokio::test]
+    async fn search_filter_is_escaped() {
+        let mut directory = StubDirectory::new();
+
+        let user = authenticate(&mut directory, &search_settings(), "*)(uid=*", "pwd")
+            .await
+            .unwrap();
+
+        assert_eq!(None, user);
+        assert_eq!(vec!["(uid=\\2a\\29\\28uid=\\2a)"], directory.filters);
+    }
+
+    #[tokio::test]
+    async fn wrong_service_account_password_is_an_error() {
+        let mut directory = StubDirectory::new();
+        let settings = Ldap {
+            bind_pwd: "wrong".to_string(),
+            ..search_settings()
+        };
+
+        let result = authenticate(&mut directory, &settings, "bob", "pwd").await;
+
+        assert!(matches!(result, Err(DbError::AuthProviderError(_))));
+    }
+
+    #[tokio::test]
+    async fn empty_password_is_rejected() {
+        let mut directory = StubDirectory::new();
+
+        let result = authenticate(&mut directory, &template_settings(), "alice", "").await;
+
+        assert!(matches!(result, Err(DbError::PasswordMismatch)));
+    }
+
+    #[test]
+    fn group_name_is_cn_of_dn() {
+        assert_eq!("devs", group_name("cn=devs,ou=groups,dc=example,dc=org"));
+        assert_eq!("devs", group_name("CN=devs,OU=groups,DC=example,DC=org"));
+        assert_eq!("devs", group_name("devs"));
+        assert_eq!(
+            "ou=devs,dc=example,dc=org",
+            group_name("ou=devs,dc=example,dc=org")
+        );
+    }
+}
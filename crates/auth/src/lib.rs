@@ -1,2 +1,7 @@
+pub mod audit;
 pub mod auth_req_token;
+pub mod ldap;
 pub mod token;
+pub mod token_cleanup;
+pub mod token_quota;
+pub mod totp;
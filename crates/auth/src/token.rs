@@ -1,13 +1,21 @@
 use appstate::AppStateData;
-use axum::extract::FromRequestParts;
+use appstate::rate_limit::Throttled;
+use axum::extract::{ConnectInfo, FromRequestParts};
 use axum::http::request::Parts;
-use axum::http::{HeaderMap, StatusCode};
-use db::DbProvider;
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Duration, Utc};
+use common::normalized_name::NormalizedName;
+use db::{ActorKind, AuditActor, AuthMethod, EndpointScope, TokenScopes};
 use rand::distr::Alphanumeric;
 use rand::{Rng, rng};
 use serde::Deserialize;
-use std::iter;
-use std::sync::Arc;
+use std::net::{IpAddr, SocketAddr};
+use std::{fmt, iter};
+use tracing::warn;
+
+/// Prefix of the short-lived publish tokens issued by trusted publishing.
+pub const TRUSTED_PUBLISH_TOKEN_PREFIX: &str = "kellnr_tp_";
 
 #[derive(Debug)]
 pub struct Token {
@@ -15,6 +23,14 @@ pub struct Token {
     pub user: String,
     pub is_admin: bool,
     pub is_read_only: bool,
+    /// Crates a token issued by trusted publishing may publish. Such tokens
+    /// do not belong to a user and can only be used to publish these crates.
+    pub trusted_crates: Option<Vec<NormalizedName>>,
+    pub scopes: TokenScopes,
+    /// Id of the authentication token, not set for trusted publishing tokens
+    pub auth_token_id: Option<i32>,
+    pub is_service_account: bool,
+    pub client_ip: Option<IpAddr>,
 }
 
 // See https://github.com/tokio-rs/axum/discussions/2281
@@ -24,6 +40,44 @@ pub enum OptionToken {
     Some(Token),
 }
 
+/// Rejection of a request whose token could not be extracted.
+#[derive(Debug)]
+pub enum TokenError {
+    Status(StatusCode),
+    /// Too many requests with invalid tokens from the client IP address
+    Throttled(Throttled),
+}
+
+impl From<StatusCode> for TokenError {
+    fn from(status: StatusCode) -> Self {
+        Self::Status(status)
+    }
+}
+
+impl From<Throttled> for TokenError {
+    fn from(throttled: Throttled) -> Self {
+        Self::Throttled(throttled)
+    }
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status(status) => status.fmt(f),
+            Self::Throttled(throttled) => throttled.fmt(f),
+        }
+    }
+}
+
+impl IntoResponse for TokenError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status) => status.into_response(),
+            Self::Throttled(throttled) => throttled.into_response(),
+        }
+    }
+}
+
 pub fn generate_token() -> String {
     let mut rng = rng();
     iter::repeat(())
@@ -33,18 +87,26 @@ pub fn generate_token() -> String {
         .collect::<String>()
 }
 
+pub fn generate_trusted_publish_token() -> String {
+    format!("{TRUSTED_PUBLISH_TOKEN_PREFIX}{}", generate_token())
+}
+
 impl Token {
-    pub async fn from_header(
+    pub async fn from_request(
         headers: &HeaderMap,
-        db: &Arc<dyn DbProvider>,
-    ) -> Result<Self, StatusCode> {
-        Self::extract_token(headers, db).await
+        extensions: &Extensions,
+        state: &AppStateData,
+    ) -> Result<Self, TokenError> {
+        Self::extract_token(headers, extensions, state).await
     }
 
     async fn extract_token(
         headers: &HeaderMap,
-        db: &Arc<dyn DbProvider>,
-    ) -> Result<Token, StatusCode> {
+        extensions: &Extensions,
+        state: &AppStateData,
+    ) -> Result<Token, TokenError> {
+        let db = &state.db;
+        let client_ip = client_ip(state, headers, extensions);
         // OptionToken code expects UNAUTHORIZED when no token is found
         let token = headers
             .get("Authorization")
@@ -52,43 +114,123 @@ impl Token {
             .to_str()
             .map_err(|_| StatusCode::BAD_REQUEST)?
             .to_owned();
+        // Invalid tokens are counted as failed logins of the client IP address. Requests with
+        // a valid token are not throttled, as the random tokens are too long to be guessed.
+        let forbidden = || -> TokenError {
+            if let Err(throttled) = state.rate_limiter.check_login(client_ip, None) {
+                return throttled.into();
+            }
+            state.rate_limiter.login_failed(client_ip, None);
+            StatusCode::FORBIDDEN.into()
+        };
+
+        if token.starts_with(TRUSTED_PUBLISH_TOKEN_PREFIX) {
+            let crates = db
+                .get_trusted_publish_crates(&token)
+                .await
+                .map_err(|_| forbidden())?;
+
+            let scopes = TokenScopes {
+                endpoint_scopes: Some(vec![EndpointScope::PublishUpdate]),
+                crate_scopes: Some(crates.iter().map(ToString::to_string).collect()),
+            };
+            return Ok(Token {
+                value: token,
+                user: String::new(),
+                is_admin: false,
+                is_read_only: false,
+                trusted_crates: Some(crates),
+                scopes,
+                auth_token_id: None,
+                is_service_account: false,
+                client_ip,
+            });
+        }
 
-        let user = db
+        let (user, auth_token) = db
             .get_user_from_token(&token)
             .await
-            .map_err(|_| StatusCode::FORBIDDEN)?;
+            .map_err(|_| forbidden())?;
+
+        let now = Utc::now();
+        let ip = client_ip.map(|ip| ip.to_string());
+        let interval =
+            Duration::seconds(state.settings.tokens.usage_update_interval_seconds as i64);
+        if auth_token.needs_usage_update(&now, ip.as_deref(), interval) {
+            // A failed usage update must not fail the request
+            if let Err(e) = db.update_auth_token_usage(auth_token.id, &now, ip).await {
+                warn!("Failed to update usage of token {}: {e}", auth_token.id);
+            }
+        }
 
         Ok(Token {
             value: token,
             user: user.name,
             is_admin: user.is_admin,
             is_read_only: user.is_read_only,
+            trusted_crates: None,
+            scopes: auth_token.scopes,
+            auth_token_id: Some(auth_token.id),
+            is_service_account: user.is_service_account,
+            client_ip,
         })
     }
+
+    /// The principal of the token as recorded in the audit log.
+    pub fn audit_actor(&self) -> AuditActor {
+        let (kind, auth) = if self.trusted_crates.is_some() {
+            (ActorKind::TrustedPublisher, AuthMethod::TrustedPublishing)
+        } else if self.is_service_account {
+            (ActorKind::ServiceAccount, AuthMethod::Token)
+        } else {
+            (ActorKind::User, AuthMethod::Token)
+        };
+        AuditActor {
+            name: self.user.clone(),
+            kind,
+            auth,
+            token_id: self.auth_token_id,
+            session_id: None,
+            ip: self.client_ip.map(|ip| ip.to_string()),
+        }
+    }
+}
+
+/// The client IP is only known if the server was started with connect info.
+/// Behind a trusted reverse proxy, it is taken from the client IP header.
+pub fn client_ip(
+    state: &AppStateData,
+    headers: &HeaderMap,
+    extensions: &Extensions,
+) -> Option<IpAddr> {
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    state.rate_limiter.client_ip(peer, headers)
 }
 
 impl FromRequestParts<AppStateData> for Token {
-    type Rejection = StatusCode;
+    type Rejection = TokenError;
 
     async fn from_request_parts(
         parts: &mut Parts,
         state: &AppStateData,
     ) -> Result<Self, Self::Rejection> {
-        Self::extract_token(&parts.headers, &state.db).await
+        Self::extract_token(&parts.headers, &parts.extensions, state).await
     }
 }
 
 impl FromRequestParts<AppStateData> for OptionToken {
-    type Rejection = StatusCode;
+    type Rejection = TokenError;
 
     async fn from_request_parts(
         parts: &mut Parts,
         state: &AppStateData,
     ) -> Result<Self, Self::Rejection> {
-        match Token::extract_token(&parts.headers, &state.db).await {
+        match Token::extract_token(&parts.headers, &parts.extensions, state).await {
             Ok(token) => Ok(OptionToken::Some(token)),
-            Err(StatusCode::UNAUTHORIZED) => Ok(OptionToken::None),
-            Err(status_code) => Err(status_code),
+            Err(TokenError::Status(StatusCode::UNAUTHORIZED)) => Ok(OptionToken::None),
+            Err(e) => Err(e),
         }
     }
 }
@@ -96,4 +238,8 @@ impl FromRequestParts<AppStateData> for OptionToken {
 #[derive(Deserialize)]
 pub struct NewTokenReqData {
     pub name: String,
+    #[serde(flatten)]
+    pub scopes: TokenScopes,
+    #[serde(default)]
+    pub expires: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,42 @@
+use chrono::{Duration, Utc};
+use db::DbProvider;
+use settings::Tokens;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Periodically deletes or flags tokens which were not used for
+/// `max_unused_days`, so stale credentials can be found and rotated.
+pub fn unused_token_cleanup(db: Arc<dyn DbProvider>, settings: Tokens) {
+    if settings.max_unused_days == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = cleanup(db.as_ref(), &settings).await {
+                error!("Unused token cleanup failed: {e}");
+            }
+            tokio::time::sleep(std::time::Duration::from_hours(1)).await;
+        }
+    });
+}
+
+async fn cleanup(db: &dyn DbProvider, settings: &Tokens) -> Result<(), db::error::DbError> {
+    let unused_since = Utc::now() - Duration::days(settings.max_unused_days as i64);
+    let count = db
+        .cleanup_unused_auth_tokens(&unused_since, settings.delete_unused)
+        .await?;
+
+    if count > 0 {
+        let action = if settings.delete_unused {
+            "Deleted"
+        } else {
+            "Flagged"
+        };
+        info!(
+            "{action} {count} token(s) unused for {} days",
+            settings.max_unused_days
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use crate::token::{Token, TokenError};
+use appstate::AppStateData;
+use appstate::rate_limit::Quota;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::warn;
+
+/// Middleware that limits the publishes per cargo token, see `settings.rate_limit.publish_per_minute`.
+pub async fn publish_quota(
+    State(state): State<AppStateData>,
+    request: Request,
+    next: Next,
+) -> Response {
+    check_quota(&state, Quota::Publish, request, next).await
+}
+
+/// Middleware that limits the downloads per cargo token, see `settings.rate_limit.download_per_minute`.
+/// Downloads without a token are not limited.
+pub async fn download_quota(
+    State(state): State<AppStateData>,
+    request: Request,
+    next: Next,
+) -> Response {
+    check_quota(&state, Quota::Download, request, next).await
+}
+
+/// The quota is counted per validated token, such that requests with random tokens
+/// cannot fill the quota windows of the rate limiter.
+async fn check_quota(state: &AppStateData, quota: Quota, request: Request, next: Next) -> Response {
+    let token = match Token::from_request(request.headers(), request.extensions(), state).await {
+        Ok(token) => token,
+        Err(TokenError::Status(StatusCode::UNAUTHORIZED)) => return next.run(request).await,
+        Err(e) => return e.into_response(),
+    };
+    // Trusted publishing tokens have no id, but are validated as well
+    let key = token
+        .auth_token_id
+        .map_or_else(|| token.value.clone(), |id| id.to_string());
+
+    if let Err(throttled) = state.rate_limiter.check_quota(quota, &key) {
+        if let Some(id) = token.auth_token_id {
+            warn!("{quota:?} quota of token {id} exceeded: {throttled}");
+        } else {
+            warn!("{quota:?} quota of trusted publishing token exceeded: {throttled}");
+        }
+        return throttled.into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use appstate::rate_limit::RateLimiter;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::get;
+    use db::error::DbError;
+    use db::mock::MockDb;
+    use db::{AuthToken, TokenScopes, User};
+    use settings::RateLimit;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_user_from_token()
+            .returning(|token| match token {
+                "token" | "other" => Ok((
+                    User::default(),
+                    AuthToken::new(
+                        if token == "token" { 1 } else { 2 },
+                        String::new(),
+                        token.to_string(),
+                        TokenScopes::default(),
+                    ),
+                )),
+                _ => Err(DbError::TokenNotFound),
+            });
+        mock_db
+            .expect_update_auth_token_usage()
+            .returning(|_, _, _| Ok(()));
+        let state = AppStateData {
+            db: Arc::new(mock_db),
+            rate_limiter: Arc::new(RateLimiter::new(RateLimit {
+                download_per_minute: 1,
+                ..RateLimit::default()
+            })),
+            ..appstate::test_state()
+        };
+        Router::new()
+            .route("/download", get(StatusCode::OK))
+            .route_layer(from_fn_with_state(state.clone(), download_quota))
+            .with_state(state)
+    }
+
+    fn download(token: Option<&str>) -> Request<Body> {
+        let mut request = Request::get("/download");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, token);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn downloads_above_quota_are_rejected() {
+        let app = app();
+
+        let first = app.clone().oneshot(download(Some("token"))).await.unwrap();
+        let second = app.clone().oneshot(download(Some("token"))).await.unwrap();
+        let other_token = app.oneshot(download(Some("other"))).await.unwrap();
+
+        assert_eq!(StatusCode::OK, first.status());
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, second.status());
+        assert!(second.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(StatusCode::OK, other_token.status());
+    }
+
+    #[tokio::test]
+    async fn invalid_tokens_are_rejected_without_quota() {
+        let app = app();
+
+        for _ in 0..3 {
+            let r = app
+                .clone()
+                .oneshot(download(Some("invalid")))
+                .await
+                .unwrap();
+            assert_eq!(StatusCode::FORBIDDEN, r.status());
+        }
+    }
+
+    #[tokio::test]
+    async fn downloads_without_token_are_not_limited() {
+        let app = app();
+
+        for _ in 0..3 {
+            let r = app.clone().oneshot(download(None)).await.unwrap();
+            assert_eq!(StatusCode::OK, r.status());
+        }
+    }
+}
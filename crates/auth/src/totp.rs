@@ -0,0 +1,168 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use common::util::generate_rand_string;
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// Number of recovery codes, which can be used once each instead of a TOTP code.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const NONCE_LEN: usize = 12;
+
+/// Creates a random TOTP secret, encoded as base32 as expected by authenticator apps.
+pub fn generate_secret() -> String {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+/// The `otpauth://` URL to add the secret to an authenticator app, e.g. as QR code.
+pub fn setup_url(secret: &str, issuer: &str, user: &str) -> Result<String, String> {
+    // A colon separates the issuer from the account name in the URL
+    let totp = totp(secret, Some(issuer.replace(':', "")), user.replace(':', ""))?;
+    Ok(totp.get_url())
+}
+
+/// Checks the code for the current time, accepting one time step of clock drift.
+/// Returns the time step of the code, such that each code can only be used once.
+pub fn verify_code(secret: &str, code: &str) -> Option<u64> {
+    let totp = totp(secret, None, String::new()).ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    code_step(&totp, code.trim(), now)
+}
+
+fn code_step(totp: &TOTP, code: &str, time: u64) -> Option<u64> {
+    let current = time / totp.step;
+    (current.saturating_sub(1)..=current + 1).find(|step| totp.check(code, step * totp.step))
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_rand_string(10))
+        .collect()
+}
+
+/// Encrypts the secret with AES-256-GCM to store it in the database.
+/// The result is the base64 encoded nonce followed by the ciphertext.
+pub fn encrypt_secret(secret: &str, key: &str) -> Result<String, String> {
+    let cipher = cipher(key)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, secret.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    let mut data = nonce.to_vec();
+    data.extend(ciphertext);
+    Ok(STANDARD.encode(data))
+}
+
+pub fn decrypt_secret(encrypted: &str, key: &str) -> Result<String, String> {
+    let cipher = cipher(key)?;
+    let data = STANDARD.decode(encrypted).map_err(|e| e.to_string())?;
+    if data.len() < NONCE_LEN {
+        return Err("Encrypted TOTP secret is too short".to_string());
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let secret = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt TOTP secret, was the key changed?".to_string())?;
+    String::from_utf8(secret).map_err(|e| e.to_string())
+}
+
+/// The AES key is derived from the configured key, which can be any string.
+fn cipher(key: &str) -> Result<Aes256Gcm, String> {
+    if key.is_empty() {
+        return Err("No TOTP encryption key configured".to_string());
+    }
+    let key = Sha256::digest(key.as_bytes());
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+fn totp(secret: &str, issuer: Option<String>, account: String) -> Result<TOTP, String> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| e.to_string())?;
+    // No skew, as the clock drift is handled when the code is checked
+    TOTP::new(Algorithm::SHA1, 6, 0, 30, secret, issuer, account).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_code_accepts_current_code_only() {
+        let secret = generate_secret();
+        let totp = totp(&secret, None, String::new()).unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        assert!(verify_code(&secret, &totp.generate(now)).is_some());
+        assert!(verify_code(&secret, &format!(" {} ", totp.generate(now))).is_some());
+        assert!(verify_code(&secret, &totp.generate(now - 300)).is_none());
+        assert!(verify_code(&secret, "abcdef").is_none());
+        assert!(verify_code("invalid secret", "123456").is_none());
+    }
+
+    #[test]
+    fn code_step_accepts_one_step_of_drift() {
+        let totp = totp(&generate_secret(), None, String::new()).unwrap();
+
+        assert_eq!(Some(100), code_step(&totp, &totp.generate(3000), 3000));
+        assert_eq!(Some(99), code_step(&totp, &totp.generate(2970), 3000));
+        assert_eq!(Some(101), code_step(&totp, &totp.generate(3030), 3000));
+        assert_eq!(None, code_step(&totp, &totp.generate(2940), 3000));
+    }
+
+    #[test]
+    fn rfc_6238_test_vector() {
+        // SHA-1 test vector of RFC 6238 with the secret "12345678901234567890"
+        let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        let totp = totp(secret, None, String::new()).unwrap();
+
+        assert_eq!("287082", totp.generate(59));
+        assert_eq!("081804", totp.generate(1_111_111_109));
+    }
+
+    #[test]
+    fn setup_url_contains_issuer_and_user() {
+        let url = setup_url("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", "Kellnr", "admin").unwrap();
+
+        assert_eq!(
+            "otpauth://totp/Kellnr:admin?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Kellnr",
+            url
+        );
+    }
+
+    #[test]
+    fn encrypted_secret_can_be_decrypted_with_same_key_only() {
+        let secret = generate_secret();
+
+        let encrypted = encrypt_secret(&secret, "key").unwrap();
+
+        assert_ne!(secret, encrypted);
+        assert_ne!(encrypted, encrypt_secret(&secret, "key").unwrap());
+        assert_eq!(secret, decrypt_secret(&encrypted, "key").unwrap());
+        assert!(decrypt_secret(&encrypted, "other key").is_err());
+    }
+
+    #[test]
+    fn encryption_needs_a_key() {
+        assert!(encrypt_secret("secret", "").is_err());
+    }
+
+    #[test]
+    fn recovery_codes_are_unique() {
+        let mut codes = generate_recovery_codes();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(RECOVERY_CODE_COUNT, codes.len());
+    }
+}
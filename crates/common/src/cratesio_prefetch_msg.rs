@@ -1,4 +1,4 @@
-use crate::{normalized_name::NormalizedName, original_name::OriginalName, version::Version};
+use crate::{original_name::OriginalName, version::Version};
 
 pub struct InsertData {
     pub name: OriginalName,
@@ -14,8 +14,10 @@ pub struct UpdateData {
 }
 
 pub struct DownloadData {
-    pub name: NormalizedName,
+    pub name: OriginalName,
     pub version: Version,
+    /// Size of the crate file in bytes
+    pub size: usize,
 }
 
 pub enum CratesioPrefetchMsg {
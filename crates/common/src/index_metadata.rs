@@ -1,4 +1,5 @@
 use crate::{
+    normalized_name::NormalizedName,
     publish_metadata::{PublishMetadata, RegistryDep},
     version::Version,
 };
@@ -81,6 +82,10 @@ pub struct IndexMetadata {
     // }
     #[serde(skip_serializing_if = "Option::is_none")]
     pub features2: Option<BTreeMap<String, Vec<String>>>,
+    // The time the version was published in RFC 3339 format. Only provided by
+    // crates.io and missing for versions published before it was introduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubtime: Option<String>,
 }
 
 impl IndexMetadata {
@@ -157,6 +162,7 @@ impl IndexMetadata {
             links: registry_metadata.links.clone(),
             v: Some(1),
             features2: None,
+            pubtime: None,
         }
     }
 
@@ -171,6 +177,7 @@ impl IndexMetadata {
             links: None,
             v: Some(1),
             features2: None,
+            pubtime: None,
         }
     }
 
@@ -310,6 +317,23 @@ impl From<RegistryDep> for IndexDep {
     }
 }
 
+/// Path of the index file of the crate below the root of a sparse index, e.g. `ro/ck/rocket`.
+pub fn sparse_index_path(name: &NormalizedName) -> String {
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => {
+            let first_char = &name[0..1];
+            format!("3/{first_char}/{name}")
+        }
+        _ => {
+            let first_two = &name[0..2];
+            let second_two = &name[2..4];
+            format!("{first_two}/{second_two}/{name}")
+        }
+    }
+}
+
 pub fn metadata_path(index_path: &Path, name: &str) -> PathBuf {
     if name.len() == 1 {
         index_path.join("1").join(name.to_lowercase())
@@ -5,7 +5,10 @@ pub mod index_metadata;
 pub mod normalized_name;
 pub mod original_name;
 pub mod prefetch;
+pub mod prewarm;
 pub mod publish_metadata;
 pub mod search_result;
+pub mod single_flight;
+pub mod ttl_cache;
 pub mod util;
 pub mod version;
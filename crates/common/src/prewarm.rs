@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageStatus {
+    Pending,
+    /// The crate file was cached already
+    Cached,
+    /// The crate file was downloaded from crates.io
+    Downloaded,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageProgress {
+    pub name: String,
+    pub version: String,
+    pub status: PackageStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Progress of downloading crates.io packages into the proxy cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrewarmJob {
+    pub id: u64,
+    #[serde(skip)]
+    pub user: String,
+    pub finished: bool,
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub packages: Vec<PackageProgress>,
+}
+
+impl PrewarmJob {
+    pub fn new(id: u64, user: &str, packages: Vec<(String, String)>) -> Self {
+        Self {
+            id,
+            user: user.to_string(),
+            finished: packages.is_empty(),
+            total: packages.len(),
+            done: 0,
+            failed: 0,
+            packages: packages
+                .into_iter()
+                .map(|(name, version)| PackageProgress {
+                    name,
+                    version,
+                    status: PackageStatus::Pending,
+                    error: None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn set_result(&mut self, index: usize, result: Result<PackageStatus, String>) {
+        let package = &mut self.packages[index];
+        match result {
+            Ok(status) => package.status = status,
+            Err(e) => {
+                package.status = PackageStatus::Failed;
+                package.error = Some(e);
+                self.failed += 1;
+            }
+        }
+        self.done += 1;
+    }
+}
+
+/// Pre-warm jobs by id. The number of jobs is bounded, if the store is full the
+/// oldest finished job is replaced. The downloads of all jobs share a bounded
+/// number of permits, such that jobs cannot flood crates.io with requests.
+pub struct PrewarmJobs {
+    jobs: Mutex<(u64, BTreeMap<u64, PrewarmJob>)>,
+    capacity: usize,
+    downloads: Semaphore,
+}
+
+impl PrewarmJobs {
+    pub fn new(capacity: usize, max_downloads: usize) -> Self {
+        Self {
+            jobs: Mutex::new((0, BTreeMap::new())),
+            capacity,
+            downloads: Semaphore::new(max_downloads),
+        }
+    }
+
+    /// Adds a job for the packages, or returns `None` if the store is full of
+    /// running jobs.
+    pub fn add(&self, user: &str, packages: Vec<(String, String)>) -> Option<PrewarmJob> {
+        let mut guard = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let (last_id, jobs) = &mut *guard;
+        if jobs.len() >= self.capacity {
+            let finished = jobs.values().find(|job| job.finished).map(|job| job.id)?;
+            jobs.remove(&finished);
+        }
+        *last_id += 1;
+        let job = PrewarmJob::new(*last_id, user, packages);
+        jobs.insert(job.id, job.clone());
+        Some(job)
+    }
+
+    pub fn get(&self, id: u64) -> Option<PrewarmJob> {
+        let guard = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        guard.1.get(&id).cloned()
+    }
+
+    pub fn update(&self, id: u64, f: impl FnOnce(&mut PrewarmJob)) {
+        let mut guard = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(job) = guard.1.get_mut(&id) {
+            f(job);
+        }
+    }
+
+    /// Waits until a download of a job may start.
+    pub async fn download_permit(&self) -> SemaphorePermit<'_> {
+        // The semaphore is never closed
+        self.downloads
+            .acquire()
+            .await
+            .expect("prewarm download semaphore is closed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packages() -> Vec<(String, String)> {
+        vec![("a".to_string(), "1.0.0".to_string())]
+    }
+
+    #[test]
+    fn failed_packages_are_counted() {
+        let mut job = PrewarmJob::new(
+            1,
+            "user",
+            vec![
+                ("a".to_string(), "1.0.0".to_string()),
+                ("b".to_string(), "1.0.0".to_string()),
+            ],
+        );
+
+        job.set_result(0, Ok(PackageStatus::Downloaded));
+        job.set_result(1, Err("not available on crates.io".to_string()));
+
+        assert_eq!((2, 2, 1), (job.total, job.done, job.failed));
+        assert_eq!(PackageStatus::Failed, job.packages[1].status);
+        assert_eq!(
+            Some("not available on crates.io".to_string()),
+            job.packages[1].error
+        );
+    }
+
+    #[test]
+    fn full_store_replaces_oldest_finished_job() {
+        let jobs = PrewarmJobs::new(2, 1);
+        let first = jobs.add("user", packages()).unwrap();
+        let second = jobs.add("user", packages()).unwrap();
+
+        assert!(jobs.add("user", packages()).is_none());
+
+        jobs.update(second.id, |job| job.finished = true);
+        let third = jobs.add("user", packages()).unwrap();
+
+        assert!(jobs.get(first.id).is_some());
+        assert!(jobs.get(second.id).is_none());
+        assert_eq!(Some(third.clone()), jobs.get(third.id));
+    }
+}
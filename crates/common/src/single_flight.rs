@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Deduplicates concurrent calls with the same key. The first call runs and all calls
+/// which start before it finishes wait for and share its result. Results are not
+/// kept, a call after that runs again.
+pub struct SingleFlight<K, V> {
+    calls: Mutex<BTreeMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K: Ord + Clone, V: Clone> SingleFlight<K, V> {
+    pub const fn new() -> Self {
+        Self {
+            calls: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Runs `f`, unless a call with the same key is running already. If the running
+    /// call is cancelled, one of the waiting calls runs its own `f` instead.
+    pub async fn run<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let call = self
+            .calls
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let value = call.get_or_init(f).await.clone();
+
+        let mut calls = self.calls.lock().unwrap();
+        if calls.get(&key).is_some_and(|c| Arc::ptr_eq(c, &call)) {
+            calls.remove(&key);
+        }
+        value
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_calls_share_one_result() {
+        let flight = SingleFlight::new();
+        let runs = AtomicUsize::new(0);
+        let call = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            "data".to_string()
+        };
+
+        let results = tokio::join!(
+            flight.run("serde", call),
+            flight.run("serde", call),
+            flight.run("serde", call),
+        );
+
+        assert_eq!(1, runs.load(Ordering::SeqCst));
+        assert_eq!(
+            ("data", "data", "data"),
+            (&*results.0, &*results.1, &*results.2)
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_with_other_keys_or_later_calls_run_again() {
+        let flight = SingleFlight::new();
+        let runs = AtomicUsize::new(0);
+        let call = || async { runs.fetch_add(1, Ordering::SeqCst) };
+
+        tokio::join!(flight.run("serde", call), flight.run("tokio", call));
+        flight.run("serde", call).await;
+
+        assert_eq!(3, runs.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn call_runs_if_the_previous_one_was_cancelled() {
+        let flight = SingleFlight::new();
+
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(10),
+            flight.run("serde", std::future::pending::<u32>),
+        )
+        .await;
+        let result = flight.run("serde", || async { 2 }).await;
+
+        assert!(cancelled.is_err());
+        assert_eq!(2, result);
+    }
+}
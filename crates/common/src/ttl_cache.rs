@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A map whose entries expire after a fixed time. The number of entries is bounded,
+/// if the cache is full the oldest entry is replaced.
+pub struct TtlCache<K, V> {
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Returns the value of the key, if it was inserted within the TTL.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .get(key)
+            .filter(|(inserted, _)| inserted.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+        if entries.len() >= self.capacity
+            && !entries.contains_key(&key)
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (inserted, _))| *inserted)
+                .map(|(k, _)| k.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = TtlCache::new(Duration::from_millis(20), 10);
+        cache.insert("serde", 1);
+
+        assert_eq!(Some(1), cache.get(&"serde"));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(None, cache.get(&"serde"));
+    }
+
+    #[test]
+    fn oldest_entry_is_replaced_if_full() {
+        let cache = TtlCache::new(Duration::from_mins(1), 2);
+        cache.insert("serde", 1);
+        cache.insert("tokio", 2);
+        cache.insert("axum", 3);
+
+        assert_eq!(None, cache.get(&"serde"));
+        assert_eq!(Some(2), cache.get(&"tokio"));
+        assert_eq!(Some(3), cache.get(&"axum"));
+    }
+}
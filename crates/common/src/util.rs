@@ -9,3 +9,46 @@ pub fn generate_rand_string(length: usize) -> String {
         .take(length)
         .collect::<String>()
 }
+
+/// Matches `value` against `pattern`, where a `*` in the pattern matches any characters.
+pub fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // The first part is always present, even for an empty pattern
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard in the pattern
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_match_works() {
+        assert!(wildcard_match("main", "main"));
+        assert!(!wildcard_match("main", "main2"));
+        assert!(!wildcard_match("main", "mai"));
+        assert!(wildcard_match("refs/tags/v*", "refs/tags/v1.0.0"));
+        assert!(!wildcard_match("refs/tags/v*", "refs/heads/main"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*/release-*.yml", "ci/release-crate.yml"));
+        assert!(!wildcard_match("*/release-*.yml", "ci/release-crate.yaml"));
+        assert!(wildcard_match("a*a", "aa"));
+        assert!(!wildcard_match("a*a", "a"));
+    }
+}
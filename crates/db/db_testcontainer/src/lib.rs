@@ -36,10 +36,10 @@ pub fn db_test(_attr: TokenStream, stream: TokenStream) -> TokenStream {
             let path = path::PathBuf::from("/tmp").join(generate_rand_string(8).add(".db"));
             let con_string = db::SqliteConString {
                 path: path.to_owned(),
-                salt: "salt".to_string(),
                 admin_pwd: "123".to_string(),
                 admin_token: "token".to_string(),
-                session_age: std::time::Duration::from_secs(1),
+                session_age: std::time::Duration::from_secs(60 * 60 * 8),
+                pwd_hashing: Default::default(),
             };
             let con_string = db::ConString::Sqlite(con_string);
             let test_db = db::Database::new(&con_string, 10).await.unwrap();
@@ -58,7 +58,7 @@ pub fn db_test(_attr: TokenStream, stream: TokenStream) -> TokenStream {
 
             let pg_container = image::Postgres::default().start().await.expect("Failed to start postgres container");
             let port = pg_container.get_host_port_ipv4(image::Postgres::PG_PORT).await.expect("Failed to get port");
-            let admin = db::AdminUser::new("123".to_string(), "token".to_string(), "salt".to_string());
+            let admin = db::AdminUser::new("123".to_string(), "token".to_string());
             let pg_db = db::PgConString::new("localhost", port, "kellnr", "admin", "admin", admin);
             let pg_db = db::ConString::Postgres(pg_db);
             let test_db = db::Database::new(&pg_db, 10).await.unwrap();
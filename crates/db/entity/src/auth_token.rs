@@ -12,6 +12,19 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub token: String,
     pub user_fk: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub endpoint_scopes: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub crate_scopes: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub expires: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_used: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_used_ip: Option<String>,
+    pub flagged_unused: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -24,6 +24,10 @@ pub struct Model {
     pub links: Option<String>,
     pub v: i32,
     pub crates_io_fk: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub published: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub license: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
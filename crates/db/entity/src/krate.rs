@@ -44,6 +44,8 @@ pub enum Relation {
     CrateUser,
     #[sea_orm(has_many = "super::owner::Entity")]
     Owner,
+    #[sea_orm(has_many = "super::trusted_publisher::Entity")]
+    TrustedPublisher,
 }
 
 impl Related<super::crate_author_to_crate::Entity> for Entity {
@@ -94,4 +96,10 @@ impl Related<super::owner::Entity> for Entity {
     }
 }
 
+impl Related<super::trusted_publisher::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TrustedPublisher.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
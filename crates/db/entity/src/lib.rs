@@ -2,6 +2,7 @@
 
 pub mod prelude;
 
+pub mod audit_log;
 pub mod auth_token;
 pub mod crate_author;
 pub mod crate_author_to_crate;
@@ -13,13 +14,24 @@ pub mod crate_keyword;
 pub mod crate_keyword_to_crate;
 pub mod crate_meta;
 pub mod crate_user;
+pub mod cratesio_cached_file;
 pub mod cratesio_crate;
+pub mod cratesio_eviction_report;
 pub mod cratesio_index;
 pub mod cratesio_meta;
+pub mod cratesio_pin;
 pub mod doc_queue;
+pub mod external_identity;
 pub mod group;
 pub mod group_user;
 pub mod krate;
 pub mod owner;
+pub mod proxy_policy_rule;
+pub mod quarantine_exemption;
+pub mod recovery_code;
 pub mod session;
+pub mod trusted_publish_token;
+pub mod trusted_publisher;
 pub mod user;
+pub mod webhook;
+pub mod webhook_delivery;
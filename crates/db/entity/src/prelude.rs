@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.4
 
+pub use super::audit_log::Entity as AuditLog;
 pub use super::auth_token::Entity as AuthToken;
 pub use super::crate_author::Entity as CrateAuthor;
 pub use super::crate_author_to_crate::Entity as CrateAuthorToCrate;
@@ -11,13 +12,24 @@ pub use super::crate_keyword::Entity as CrateKeyword;
 pub use super::crate_keyword_to_crate::Entity as CrateKeywordToCrate;
 pub use super::crate_meta::Entity as CrateMeta;
 pub use super::crate_user::Entity as CrateUser;
+pub use super::cratesio_cached_file::Entity as CratesioCachedFile;
 pub use super::cratesio_crate::Entity as CratesioCrate;
+pub use super::cratesio_eviction_report::Entity as CratesioEvictionReport;
 pub use super::cratesio_index::Entity as CratesioIndex;
 pub use super::cratesio_meta::Entity as CratesioMeta;
+pub use super::cratesio_pin::Entity as CratesioPin;
 pub use super::doc_queue::Entity as DocQueue;
+pub use super::external_identity::Entity as ExternalIdentity;
 pub use super::group::Entity as Group;
 pub use super::group_user::Entity as GroupUser;
 pub use super::krate::Entity as Krate;
 pub use super::owner::Entity as Owner;
+pub use super::proxy_policy_rule::Entity as ProxyPolicyRule;
+pub use super::quarantine_exemption::Entity as QuarantineExemption;
+pub use super::recovery_code::Entity as RecoveryCode;
 pub use super::session::Entity as Session;
+pub use super::trusted_publish_token::Entity as TrustedPublishToken;
+pub use super::trusted_publisher::Entity as TrustedPublisher;
 pub use super::user::Entity as User;
+pub use super::webhook::Entity as Webhook;
+pub use super::webhook_delivery::Entity as WebhookDelivery;
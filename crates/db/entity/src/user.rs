@@ -4,6 +4,7 @@ use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "user")]
+#[expect(clippy::struct_excessive_bools)] // columns of the table
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
@@ -15,6 +16,15 @@ pub struct Model {
     pub salt: String,
     pub is_admin: bool,
     pub is_read_only: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    pub totp_failed_attempts: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub totp_last_failure: Option<String>,
+    pub totp_last_step: Option<i64>,
+    pub is_service_account: bool,
+    pub owner_group_fk: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -23,10 +33,22 @@ pub enum Relation {
     AuthToken,
     #[sea_orm(has_many = "super::crate_user::Entity")]
     CrateUser,
+    #[sea_orm(has_many = "super::external_identity::Entity")]
+    ExternalIdentity,
+    #[sea_orm(
+        belongs_to = "super::group::Entity",
+        from = "Column::OwnerGroupFk",
+        to = "super::group::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Group,
     #[sea_orm(has_many = "super::group_user::Entity")]
     GroupUser,
     #[sea_orm(has_many = "super::owner::Entity")]
     Owner,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
     #[sea_orm(has_many = "super::session::Entity")]
     Session,
 }
@@ -43,6 +65,18 @@ impl Related<super::crate_user::Entity> for Entity {
     }
 }
 
+impl Related<super::external_identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExternalIdentity.def()
+    }
+}
+
+impl Related<super::group::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Group.def()
+    }
+}
+
 impl Related<super::group_user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::GroupUser.def()
@@ -55,6 +89,12 @@ impl Related<super::owner::Entity> for Entity {
     }
 }
 
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
 impl Related<super::session::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Session.def()
@@ -10,6 +10,13 @@ pub enum UserIden {
     Salt,
     IsAdmin,
     IsReadOnly,
+    TotpSecret,
+    TotpEnabled,
+    TotpFailedAttempts,
+    TotpLastFailure,
+    TotpLastStep,
+    IsServiceAccount,
+    OwnerGroupFk,
 }
 
 #[derive(Iden)]
@@ -21,6 +28,10 @@ pub enum SessionIden {
     Created,
     #[iden = "user_fk"]
     UserFk,
+    #[iden = "last_seen"]
+    LastSeen,
+    #[iden = "user_agent"]
+    UserAgent,
 }
 
 #[derive(Iden)]
@@ -87,6 +98,18 @@ pub enum AuthTokenIden {
     Token,
     #[iden = "user_fk"]
     UserFk,
+    #[iden = "endpoint_scopes"]
+    EndpointScopes,
+    #[iden = "crate_scopes"]
+    CrateScopes,
+    Created,
+    Expires,
+    #[iden = "last_used"]
+    LastUsed,
+    #[iden = "last_used_ip"]
+    LastUsedIp,
+    #[iden = "flagged_unused"]
+    FlaggedUnused,
 }
 
 #[derive(Iden)]
@@ -191,6 +214,8 @@ pub enum CratesIoIndexIden {
     Links,
     V,
     CratesIoFk,
+    Published,
+    License,
 }
 
 #[derive(Iden)]
@@ -233,3 +258,163 @@ pub enum CrateGroupIden {
     #[iden = "group_fk"]
     GroupFk,
 }
+
+#[derive(Iden)]
+pub enum TrustedPublisherIden {
+    #[iden = "trusted_publisher"]
+    Table,
+    Id,
+    #[iden = "crate_fk"]
+    CrateFk,
+    Issuer,
+    Audience,
+    JwksUrl,
+    Claims,
+}
+
+#[derive(Iden)]
+pub enum TrustedPublishTokenIden {
+    #[iden = "trusted_publish_token"]
+    Table,
+    Id,
+    Token,
+    #[iden = "trusted_publisher_fk"]
+    TrustedPublisherFk,
+    Expires,
+}
+
+#[derive(Iden)]
+pub enum RecoveryCodeIden {
+    #[iden = "recovery_code"]
+    Table,
+    Id,
+    #[iden = "user_fk"]
+    UserFk,
+    Code,
+}
+
+#[derive(Iden)]
+pub enum AuditLogIden {
+    #[iden = "audit_log"]
+    Table,
+    Id,
+    Created,
+    Actor,
+    #[iden = "actor_kind"]
+    ActorKind,
+    Auth,
+    #[iden = "token_id"]
+    TokenId,
+    #[iden = "session_id"]
+    SessionId,
+    Ip,
+    Action,
+    Target,
+    Before,
+    After,
+}
+
+#[derive(Iden)]
+pub enum WebhookIden {
+    #[iden = "webhook"]
+    Table,
+    Id,
+    Url,
+    Secret,
+    Events,
+    #[iden = "crate_pattern"]
+    CratePattern,
+    Created,
+}
+
+#[derive(Iden)]
+pub enum WebhookDeliveryIden {
+    #[iden = "webhook_delivery"]
+    Table,
+    Id,
+    #[iden = "webhook_fk"]
+    WebhookFk,
+    Event,
+    Payload,
+    Status,
+    Attempts,
+    #[iden = "next_attempt"]
+    NextAttempt,
+    #[iden = "last_attempt"]
+    LastAttempt,
+    #[iden = "response_status"]
+    ResponseStatus,
+    Error,
+    Created,
+}
+
+#[derive(Iden)]
+pub enum QuarantineExemptionIden {
+    #[iden = "quarantine_exemption"]
+    Table,
+    Id,
+    Name,
+    Created,
+}
+
+#[derive(Iden)]
+pub enum ProxyPolicyRuleIden {
+    #[iden = "proxy_policy_rule"]
+    Table,
+    Id,
+    Action,
+    #[iden = "crate_pattern"]
+    CratePattern,
+    #[iden = "version_req"]
+    VersionReq,
+    Licenses,
+    Group,
+    Reason,
+    Created,
+}
+
+#[derive(Iden)]
+pub enum CratesIoCachedFileIden {
+    #[iden = "cratesio_cached_file"]
+    Table,
+    Id,
+    Name,
+    Version,
+    Size,
+    #[iden = "last_downloaded"]
+    LastDownloaded,
+}
+
+#[derive(Iden)]
+pub enum CratesIoPinIden {
+    #[iden = "cratesio_pin"]
+    Table,
+    Id,
+    Name,
+    Version,
+    Created,
+}
+
+#[derive(Iden)]
+pub enum CratesIoEvictionReportIden {
+    #[iden = "cratesio_eviction_report"]
+    Table,
+    Id,
+    Started,
+    Finished,
+    Evicted,
+    #[iden = "reclaimed_bytes"]
+    ReclaimedBytes,
+    #[iden = "remaining_bytes"]
+    RemainingBytes,
+}
+
+#[derive(Iden)]
+pub enum ExternalIdentityIden {
+    #[iden = "external_identity"]
+    Table,
+    Id,
+    UserFk,
+    Issuer,
+    Subject,
+}
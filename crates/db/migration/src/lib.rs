@@ -29,6 +29,20 @@ mod m20250319_191043_add_groups_entities;
 mod m20250412_0000012_hash_tokens;
 mod m20250412_0000012_hash_tokens_entities;
 mod m20250414_102510_add_unique_indices;
+mod m20261018_120000_add_trusted_publishing;
+mod m20261018_130000_add_token_scopes;
+mod m20261018_140000_add_token_usage;
+mod m20261018_150000_add_session_activity;
+mod m20261018_160000_add_totp;
+mod m20261018_170000_add_service_accounts;
+mod m20261018_180000_add_audit_log;
+mod m20261018_190000_add_webhooks;
+mod m20261018_200000_add_cratesio_quarantine;
+mod m20261018_210000_add_proxy_policy;
+mod m20261018_220000_add_cratesio_eviction;
+mod m20261018_230000_add_external_identities;
+mod m20261018_233000_add_totp_last_step;
+mod m20261018_234000_add_audit_log_session;
 mod old_index_metadata;
 
 pub struct Migrator;
@@ -52,6 +66,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20250319_191043_add_groups::Migration),
             Box::new(m20250412_0000012_hash_tokens::Migration),
             Box::new(m20250414_102510_add_unique_indices::Migration),
+            Box::new(m20261018_120000_add_trusted_publishing::Migration),
+            Box::new(m20261018_130000_add_token_scopes::Migration),
+            Box::new(m20261018_140000_add_token_usage::Migration),
+            Box::new(m20261018_150000_add_session_activity::Migration),
+            Box::new(m20261018_160000_add_totp::Migration),
+            Box::new(m20261018_170000_add_service_accounts::Migration),
+            Box::new(m20261018_180000_add_audit_log::Migration),
+            Box::new(m20261018_190000_add_webhooks::Migration),
+            Box::new(m20261018_200000_add_cratesio_quarantine::Migration),
+            Box::new(m20261018_210000_add_proxy_policy::Migration),
+            Box::new(m20261018_220000_add_cratesio_eviction::Migration),
+            Box::new(m20261018_230000_add_external_identities::Migration),
+            Box::new(m20261018_233000_add_totp_last_step::Migration),
+            Box::new(m20261018_234000_add_audit_log_session::Migration),
         ]
     }
 }
@@ -4,6 +4,7 @@ use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "crate_author")]
+#[expect(dead_code)]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
@@ -12,6 +13,7 @@ pub struct Model {
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+#[expect(dead_code)]
 pub enum Relation {
     #[sea_orm(has_many = "super::crate_author_to_crate::Entity")]
     CrateAuthorToCrate,
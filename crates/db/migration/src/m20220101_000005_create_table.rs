@@ -2,15 +2,12 @@ use crate::m20220101_000005_create_table_entities::{
     crate_author, crate_author_to_crate, crate_category, crate_category_to_crate, crate_index,
     crate_keyword, crate_keyword_to_crate, crate_meta, krate,
 };
-use crate::old_index_metadata::OldIndexDep;
 use crate::old_index_metadata::OldIndexMetadata;
 use common::index_metadata::metadata_path;
 use common::version::Version;
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
 use sea_orm_migration::prelude::*;
-use serde::{Deserialize, Serialize};
 use settings::{Settings, get_settings};
-use std::collections::HashMap;
 use tracing::{debug, error};
 
 #[derive(DeriveMigrationName)]
@@ -396,12 +393,6 @@ async fn fill_new_columns(
     Ok(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Deps(Vec<OldIndexDep>);
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Features(HashMap<String, Vec<String>>);
-
 async fn fill_crate_index(
     db: &SchemaManagerConnection<'_>,
     index: &OldIndexMetadata,
@@ -98,6 +98,7 @@ async fn compute_etag(
             yanked: ci.yanked,
             links: ci.links.clone(),
             v: Some(ci.v as u32),
+            pubtime: None,
         };
         index_metadata.push(cm);
     }
@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{CrateIden, TrustedPublishTokenIden, TrustedPublisherIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TrustedPublisherIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TrustedPublisherIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublisherIden::CrateFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("crate_fk")
+                            .from(TrustedPublisherIden::Table, TrustedPublisherIden::CrateFk)
+                            .to(CrateIden::Table, CrateIden::Id)
+                            .on_update(ForeignKeyAction::NoAction)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublisherIden::Issuer)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublisherIden::Audience)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublisherIden::JwksUrl)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublisherIden::Claims)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TrustedPublishTokenIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TrustedPublishTokenIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublishTokenIden::Token)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublishTokenIden::TrustedPublisherFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("trusted_publisher_fk")
+                            .from(
+                                TrustedPublishTokenIden::Table,
+                                TrustedPublishTokenIden::TrustedPublisherFk,
+                            )
+                            .to(TrustedPublisherIden::Table, TrustedPublisherIden::Id)
+                            .on_update(ForeignKeyAction::NoAction)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(TrustedPublishTokenIden::Expires)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A token issued for multiple crates has one row per trusted publisher
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-trusted-publish-token")
+                    .table(TrustedPublishTokenIden::Table)
+                    .col(TrustedPublishTokenIden::Token)
+                    .col(TrustedPublishTokenIden::TrustedPublisherFk)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Drop tables in reverse order to their creation
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(TrustedPublishTokenIden::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TrustedPublisherIden::Table).to_owned())
+            .await
+    }
+}
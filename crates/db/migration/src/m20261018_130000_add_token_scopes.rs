@@ -0,0 +1,60 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::AuthTokenIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Sqlite does not support multiple alter
+        // options in one statement, so each column is added on its own.
+        // Both columns hold JSON arrays and are NULL for unrestricted tokens.
+
+        if !manager.has_column("auth_token", "endpoint_scopes").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(AuthTokenIden::Table)
+                        .add_column_if_not_exists(text_null(AuthTokenIden::EndpointScopes))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        if !manager.has_column("auth_token", "crate_scopes").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(AuthTokenIden::Table)
+                        .add_column_if_not_exists(text_null(AuthTokenIden::CrateScopes))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthTokenIden::Table)
+                    .drop_column(AuthTokenIden::EndpointScopes)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthTokenIden::Table)
+                    .drop_column(AuthTokenIden::CrateScopes)
+                    .to_owned(),
+            )
+            .await
+    }
+}
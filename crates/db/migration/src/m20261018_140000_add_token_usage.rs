@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::AuthTokenIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Sqlite does not support multiple alter
+        // options in one statement, so each column is added on its own.
+
+        let columns = [
+            ("created", text_null(AuthTokenIden::Created)),
+            ("expires", text_null(AuthTokenIden::Expires)),
+            ("last_used", text_null(AuthTokenIden::LastUsed)),
+            ("last_used_ip", text_null(AuthTokenIden::LastUsedIp)),
+            (
+                "flagged_unused",
+                boolean(AuthTokenIden::FlaggedUnused)
+                    .default(false)
+                    .to_owned(),
+            ),
+        ];
+
+        for (name, mut column) in columns {
+            if !manager.has_column("auth_token", name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(AuthTokenIden::Table)
+                            .add_column_if_not_exists(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        // Existing tokens count as created now, such that they are not
+        // considered unused right away.
+        let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(AuthTokenIden::Table)
+                    .value(AuthTokenIden::Created, now)
+                    .and_where(Expr::col(AuthTokenIden::Created).is_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            AuthTokenIden::Created,
+            AuthTokenIden::Expires,
+            AuthTokenIden::LastUsed,
+            AuthTokenIden::LastUsedIp,
+            AuthTokenIden::FlaggedUnused,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(AuthTokenIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
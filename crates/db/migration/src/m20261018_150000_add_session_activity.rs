@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::SessionIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Sqlite does not support multiple alter
+        // options in one statement, so each column is added on its own.
+
+        let columns = [
+            ("last_seen", text_null(SessionIden::LastSeen)),
+            ("user_agent", text_null(SessionIden::UserAgent)),
+        ];
+
+        for (name, mut column) in columns {
+            if !manager.has_column("session", name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(SessionIden::Table)
+                            .add_column_if_not_exists(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        // Existing sessions were last seen when they were created
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(SessionIden::Table)
+                    .value(SessionIden::LastSeen, Expr::col(SessionIden::Created))
+                    .and_where(Expr::col(SessionIden::LastSeen).is_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [SessionIden::LastSeen, SessionIden::UserAgent] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(SessionIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
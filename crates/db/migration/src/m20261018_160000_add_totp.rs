@@ -0,0 +1,93 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::{RecoveryCodeIden, UserIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Sqlite does not support multiple alter
+        // options in one statement, so each column is added on its own.
+
+        let columns = [
+            ("totp_secret", text_null(UserIden::TotpSecret)),
+            (
+                "totp_enabled",
+                boolean(UserIden::TotpEnabled).default(false).to_owned(),
+            ),
+            (
+                "totp_failed_attempts",
+                integer(UserIden::TotpFailedAttempts).default(0).to_owned(),
+            ),
+            ("totp_last_failure", text_null(UserIden::TotpLastFailure)),
+        ];
+
+        for (name, mut column) in columns {
+            if !manager.has_column("user", name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(UserIden::Table)
+                            .add_column_if_not_exists(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCodeIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecoveryCodeIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RecoveryCodeIden::UserFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("user_fk")
+                            .from(RecoveryCodeIden::Table, RecoveryCodeIden::UserFk)
+                            .to(UserIden::Table, UserIden::Id)
+                            .on_update(ForeignKeyAction::NoAction)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(RecoveryCodeIden::Code).text().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecoveryCodeIden::Table).to_owned())
+            .await?;
+        for column in [
+            UserIden::TotpSecret,
+            UserIden::TotpEnabled,
+            UserIden::TotpFailedAttempts,
+            UserIden::TotpLastFailure,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
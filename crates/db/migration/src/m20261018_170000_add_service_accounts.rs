@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::UserIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Sqlite does not support multiple alter
+        // options in one statement, so each column is added on its own.
+        // Sqlite cannot add foreign keys to existing tables, so the owner group
+        // is cleared when the group is deleted.
+
+        let columns = [
+            (
+                "is_service_account",
+                boolean(UserIden::IsServiceAccount)
+                    .default(false)
+                    .to_owned(),
+            ),
+            ("owner_group_fk", big_integer_null(UserIden::OwnerGroupFk)),
+        ];
+
+        for (name, mut column) in columns {
+            if !manager.has_column("user", name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(UserIden::Table)
+                            .add_column_if_not_exists(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [UserIden::IsServiceAccount, UserIden::OwnerGroupFk] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+use crate::iden::AuditLogIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The audit log has no foreign keys, as entries must outlive
+        // the users, tokens and crates they refer to.
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLogIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLogIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLogIden::Created).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Actor).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::ActorKind).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Auth).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::TokenId).integer().null())
+                    .col(ColumnDef::new(AuditLogIden::Ip).text().null())
+                    .col(ColumnDef::new(AuditLogIden::Action).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Target).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Before).text().null())
+                    .col(ColumnDef::new(AuditLogIden::After).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit-log-created")
+                    .table(AuditLogIden::Table)
+                    .col(AuditLogIden::Created)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLogIden::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,120 @@
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{WebhookDeliveryIden, WebhookIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebhookIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WebhookIden::Url).text().not_null())
+                    .col(ColumnDef::new(WebhookIden::Secret).text().not_null())
+                    .col(ColumnDef::new(WebhookIden::Events).text().not_null())
+                    .col(ColumnDef::new(WebhookIden::CratePattern).text().not_null())
+                    .col(ColumnDef::new(WebhookIden::Created).text().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The deliveries are the outbox of the webhooks and their delivery history
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveryIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::WebhookFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("webhook_fk")
+                            .from(WebhookDeliveryIden::Table, WebhookDeliveryIden::WebhookFk)
+                            .to(WebhookIden::Table, WebhookIden::Id)
+                            .on_update(ForeignKeyAction::NoAction)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveryIden::Event).text().not_null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Payload)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Status)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::NextAttempt)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::LastAttempt)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::ResponseStatus)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveryIden::Error).text().null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Created)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-webhook-delivery-next-attempt")
+                    .table(WebhookDeliveryIden::Table)
+                    .col(WebhookDeliveryIden::Status)
+                    .col(WebhookDeliveryIden::NextAttempt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookDeliveryIden::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(WebhookIden::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::{CratesIoIndexIden, QuarantineExemptionIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Existing versions keep an unknown publish
+        // time, such that they are never quarantined.
+        if !manager.has_column("cratesio_index", "published").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(CratesIoIndexIden::Table)
+                        .add_column_if_not_exists(text_null(CratesIoIndexIden::Published))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(QuarantineExemptionIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(QuarantineExemptionIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(QuarantineExemptionIden::Name)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(QuarantineExemptionIden::Created)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(QuarantineExemptionIden::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CratesIoIndexIden::Table)
+                    .drop_column(CratesIoIndexIden::Published)
+                    .to_owned(),
+            )
+            .await
+    }
+}
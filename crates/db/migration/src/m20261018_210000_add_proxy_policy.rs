@@ -0,0 +1,86 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::{CratesIoIndexIden, ProxyPolicyRuleIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. The licenses are filled in by the next
+        // update of the cached crates.
+        if !manager.has_column("cratesio_index", "license").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(CratesIoIndexIden::Table)
+                        .add_column_if_not_exists(text_null(CratesIoIndexIden::License))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProxyPolicyRuleIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::Action)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::CratePattern)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::VersionReq)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::Licenses)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ProxyPolicyRuleIden::Group).text().null())
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::Reason)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProxyPolicyRuleIden::Created)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProxyPolicyRuleIden::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CratesIoIndexIden::Table)
+                    .drop_column(CratesIoIndexIden::License)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,153 @@
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{CratesIoCachedFileIden, CratesIoEvictionReportIden, CratesIoPinIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Crate files in the crates.io storage are tracked from now on. Files cached
+        // before are tracked as soon as they are downloaded again.
+        manager
+            .create_table(
+                Table::create()
+                    .table(CratesIoCachedFileIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CratesIoCachedFileIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoCachedFileIden::Name)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoCachedFileIden::Version)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoCachedFileIden::Size)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoCachedFileIden::LastDownloaded)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-cratesio-cached-file")
+                    .table(CratesIoCachedFileIden::Table)
+                    .col(CratesIoCachedFileIden::Name)
+                    .col(CratesIoCachedFileIden::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CratesIoPinIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CratesIoPinIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CratesIoPinIden::Name).text().not_null())
+                    .col(ColumnDef::new(CratesIoPinIden::Version).text().not_null())
+                    .col(ColumnDef::new(CratesIoPinIden::Created).text().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-cratesio-pin")
+                    .table(CratesIoPinIden::Table)
+                    .col(CratesIoPinIden::Name)
+                    .col(CratesIoPinIden::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CratesIoEvictionReportIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CratesIoEvictionReportIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoEvictionReportIden::Started)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoEvictionReportIden::Finished)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoEvictionReportIden::Evicted)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoEvictionReportIden::ReclaimedBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CratesIoEvictionReportIden::RemainingBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(CratesIoEvictionReportIden::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CratesIoPinIden::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(CratesIoCachedFileIden::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
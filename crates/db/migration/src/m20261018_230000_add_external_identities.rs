@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{ExternalIdentityIden, UserIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Users of an external identity provider are found by the issuer and the
+        // subject of the provider, as the user name may be chosen by the user.
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExternalIdentityIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExternalIdentityIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalIdentityIden::UserFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("user_fk")
+                            .from(ExternalIdentityIden::Table, ExternalIdentityIden::UserFk)
+                            .to(UserIden::Table, UserIden::Id)
+                            .on_update(ForeignKeyAction::NoAction)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalIdentityIden::Issuer)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalIdentityIden::Subject)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-external-identity")
+                    .table(ExternalIdentityIden::Table)
+                    .col(ExternalIdentityIden::Issuer)
+                    .col(ExternalIdentityIden::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExternalIdentityIden::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::UserIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. The time step of the last used TOTP code
+        // is stored, such that a code cannot be used twice.
+        if !manager.has_column("user", "totp_last_step").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserIden::Table)
+                        .add_column_if_not_exists(big_integer_null(UserIden::TotpLastStep))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserIden::Table)
+                    .drop_column(UserIden::TotpLastStep)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::AuditLogIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Manual check if the column exists is needed, as Sqlite does not support
+        // ALTER TABLE IF COLUMN EXISTS. Older entries have no session.
+        if !manager.has_column("audit_log", "session_id").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(AuditLogIden::Table)
+                        .add_column_if_not_exists(big_integer_null(AuditLogIden::SessionId))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuditLogIden::Table)
+                    .drop_column(AuditLogIden::SessionId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+
+/// Kind of the principal which performed an audited action.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorKind {
+    #[default]
+    User,
+    ServiceAccount,
+    /// Publish tokens issued by trusted publishing do not belong to a user
+    TrustedPublisher,
+}
+
+/// How the actor was authenticated.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    #[default]
+    Session,
+    Token,
+    ReverseProxy,
+    TrustedPublishing,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditActor {
+    pub name: String,
+    pub kind: ActorKind,
+    pub auth: AuthMethod,
+    /// Id of the authentication token, if the actor used one
+    pub token_id: Option<i32>,
+    /// Id of the UI session, if the actor used one
+    pub session_id: Option<i64>,
+    pub ip: Option<String>,
+}
+
+/// Mutating registry and admin actions which are recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Publish,
+    Yank,
+    Unyank,
+    AddEmptyCrate,
+    DeleteCrate,
+    DeleteVersion,
+    BuildDocs,
+    AddOwner,
+    RemoveOwner,
+    AddCrateUser,
+    RemoveCrateUser,
+    AddCrateGroup,
+    RemoveCrateGroup,
+    ChangeDownloadRestricted,
+    AddTrustedPublisher,
+    DeleteTrustedPublisher,
+    AddUser,
+    DeleteUser,
+    ResetPassword,
+    ChangePassword,
+    ChangeReadOnly,
+    ResetTotp,
+    AddExternalIdentity,
+    DeleteExternalIdentity,
+    LogoutUser,
+    ClearLockout,
+    AddToken,
+    DeleteToken,
+    AddGroup,
+    DeleteGroup,
+    AddGroupUser,
+    RemoveGroupUser,
+    AddServiceAccount,
+    DeleteServiceAccount,
+    AddWebhook,
+    DeleteWebhook,
+    VerifyCratesioCache,
+    AddQuarantineExemption,
+    DeleteQuarantineExemption,
+    AddProxyPolicyRule,
+    DeleteProxyPolicyRule,
+    AddCratesioPin,
+    DeleteCratesioPin,
+    EvictCratesioCache,
+}
+
+/// An action to record in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEvent {
+    pub actor: AuditActor,
+    pub action: AuditAction,
+    /// Crate, user, group or token the action was performed on
+    pub target: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(actor: AuditActor, action: AuditAction, target: impl Display) -> Self {
+        Self {
+            actor,
+            action,
+            target: target.to_string(),
+            before: None,
+            after: None,
+        }
+    }
+
+    /// State of the target before the action.
+    #[must_use]
+    pub fn before(mut self, details: Value) -> Self {
+        self.before = Some(details);
+        self
+    }
+
+    /// State of the target after the action.
+    #[must_use]
+    pub fn after(mut self, details: Value) -> Self {
+        self.after = Some(details);
+        self
+    }
+}
+
+/// Entry of the audit log. Actions and kinds are plain strings,
+/// such that entries of newer versions can always be read.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created: String,
+    pub actor: String,
+    pub actor_kind: String,
+    pub auth: String,
+    pub token_id: Option<i32>,
+    pub session_id: Option<i64>,
+    pub ip: Option<String>,
+    pub action: String,
+    pub target: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Filter for audit log entries. All set fields have to match.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub actor_kind: Option<String>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    /// Entries created at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Entries created before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Entries with a smaller id, to page through the entries without an offset
+    pub before_id: Option<i64>,
+}
+
+/// Name of a unit variant as it is serialized, e.g. `service_account`.
+pub(crate) fn variant_name(variant: &impl Serialize) -> String {
+    match serde_json::to_value(variant) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,138 @@
+use crate::DbProvider;
+use crate::error::DbError;
+use crate::provider::DbResult;
+use common::util::generate_rand_string;
+use sea_orm::prelude::async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// External directory, e.g. LDAP, which checks the credentials of users
+/// instead of the password hashes stored in the database.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Returns `None`, if the user is unknown to the provider, such that
+    /// users which only exist in Kellnr, e.g. the admin, can still log in.
+    async fn authenticate(&self, name: &str, pwd: &str) -> DbResult<Option<ExternalUser>>;
+}
+
+/// User authenticated by an external identity provider. Attributes which are `None`
+/// are not managed by the provider and keep their value in Kellnr.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternalUser {
+    pub name: String,
+    /// Stable identity of the user at the provider. If set, the Kellnr user is looked up
+    /// by the identity instead of the name, which the user may be able to choose freely.
+    pub identity: Option<ExternalIdentity>,
+    pub is_admin: Option<bool>,
+    pub is_read_only: Option<bool>,
+    /// Kellnr groups the user is a member of. Groups which do not exist in Kellnr are ignored.
+    pub groups: Option<Vec<String>>,
+}
+
+/// Identity of a user at an external provider, e.g. the issuer and `sub` claim of an OIDC token.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+}
+
+impl ExternalUser {
+    /// Maps the groups of the user at the provider to the roles in Kellnr. A role is only
+    /// managed by the provider if groups are configured for it. Group names are compared
+    /// case-insensitively, as directories like LDAP do.
+    pub fn from_groups(
+        name: String,
+        identity: Option<ExternalIdentity>,
+        groups: Vec<String>,
+        admin_groups: &[String],
+        read_only_groups: &[String],
+        sync_groups: bool,
+    ) -> Self {
+        let mapped = |configured: &[String]| {
+            (!configured.is_empty()).then(|| {
+                configured
+                    .iter()
+                    .any(|c| groups.iter().any(|g| g.eq_ignore_ascii_case(c)))
+            })
+        };
+
+        Self {
+            name,
+            identity,
+            is_admin: mapped(admin_groups),
+            is_read_only: mapped(read_only_groups),
+            groups: sync_groups.then_some(groups),
+        }
+    }
+
+    /// Creates the user on the first login and updates its roles and group memberships.
+    /// Returns the name of the Kellnr user.
+    ///
+    /// If the user has an identity, an existing Kellnr user is only used if it is linked
+    /// to the identity, such that the provider cannot take over other accounts by name.
+    pub async fn sync(&self, db: &dyn DbProvider) -> DbResult<String> {
+        let name = match &self.identity {
+            Some(identity) => {
+                if let Some(name) = db
+                    .get_external_identity_user(&identity.issuer, &identity.subject)
+                    .await?
+                {
+                    name
+                } else {
+                    // Users which exist already have to be linked explicitly
+                    match db.get_user(&self.name).await {
+                        Ok(_) => return Err(DbError::ExternalIdentityNotLinked(self.name.clone())),
+                        Err(DbError::UserNotFound(_)) => self.create_user(db).await?,
+                        Err(e) => return Err(e),
+                    }
+                    db.add_external_identity(&self.name, identity).await?;
+                    self.name.clone()
+                }
+            }
+            None => self.name.clone(),
+        };
+
+        match db.get_user(&name).await {
+            Ok(user) if user.is_service_account => {
+                return Err(DbError::ServiceAccountLogin(name));
+            }
+            Ok(user) => {
+                if let Some(is_admin) = self.is_admin.filter(|a| *a != user.is_admin) {
+                    db.change_admin_state(&name, is_admin).await?;
+                }
+                if let Some(is_read_only) = self.is_read_only.filter(|r| *r != user.is_read_only) {
+                    db.change_read_only_state(&name, is_read_only).await?;
+                }
+            }
+            Err(DbError::UserNotFound(_)) if self.identity.is_none() => {
+                self.create_user(db).await?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        if let Some(groups) = &self.groups {
+            for group in db.get_groups().await? {
+                let is_member = db.is_group_user(&group.name, &name).await?;
+                let should_be_member = groups.contains(&group.name);
+                if should_be_member && !is_member {
+                    db.add_group_user(&group.name, &name).await?;
+                } else if !should_be_member && is_member {
+                    db.delete_group_user(&group.name, &name).await?;
+                }
+            }
+        }
+
+        Ok(name)
+    }
+
+    async fn create_user(&self, db: &dyn DbProvider) -> DbResult<()> {
+        // The user logs in with the provider only, so the password is never used
+        let pwd = generate_rand_string(32);
+        db.add_user(
+            &self.name,
+            &pwd,
+            self.is_admin.unwrap_or(false),
+            self.is_read_only.unwrap_or(false),
+        )
+        .await
+    }
+}
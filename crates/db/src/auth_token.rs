@@ -1,15 +1,221 @@
+use crate::database::DB_DATE_FORMAT;
+use chrono::{DateTime, Duration, Utc};
+use common::util::wildcard_match;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AuthToken {
     pub id: i32,
     pub name: String,
     #[serde(skip_serializing)]
-    token: String,
+    pub(crate) token: String,
+    #[serde(flatten)]
+    pub scopes: TokenScopes,
+    pub created: String,
+    pub expires: Option<String>,
+    pub last_used: Option<String>,
+    pub last_used_ip: Option<String>,
+    /// Set by the unused token cleanup, if the token was not used for a long time
+    pub flagged_unused: bool,
 }
 
 impl AuthToken {
-    pub fn new(id: i32, name: String, token: String) -> Self {
-        Self { id, name, token }
+    pub fn new(id: i32, name: String, token: String, scopes: TokenScopes) -> Self {
+        Self {
+            id,
+            name,
+            token,
+            scopes,
+            ..Default::default()
+        }
+    }
+
+    /// The usage of a token is only written to the database if the last write is older
+    /// than `interval` or the client IP changed, so not every request results in a write.
+    pub fn needs_usage_update(
+        &self,
+        now: &DateTime<Utc>,
+        ip: Option<&str>,
+        interval: Duration,
+    ) -> bool {
+        let threshold = (*now - interval).format(DB_DATE_FORMAT).to_string();
+        let outdated = self
+            .last_used
+            .as_ref()
+            .is_none_or(|last_used| *last_used < threshold);
+        outdated || (ip.is_some() && self.last_used_ip.as_deref() != ip)
+    }
+}
+
+/// Endpoints a scoped token can be used for, named like the scopes of crates.io.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndpointScope {
+    PublishNew,
+    PublishUpdate,
+    Yank,
+    ChangeOwners,
+    Download,
+    DocsUpload,
+}
+
+impl fmt::Display for EndpointScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self {
+            EndpointScope::PublishNew => "publish-new",
+            EndpointScope::PublishUpdate => "publish-update",
+            EndpointScope::Yank => "yank",
+            EndpointScope::ChangeOwners => "change-owners",
+            EndpointScope::Download => "download",
+            EndpointScope::DocsUpload => "docs-upload",
+        };
+        write!(f, "{scope}")
+    }
+}
+
+/// Restricts a token to endpoints and crates. A scope which is `None`
+/// does not restrict the token, e.g. tokens created before scopes existed.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenScopes {
+    #[serde(default)]
+    pub endpoint_scopes: Option<Vec<EndpointScope>>,
+    /// Crate names or glob patterns, e.g. `acme-*`
+    #[serde(default)]
+    pub crate_scopes: Option<Vec<String>>,
+}
+
+impl TokenScopes {
+    pub fn allows_endpoint(&self, scope: EndpointScope) -> bool {
+        self.endpoint_scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope))
+    }
+
+    /// Crate names are compared case-insensitive, like the normalized crate names.
+    pub fn allows_crate(&self, crate_name: &str) -> bool {
+        let crate_name = crate_name.to_lowercase();
+        self.crate_scopes.as_ref().is_none_or(|patterns| {
+            patterns
+                .iter()
+                .any(|pattern| wildcard_match(&pattern.to_lowercase(), &crate_name))
+        })
+    }
+
+    pub fn allows(&self, scope: EndpointScope, crate_name: &str) -> bool {
+        self.allows_endpoint(scope) && self.allows_crate(crate_name)
+    }
+
+    /// An empty scope list would make the token useless, and crate patterns
+    /// may only contain the characters of crate names and `*`.
+    pub fn is_valid(&self) -> bool {
+        let endpoints_valid = self
+            .endpoint_scopes
+            .as_ref()
+            .is_none_or(|scopes| !scopes.is_empty());
+        let crates_valid = self.crate_scopes.as_ref().is_none_or(|patterns| {
+            !patterns.is_empty()
+                && patterns.iter().all(|pattern| {
+                    !pattern.is_empty()
+                        && pattern
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '*'))
+                })
+        });
+        endpoints_valid && crates_valid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_update_needed_if_outdated_or_ip_changed() {
+        let now = Utc::now();
+        let token = AuthToken {
+            last_used: Some(
+                (now - Duration::seconds(60))
+                    .format(DB_DATE_FORMAT)
+                    .to_string(),
+            ),
+            last_used_ip: Some("10.0.0.1".to_string()),
+            ..AuthToken::default()
+        };
+        let interval = Duration::seconds(300);
+
+        assert!(AuthToken::default().needs_usage_update(&now, None, interval));
+        assert!(!token.needs_usage_update(&now, None, interval));
+        assert!(!token.needs_usage_update(&now, Some("10.0.0.1"), interval));
+        assert!(token.needs_usage_update(&now, Some("10.0.0.2"), interval));
+        assert!(token.needs_usage_update(&now, None, Duration::seconds(30)));
+    }
+
+    #[test]
+    fn unrestricted_scopes_allow_everything() {
+        let scopes = TokenScopes::default();
+
+        assert!(scopes.allows(EndpointScope::PublishNew, "any"));
+        assert!(scopes.allows(EndpointScope::Download, "crate"));
+    }
+
+    #[test]
+    fn endpoint_scopes_restrict_endpoints() {
+        let scopes = TokenScopes {
+            endpoint_scopes: Some(vec![EndpointScope::Download]),
+            crate_scopes: None,
+        };
+
+        assert!(scopes.allows(EndpointScope::Download, "any"));
+        assert!(!scopes.allows(EndpointScope::PublishUpdate, "any"));
+        assert!(!scopes.allows(EndpointScope::Yank, "any"));
+    }
+
+    #[test]
+    fn crate_scopes_restrict_crates() {
+        let scopes = TokenScopes {
+            endpoint_scopes: None,
+            crate_scopes: Some(vec!["acme-*".to_string(), "Exact".to_string()]),
+        };
+
+        assert!(scopes.allows(EndpointScope::PublishNew, "acme-core"));
+        assert!(scopes.allows(EndpointScope::PublishNew, "ACME-cli"));
+        assert!(scopes.allows(EndpointScope::PublishNew, "exact"));
+        assert!(!scopes.allows(EndpointScope::PublishNew, "exact2"));
+        assert!(!scopes.allows(EndpointScope::PublishNew, "other-acme"));
+    }
+
+    #[test]
+    fn empty_scopes_and_invalid_patterns_are_invalid() {
+        let scopes = |endpoint_scopes, crate_scopes| TokenScopes {
+            endpoint_scopes,
+            crate_scopes,
+        };
+
+        assert!(scopes(None, None).is_valid());
+        assert!(
+            scopes(
+                Some(vec![EndpointScope::Yank]),
+                Some(vec!["acme-*".to_string()])
+            )
+            .is_valid()
+        );
+        assert!(!scopes(Some(vec![]), None).is_valid());
+        assert!(!scopes(None, Some(vec![])).is_valid());
+        assert!(!scopes(None, Some(vec![String::new()])).is_valid());
+        assert!(!scopes(None, Some(vec!["acme/*".to_string()])).is_valid());
+    }
+
+    #[test]
+    fn scopes_are_serialized_kebab_case() {
+        let scopes = TokenScopes {
+            endpoint_scopes: Some(vec![EndpointScope::PublishNew, EndpointScope::DocsUpload]),
+            crate_scopes: None,
+        };
+
+        assert_eq!(
+            r#"{"endpoint_scopes":["publish-new","docs-upload"],"crate_scopes":null}"#,
+            serde_json::to_string(&scopes).unwrap()
+        );
     }
 }
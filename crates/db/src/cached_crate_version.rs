@@ -0,0 +1,11 @@
+use common::original_name::OriginalName;
+
+/// Version of a crate in the cached crates.io index, with the checksum of its crate file
+/// and its license, if known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedCrateVersion {
+    pub name: OriginalName,
+    pub version: String,
+    pub cksum: String,
+    pub license: Option<String>,
+}
@@ -1,5 +1,4 @@
-use crate::password::generate_salt;
-use settings::Settings;
+use settings::{PasswordHashing, Registry, Settings};
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -23,22 +22,29 @@ impl Display for ConString {
 impl ConString {
     pub fn admin_pwd(&self) -> String {
         match self {
-            ConString::Postgres(p) => p.admin.pwd.to_string(),
-            ConString::Sqlite(s) => s.admin_pwd.to_string(),
+            ConString::Postgres(p) => p.admin.pwd.clone(),
+            ConString::Sqlite(s) => s.admin_pwd.clone(),
         }
     }
 
-    pub fn salt(&self) -> String {
+    pub fn pwd_hashing(&self) -> PasswordHashing {
         match self {
-            ConString::Postgres(p) => p.admin.salt.to_string(),
-            ConString::Sqlite(s) => s.salt.to_string(),
+            ConString::Postgres(p) => p.pwd_hashing.clone(),
+            ConString::Sqlite(s) => s.pwd_hashing.clone(),
+        }
+    }
+
+    pub fn session_age(&self) -> Duration {
+        match self {
+            ConString::Postgres(p) => p.session_age,
+            ConString::Sqlite(s) => s.session_age,
         }
     }
 
     pub fn admin_token(&self) -> String {
         match self {
-            ConString::Postgres(p) => p.admin.token.to_string(),
-            ConString::Sqlite(s) => s.admin_token.to_string(),
+            ConString::Postgres(p) => p.admin.token.clone(),
+            ConString::Sqlite(s) => s.admin_token.clone(),
         }
     }
 }
@@ -47,12 +53,11 @@ impl ConString {
 pub struct AdminUser {
     pub pwd: String,
     pub token: String,
-    pub salt: String,
 }
 
 impl AdminUser {
-    pub fn new(pwd: String, token: String, salt: String) -> Self {
-        Self { pwd, token, salt }
+    pub fn new(pwd: String, token: String) -> Self {
+        Self { pwd, token }
     }
 }
 
@@ -64,6 +69,8 @@ pub struct PgConString {
     user: String,
     pwd: String,
     admin: AdminUser,
+    session_age: Duration,
+    pwd_hashing: PasswordHashing,
 }
 
 impl PgConString {
@@ -75,6 +82,8 @@ impl PgConString {
             user: user.to_owned(),
             pwd: pwd.to_owned(),
             admin,
+            session_age: Duration::from_secs(Registry::default().session_age_seconds),
+            pwd_hashing: PasswordHashing::default(),
         }
     }
 }
@@ -90,8 +99,9 @@ impl From<&Settings> for PgConString {
             admin: AdminUser {
                 pwd: s.setup.admin_pwd.clone(),
                 token: s.setup.admin_token.clone(),
-                salt: generate_salt(),
             },
+            session_age: Duration::from_secs(s.registry.session_age_seconds),
+            pwd_hashing: s.password_hashing.clone(),
         }
     }
 }
@@ -109,26 +119,20 @@ impl Display for PgConString {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SqliteConString {
     pub path: PathBuf,
-    pub salt: String,
     pub admin_pwd: String,
     pub admin_token: String,
     pub session_age: Duration,
+    pub pwd_hashing: PasswordHashing,
 }
 
 impl SqliteConString {
-    pub fn new(
-        path: &Path,
-        salt: &str,
-        admin_pwd: &str,
-        admin_token: &str,
-        session_age: Duration,
-    ) -> Self {
+    pub fn new(path: &Path, admin_pwd: &str, admin_token: &str, session_age: Duration) -> Self {
         Self {
             path: path.to_owned(),
-            salt: salt.to_owned(),
             admin_pwd: admin_pwd.to_owned(),
             admin_token: admin_token.to_owned(),
             session_age,
+            pwd_hashing: PasswordHashing::default(),
         }
     }
 }
@@ -137,10 +141,10 @@ impl From<&Settings> for SqliteConString {
     fn from(settings: &Settings) -> Self {
         Self {
             path: settings.sqlite_path(),
-            salt: generate_salt(),
             admin_pwd: settings.setup.admin_pwd.clone(),
             admin_token: settings.setup.admin_token.clone(),
             session_age: Duration::from_secs(settings.registry.session_age_seconds),
+            pwd_hashing: settings.password_hashing.clone(),
         }
     }
 }
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateLicense {
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+}
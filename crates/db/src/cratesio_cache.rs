@@ -0,0 +1,45 @@
+use common::normalized_name::NormalizedName;
+use common::original_name::OriginalName;
+use serde::{Deserialize, Serialize};
+
+/// Crate file in the crates.io storage, with the time it was last downloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedCratesIoFile {
+    /// Name as in the case-sensitive name of the file in the storage
+    pub name: String,
+    pub version: String,
+    /// Size of the crate file in bytes
+    pub size: i64,
+    pub last_downloaded: String,
+    /// Whether the file was last downloaded before the age limit of the cache
+    pub expired: bool,
+}
+
+impl CachedCratesIoFile {
+    /// Name of the crate as it is stored in pins
+    pub fn normalized_name(&self) -> NormalizedName {
+        OriginalName::from_unchecked(self.name.clone()).to_normalized()
+    }
+}
+
+/// Crate version which is never evicted from the crates.io storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CratesIoPin {
+    /// Normalized name, such that all files of the version are pinned regardless
+    /// of the case of their name
+    pub name: String,
+    pub version: String,
+    pub created: String,
+}
+
+/// Result of a run of the eviction of crate files from the crates.io storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CratesIoEvictionReport {
+    pub id: i64,
+    pub started: String,
+    pub finished: String,
+    /// Evicted versions as `name@version`
+    pub evicted: Vec<String>,
+    pub reclaimed_bytes: i64,
+    pub remaining_bytes: i64,
+}
@@ -1,8 +1,17 @@
-use crate::password::{generate_salt, hash_pwd, hash_token};
+use crate::audit::variant_name;
+use crate::password::{hash_pwd, hash_token, needs_rehash, verify_pwd};
 use crate::provider::{DbResult, PrefetchState};
 use crate::tables::init_database;
-use crate::{AuthToken, CrateMeta, CrateSummary, DbProvider, Group, User, error::DbError};
-use crate::{ConString, DocQueueEntry};
+use crate::{
+    AuditEntry, AuditEvent, AuditFilter, AuthProvider, AuthToken, CachedCrateVersion,
+    CachedCratesIoFile, CrateLicense, CrateMeta, CrateSummary, CratesIoEvictionReport, CratesIoPin,
+    DbProvider, ExternalIdentity, Group, ServiceAccount, Session, TokenScopes, TrustedPublisher,
+    User, error::DbError,
+};
+use crate::{
+    ConString, DeliveryAttempt, DeliveryStatus, DocQueueEntry, PendingDelivery, PolicyAction,
+    ProxyPolicyRule, QuarantineExemption, Webhook, WebhookDelivery, WebhookEvent,
+};
 use chrono::{DateTime, Utc};
 use common::crate_data::{CrateData, CrateRegistryDep, CrateVersionData};
 use common::crate_overview::CrateOverview;
@@ -12,12 +21,15 @@ use common::normalized_name::NormalizedName;
 use common::original_name::OriginalName;
 use common::prefetch::Prefetch;
 use common::publish_metadata::PublishMetadata;
+use common::util::wildcard_match;
 use common::version::Version;
 use entity::{
-    auth_token, crate_author, crate_author_to_crate, crate_category, crate_category_to_crate,
-    crate_group, crate_index, crate_keyword, crate_keyword_to_crate, crate_meta, crate_user,
-    cratesio_crate, cratesio_index, cratesio_meta, doc_queue, group, group_user, krate, owner,
-    prelude::*, session, user,
+    audit_log, auth_token, crate_author, crate_author_to_crate, crate_category,
+    crate_category_to_crate, crate_group, crate_index, crate_keyword, crate_keyword_to_crate,
+    crate_meta, crate_user, cratesio_cached_file, cratesio_crate, cratesio_eviction_report,
+    cratesio_index, cratesio_meta, cratesio_pin, doc_queue, external_identity, group, group_user,
+    krate, owner, prelude::*, proxy_policy_rule, quarantine_exemption, recovery_code, session,
+    trusted_publish_token, trusted_publisher, user, webhook, webhook_delivery,
 };
 use migration::iden::{
     AuthTokenIden, CrateIden, CrateMetaIden, CratesIoIden, CratesIoMetaIden, GroupIden,
@@ -25,22 +37,39 @@ use migration::iden::{
 use sea_orm::sea_query::{Alias, Cond, Expr, JoinType, Order, Query, UnionType};
 use sea_orm::{
     ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
-    FromQueryResult, InsertResult, ModelTrait, QueryFilter, RelationTrait, Set,
+    FromQueryResult, InsertResult, ModelTrait, PaginatorTrait, QueryFilter, RelationTrait, Set,
     prelude::async_trait::async_trait,
     query::{QueryOrder, QuerySelect, TransactionTrait},
 };
-use std::collections::BTreeMap;
+use settings::PasswordHashing;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
 
-const DB_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+pub(crate) const DB_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Sessions are renewed at most once per interval, to avoid a write on every request
+const SESSION_RENEWAL_INTERVAL: chrono::Duration = chrono::Duration::minutes(1);
 
 pub struct Database {
     db_con: DatabaseConnection,
+    pwd_hashing: PasswordHashing,
+    session_age: chrono::Duration,
+    auth_provider: Option<Arc<dyn AuthProvider>>,
 }
 
 impl Database {
-    pub fn existing(db_con: DatabaseConnection) -> Self {
-        Self { db_con }
+    pub fn existing(
+        db_con: DatabaseConnection,
+        pwd_hashing: PasswordHashing,
+        session_age: chrono::Duration,
+    ) -> Self {
+        Self {
+            db_con,
+            pwd_hashing,
+            session_age,
+            auth_provider: None,
+        }
     }
 
     pub async fn new(con: &ConString, max_con: u32) -> Result<Self, DbError> {
@@ -52,7 +81,29 @@ impl Database {
             insert_admin_credentials(&db_con, con).await?;
         }
 
-        Ok(Self { db_con })
+        let session_age = chrono::Duration::from_std(con.session_age())
+            .map_err(|e| DbError::InitializationError(e.to_string()))?;
+
+        Ok(Self {
+            db_con,
+            pwd_hashing: con.pwd_hashing(),
+            session_age,
+            auth_provider: None,
+        })
+    }
+
+    /// Authenticates users against the provider before the password hashes in the database.
+    #[must_use]
+    pub fn with_auth_provider(mut self, auth_provider: Arc<dyn AuthProvider>) -> Self {
+        self.auth_provider = Some(auth_provider);
+        self
+    }
+
+    /// Sessions which were last seen before the returned date are expired
+    fn session_expiry_limit(&self) -> String {
+        (Utc::now() - self.session_age)
+            .format(DB_DATE_FORMAT)
+            .to_string()
     }
 }
 
@@ -128,13 +179,35 @@ impl DbProvider for Database {
     }
 
     async fn authenticate_user(&self, name: &str, pwd: &str) -> DbResult<User> {
+        if let Some(provider) = &self.auth_provider {
+            match provider.authenticate(name, pwd).await {
+                Ok(Some(external)) => {
+                    let name = external.sync(self).await?;
+                    return self.get_user(&name).await;
+                }
+                Ok(None) => {}
+                Err(DbError::PasswordMismatch) => return Err(DbError::PasswordMismatch),
+                // Users of the provider cannot log in with their random Kellnr password,
+                // so local users like the admin are not locked out if the provider is down.
+                Err(e) => warn!("Auth provider failed, falling back to Kellnr users: {e}"),
+            }
+        }
+
         let user = self.get_user(name).await?;
 
-        if hash_pwd(pwd, &user.salt) == user.pwd {
-            Ok(user)
-        } else {
-            Err(DbError::PasswordMismatch)
+        if user.is_service_account || !verify_pwd(pwd, &user.pwd, &user.salt).await {
+            return Err(DbError::PasswordMismatch);
         }
+
+        // Upgrade legacy hashes and hashes with outdated parameters,
+        // which is only possible while the plain password is known.
+        // The login must not fail because of the upgrade, it is retried on the next login.
+        if needs_rehash(&user.pwd, &self.pwd_hashing)
+            && let Err(e) = self.change_pwd(name, pwd).await
+        {
+            warn!("Failed to upgrade the password hash of user {name}: {e}");
+        }
+        Ok(user)
     }
 
     async fn increase_download_counter(
@@ -224,25 +297,62 @@ impl DbProvider for Database {
         }
     }
 
+    async fn get_session_id(&self, session_token: &str) -> DbResult<i64> {
+        session::Entity::find()
+            .filter(session::Column::Token.eq(session_token))
+            .one(&self.db_con)
+            .await?
+            .map(|s| s.id)
+            .ok_or(DbError::SessionNotFound)
+    }
+
     async fn validate_session(&self, session_token: &str) -> DbResult<(String, bool)> {
-        let u = user::Entity::find()
-            .join(JoinType::InnerJoin, user::Relation::Session.def())
+        let (s, u) = session::Entity::find()
+            .find_also_related(user::Entity)
             .filter(session::Column::Token.eq(session_token))
             .one(&self.db_con)
             .await?
             .ok_or(DbError::SessionNotFound)?;
+        let u = u.ok_or(DbError::SessionNotFound)?;
+
+        let last_seen = s.last_seen.clone().unwrap_or_else(|| s.created.clone());
+        if last_seen < self.session_expiry_limit() {
+            s.delete(&self.db_con).await?;
+            return Err(DbError::SessionNotFound);
+        }
+
+        let now = Utc::now();
+        if last_seen
+            < (now - SESSION_RENEWAL_INTERVAL)
+                .format(DB_DATE_FORMAT)
+                .to_string()
+        {
+            let mut s: session::ActiveModel = s.into();
+            s.last_seen = Set(Some(now.format(DB_DATE_FORMAT).to_string()));
+            s.update(&self.db_con).await?;
+        }
 
         Ok((u.name, u.is_admin))
     }
 
-    async fn add_session_token(&self, name: &str, session_token: &str) -> DbResult<()> {
+    async fn add_session_token(
+        &self,
+        name: &str,
+        session_token: &str,
+        user_agent: Option<String>,
+    ) -> DbResult<()> {
         let user = self.get_user(name).await?;
+        if user.is_service_account {
+            return Err(DbError::ServiceAccountLogin(name.to_owned()));
+        }
         let created = Utc::now().format(DB_DATE_FORMAT).to_string();
 
         let s = session::ActiveModel {
             token: Set(session_token.to_owned()),
-            created: Set(created),
+            created: Set(created.clone()),
             user_fk: Set(user.id as i64),
+            last_seen: Set(Some(created)),
+            user_agent: Set(user_agent),
             ..Default::default()
         };
 
@@ -250,6 +360,61 @@ impl DbProvider for Database {
         Ok(())
     }
 
+    async fn get_sessions(&self, user_name: &str, current_token: &str) -> DbResult<Vec<Session>> {
+        let sessions = session::Entity::find()
+            .join(JoinType::InnerJoin, session::Relation::User.def())
+            .filter(user::Column::Name.eq(user_name))
+            .filter(session::Column::LastSeen.gte(self.session_expiry_limit()))
+            .order_by_desc(session::Column::LastSeen)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|s| Session {
+                id: s.id,
+                last_seen: s.last_seen.unwrap_or_else(|| s.created.clone()),
+                created: s.created,
+                user_agent: s.user_agent,
+                current: s.token == current_token,
+            })
+            .collect())
+    }
+
+    async fn delete_session(&self, user_name: &str, id: i64) -> DbResult<()> {
+        let s = session::Entity::find_by_id(id)
+            .join(JoinType::InnerJoin, session::Relation::User.def())
+            .filter(user::Column::Name.eq(user_name))
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::SessionNotFound)?;
+
+        s.delete(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, user_name: &str) -> DbResult<u64> {
+        let user = self.get_user(user_name).await?;
+
+        let result = session::Entity::delete_many()
+            .filter(session::Column::UserFk.eq(user.id))
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    async fn cleanup_expired_sessions(&self) -> DbResult<u64> {
+        let result = session::Entity::delete_many()
+            .filter(
+                Cond::any()
+                    .add(session::Column::LastSeen.lt(self.session_expiry_limit()))
+                    .add(session::Column::LastSeen.is_null()),
+            )
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
     async fn add_crate_user(&self, crate_name: &NormalizedName, user: &str) -> DbResult<()> {
         let user_fk = user::Entity::find()
             .filter(user::Column::Name.eq(user))
@@ -482,6 +647,8 @@ impl DbProvider for Database {
                 salt: u.salt,
                 is_admin: u.is_admin,
                 is_read_only: u.is_read_only,
+                totp_enabled: u.totp_enabled,
+                is_service_account: u.is_service_account,
             })
             .collect())
     }
@@ -502,6 +669,8 @@ impl DbProvider for Database {
                 salt: u.salt,
                 is_admin: u.is_admin,
                 is_read_only: u.is_read_only,
+                totp_enabled: u.totp_enabled,
+                is_service_account: u.is_service_account,
             })
             .collect())
     }
@@ -522,6 +691,22 @@ impl DbProvider for Database {
             .collect())
     }
 
+    async fn get_user_groups(&self, user: &str) -> DbResult<Vec<Group>> {
+        let g = group::Entity::find()
+            .join(JoinType::InnerJoin, group::Relation::GroupUser.def())
+            .join(JoinType::InnerJoin, group_user::Relation::User.def())
+            .filter(user::Column::Name.eq(user))
+            .all(&self.db_con)
+            .await?;
+
+        Ok(g.into_iter()
+            .map(|g| Group {
+                id: g.id as i32,
+                name: g.name,
+            })
+            .collect())
+    }
+
     async fn get_group_users(&self, group_name: &str) -> DbResult<Vec<User>> {
         let u = user::Entity::find()
             .join(JoinType::InnerJoin, user::Relation::GroupUser.def())
@@ -538,6 +723,8 @@ impl DbProvider for Database {
                 salt: u.salt,
                 is_admin: u.is_admin,
                 is_read_only: u.is_read_only,
+                totp_enabled: u.totp_enabled,
+                is_service_account: u.is_service_account,
             })
             .collect())
     }
@@ -584,13 +771,19 @@ impl DbProvider for Database {
             .await?
             .ok_or_else(|| DbError::GroupNotFound(group_name.to_owned()))?;
 
+        // Only admins manage the service accounts of a deleted group
+        user::Entity::update_many()
+            .col_expr(user::Column::OwnerGroupFk, Expr::value(Option::<i64>::None))
+            .filter(user::Column::OwnerGroupFk.eq(g.id))
+            .exec(&self.db_con)
+            .await?;
+
         g.delete(&self.db_con).await?;
         Ok(())
     }
 
     async fn change_pwd(&self, user_name: &str, new_pwd: &str) -> DbResult<()> {
-        let salt = generate_salt();
-        let hashed = hash_pwd(new_pwd, &salt);
+        let hashed = hash_pwd(new_pwd, &self.pwd_hashing).await?;
 
         let mut u: user::ActiveModel = user::Entity::find()
             .filter(user::Column::Name.eq(user_name))
@@ -600,7 +793,8 @@ impl DbProvider for Database {
             .into();
 
         u.pwd = Set(hashed.clone());
-        u.salt = Set(salt);
+        // The salt is part of the hash
+        u.salt = Set(String::new());
 
         u.update(&self.db_con).await?;
         Ok(())
@@ -620,6 +814,236 @@ impl DbProvider for Database {
         Ok(())
     }
 
+    async fn change_admin_state(&self, user_name: &str, state: bool) -> DbResult<()> {
+        let mut u: user::ActiveModel = user::Entity::find()
+            .filter(user::Column::Name.eq(user_name))
+            .one(&self.db_con)
+            .await?
+            .ok_or_else(|| DbError::UserNotFound(user_name.to_owned()))?
+            .into();
+
+        u.is_admin = Set(state);
+
+        u.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn get_external_identity_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> DbResult<Option<String>> {
+        let user = external_identity::Entity::find()
+            .filter(external_identity::Column::Issuer.eq(issuer))
+            .filter(external_identity::Column::Subject.eq(subject))
+            .find_also_related(user::Entity)
+            .one(&self.db_con)
+            .await?
+            .and_then(|(_, u)| u);
+        Ok(user.map(|u| u.name))
+    }
+
+    async fn get_external_identities(&self, user_name: &str) -> DbResult<Vec<ExternalIdentity>> {
+        let user = self.get_user(user_name).await?;
+
+        let identities = external_identity::Entity::find()
+            .filter(external_identity::Column::UserFk.eq(user.id))
+            .order_by_asc(external_identity::Column::Id)
+            .all(&self.db_con)
+            .await?;
+        Ok(identities
+            .into_iter()
+            .map(|i| ExternalIdentity {
+                issuer: i.issuer,
+                subject: i.subject,
+            })
+            .collect())
+    }
+
+    async fn add_external_identity(
+        &self,
+        user_name: &str,
+        identity: &ExternalIdentity,
+    ) -> DbResult<()> {
+        let user = self.get_user(user_name).await?;
+
+        external_identity::ActiveModel {
+            user_fk: Set(i64::from(user.id)),
+            issuer: Set(identity.issuer.clone()),
+            subject: Set(identity.subject.clone()),
+            ..Default::default()
+        }
+        .insert(&self.db_con)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_external_identity(
+        &self,
+        user_name: &str,
+        identity: &ExternalIdentity,
+    ) -> DbResult<()> {
+        let user = self.get_user(user_name).await?;
+
+        let result = external_identity::Entity::delete_many()
+            .filter(external_identity::Column::UserFk.eq(user.id))
+            .filter(external_identity::Column::Issuer.eq(&identity.issuer))
+            .filter(external_identity::Column::Subject.eq(&identity.subject))
+            .exec(&self.db_con)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DbError::ExternalIdentityNotFound(format!(
+                "{}/{}",
+                identity.issuer, identity.subject
+            )));
+        }
+        Ok(())
+    }
+
+    async fn get_totp_secret(&self, user_name: &str) -> DbResult<Option<String>> {
+        let u = user::Entity::find()
+            .filter(user::Column::Name.eq(user_name))
+            .one(&self.db_con)
+            .await?
+            .ok_or_else(|| DbError::UserNotFound(user_name.to_owned()))?;
+
+        Ok(u.totp_secret)
+    }
+
+    async fn set_totp_secret(&self, user_name: &str, secret: &str) -> DbResult<()> {
+        let mut u: user::ActiveModel = user::Entity::find()
+            .filter(user::Column::Name.eq(user_name))
+            .one(&self.db_con)
+            .await?
+            .ok_or_else(|| DbError::UserNotFound(user_name.to_owned()))?
+            .into();
+
+        u.totp_secret = Set(Some(secret.to_owned()));
+        u.totp_enabled = Set(false);
+        u.totp_last_step = Set(None);
+
+        u.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn enable_totp(&self, user_name: &str, recovery_codes: &[String]) -> DbResult<()> {
+        let user = self.get_user(user_name).await?;
+        let txn = self.db_con.begin().await?;
+
+        user::Entity::update_many()
+            .col_expr(user::Column::TotpEnabled, Expr::value(true))
+            .filter(user::Column::Id.eq(user.id))
+            .exec(&txn)
+            .await?;
+        recovery_code::Entity::delete_many()
+            .filter(recovery_code::Column::UserFk.eq(user.id))
+            .exec(&txn)
+            .await?;
+        for code in recovery_codes {
+            recovery_code::ActiveModel {
+                user_fk: Set(i64::from(user.id)),
+                code: Set(hash_token(code)),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_name: &str) -> DbResult<()> {
+        let user = self.get_user(user_name).await?;
+        let txn = self.db_con.begin().await?;
+
+        user::Entity::update_many()
+            .col_expr(user::Column::TotpEnabled, Expr::value(false))
+            .col_expr(
+                user::Column::TotpSecret,
+                Expr::value(Option::<String>::None),
+            )
+            .col_expr(user::Column::TotpLastStep, Expr::value(Option::<i64>::None))
+            .filter(user::Column::Id.eq(user.id))
+            .exec(&txn)
+            .await?;
+        recovery_code::Entity::delete_many()
+            .filter(recovery_code::Column::UserFk.eq(user.id))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn use_recovery_code(&self, user_name: &str, code: &str) -> DbResult<bool> {
+        let user = self.get_user(user_name).await?;
+
+        let result = recovery_code::Entity::delete_many()
+            .filter(recovery_code::Column::UserFk.eq(user.id))
+            .filter(recovery_code::Column::Code.eq(hash_token(code)))
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    async fn get_totp_failures(&self, user_name: &str, since: &DateTime<Utc>) -> DbResult<i32> {
+        let u = user::Entity::find()
+            .filter(user::Column::Name.eq(user_name))
+            .one(&self.db_con)
+            .await?
+            .ok_or_else(|| DbError::UserNotFound(user_name.to_owned()))?;
+
+        let since = since.format(DB_DATE_FORMAT).to_string();
+        match u.totp_last_failure {
+            Some(last_failure) if last_failure >= since => Ok(u.totp_failed_attempts),
+            _ => Ok(0),
+        }
+    }
+
+    async fn add_totp_failure(&self, user_name: &str, since: &DateTime<Utc>) -> DbResult<i32> {
+        let failures = self.get_totp_failures(user_name, since).await? + 1;
+
+        user::Entity::update_many()
+            .col_expr(user::Column::TotpFailedAttempts, Expr::value(failures))
+            .col_expr(
+                user::Column::TotpLastFailure,
+                Expr::value(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            )
+            .filter(user::Column::Name.eq(user_name))
+            .exec(&self.db_con)
+            .await?;
+        Ok(failures)
+    }
+
+    async fn reset_totp_failures(&self, user_name: &str) -> DbResult<()> {
+        user::Entity::update_many()
+            .col_expr(user::Column::TotpFailedAttempts, Expr::value(0))
+            .col_expr(
+                user::Column::TotpLastFailure,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(user::Column::Name.eq(user_name))
+            .exec(&self.db_con)
+            .await?;
+        Ok(())
+    }
+
+    async fn use_totp_step(&self, user_name: &str, step: i64) -> DbResult<bool> {
+        // A single update, such that concurrent logins cannot use the same code
+        let result = user::Entity::update_many()
+            .col_expr(user::Column::TotpLastStep, Expr::value(step))
+            .filter(user::Column::Name.eq(user_name))
+            .filter(
+                Cond::any()
+                    .add(user::Column::TotpLastStep.is_null())
+                    .add(user::Column::TotpLastStep.lt(step)),
+            )
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
     async fn crate_version_exists(&self, crate_id: i64, version: &str) -> DbResult<bool> {
         let cm = crate_meta::Entity::find()
             .filter(
@@ -663,8 +1087,27 @@ impl DbProvider for Database {
         Ok(())
     }
 
-    async fn add_auth_token(&self, name: &str, token: &str, user: &str) -> DbResult<()> {
+    async fn add_auth_token(
+        &self,
+        name: &str,
+        token: &str,
+        user: &str,
+        scopes: &TokenScopes,
+        expires: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
         let hashed_token = hash_token(token);
+        let endpoint_scopes = scopes
+            .endpoint_scopes
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| DbError::FailedToConvertToJson(e.to_string()))?;
+        let crate_scopes = scopes
+            .crate_scopes
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| DbError::FailedToConvertToJson(e.to_string()))?;
 
         let user = user::Entity::find()
             .filter(user::Column::Name.eq(user))
@@ -676,6 +1119,10 @@ impl DbProvider for Database {
             name: Set(name.to_owned()),
             token: Set(hashed_token.clone()),
             user_fk: Set(user.id),
+            endpoint_scopes: Set(endpoint_scopes),
+            crate_scopes: Set(crate_scopes),
+            created: Set(Some(Utc::now().format(DB_DATE_FORMAT).to_string())),
+            expires: Set(expires.map(|e| e.format(DB_DATE_FORMAT).to_string())),
             ..Default::default()
         };
 
@@ -684,24 +1131,92 @@ impl DbProvider for Database {
         Ok(())
     }
 
-    async fn get_user_from_token(&self, token: &str) -> DbResult<User> {
+    async fn get_user_from_token(&self, token: &str) -> DbResult<(User, AuthToken)> {
         let token = hash_token(token);
+        let now = Utc::now().format(DB_DATE_FORMAT).to_string();
 
-        let u = user::Entity::find()
-            .join(JoinType::InnerJoin, user::Relation::AuthToken.def())
+        let (at, u) = auth_token::Entity::find()
+            .find_also_related(user::Entity)
             .filter(Expr::col((AuthTokenIden::Table, AuthTokenIden::Token)).eq(token))
+            .filter(
+                Cond::any()
+                    .add(auth_token::Column::Expires.is_null())
+                    .add(auth_token::Column::Expires.gt(now)),
+            )
             .one(&self.db_con)
             .await?
             .ok_or(DbError::TokenNotFound)?;
+        let u = u.ok_or(DbError::TokenNotFound)?;
 
-        Ok(User {
+        let user = User {
             id: u.id as i32,
             name: u.name,
             pwd: u.pwd,
             salt: u.salt,
             is_admin: u.is_admin,
             is_read_only: u.is_read_only,
-        })
+            totp_enabled: u.totp_enabled,
+            is_service_account: u.is_service_account,
+        };
+        Ok((user, to_auth_token(at)?))
+    }
+
+    async fn update_auth_token_usage(
+        &self,
+        id: i32,
+        last_used: &DateTime<Utc>,
+        ip: Option<String>,
+    ) -> DbResult<()> {
+        let mut update = Query::update();
+        update
+            .table(AuthTokenIden::Table)
+            .value(
+                AuthTokenIden::LastUsed,
+                last_used.format(DB_DATE_FORMAT).to_string(),
+            )
+            .value(AuthTokenIden::FlaggedUnused, false)
+            .and_where(Expr::col(AuthTokenIden::Id).eq(id));
+        // Keep the last known IP, if the client IP is not available
+        if let Some(ip) = ip {
+            update.value(AuthTokenIden::LastUsedIp, ip);
+        }
+
+        let builder = self.db_con.get_database_backend();
+        self.db_con.execute(builder.build(&update)).await?;
+        Ok(())
+    }
+
+    async fn cleanup_unused_auth_tokens(
+        &self,
+        unused_since: &DateTime<Utc>,
+        delete: bool,
+    ) -> DbResult<u64> {
+        let unused_since = unused_since.format(DB_DATE_FORMAT).to_string();
+        // Tokens which were never used count from their creation
+        let unused = Cond::any()
+            .add(auth_token::Column::LastUsed.lt(unused_since.clone()))
+            .add(
+                Cond::all()
+                    .add(auth_token::Column::LastUsed.is_null())
+                    .add(auth_token::Column::Created.lt(unused_since)),
+            );
+
+        let affected = if delete {
+            auth_token::Entity::delete_many()
+                .filter(unused)
+                .exec(&self.db_con)
+                .await?
+                .rows_affected
+        } else {
+            auth_token::Entity::update_many()
+                .col_expr(auth_token::Column::FlaggedUnused, Expr::value(true))
+                .filter(unused)
+                .filter(auth_token::Column::FlaggedUnused.eq(false))
+                .exec(&self.db_con)
+                .await?
+                .rows_affected
+        };
+        Ok(affected)
     }
 
     async fn get_user(&self, name: &str) -> DbResult<User> {
@@ -718,6 +1233,8 @@ impl DbProvider for Database {
             salt: u.salt,
             is_admin: u.is_admin,
             is_read_only: u.is_read_only,
+            totp_enabled: u.totp_enabled,
+            is_service_account: u.is_service_account,
         })
     }
 
@@ -741,10 +1258,7 @@ impl DbProvider for Database {
             .all(&self.db_con)
             .await?;
 
-        Ok(at
-            .into_iter()
-            .map(|x| AuthToken::new(x.id as i32, x.name, x.token))
-            .collect())
+        at.into_iter().map(to_auth_token).collect()
     }
 
     async fn delete_auth_token(&self, id: i32) -> DbResult<()> {
@@ -830,16 +1344,15 @@ impl DbProvider for Database {
         &self,
         name: &str,
         pwd: &str,
-        salt: &str,
         is_admin: bool,
         is_read_only: bool,
     ) -> DbResult<()> {
-        let hashed_pwd = hash_pwd(pwd, salt);
+        let hashed_pwd = hash_pwd(pwd, &self.pwd_hashing).await?;
 
         let u = user::ActiveModel {
             name: Set(name.to_owned()),
             pwd: Set(hashed_pwd),
-            salt: Set(salt.to_owned()),
+            salt: Set(String::new()),
             is_admin: Set(is_admin),
             is_read_only: Set(is_read_only),
             ..Default::default()
@@ -849,21 +1362,148 @@ impl DbProvider for Database {
         Ok(())
     }
 
-    async fn add_group(&self, name: &str) -> DbResult<()> {
-        let g = group::ActiveModel {
+    async fn add_service_account(
+        &self,
+        name: &str,
+        group: Option<String>,
+        is_read_only: bool,
+    ) -> DbResult<()> {
+        let group_fk = match group {
+            Some(group) => Some(i64::from(self.get_group(&group).await?.id)),
+            None => None,
+        };
+
+        // The empty password hash never matches, login is prevented anyway
+        let u = user::ActiveModel {
             name: Set(name.to_owned()),
+            pwd: Set(String::new()),
+            salt: Set(String::new()),
+            is_admin: Set(false),
+            is_read_only: Set(is_read_only),
+            is_service_account: Set(true),
+            owner_group_fk: Set(group_fk),
             ..Default::default()
         };
 
-        g.insert(&self.db_con).await?;
+        u.insert(&self.db_con).await?;
         Ok(())
     }
 
-    async fn get_users(&self) -> DbResult<Vec<User>> {
-        let users = user::Entity::find()
-            .order_by_asc(user::Column::Name)
-            .all(&self.db_con)
-            .await?;
+    async fn get_service_account(&self, name: &str) -> DbResult<ServiceAccount> {
+        let (u, g) = user::Entity::find()
+            .find_also_related(group::Entity)
+            .filter(user::Column::Name.eq(name))
+            .filter(user::Column::IsServiceAccount.eq(true))
+            .one(&self.db_con)
+            .await?
+            .ok_or_else(|| DbError::UserNotFound(name.to_owned()))?;
+
+        Ok(ServiceAccount {
+            name: u.name,
+            group: g.map(|g| g.name),
+            is_read_only: u.is_read_only,
+        })
+    }
+
+    async fn get_service_accounts(&self) -> DbResult<Vec<ServiceAccount>> {
+        let accounts = user::Entity::find()
+            .find_also_related(group::Entity)
+            .filter(user::Column::IsServiceAccount.eq(true))
+            .order_by_asc(user::Column::Name)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(accounts
+            .into_iter()
+            .map(|(u, g)| ServiceAccount {
+                name: u.name,
+                group: g.map(|g| g.name),
+                is_read_only: u.is_read_only,
+            })
+            .collect())
+    }
+
+    async fn add_audit_entry(&self, event: &AuditEvent) -> DbResult<()> {
+        let to_text =
+            |details: &Option<serde_json::Value>| details.as_ref().map(ToString::to_string);
+        let entry = audit_log::ActiveModel {
+            created: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            actor: Set(event.actor.name.clone()),
+            actor_kind: Set(variant_name(&event.actor.kind)),
+            auth: Set(variant_name(&event.actor.auth)),
+            token_id: Set(event.actor.token_id),
+            session_id: Set(event.actor.session_id),
+            ip: Set(event.actor.ip.clone()),
+            action: Set(variant_name(&event.action)),
+            target: Set(event.target.clone()),
+            before: Set(to_text(&event.before)),
+            after: Set(to_text(&event.after)),
+            ..Default::default()
+        };
+
+        entry.insert(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<Vec<AuditEntry>> {
+        let entries = audit_log::Entity::find()
+            .filter(audit_filter(filter))
+            .order_by_desc(audit_log::Column::Id)
+            .limit(limit)
+            .offset(offset)
+            .all(&self.db_con)
+            .await?;
+
+        // Details which are no valid JSON are returned as string
+        let from_text = |details: Option<String>| {
+            details.map(|d| serde_json::from_str(&d).unwrap_or(serde_json::Value::String(d)))
+        };
+        Ok(entries
+            .into_iter()
+            .map(|e| AuditEntry {
+                id: e.id,
+                created: e.created,
+                actor: e.actor,
+                actor_kind: e.actor_kind,
+                auth: e.auth,
+                token_id: e.token_id,
+                session_id: e.session_id,
+                ip: e.ip,
+                action: e.action,
+                target: e.target,
+                before: from_text(e.before),
+                after: from_text(e.after),
+            })
+            .collect())
+    }
+
+    async fn count_audit_entries(&self, filter: &AuditFilter) -> DbResult<u64> {
+        Ok(audit_log::Entity::find()
+            .filter(audit_filter(filter))
+            .count(&self.db_con)
+            .await?)
+    }
+
+    async fn add_group(&self, name: &str) -> DbResult<()> {
+        let g = group::ActiveModel {
+            name: Set(name.to_owned()),
+            ..Default::default()
+        };
+
+        g.insert(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn get_users(&self) -> DbResult<Vec<User>> {
+        let users = user::Entity::find()
+            .order_by_asc(user::Column::Name)
+            .all(&self.db_con)
+            .await?;
 
         Ok(users
             .into_iter()
@@ -874,6 +1514,8 @@ impl DbProvider for Database {
                 salt: u.salt,
                 is_admin: u.is_admin,
                 is_read_only: u.is_read_only,
+                totp_enabled: u.totp_enabled,
+                is_service_account: u.is_service_account,
             })
             .collect())
     }
@@ -1006,6 +1648,26 @@ impl DbProvider for Database {
         Ok(krates)
     }
 
+    async fn get_crate_licenses(&self) -> DbResult<Vec<CrateLicense>> {
+        let licenses = crate_meta::Entity::find()
+            .find_also_related(krate::Entity)
+            .order_by_asc(crate_meta::Column::CrateFk)
+            .order_by_asc(crate_meta::Column::Id)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .filter_map(|(cm, k)| {
+                k.map(|k| CrateLicense {
+                    name: k.original_name,
+                    version: cm.version,
+                    license: cm.license,
+                })
+            })
+            .collect();
+
+        Ok(licenses)
+    }
+
     async fn add_doc_queue(
         &self,
         krate: &NormalizedName,
@@ -1220,6 +1882,73 @@ impl DbProvider for Database {
         Ok(result)
     }
 
+    async fn search_in_crate_name_paged(
+        &self,
+        contains: &str,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<(u64, Vec<CrateOverview>)> {
+        let stmt_filter = Query::select()
+            .from(CrateMetaIden::Table)
+            .inner_join(
+                CrateIden::Table,
+                Expr::col((CrateMetaIden::Table, CrateMetaIden::CrateFk))
+                    .equals((CrateIden::Table, CrateIden::Id)),
+            )
+            .and_where(Expr::col((CrateIden::Table, CrateIden::Name)).like(format!("%{contains}%")))
+            .and_where(
+                Expr::col((CrateMetaIden::Table, CrateMetaIden::Version))
+                    .equals((CrateIden::Table, CrateIden::MaxVersion)),
+            )
+            .to_owned();
+
+        let stmt_count = stmt_filter
+            .clone()
+            .expr_as(
+                Expr::col((CrateIden::Table, CrateIden::Id)).count(),
+                Alias::new("count"),
+            )
+            .to_owned();
+
+        #[derive(Debug, PartialEq, FromQueryResult)]
+        struct SelectResult {
+            count: Option<i64>,
+        }
+
+        let builder = self.db_con.get_database_backend();
+        let total = SelectResult::find_by_statement(builder.build(&stmt_count))
+            .one(&self.db_con)
+            .await?
+            .and_then(|r| r.count)
+            .ok_or(DbError::FailedToCountCrates)?;
+
+        let stmt = stmt_filter
+            .clone()
+            .expr_as(Expr::col(CrateIden::OriginalName), Alias::new("name"))
+            .expr_as(Expr::col(CrateIden::MaxVersion), Alias::new("version"))
+            .expr_as(Expr::col(CrateIden::LastUpdated), Alias::new("date"))
+            .expr_as(
+                Expr::col(CrateIden::TotalDownloads),
+                Alias::new("total_downloads"),
+            )
+            .expr_as(Expr::col(CrateIden::Description), Alias::new("description"))
+            .expr_as(
+                Expr::col(CrateMetaIden::Documentation),
+                Alias::new("documentation"),
+            )
+            .expr_as(Expr::cust("false"), Alias::new("is_cache"))
+            .order_by(CrateIden::OriginalName, Order::Asc)
+            .limit(limit)
+            .offset(offset)
+            .to_owned();
+
+        let crates = CrateOverview::find_by_statement(builder.build(&stmt))
+            .all(&self.db_con)
+            .await?;
+
+        Ok((total as u64, crates))
+    }
+
     async fn get_crate_overview_list(
         &self,
         limit: u64,
@@ -1635,6 +2364,13 @@ impl DbProvider for Database {
             .one(&self.db_con)
             .await?;
 
+        // Versions of a crate seen for the first time are not considered new, as
+        // they may be arbitrarily old. Later versions were published when they
+        // were seen, if crates.io does not provide the publish time.
+        let first_seen = krate
+            .is_some()
+            .then(|| Utc::now().format(DB_DATE_FORMAT).to_string());
+
         let krate = if let Some(krate) = krate {
             let mut krate: cratesio_crate::ActiveModel = krate.into();
             krate.e_tag = Set(etag.to_string());
@@ -1695,6 +2431,13 @@ impl DbProvider for Database {
                     links: Set(index.links.clone()),
                     v: Set(index.v.unwrap_or(1) as i32),
                     crates_io_fk: Set(krate.id),
+                    published: Set(index
+                        .pubtime
+                        .as_deref()
+                        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                        .map(|t| t.with_timezone(&Utc).format(DB_DATE_FORMAT).to_string())
+                        .or_else(|| first_seen.clone())),
+                    license: Set(None),
                 };
 
                 new_index.insert(&self.db_con).await?;
@@ -1737,6 +2480,437 @@ impl DbProvider for Database {
         Ok(msgs)
     }
 
+    async fn get_cratesio_checksum(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<Option<String>> {
+        let index = cratesio_index::Entity::find()
+            .inner_join(cratesio_crate::Entity)
+            .filter(cratesio_crate::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_index::Column::Vers.eq(version.to_string()))
+            .one(&self.db_con)
+            .await?;
+        Ok(index.map(|i| i.cksum))
+    }
+
+    async fn get_cratesio_crate_versions(
+        &self,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<Vec<CachedCrateVersion>> {
+        let versions = cratesio_index::Entity::find()
+            .find_also_related(cratesio_crate::Entity)
+            .order_by_asc(cratesio_index::Column::Id)
+            .limit(limit)
+            .offset(offset)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(versions
+            .into_iter()
+            .filter_map(|(index, krate)| {
+                krate.map(|krate| CachedCrateVersion {
+                    name: OriginalName::from_unchecked(krate.original_name),
+                    version: index.vers,
+                    cksum: index.cksum,
+                    license: index.license,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_quarantined_cratesio_versions(
+        &self,
+        crate_name: &NormalizedName,
+        published_after: &DateTime<Utc>,
+    ) -> DbResult<Vec<String>> {
+        if self.is_quarantine_exempt(crate_name).await? {
+            return Ok(vec![]);
+        }
+
+        let versions = cratesio_index::Entity::find()
+            .inner_join(cratesio_crate::Entity)
+            .filter(cratesio_crate::Column::Name.eq(crate_name.to_string()))
+            .filter(
+                cratesio_index::Column::Published
+                    .gt(published_after.format(DB_DATE_FORMAT).to_string()),
+            )
+            .all(&self.db_con)
+            .await?;
+        Ok(versions.into_iter().map(|i| i.vers).collect())
+    }
+
+    async fn is_quarantined_cratesio_version(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+        published_after: &DateTime<Utc>,
+    ) -> DbResult<Option<bool>> {
+        if self.is_quarantine_exempt(crate_name).await? {
+            return Ok(Some(false));
+        }
+
+        let index = cratesio_index::Entity::find()
+            .inner_join(cratesio_crate::Entity)
+            .filter(cratesio_crate::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_index::Column::Vers.eq(version.to_string()))
+            .one(&self.db_con)
+            .await?;
+        // Versions without publish time were seen with the crate for the first time
+        // and are not considered new
+        let published_after = published_after.format(DB_DATE_FORMAT).to_string();
+        Ok(index.map(|i| i.published.is_some_and(|p| p > published_after)))
+    }
+
+    async fn is_quarantine_exempt(&self, crate_name: &NormalizedName) -> DbResult<bool> {
+        let exempt = quarantine_exemption::Entity::find()
+            .filter(quarantine_exemption::Column::Name.eq(crate_name.to_string()))
+            .one(&self.db_con)
+            .await?;
+        Ok(exempt.is_some())
+    }
+
+    async fn get_quarantine_exemptions(&self) -> DbResult<Vec<QuarantineExemption>> {
+        let exemptions = quarantine_exemption::Entity::find()
+            .order_by_asc(quarantine_exemption::Column::Name)
+            .all(&self.db_con)
+            .await?;
+        Ok(exemptions
+            .into_iter()
+            .map(|e| QuarantineExemption {
+                name: e.name,
+                created: e.created,
+            })
+            .collect())
+    }
+
+    async fn add_quarantine_exemption(&self, crate_name: &NormalizedName) -> DbResult<()> {
+        let exists = quarantine_exemption::Entity::find()
+            .filter(quarantine_exemption::Column::Name.eq(crate_name.to_string()))
+            .one(&self.db_con)
+            .await?;
+        if exists.is_none() {
+            let exemption = quarantine_exemption::ActiveModel {
+                name: Set(crate_name.to_string()),
+                created: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+                ..Default::default()
+            };
+            exemption.insert(&self.db_con).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_quarantine_exemption(&self, crate_name: &NormalizedName) -> DbResult<()> {
+        let result = quarantine_exemption::Entity::delete_many()
+            .filter(quarantine_exemption::Column::Name.eq(crate_name.to_string()))
+            .exec(&self.db_con)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DbError::QuarantineExemptionNotFound(crate_name.to_string()));
+        }
+        Ok(())
+    }
+
+    async fn get_cratesio_licenses(
+        &self,
+        crate_name: &NormalizedName,
+    ) -> DbResult<BTreeMap<String, String>> {
+        let versions = cratesio_index::Entity::find()
+            .inner_join(cratesio_crate::Entity)
+            .filter(cratesio_crate::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_index::Column::License.is_not_null())
+            .all(&self.db_con)
+            .await?;
+        Ok(versions
+            .into_iter()
+            .filter_map(|i| i.license.map(|license| (i.vers, license)))
+            .collect())
+    }
+
+    async fn set_cratesio_licenses(
+        &self,
+        crate_name: &NormalizedName,
+        licenses: &BTreeMap<String, String>,
+    ) -> DbResult<()> {
+        let versions = cratesio_index::Entity::find()
+            .inner_join(cratesio_crate::Entity)
+            .filter(cratesio_crate::Column::Name.eq(crate_name.to_string()))
+            .all(&self.db_con)
+            .await?;
+        for index in versions {
+            let license = licenses.get(&index.vers);
+            if license.is_some() && license != index.license.as_ref() {
+                let mut index: cratesio_index::ActiveModel = index.into();
+                index.license = Set(license.cloned());
+                index.update(&self.db_con).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_proxy_policy_rules(&self) -> DbResult<Vec<ProxyPolicyRule>> {
+        proxy_policy_rule::Entity::find()
+            .order_by_asc(proxy_policy_rule::Column::Id)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(to_proxy_policy_rule)
+            .collect()
+    }
+
+    async fn add_proxy_policy_rule(
+        &self,
+        action: PolicyAction,
+        crate_pattern: &str,
+        version_req: Option<String>,
+        licenses: &[String],
+        group: Option<String>,
+        reason: &str,
+    ) -> DbResult<i64> {
+        let licenses = serde_json::to_string(licenses)
+            .map_err(|e| DbError::FailedToConvertToJson(e.to_string()))?;
+
+        let rule = proxy_policy_rule::ActiveModel {
+            action: Set(variant_name(&action)),
+            crate_pattern: Set(crate_pattern.to_owned()),
+            version_req: Set(version_req),
+            licenses: Set(licenses),
+            group: Set(group),
+            reason: Set(reason.to_owned()),
+            created: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            ..Default::default()
+        };
+
+        let rule = rule.insert(&self.db_con).await?;
+        Ok(rule.id)
+    }
+
+    async fn delete_proxy_policy_rule(&self, id: i64) -> DbResult<()> {
+        let r = proxy_policy_rule::Entity::find_by_id(id)
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::ProxyPolicyRuleNotFound(id))?;
+
+        r.delete(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn touch_cratesio_cached_file(
+        &self,
+        crate_name: &OriginalName,
+        version: &Version,
+        size: i64,
+    ) -> DbResult<()> {
+        let now = Utc::now().format(DB_DATE_FORMAT).to_string();
+        let existing = cratesio_cached_file::Entity::find()
+            .filter(cratesio_cached_file::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_cached_file::Column::Version.eq(version.to_string()))
+            .one(&self.db_con)
+            .await?;
+
+        if let Some(file) = existing {
+            let mut file: cratesio_cached_file::ActiveModel = file.into();
+            file.size = Set(size);
+            file.last_downloaded = Set(now);
+            file.update(&self.db_con).await?;
+        } else {
+            let file = cratesio_cached_file::ActiveModel {
+                name: Set(crate_name.to_string()),
+                version: Set(version.to_string()),
+                size: Set(size),
+                last_downloaded: Set(now),
+                ..Default::default()
+            };
+            file.insert(&self.db_con).await?;
+        }
+        Ok(())
+    }
+
+    async fn track_cratesio_cached_files(
+        &self,
+        files: &[(OriginalName, Version, i64)],
+    ) -> DbResult<u64> {
+        let tracked: BTreeSet<(String, String)> = cratesio_cached_file::Entity::find()
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(|f| (f.name, f.version))
+            .collect();
+        let now = Utc::now().format(DB_DATE_FORMAT).to_string();
+        let untracked: Vec<_> = files
+            .iter()
+            .filter(|(name, version, _)| {
+                !tracked.contains(&(name.to_string(), version.to_string()))
+            })
+            .map(|(name, version, size)| cratesio_cached_file::ActiveModel {
+                name: Set(name.to_string()),
+                version: Set(version.to_string()),
+                size: Set(*size),
+                last_downloaded: Set(now.clone()),
+                ..Default::default()
+            })
+            .collect();
+
+        let added = untracked.len() as u64;
+        // Inserted in batches, as the number of parameters of a statement is limited
+        for batch in untracked.chunks(1000) {
+            cratesio_cached_file::Entity::insert_many(batch.to_vec())
+                .exec(&self.db_con)
+                .await?;
+        }
+        Ok(added)
+    }
+
+    async fn get_cratesio_cached_files(
+        &self,
+        expired_before: Option<DateTime<Utc>>,
+    ) -> DbResult<Vec<CachedCratesIoFile>> {
+        let expired_before = expired_before.map(|d| d.format(DB_DATE_FORMAT).to_string());
+        let files = cratesio_cached_file::Entity::find()
+            .order_by_asc(cratesio_cached_file::Column::LastDownloaded)
+            .order_by_asc(cratesio_cached_file::Column::Id)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(files
+            .into_iter()
+            .map(|f| CachedCratesIoFile {
+                expired: expired_before
+                    .as_ref()
+                    .is_some_and(|before| f.last_downloaded < *before),
+                name: f.name,
+                version: f.version,
+                size: f.size,
+                last_downloaded: f.last_downloaded,
+            })
+            .collect())
+    }
+
+    async fn delete_cratesio_cached_file(
+        &self,
+        crate_name: &OriginalName,
+        version: &Version,
+    ) -> DbResult<()> {
+        cratesio_cached_file::Entity::delete_many()
+            .filter(cratesio_cached_file::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_cached_file::Column::Version.eq(version.to_string()))
+            .exec(&self.db_con)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_cratesio_pins(&self) -> DbResult<Vec<CratesIoPin>> {
+        let pins = cratesio_pin::Entity::find()
+            .order_by_asc(cratesio_pin::Column::Name)
+            .order_by_asc(cratesio_pin::Column::Version)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(pins
+            .into_iter()
+            .map(|p| CratesIoPin {
+                name: p.name,
+                version: p.version,
+                created: p.created,
+            })
+            .collect())
+    }
+
+    async fn add_cratesio_pin(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<()> {
+        let exists = cratesio_pin::Entity::find()
+            .filter(cratesio_pin::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_pin::Column::Version.eq(version.to_string()))
+            .one(&self.db_con)
+            .await?;
+        if exists.is_none() {
+            let pin = cratesio_pin::ActiveModel {
+                name: Set(crate_name.to_string()),
+                version: Set(version.to_string()),
+                created: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+                ..Default::default()
+            };
+            pin.insert(&self.db_con).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_cratesio_pin(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<()> {
+        let result = cratesio_pin::Entity::delete_many()
+            .filter(cratesio_pin::Column::Name.eq(crate_name.to_string()))
+            .filter(cratesio_pin::Column::Version.eq(version.to_string()))
+            .exec(&self.db_con)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DbError::CratesIoPinNotFound(format!(
+                "{crate_name}@{version}"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn add_cratesio_eviction_report(
+        &self,
+        started: &DateTime<Utc>,
+        evicted: &[String],
+        reclaimed_bytes: i64,
+        remaining_bytes: i64,
+    ) -> DbResult<CratesIoEvictionReport> {
+        let evicted_json = serde_json::to_string(evicted)
+            .map_err(|e| DbError::FailedToConvertToJson(e.to_string()))?;
+
+        let report = cratesio_eviction_report::ActiveModel {
+            started: Set(started.format(DB_DATE_FORMAT).to_string()),
+            finished: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            evicted: Set(evicted_json),
+            reclaimed_bytes: Set(reclaimed_bytes),
+            remaining_bytes: Set(remaining_bytes),
+            ..Default::default()
+        };
+
+        let report = report.insert(&self.db_con).await?;
+        Ok(CratesIoEvictionReport {
+            id: report.id,
+            started: report.started,
+            finished: report.finished,
+            evicted: evicted.to_vec(),
+            reclaimed_bytes: report.reclaimed_bytes,
+            remaining_bytes: report.remaining_bytes,
+        })
+    }
+
+    async fn get_cratesio_eviction_reports(
+        &self,
+        limit: u64,
+    ) -> DbResult<Vec<CratesIoEvictionReport>> {
+        cratesio_eviction_report::Entity::find()
+            .order_by_desc(cratesio_eviction_report::Column::Id)
+            .limit(limit)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(|r| {
+                Ok(CratesIoEvictionReport {
+                    id: r.id,
+                    started: r.started,
+                    finished: r.finished,
+                    evicted: serde_json::from_str(&r.evicted)
+                        .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))?,
+                    reclaimed_bytes: r.reclaimed_bytes,
+                    remaining_bytes: r.remaining_bytes,
+                })
+            })
+            .collect()
+    }
+
     async fn unyank_crate(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()> {
         let ci = crate_index::Entity::find()
             .filter(crate_index::Column::Name.eq(crate_name.to_string()))
@@ -1776,6 +2950,441 @@ impl DbProvider for Database {
 
         Ok(())
     }
+
+    async fn add_trusted_publisher(
+        &self,
+        crate_name: &NormalizedName,
+        issuer: &str,
+        audience: &str,
+        jwks_url: &str,
+        claims: &BTreeMap<String, String>,
+    ) -> DbResult<i32> {
+        let crate_id = self
+            .get_crate_id(crate_name)
+            .await?
+            .ok_or_else(|| DbError::CrateNotFound(crate_name.to_string()))?;
+        let claims = serde_json::to_string(claims)
+            .map_err(|e| DbError::FailedToConvertToJson(e.to_string()))?;
+
+        let tp = trusted_publisher::ActiveModel {
+            crate_fk: Set(crate_id),
+            issuer: Set(issuer.to_owned()),
+            audience: Set(audience.to_owned()),
+            jwks_url: Set(jwks_url.to_owned()),
+            claims: Set(claims),
+            ..Default::default()
+        };
+
+        let tp = tp.insert(&self.db_con).await?;
+        i32::try_from(tp.id).map_err(|_| DbError::TrustedPublisherIdOutOfRange(tp.id))
+    }
+
+    async fn get_trusted_publishers(
+        &self,
+        crate_name: &NormalizedName,
+    ) -> DbResult<Vec<TrustedPublisher>> {
+        let tp = trusted_publisher::Entity::find()
+            .find_also_related(krate::Entity)
+            .filter(krate::Column::Name.eq(crate_name.to_string()))
+            .order_by_asc(trusted_publisher::Column::Id)
+            .all(&self.db_con)
+            .await?;
+
+        tp.into_iter().map(to_trusted_publisher).collect()
+    }
+
+    async fn get_trusted_publishers_by_issuer(
+        &self,
+        issuer: &str,
+    ) -> DbResult<Vec<TrustedPublisher>> {
+        let tp = trusted_publisher::Entity::find()
+            .find_also_related(krate::Entity)
+            .filter(trusted_publisher::Column::Issuer.eq(issuer))
+            .order_by_asc(trusted_publisher::Column::Id)
+            .all(&self.db_con)
+            .await?;
+
+        tp.into_iter().map(to_trusted_publisher).collect()
+    }
+
+    async fn delete_trusted_publisher(&self, crate_name: &NormalizedName, id: i32) -> DbResult<()> {
+        let tp = trusted_publisher::Entity::find_by_id(id as i64)
+            .join(
+                JoinType::InnerJoin,
+                trusted_publisher::Relation::Krate.def(),
+            )
+            .filter(krate::Column::Name.eq(crate_name.to_string()))
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::TrustedPublisherNotFound(id))?;
+
+        tp.delete(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn add_trusted_publish_token(
+        &self,
+        token: &str,
+        publisher_ids: &[i32],
+        expires: &DateTime<Utc>,
+    ) -> DbResult<()> {
+        let hashed_token = hash_token(token);
+        let now = Utc::now().format(DB_DATE_FORMAT).to_string();
+        let expires = expires.format(DB_DATE_FORMAT).to_string();
+
+        let txn = self.db_con.begin().await?;
+
+        // Clean up tokens which expired in the meantime
+        trusted_publish_token::Entity::delete_many()
+            .filter(trusted_publish_token::Column::Expires.lte(now))
+            .exec(&txn)
+            .await?;
+
+        for id in publisher_ids {
+            let tpt = trusted_publish_token::ActiveModel {
+                token: Set(hashed_token.clone()),
+                trusted_publisher_fk: Set(*id as i64),
+                expires: Set(expires.clone()),
+                ..Default::default()
+            };
+            tpt.insert(&txn).await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn get_trusted_publish_crates(&self, token: &str) -> DbResult<Vec<NormalizedName>> {
+        let hashed_token = hash_token(token);
+        let now = Utc::now().format(DB_DATE_FORMAT).to_string();
+
+        let crates: Vec<NormalizedName> = krate::Entity::find()
+            .join(JoinType::InnerJoin, krate::Relation::TrustedPublisher.def())
+            .join(
+                JoinType::InnerJoin,
+                trusted_publisher::Relation::TrustedPublishToken.def(),
+            )
+            .filter(trusted_publish_token::Column::Token.eq(hashed_token))
+            .filter(trusted_publish_token::Column::Expires.gt(now))
+            .order_by_asc(krate::Column::Name)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(|k| NormalizedName::from_unchecked(k.name))
+            .collect();
+
+        if crates.is_empty() {
+            Err(DbError::TokenNotFound)
+        } else {
+            Ok(crates)
+        }
+    }
+
+    async fn delete_trusted_publish_token(&self, token: &str) -> DbResult<()> {
+        trusted_publish_token::Entity::delete_many()
+            .filter(trusted_publish_token::Column::Token.eq(hash_token(token)))
+            .exec(&self.db_con)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        crate_pattern: &str,
+    ) -> DbResult<i64> {
+        let events = serde_json::to_string(events)
+            .map_err(|e| DbError::FailedToConvertToJson(e.to_string()))?;
+
+        let w = webhook::ActiveModel {
+            url: Set(url.to_owned()),
+            secret: Set(secret.to_owned()),
+            events: Set(events),
+            crate_pattern: Set(crate_pattern.to_owned()),
+            created: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            ..Default::default()
+        };
+
+        let w = w.insert(&self.db_con).await?;
+        Ok(w.id)
+    }
+
+    async fn get_webhooks(&self) -> DbResult<Vec<Webhook>> {
+        webhook::Entity::find()
+            .order_by_asc(webhook::Column::Id)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(to_webhook)
+            .collect()
+    }
+
+    async fn delete_webhook(&self, id: i64) -> DbResult<()> {
+        let w = webhook::Entity::find_by_id(id)
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::WebhookNotFound(id))?;
+
+        w.delete(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn enqueue_webhook_event(
+        &self,
+        event: WebhookEvent,
+        crate_name: &NormalizedName,
+        payload: &str,
+    ) -> DbResult<u64> {
+        let now = Utc::now().format(DB_DATE_FORMAT).to_string();
+        let mut count = 0;
+
+        for w in self.get_webhooks().await? {
+            if !w.events.contains(&event) || !wildcard_match(&w.crate_pattern, crate_name) {
+                continue;
+            }
+            let delivery = webhook_delivery::ActiveModel {
+                webhook_fk: Set(w.id),
+                event: Set(variant_name(&event)),
+                payload: Set(payload.to_owned()),
+                status: Set(variant_name(&DeliveryStatus::Pending)),
+                attempts: Set(0),
+                next_attempt: Set(Some(now.clone())),
+                created: Set(now.clone()),
+                ..Default::default()
+            };
+            delivery.insert(&self.db_con).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn get_due_webhook_deliveries(
+        &self,
+        now: &DateTime<Utc>,
+        limit: u64,
+    ) -> DbResult<Vec<PendingDelivery>> {
+        let deliveries = webhook_delivery::Entity::find()
+            .find_also_related(webhook::Entity)
+            .filter(webhook_delivery::Column::Status.eq(variant_name(&DeliveryStatus::Pending)))
+            .filter(
+                webhook_delivery::Column::NextAttempt.lte(now.format(DB_DATE_FORMAT).to_string()),
+            )
+            .order_by_asc(webhook_delivery::Column::Id)
+            .limit(limit)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(deliveries
+            .into_iter()
+            .filter_map(|(d, w)| {
+                w.map(|w| PendingDelivery {
+                    id: d.id,
+                    url: w.url,
+                    secret: w.secret,
+                    event: d.event,
+                    payload: d.payload,
+                    attempts: u32::try_from(d.attempts).unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    async fn update_webhook_delivery(&self, id: i64, attempt: &DeliveryAttempt) -> DbResult<()> {
+        let d = webhook_delivery::Entity::find_by_id(id)
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::WebhookDeliveryNotFound(id))?;
+
+        let attempts = d.attempts + 1;
+        let mut d: webhook_delivery::ActiveModel = d.into();
+        d.status = Set(variant_name(&attempt.status));
+        d.attempts = Set(attempts);
+        d.last_attempt = Set(Some(Utc::now().format(DB_DATE_FORMAT).to_string()));
+        d.next_attempt = Set(attempt
+            .next_attempt
+            .map(|next| next.format(DB_DATE_FORMAT).to_string()));
+        d.response_status = Set(attempt.response_status.map(i32::from));
+        d.error = Set(attempt.error.clone());
+        d.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn retry_webhook_delivery(&self, id: i64) -> DbResult<()> {
+        let d = webhook_delivery::Entity::find_by_id(id)
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::WebhookDeliveryNotFound(id))?;
+
+        // A manual retry starts over with all attempts
+        let mut d: webhook_delivery::ActiveModel = d.into();
+        d.status = Set(variant_name(&DeliveryStatus::Pending));
+        d.attempts = Set(0);
+        d.next_attempt = Set(Some(Utc::now().format(DB_DATE_FORMAT).to_string()));
+        d.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<Vec<WebhookDelivery>> {
+        let mut query = webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::WebhookFk.eq(webhook_id));
+        if let Some(status) = status {
+            query = query.filter(webhook_delivery::Column::Status.eq(variant_name(&status)));
+        }
+        let deliveries = query
+            .order_by_desc(webhook_delivery::Column::Id)
+            .limit(limit)
+            .offset(offset)
+            .all(&self.db_con)
+            .await?;
+
+        Ok(deliveries.into_iter().map(to_webhook_delivery).collect())
+    }
+}
+
+fn to_trusted_publisher(
+    (tp, k): (trusted_publisher::Model, Option<krate::Model>),
+) -> DbResult<TrustedPublisher> {
+    let claims = serde_json::from_str(&tp.claims)
+        .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))?;
+
+    Ok(TrustedPublisher {
+        id: i32::try_from(tp.id).map_err(|_| DbError::TrustedPublisherIdOutOfRange(tp.id))?,
+        // The crate always exists, as the foreign key is not nullable
+        crate_name: k.map(|k| k.original_name).unwrap_or_default(),
+        issuer: tp.issuer,
+        audience: tp.audience,
+        jwks_url: tp.jwks_url,
+        claims,
+    })
+}
+
+fn audit_filter(filter: &AuditFilter) -> Cond {
+    let format = |time: &DateTime<Utc>| time.format(DB_DATE_FORMAT).to_string();
+    Cond::all()
+        .add_option(
+            filter
+                .actor
+                .as_ref()
+                .map(|a| audit_log::Column::Actor.eq(a)),
+        )
+        .add_option(
+            filter
+                .actor_kind
+                .as_ref()
+                .map(|k| audit_log::Column::ActorKind.eq(k)),
+        )
+        .add_option(
+            filter
+                .action
+                .as_ref()
+                .map(|a| audit_log::Column::Action.eq(a)),
+        )
+        .add_option(
+            filter
+                .target
+                .as_ref()
+                .map(|t| audit_log::Column::Target.eq(t)),
+        )
+        .add_option(
+            filter
+                .from
+                .as_ref()
+                .map(|from| audit_log::Column::Created.gte(format(from))),
+        )
+        .add_option(
+            filter
+                .to
+                .as_ref()
+                .map(|to| audit_log::Column::Created.lt(format(to))),
+        )
+        .add_option(filter.before_id.map(|id| audit_log::Column::Id.lt(id)))
+}
+
+fn to_webhook(w: webhook::Model) -> DbResult<Webhook> {
+    let events = serde_json::from_str(&w.events)
+        .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))?;
+
+    Ok(Webhook {
+        id: w.id,
+        url: w.url,
+        secret: w.secret,
+        events,
+        crate_pattern: w.crate_pattern,
+        created: w.created,
+    })
+}
+
+fn to_proxy_policy_rule(r: proxy_policy_rule::Model) -> DbResult<ProxyPolicyRule> {
+    let licenses = serde_json::from_str(&r.licenses)
+        .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))?;
+    let action = match r.action.as_str() {
+        "allow" => PolicyAction::Allow,
+        _ => PolicyAction::Deny,
+    };
+
+    Ok(ProxyPolicyRule {
+        id: r.id,
+        action,
+        crate_pattern: r.crate_pattern,
+        version_req: r.version_req,
+        licenses,
+        group: r.group,
+        reason: r.reason,
+        created: r.created,
+    })
+}
+
+fn to_webhook_delivery(d: webhook_delivery::Model) -> WebhookDelivery {
+    let status = match d.status.as_str() {
+        "delivered" => DeliveryStatus::Delivered,
+        "failed" => DeliveryStatus::Failed,
+        _ => DeliveryStatus::Pending,
+    };
+    let payload = serde_json::from_str(&d.payload).unwrap_or(serde_json::Value::String(d.payload));
+
+    WebhookDelivery {
+        id: d.id,
+        webhook_id: d.webhook_fk,
+        event: d.event,
+        payload,
+        status,
+        attempts: u32::try_from(d.attempts).unwrap_or_default(),
+        next_attempt: d.next_attempt,
+        last_attempt: d.last_attempt,
+        response_status: d.response_status.and_then(|s| u16::try_from(s).ok()),
+        error: d.error,
+        created: d.created,
+    }
+}
+
+fn to_auth_token(at: auth_token::Model) -> DbResult<AuthToken> {
+    fn from_json<T: serde::de::DeserializeOwned>(json: Option<&str>) -> DbResult<Option<T>> {
+        json.map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))
+    }
+
+    let scopes = TokenScopes {
+        endpoint_scopes: from_json(at.endpoint_scopes.as_deref())?,
+        crate_scopes: from_json(at.crate_scopes.as_deref())?,
+    };
+
+    Ok(AuthToken {
+        created: at.created.unwrap_or_default(),
+        expires: at.expires,
+        last_used: at.last_used,
+        last_used_ip: at.last_used_ip,
+        flagged_unused: at.flagged_unused,
+        ..AuthToken::new(at.id as i32, at.name, at.token, scopes)
+    })
 }
 
 // Db methods
@@ -1808,12 +3417,12 @@ async fn insert_admin_credentials<C: ConnectionTrait>(
     db_con: &C,
     con_string: &ConString,
 ) -> DbResult<()> {
-    let hashed_pwd = hash_pwd(&con_string.admin_pwd(), &con_string.salt());
+    let hashed_pwd = hash_pwd(&con_string.admin_pwd(), &con_string.pwd_hashing()).await?;
 
     let admin = user::ActiveModel {
         name: Set("admin".to_string()),
         pwd: Set(hashed_pwd),
-        salt: Set(con_string.salt()),
+        salt: Set(String::new()),
         is_admin: Set(true),
         is_read_only: Set(false),
         ..Default::default()
@@ -1826,6 +3435,7 @@ async fn insert_admin_credentials<C: ConnectionTrait>(
         name: Set("admin".to_string()),
         token: Set(auth_token),
         user_fk: Set(res.last_insert_id),
+        created: Set(Some(Utc::now().format(DB_DATE_FORMAT).to_string())),
         ..Default::default()
     };
     auth_token::Entity::insert(auth_token).exec(db_con).await?;
@@ -1916,7 +3526,7 @@ async fn add_crate_metadata<C: ConnectionTrait>(
 ) -> DbResult<()> {
     let cm = crate_meta::ActiveModel {
         id: ActiveValue::default(),
-        version: Set(pub_metadata.vers.to_string()),
+        version: Set(pub_metadata.vers.clone()),
         created: Set(created.to_string()),
         downloads: Set(0),
         crate_fk: Set(crate_id),
@@ -2119,6 +3729,7 @@ fn crate_index_model_to_index_metadata(
             links: ci.links,
             v: Some(ci.v as u32),
             features2: None,
+            pubtime: None,
         };
         index_metadata.push(cm);
     }
@@ -2155,14 +3766,15 @@ fn cratesio_index_model_to_index_metadata(
 
         let cm = IndexMetadata {
             name: ci.name,
-            vers: ci.vers.to_string(),
+            vers: ci.vers.clone(),
             deps,
-            cksum: ci.cksum.to_string(),
+            cksum: ci.cksum.clone(),
             features,
             features2,
             yanked: ci.yanked,
             links: ci.links.clone(),
             v: Some(ci.v as u32),
+            pubtime: None,
         };
         index_metadata.push(cm);
     }
@@ -2204,6 +3816,47 @@ pub mod test_utils {
 
     use super::*;
 
+    pub async fn test_set_session_last_seen(
+        db: &Database,
+        session_token: &str,
+        last_seen: &DateTime<Utc>,
+    ) -> DbResult<()> {
+        session::Entity::update_many()
+            .col_expr(
+                session::Column::LastSeen,
+                Expr::value(last_seen.format(DB_DATE_FORMAT).to_string()),
+            )
+            .filter(session::Column::Token.eq(session_token))
+            .exec(&db.db_con)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns a database on the same connection, which authenticates users with the provider.
+    pub fn test_with_auth_provider(db: &Database, provider: Arc<dyn AuthProvider>) -> Database {
+        Database::existing(db.db_con.clone(), db.pwd_hashing.clone(), db.session_age)
+            .with_auth_provider(provider)
+    }
+
+    /// Adds a user with a password hash from before Argon2id was introduced.
+    pub async fn test_add_legacy_user(
+        db: &Database,
+        name: &str,
+        pwd: &str,
+        salt: &str,
+    ) -> DbResult<()> {
+        let u = user::ActiveModel {
+            name: Set(name.to_owned()),
+            pwd: Set(sha256::digest(format!("{pwd}{salt}"))),
+            salt: Set(salt.to_owned()),
+            is_admin: Set(false),
+            is_read_only: Set(false),
+            ..Default::default()
+        };
+        u.insert(&db.db_con).await?;
+        Ok(())
+    }
+
     pub async fn test_add_cached_crate_with_downloads(
         db: &Database,
         name: &str,
@@ -2245,6 +3898,7 @@ pub mod test_utils {
             yanked: false,
             links: None,
             v: Some(1),
+            pubtime: None,
         }];
 
         db.add_cratesio_prefetch_data(
@@ -2274,7 +3928,7 @@ pub mod test_utils {
             .one(&db.db_con)
             .await?;
         if user.is_none() {
-            db.add_user(name, "pwd", "salt", false, false).await?;
+            db.add_user(name, "pwd", false, false).await?;
         }
 
         db.add_crate(&pm, "cksum", created, owner).await
@@ -2298,7 +3952,7 @@ pub mod test_utils {
             .one(&db.db_con)
             .await?;
         if user.is_none() {
-            db.add_user(name, "pwd", "salt", false, false).await?;
+            db.add_user(name, "pwd", false, false).await?;
         }
 
         db.add_crate(&pm, "cksum", created, owner).await?;
@@ -2330,7 +3984,7 @@ pub mod test_utils {
         downloads: Option<i64>,
     ) -> DbResult<()> {
         let cm = crate_meta::ActiveModel {
-            id: Default::default(),
+            id: ActiveValue::default(),
             version: Set(version.to_string()),
             created: Set(created.to_string()),
             downloads: Set(downloads.unwrap_or_default()),
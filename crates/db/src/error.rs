@@ -6,6 +6,10 @@ pub enum DbError {
     PostgresError(#[from] sea_orm::DbErr), // TODO find a good way to remove "postgres" as dependency here
     #[error("Passwords did not match")]
     PasswordMismatch,
+    #[error("Failed to hash password: {0}")]
+    PasswordHashingError(String),
+    #[error("Authentication provider failed: {0}")]
+    AuthProviderError(String),
     #[error("Failed to get parent directory for index")]
     NoIndexParentDirectory,
     #[error("Failed to create database directory")]
@@ -14,6 +18,8 @@ pub enum DbError {
     InitializationError(String),
     #[error("User not found: {0}")]
     UserNotFound(String),
+    #[error("Service account {0} cannot log in")]
+    ServiceAccountLogin(String),
     #[error("Group not found: {0}")]
     GroupNotFound(String),
     #[error("Owner not found: {0}")]
@@ -50,6 +56,24 @@ pub enum DbError {
     CrateIndexNotFound(String, String),
     #[error("Invalid crate name {0}")]
     InvalidCrateName(String),
+    #[error("External identity {0} not found")]
+    ExternalIdentityNotFound(String),
+    #[error("User {0} exists, but is not linked to the identity of the provider")]
+    ExternalIdentityNotLinked(String),
+    #[error("Trusted publisher {0} not found")]
+    TrustedPublisherNotFound(i32),
+    #[error("Trusted publisher id {0} is out of range")]
+    TrustedPublisherIdOutOfRange(i64),
+    #[error("Webhook {0} not found")]
+    WebhookNotFound(i64),
+    #[error("Webhook delivery {0} not found")]
+    WebhookDeliveryNotFound(i64),
+    #[error("Quarantine exemption for crate {0} not found")]
+    QuarantineExemptionNotFound(String),
+    #[error("Proxy policy rule {0} not found")]
+    ProxyPolicyRuleNotFound(i64),
+    #[error("Pin of crates.io crate {0} not found")]
+    CratesIoPinNotFound(String),
     #[error("Crates.io index data is missing for crate {0}")]
     MissingCratesIoIndexData(String),
 }
@@ -1,7 +1,12 @@
+mod audit;
+mod auth_provider;
 mod auth_token;
+mod cached_crate_version;
 mod con_string;
+mod crate_license;
 mod crate_meta;
 mod crate_summary;
+mod cratesio_cache;
 mod database;
 mod doc_queue_entry;
 pub mod error;
@@ -9,21 +14,42 @@ mod group;
 mod krate;
 pub mod password;
 pub mod provider;
+mod proxy_policy;
+mod quarantine_exemption;
+mod service_account;
+mod session;
 mod tables;
+mod trusted_publisher;
 mod user;
+mod webhook;
 
 // Re-exports
 pub use crate::database::{Database, test_utils};
-pub use auth_token::AuthToken;
+pub use audit::{
+    ActorKind, AuditAction, AuditActor, AuditEntry, AuditEvent, AuditFilter, AuthMethod,
+};
+pub use auth_provider::{AuthProvider, ExternalIdentity, ExternalUser};
+pub use auth_token::{AuthToken, EndpointScope, TokenScopes};
+pub use cached_crate_version::CachedCrateVersion;
 pub use con_string::AdminUser;
 pub use con_string::ConString;
 pub use con_string::PgConString;
 pub use con_string::SqliteConString;
+pub use crate_license::CrateLicense;
 pub use crate_meta::CrateMeta;
 pub use crate_summary::CrateSummary;
+pub use cratesio_cache::{CachedCratesIoFile, CratesIoEvictionReport, CratesIoPin};
 pub use doc_queue_entry::DocQueueEntry;
 pub use group::Group;
 pub use krate::Crate;
 pub use provider::DbProvider;
 pub use provider::mock;
+pub use proxy_policy::{PolicyAction, ProxyPolicyRule};
+pub use quarantine_exemption::QuarantineExemption;
+pub use service_account::ServiceAccount;
+pub use session::Session;
+pub use trusted_publisher::TrustedPublisher;
 pub use user::User;
+pub use webhook::{
+    DeliveryAttempt, DeliveryStatus, PendingDelivery, Webhook, WebhookDelivery, WebhookEvent,
+};
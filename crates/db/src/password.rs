@@ -1,43 +1,157 @@
-use common::util::generate_rand_string;
+use crate::error::DbError;
+use crate::provider::DbResult;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use settings::PasswordHashing;
 
-const SALT_LENGTH: usize = 10;
+/// Hashes the password with Argon2id. The returned PHC string contains
+/// the algorithm, its parameters and the salt. Hashing is slow by design,
+/// so it runs on the blocking thread pool.
+pub async fn hash_pwd(pwd: &str, hashing: &PasswordHashing) -> DbResult<String> {
+    let (pwd, hashing) = (pwd.to_owned(), hashing.clone());
+    tokio::task::spawn_blocking(move || hash_pwd_blocking(&pwd, &hashing))
+        .await
+        .map_err(|e| DbError::PasswordHashingError(e.to_string()))?
+}
+
+/// Verifies the password against an Argon2id PHC string or, for passwords
+/// which were not upgraded yet, against the SHA-256 hash of `pwd + salt`.
+/// Like the hashing, the verification runs on the blocking thread pool.
+pub async fn verify_pwd(pwd: &str, hash: &str, salt: &str) -> bool {
+    let (pwd, hash, salt) = (pwd.to_owned(), hash.to_owned(), salt.to_owned());
+    tokio::task::spawn_blocking(move || verify_pwd_blocking(&pwd, &hash, &salt))
+        .await
+        .unwrap_or(false)
+}
+
+fn hash_pwd_blocking(pwd: &str, hashing: &PasswordHashing) -> DbResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2(hashing)?
+        .hash_password(pwd.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| DbError::PasswordHashingError(e.to_string()))
+}
 
-pub fn hash_pwd(pwd: &str, salt: &str) -> String {
+fn verify_pwd_blocking(pwd: &str, hash: &str, salt: &str) -> bool {
+    match PasswordHash::new(hash) {
+        // The parameters of the hash are used, not the configured ones
+        Ok(hash) => Argon2::default()
+            .verify_password(pwd.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => legacy_hash_pwd(pwd, salt) == hash,
+    }
+}
+
+/// Legacy hashes and hashes with other parameters than the configured
+/// ones have to be replaced.
+pub fn needs_rehash(hash: &str, hashing: &PasswordHashing) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return true;
+    };
+    let Ok(params) = Params::try_from(&hash) else {
+        return true;
+    };
+
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || params.m_cost() != hashing.memory_kib
+        || params.t_cost() != hashing.iterations
+        || params.p_cost() != hashing.parallelism
+}
+
+/// Password hash used before Argon2id was introduced.
+fn legacy_hash_pwd(pwd: &str, salt: &str) -> String {
     let concat = format!("{pwd}{salt}");
     sha256::digest(concat)
 }
 
-pub fn hash_token(token: &str) -> String {
-    sha256::digest(token)
+fn argon2(hashing: &PasswordHashing) -> DbResult<Argon2<'static>> {
+    let params = Params::new(
+        hashing.memory_kib,
+        hashing.iterations,
+        hashing.parallelism,
+        None,
+    )
+    .map_err(|e| DbError::PasswordHashingError(e.to_string()))?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
 }
 
-pub fn generate_salt() -> String {
-    generate_rand_string(SALT_LENGTH)
+pub fn hash_token(token: &str) -> String {
+    sha256::digest(token)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn generate_salt_creates_random_string() {
-        let s1 = generate_salt();
-        let s2 = generate_salt();
-
-        assert_eq!(SALT_LENGTH, s1.len());
-        assert_eq!(SALT_LENGTH, s2.len());
-        assert_ne!(s1, s2);
+    fn fast_hashing() -> PasswordHashing {
+        PasswordHashing {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
     }
 
     #[test]
-    fn hash_pwd_computes_correct_hash() {
+    fn legacy_hash_pwd_computes_correct_hash() {
         let pwd = "admin";
         let salt = "C6udtgbngX";
-        let hash = hash_pwd(pwd, salt);
+        let hash = legacy_hash_pwd(pwd, salt);
 
         assert_eq!(
             hash,
             "5dcec54caf0f55652766f71c32a0eac6538e7faeeab9301f956a58b7dbad02fb"
         );
     }
+
+    #[test]
+    fn hash_pwd_creates_argon2id_phc_string() {
+        let hash = hash_pwd_blocking("admin", &fast_hashing()).unwrap();
+
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert_ne!(hash, hash_pwd_blocking("admin", &fast_hashing()).unwrap());
+    }
+
+    #[test]
+    fn verify_pwd_with_argon2id_hash() {
+        let hash = hash_pwd_blocking("admin", &fast_hashing()).unwrap();
+
+        assert!(verify_pwd_blocking("admin", &hash, ""));
+        assert!(!verify_pwd_blocking("wrong", &hash, ""));
+    }
+
+    #[test]
+    fn verify_pwd_with_legacy_hash() {
+        let hash = "5dcec54caf0f55652766f71c32a0eac6538e7faeeab9301f956a58b7dbad02fb";
+
+        assert!(verify_pwd_blocking("admin", hash, "C6udtgbngX"));
+        assert!(!verify_pwd_blocking("admin", hash, "other salt"));
+    }
+
+    #[test]
+    fn needs_rehash_for_legacy_hash_and_changed_parameters() {
+        let hashing = fast_hashing();
+        let hash = hash_pwd_blocking("admin", &hashing).unwrap();
+        let stronger = PasswordHashing {
+            iterations: 2,
+            ..fast_hashing()
+        };
+
+        assert!(!needs_rehash(&hash, &hashing));
+        assert!(needs_rehash(&hash, &stronger));
+        assert!(needs_rehash(
+            "5dcec54caf0f55652766f71c32a0eac6538e7faeeab9301f956a58b7dbad02fb",
+            &hashing
+        ));
+    }
+
+    #[test]
+    fn invalid_parameters_are_an_error() {
+        let hashing = PasswordHashing {
+            memory_kib: 0,
+            ..fast_hashing()
+        };
+
+        assert!(hash_pwd_blocking("admin", &hashing).is_err());
+    }
 }
@@ -1,4 +1,10 @@
-use crate::{AuthToken, CrateSummary, DocQueueEntry, Group, User, crate_meta, error::DbError};
+use crate::{
+    AuditEntry, AuditEvent, AuditFilter, AuthToken, CachedCrateVersion, CachedCratesIoFile,
+    CrateLicense, CrateSummary, CratesIoEvictionReport, CratesIoPin, DeliveryAttempt,
+    DeliveryStatus, DocQueueEntry, ExternalIdentity, Group, PendingDelivery, PolicyAction,
+    ProxyPolicyRule, QuarantineExemption, ServiceAccount, Session, TokenScopes, TrustedPublisher,
+    User, Webhook, WebhookDelivery, WebhookEvent, crate_meta, error::DbError,
+};
 use chrono::{DateTime, Utc};
 use common::crate_data::CrateData;
 use common::crate_overview::CrateOverview;
@@ -11,6 +17,7 @@ use common::publish_metadata::PublishMetadata;
 use common::version::Version;
 use crate_meta::CrateMeta;
 use sea_orm::prelude::async_trait::async_trait;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 pub type DbResult<T> = Result<T, DbError>;
@@ -35,8 +42,22 @@ pub trait DbProvider: Send + Sync {
         crate_name: &NormalizedName,
         crate_version: &Version,
     ) -> DbResult<()>;
+    /// Returns the user of the session, if the session was seen within the session age.
+    /// The session age is renewed on activity.
+    /// Id of the session, which is shown to the user instead of the token.
+    async fn get_session_id(&self, session_token: &str) -> DbResult<i64>;
     async fn validate_session(&self, session_token: &str) -> DbResult<(String, bool)>;
-    async fn add_session_token(&self, name: &str, session_token: &str) -> DbResult<()>;
+    async fn add_session_token(
+        &self,
+        name: &str,
+        session_token: &str,
+        user_agent: Option<String>,
+    ) -> DbResult<()>;
+    async fn get_sessions(&self, user_name: &str, current_token: &str) -> DbResult<Vec<Session>>;
+    async fn delete_session(&self, user_name: &str, id: i64) -> DbResult<()>;
+    async fn delete_user_sessions(&self, user_name: &str) -> DbResult<u64>;
+    /// Deletes all sessions which were not seen within the session age
+    async fn cleanup_expired_sessions(&self) -> DbResult<u64>;
     async fn add_crate_user(&self, crate_name: &NormalizedName, user: &str) -> DbResult<()>;
     async fn add_owner(&self, crate_name: &NormalizedName, owner: &str) -> DbResult<()>;
     async fn is_download_restricted(&self, crate_name: &NormalizedName) -> DbResult<bool>;
@@ -55,12 +76,74 @@ pub trait DbProvider: Send + Sync {
     async fn delete_user(&self, user_name: &str) -> DbResult<()>;
     async fn change_pwd(&self, user_name: &str, new_pwd: &str) -> DbResult<()>;
     async fn change_read_only_state(&self, user_name: &str, state: bool) -> DbResult<()>;
+    async fn change_admin_state(&self, user_name: &str, state: bool) -> DbResult<()>;
+    /// Returns the name of the user linked to the identity of an external provider.
+    async fn get_external_identity_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> DbResult<Option<String>>;
+    async fn get_external_identities(&self, user_name: &str) -> DbResult<Vec<ExternalIdentity>>;
+    /// Links the identity of an external provider to the user, such that
+    /// the provider can log in as the user.
+    async fn add_external_identity(
+        &self,
+        user_name: &str,
+        identity: &ExternalIdentity,
+    ) -> DbResult<()>;
+    async fn delete_external_identity(
+        &self,
+        user_name: &str,
+        identity: &ExternalIdentity,
+    ) -> DbResult<()>;
+    /// Returns the encrypted TOTP secret of the user, which is set before TOTP is enabled.
+    async fn get_totp_secret(&self, user_name: &str) -> DbResult<Option<String>>;
+    /// Sets the encrypted TOTP secret of the user. TOTP is not enabled until it is confirmed.
+    async fn set_totp_secret(&self, user_name: &str, secret: &str) -> DbResult<()>;
+    /// Enables TOTP and replaces the recovery codes of the user.
+    async fn enable_totp(&self, user_name: &str, recovery_codes: &[String]) -> DbResult<()>;
+    /// Disables TOTP and deletes the secret and the recovery codes of the user.
+    async fn disable_totp(&self, user_name: &str) -> DbResult<()>;
+    /// Deletes the recovery code, such that it can be used only once.
+    /// Returns `false` if the code does not exist.
+    async fn use_recovery_code(&self, user_name: &str, code: &str) -> DbResult<bool>;
+    /// Returns the number of consecutive wrong TOTP codes of the user,
+    /// if the last one was entered after `since`.
+    async fn get_totp_failures(&self, user_name: &str, since: &DateTime<Utc>) -> DbResult<i32>;
+    /// Counts a wrong TOTP code of the user. The count starts again, if the
+    /// last wrong code was entered before `since`. Returns the new count.
+    async fn add_totp_failure(&self, user_name: &str, since: &DateTime<Utc>) -> DbResult<i32>;
+    async fn reset_totp_failures(&self, user_name: &str) -> DbResult<()>;
+    /// Stores the time step of the last used TOTP code. Returns `false`, if a code
+    /// of the step or a later step was used already.
+    async fn use_totp_step(&self, user_name: &str, step: i64) -> DbResult<bool>;
     async fn crate_version_exists(&self, crate_id: i64, version: &str) -> DbResult<bool>;
     async fn get_max_version_from_id(&self, crate_id: i64) -> DbResult<Version>;
     async fn get_max_version_from_name(&self, crate_name: &NormalizedName) -> DbResult<Version>;
     async fn update_max_version(&self, crate_id: i64, version: &Version) -> DbResult<()>;
-    async fn add_auth_token(&self, name: &str, token: &str, user: &str) -> DbResult<()>;
-    async fn get_user_from_token(&self, token: &str) -> DbResult<User>;
+    async fn add_auth_token(
+        &self,
+        name: &str,
+        token: &str,
+        user: &str,
+        scopes: &TokenScopes,
+        expires: Option<DateTime<Utc>>,
+    ) -> DbResult<()>;
+    /// Expired tokens are not found
+    async fn get_user_from_token(&self, token: &str) -> DbResult<(User, AuthToken)>;
+    async fn update_auth_token_usage(
+        &self,
+        id: i32,
+        last_used: &DateTime<Utc>,
+        ip: Option<String>,
+    ) -> DbResult<()>;
+    /// Deletes or flags tokens which were not used since `unused_since`
+    /// and returns the number of affected tokens.
+    async fn cleanup_unused_auth_tokens(
+        &self,
+        unused_since: &DateTime<Utc>,
+        delete: bool,
+    ) -> DbResult<u64>;
     async fn get_user(&self, name: &str) -> DbResult<User>;
     async fn get_auth_tokens(&self, user_name: &str) -> DbResult<Vec<AuthToken>>;
     async fn delete_auth_token(&self, id: i32) -> DbResult<()>;
@@ -70,11 +153,29 @@ pub trait DbProvider: Send + Sync {
         &self,
         name: &str,
         pwd: &str,
-        salt: &str,
         is_admin: bool,
         is_read_only: bool,
     ) -> DbResult<()>;
     async fn get_users(&self) -> DbResult<Vec<User>>;
+    /// Adds a user without password, which can only authenticate with tokens.
+    async fn add_service_account(
+        &self,
+        name: &str,
+        group: Option<String>,
+        is_read_only: bool,
+    ) -> DbResult<()>;
+    async fn get_service_account(&self, name: &str) -> DbResult<ServiceAccount>;
+    async fn get_service_accounts(&self) -> DbResult<Vec<ServiceAccount>>;
+    /// Appends an entry to the audit log. Entries are never changed or deleted.
+    async fn add_audit_entry(&self, event: &AuditEvent) -> DbResult<()>;
+    /// Returns the matching audit log entries, newest first.
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<Vec<AuditEntry>>;
+    async fn count_audit_entries(&self, filter: &AuditFilter) -> DbResult<u64>;
     async fn add_group(&self, name: &str) -> DbResult<()>;
     async fn get_group(&self, name: &str) -> DbResult<Group>;
     async fn get_groups(&self) -> DbResult<Vec<Group>>;
@@ -83,6 +184,7 @@ pub trait DbProvider: Send + Sync {
     async fn delete_group_user(&self, group_name: &str, user: &str) -> DbResult<()>;
     async fn get_group_users(&self, group_name: &str) -> DbResult<Vec<User>>;
     async fn is_group_user(&self, group_name: &str, group: &str) -> DbResult<bool>;
+    async fn get_user_groups(&self, user: &str) -> DbResult<Vec<Group>>;
     async fn add_crate_group(&self, crate_name: &NormalizedName, group: &str) -> DbResult<()>;
     async fn delete_crate_group(&self, crate_name: &NormalizedName, group: &str) -> DbResult<()>;
     async fn get_crate_groups(&self, crate_name: &NormalizedName) -> DbResult<Vec<Group>>;
@@ -96,6 +198,7 @@ pub trait DbProvider: Send + Sync {
     async fn get_total_cached_crate_versions(&self) -> DbResult<u64>;
     async fn get_total_cached_downloads(&self) -> DbResult<u64>;
     async fn get_crate_summaries(&self) -> DbResult<Vec<CrateSummary>>;
+    async fn get_crate_licenses(&self) -> DbResult<Vec<CrateLicense>>;
     async fn add_doc_queue(
         &self,
         krate: &NormalizedName,
@@ -112,6 +215,12 @@ pub trait DbProvider: Send + Sync {
         contains: &str,
         cache: bool,
     ) -> DbResult<Vec<CrateOverview>>;
+    async fn search_in_crate_name_paged(
+        &self,
+        contains: &str,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<(u64, Vec<CrateOverview>)>;
     async fn get_crate_overview_list(
         &self,
         limit: u64,
@@ -155,8 +264,169 @@ pub trait DbProvider: Send + Sync {
         indices: &[IndexMetadata],
     ) -> DbResult<Prefetch>;
     async fn get_cratesio_index_update_list(&self) -> DbResult<Vec<CratesioPrefetchMsg>>;
+    /// Checksum of the crate version in the cached crates.io index
+    async fn get_cratesio_checksum(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<Option<String>>;
+    async fn get_cratesio_crate_versions(
+        &self,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<Vec<CachedCrateVersion>>;
+    /// Versions of the crates.io crate published after the given time, unless the
+    /// crate is exempt from the quarantine
+    async fn get_quarantined_cratesio_versions(
+        &self,
+        crate_name: &NormalizedName,
+        published_after: &DateTime<Utc>,
+    ) -> DbResult<Vec<String>>;
+    /// Whether the version of the crates.io crate was published after the given time,
+    /// unless the crate is exempt from the quarantine. `None` if the version is unknown.
+    async fn is_quarantined_cratesio_version(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+        published_after: &DateTime<Utc>,
+    ) -> DbResult<Option<bool>>;
+    async fn is_quarantine_exempt(&self, crate_name: &NormalizedName) -> DbResult<bool>;
+    async fn get_quarantine_exemptions(&self) -> DbResult<Vec<QuarantineExemption>>;
+    async fn add_quarantine_exemption(&self, crate_name: &NormalizedName) -> DbResult<()>;
+    async fn delete_quarantine_exemption(&self, crate_name: &NormalizedName) -> DbResult<()>;
+    /// Licenses of the versions of the crates.io crate, by version
+    async fn get_cratesio_licenses(
+        &self,
+        crate_name: &NormalizedName,
+    ) -> DbResult<BTreeMap<String, String>>;
+    async fn set_cratesio_licenses(
+        &self,
+        crate_name: &NormalizedName,
+        licenses: &BTreeMap<String, String>,
+    ) -> DbResult<()>;
+    async fn get_proxy_policy_rules(&self) -> DbResult<Vec<ProxyPolicyRule>>;
+    async fn add_proxy_policy_rule(
+        &self,
+        action: PolicyAction,
+        crate_pattern: &str,
+        version_req: Option<String>,
+        licenses: &[String],
+        group: Option<String>,
+        reason: &str,
+    ) -> DbResult<i64>;
+    async fn delete_proxy_policy_rule(&self, id: i64) -> DbResult<()>;
+    /// Records a download of a cached crates.io crate file, which protects it from eviction
+    async fn touch_cratesio_cached_file(
+        &self,
+        crate_name: &OriginalName,
+        version: &Version,
+        size: i64,
+    ) -> DbResult<()>;
+    /// Tracks crate files of the crates.io storage which are not tracked yet, e.g. as
+    /// they were cached before the eviction existed, as downloaded now. Returns the
+    /// number of files which were added.
+    async fn track_cratesio_cached_files(
+        &self,
+        files: &[(OriginalName, Version, i64)],
+    ) -> DbResult<u64>;
+    /// Cached crates.io crate files, least recently downloaded first. Files last
+    /// downloaded before `expired_before` are marked as expired.
+    async fn get_cratesio_cached_files(
+        &self,
+        expired_before: Option<DateTime<Utc>>,
+    ) -> DbResult<Vec<CachedCratesIoFile>>;
+    async fn delete_cratesio_cached_file(
+        &self,
+        crate_name: &OriginalName,
+        version: &Version,
+    ) -> DbResult<()>;
+    async fn get_cratesio_pins(&self) -> DbResult<Vec<CratesIoPin>>;
+    async fn add_cratesio_pin(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<()>;
+    async fn delete_cratesio_pin(
+        &self,
+        crate_name: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<()>;
+    /// Stores the result of an eviction run which started at `started` and finished now
+    async fn add_cratesio_eviction_report(
+        &self,
+        started: &DateTime<Utc>,
+        evicted: &[String],
+        reclaimed_bytes: i64,
+        remaining_bytes: i64,
+    ) -> DbResult<CratesIoEvictionReport>;
+    /// The latest eviction reports, newest first
+    async fn get_cratesio_eviction_reports(
+        &self,
+        limit: u64,
+    ) -> DbResult<Vec<CratesIoEvictionReport>>;
     async fn unyank_crate(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()>;
     async fn yank_crate(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()>;
+    async fn add_trusted_publisher(
+        &self,
+        crate_name: &NormalizedName,
+        issuer: &str,
+        audience: &str,
+        jwks_url: &str,
+        claims: &BTreeMap<String, String>,
+    ) -> DbResult<i32>;
+    async fn get_trusted_publishers(
+        &self,
+        crate_name: &NormalizedName,
+    ) -> DbResult<Vec<TrustedPublisher>>;
+    async fn get_trusted_publishers_by_issuer(
+        &self,
+        issuer: &str,
+    ) -> DbResult<Vec<TrustedPublisher>>;
+    async fn delete_trusted_publisher(&self, crate_name: &NormalizedName, id: i32) -> DbResult<()>;
+    async fn add_trusted_publish_token(
+        &self,
+        token: &str,
+        publisher_ids: &[i32],
+        expires: &DateTime<Utc>,
+    ) -> DbResult<()>;
+    async fn get_trusted_publish_crates(&self, token: &str) -> DbResult<Vec<NormalizedName>>;
+    async fn delete_trusted_publish_token(&self, token: &str) -> DbResult<()>;
+    async fn add_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        crate_pattern: &str,
+    ) -> DbResult<i64>;
+    async fn get_webhooks(&self) -> DbResult<Vec<Webhook>>;
+    /// Deletes the webhook and its delivery history.
+    async fn delete_webhook(&self, id: i64) -> DbResult<()>;
+    /// Adds a pending delivery of the payload for each webhook subscribed to the
+    /// event of the crate and returns the number of deliveries.
+    async fn enqueue_webhook_event(
+        &self,
+        event: WebhookEvent,
+        crate_name: &NormalizedName,
+        payload: &str,
+    ) -> DbResult<u64>;
+    /// Returns the pending deliveries whose next attempt is due, oldest first.
+    async fn get_due_webhook_deliveries(
+        &self,
+        now: &DateTime<Utc>,
+        limit: u64,
+    ) -> DbResult<Vec<PendingDelivery>>;
+    /// Records an attempt of the delivery.
+    async fn update_webhook_delivery(&self, id: i64, attempt: &DeliveryAttempt) -> DbResult<()>;
+    /// Schedules a delivery to be sent again immediately, e.g. after it failed.
+    async fn retry_webhook_delivery(&self, id: i64) -> DbResult<()>;
+    /// Returns the deliveries of the webhook, newest first.
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<Vec<WebhookDelivery>>;
 }
 
 pub mod mock {
@@ -195,11 +465,31 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn get_session_id(&self, _session_token: &str) -> DbResult<i64> {
+                unimplemented!()
+            }
+
             async fn validate_session(&self, _session_token: &str) -> DbResult<(String, bool)> {
                 unimplemented!()
             }
 
-            async fn add_session_token(&self, _name: &str, _session_token: &str) -> DbResult<()> {
+            async fn add_session_token(&self, _name: &str, _session_token: &str, _user_agent: Option<String>) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_sessions(&self, _user_name: &str, _current_token: &str) -> DbResult<Vec<Session>> {
+                unimplemented!()
+            }
+
+            async fn delete_session(&self, _user_name: &str, _id: i64) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn delete_user_sessions(&self, _user_name: &str) -> DbResult<u64> {
+                unimplemented!()
+            }
+
+            async fn cleanup_expired_sessions(&self) -> DbResult<u64> {
                 unimplemented!()
             }
 
@@ -263,6 +553,62 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn change_admin_state(&self, _user_name: &str, _state: bool) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_external_identity_user(&self, _issuer: &str, _subject: &str) -> DbResult<Option<String>> {
+                unimplemented!()
+            }
+
+            async fn get_external_identities(&self, _user_name: &str) -> DbResult<Vec<ExternalIdentity>> {
+                unimplemented!()
+            }
+
+            async fn add_external_identity(&self, _user_name: &str, _identity: &ExternalIdentity) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn delete_external_identity(&self, _user_name: &str, _identity: &ExternalIdentity) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_totp_secret(&self, _user_name: &str) -> DbResult<Option<String>> {
+                unimplemented!()
+            }
+
+            async fn set_totp_secret(&self, _user_name: &str, _secret: &str) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn enable_totp(&self, _user_name: &str, _recovery_codes: &[String]) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn disable_totp(&self, _user_name: &str) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn use_recovery_code(&self, _user_name: &str, _code: &str) -> DbResult<bool> {
+                unimplemented!()
+            }
+
+            async fn get_totp_failures(&self, _user_name: &str, _since: &DateTime<Utc>) -> DbResult<i32> {
+                unimplemented!()
+            }
+
+            async fn add_totp_failure(&self, _user_name: &str, _since: &DateTime<Utc>) -> DbResult<i32> {
+                unimplemented!()
+            }
+
+            async fn reset_totp_failures(&self, _user_name: &str) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn use_totp_step(&self, _user_name: &str, _step: i64) -> DbResult<bool> {
+                unimplemented!()
+            }
+
             async fn crate_version_exists(&self, _crate_id: i64, _version: &str) -> DbResult<bool> {
                 unimplemented!()
             }
@@ -279,11 +625,19 @@ pub mod mock {
                 unimplemented!()
             }
 
-            async fn add_auth_token(&self, _name: &str, _token: &str, _user: &str) -> DbResult<()> {
+            async fn add_auth_token(&self, _name: &str, _token: &str, _user: &str, _scopes: &TokenScopes, _expires: Option<DateTime<Utc>>) -> DbResult<()> {
                 unimplemented!()
             }
 
-            async fn get_user_from_token(&self, _token: &str) -> DbResult<User> {
+            async fn get_user_from_token(&self, _token: &str) -> DbResult<(User, AuthToken)> {
+                unimplemented!()
+            }
+
+            async fn update_auth_token_usage(&self, _id: i32, _last_used: &DateTime<Utc>, _ip: Option<String>) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn cleanup_unused_auth_tokens(&self, _unused_since: &DateTime<Utc>, _delete: bool) -> DbResult<u64> {
                 unimplemented!()
             }
 
@@ -307,7 +661,7 @@ pub mod mock {
                 unimplemented!()
             }
 
-            async fn add_user(&self, _name: &str, _pwd: &str, _salt: &str, _is_admin: bool, _is_read_only: bool) -> DbResult<()> {
+            async fn add_user(&self, _name: &str, _pwd: &str, _is_admin: bool, _is_read_only: bool) -> DbResult<()> {
                 unimplemented!()
             }
 
@@ -315,6 +669,30 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn add_service_account(&self, _name: &str, _group: Option<String>, _is_read_only: bool) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_service_account(&self, _name: &str) -> DbResult<ServiceAccount> {
+                unimplemented!()
+            }
+
+            async fn get_service_accounts(&self) -> DbResult<Vec<ServiceAccount>> {
+                unimplemented!()
+            }
+
+            async fn add_audit_entry(&self, _event: &AuditEvent) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_audit_entries(&self, _filter: &AuditFilter, _limit: u64, _offset: u64) -> DbResult<Vec<AuditEntry>> {
+                unimplemented!()
+            }
+
+            async fn count_audit_entries(&self, _filter: &AuditFilter) -> DbResult<u64> {
+                unimplemented!()
+            }
+
             async fn get_total_unique_crates(&self) -> DbResult<u32> {
                 unimplemented!()
             }
@@ -343,6 +721,10 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn get_crate_licenses(&self) -> DbResult<Vec<CrateLicense>> {
+                unimplemented!()
+            }
+
                 async fn add_doc_queue(&self, krate: &NormalizedName, version: &Version, path: &Path) -> DbResult<()>{
                     unimplemented!()
                 }
@@ -375,6 +757,10 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn search_in_crate_name_paged(&self, contains: &str, limit: u64, offset: u64) -> DbResult<(u64, Vec<CrateOverview>)> {
+                unimplemented!()
+            }
+
             async fn get_crate_overview_list(&self, limit: u64, offset: u64, cache: bool) -> DbResult<Vec<CrateOverview >> {
                 unimplemented!()
             }
@@ -422,6 +808,102 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn get_cratesio_checksum(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<Option<String>> {
+                unimplemented!()
+            }
+
+            async fn get_cratesio_crate_versions(&self, limit: u64, offset: u64) -> DbResult<Vec<CachedCrateVersion>> {
+                unimplemented!()
+            }
+
+            async fn get_quarantined_cratesio_versions(&self, crate_name: &NormalizedName, published_after: &DateTime<Utc>) -> DbResult<Vec<String>> {
+                unimplemented!()
+            }
+
+            async fn is_quarantined_cratesio_version(&self, crate_name: &NormalizedName, version: &Version, published_after: &DateTime<Utc>) -> DbResult<Option<bool>> {
+                unimplemented!()
+            }
+
+            async fn is_quarantine_exempt(&self, crate_name: &NormalizedName) -> DbResult<bool> {
+                unimplemented!()
+            }
+
+            async fn get_quarantine_exemptions(&self) -> DbResult<Vec<QuarantineExemption>> {
+                unimplemented!()
+            }
+
+            async fn add_quarantine_exemption(&self, crate_name: &NormalizedName) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn delete_quarantine_exemption(&self, crate_name: &NormalizedName) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_cratesio_licenses(&self, crate_name: &NormalizedName) -> DbResult<BTreeMap<String, String>> {
+                unimplemented!()
+            }
+
+            async fn set_cratesio_licenses(&self, crate_name: &NormalizedName, licenses: &BTreeMap<String, String>) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_proxy_policy_rules(&self) -> DbResult<Vec<ProxyPolicyRule>> {
+                unimplemented!()
+            }
+
+            async fn add_proxy_policy_rule(
+                &self,
+                action: PolicyAction,
+                crate_pattern: &str,
+                version_req: Option<String>,
+                licenses: &[String],
+                group: Option<String>,
+                reason: &str,
+            ) -> DbResult<i64> {
+                unimplemented!()
+            }
+
+            async fn delete_proxy_policy_rule(&self, id: i64) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn touch_cratesio_cached_file(&self, crate_name: &OriginalName, version: &Version, size: i64) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn track_cratesio_cached_files(&self, files: &[(OriginalName, Version, i64)]) -> DbResult<u64> {
+                unimplemented!()
+            }
+
+            async fn get_cratesio_cached_files(&self, expired_before: Option<DateTime<Utc>>) -> DbResult<Vec<CachedCratesIoFile>> {
+                unimplemented!()
+            }
+
+            async fn delete_cratesio_cached_file(&self, crate_name: &OriginalName, version: &Version) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_cratesio_pins(&self) -> DbResult<Vec<CratesIoPin>> {
+                unimplemented!()
+            }
+
+            async fn add_cratesio_pin(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn delete_cratesio_pin(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn add_cratesio_eviction_report(&self, started: &DateTime<Utc>, evicted: &[String], reclaimed_bytes: i64, remaining_bytes: i64) -> DbResult<CratesIoEvictionReport> {
+                unimplemented!()
+            }
+
+            async fn get_cratesio_eviction_reports(&self, limit: u64) -> DbResult<Vec<CratesIoEvictionReport>> {
+                unimplemented!()
+            }
+
             async fn unyank_crate(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()> {
                 unimplemented!()
             }
@@ -454,6 +936,9 @@ pub mod mock {
             async fn is_group_user(&self, group_name: &str, user: &str) -> DbResult<bool> {
                 uninplemented!()
             }
+            async fn get_user_groups(&self, user: &str) -> DbResult<Vec<Group>> {
+                uninplemented!()
+            }
 
             async fn add_crate_group(&self, crate_name: &NormalizedName, group: &str) -> DbResult<()>{
                 uninplemented!()
@@ -471,6 +956,66 @@ pub mod mock {
             async fn is_crate_group_user(&self, crate_name: &NormalizedName, user: &str) -> DbResult<bool>{
                 uninplemented!()
             }
+
+            async fn add_trusted_publisher(&self, crate_name: &NormalizedName, issuer: &str, audience: &str, jwks_url: &str, claims: &BTreeMap<String, String>) -> DbResult<i32> {
+                unimplemented!()
+            }
+
+            async fn get_trusted_publishers(&self, crate_name: &NormalizedName) -> DbResult<Vec<TrustedPublisher>> {
+                unimplemented!()
+            }
+
+            async fn get_trusted_publishers_by_issuer(&self, issuer: &str) -> DbResult<Vec<TrustedPublisher>> {
+                unimplemented!()
+            }
+
+            async fn delete_trusted_publisher(&self, crate_name: &NormalizedName, id: i32) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn add_trusted_publish_token(&self, token: &str, publisher_ids: &[i32], expires: &DateTime<Utc>) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_trusted_publish_crates(&self, token: &str) -> DbResult<Vec<NormalizedName>> {
+                unimplemented!()
+            }
+
+            async fn delete_trusted_publish_token(&self, token: &str) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn add_webhook(&self, url: &str, secret: &str, events: &[WebhookEvent], crate_pattern: &str) -> DbResult<i64> {
+                unimplemented!()
+            }
+
+            async fn get_webhooks(&self) -> DbResult<Vec<Webhook>> {
+                unimplemented!()
+            }
+
+            async fn delete_webhook(&self, id: i64) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn enqueue_webhook_event(&self, event: WebhookEvent, crate_name: &NormalizedName, payload: &str) -> DbResult<u64> {
+                unimplemented!()
+            }
+
+            async fn get_due_webhook_deliveries(&self, now: &DateTime<Utc>, limit: u64) -> DbResult<Vec<PendingDelivery>> {
+                unimplemented!()
+            }
+
+            async fn update_webhook_delivery(&self, id: i64, attempt: &DeliveryAttempt) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn retry_webhook_delivery(&self, id: i64) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_webhook_deliveries(&self, webhook_id: i64, status: Option<DeliveryStatus>, limit: u64, offset: u64) -> DbResult<Vec<WebhookDelivery>> {
+                unimplemented!()
+            }
         }
     }
 }
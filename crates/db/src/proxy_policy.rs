@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Only matching versions can be used, if any allow rule applies to the user
+    Allow,
+    /// Matching versions are blocked
+    Deny,
+}
+
+/// Rule of the policy for crates.io crates served by the proxy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyPolicyRule {
+    pub id: i64,
+    pub action: PolicyAction,
+    /// Crates the rule applies to, where `*` matches any characters
+    pub crate_pattern: String,
+    /// Versions the rule applies to, e.g. `<1.2.3`. All versions if not set.
+    pub version_req: Option<String>,
+    /// An allow rule matches versions which can be used under these licenses only,
+    /// a deny rule versions which cannot be used without them. Ignored if empty.
+    pub licenses: Vec<String>,
+    /// Group whose members the rule applies to. Applies to everyone if not set.
+    pub group: Option<String>,
+    /// Shown to users of blocked crates
+    pub reason: String,
+    pub created: String,
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Crate which is exempt from the quarantine of new crates.io versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantineExemption {
+    pub name: String,
+    pub created: String,
+}
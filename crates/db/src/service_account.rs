@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceAccount {
+    pub name: String,
+    /// Members of the group manage the service account. Without a group, only admins can.
+    pub group: Option<String>,
+    pub is_read_only: bool,
+}
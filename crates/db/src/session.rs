@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Login session of a user in the web UI. The session token itself is never exposed.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: i64,
+    pub created: String,
+    pub last_seen: String,
+    pub user_agent: Option<String>,
+    /// `true` for the session which was used to request the list
+    pub current: bool,
+}
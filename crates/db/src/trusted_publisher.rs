@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Trust rule for a crate. CI systems can exchange an OIDC token for a publish token
+/// of the crate, if the token is signed by a key of the JWKS, is issued by the issuer
+/// for the audience and all claims match.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedPublisher {
+    pub id: i32,
+    pub crate_name: String,
+    pub issuer: String,
+    pub audience: String,
+    pub jwks_url: String,
+    pub claims: BTreeMap<String, String>,
+}
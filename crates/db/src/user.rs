@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[expect(clippy::struct_excessive_bools)] // independent flags of the user table
 pub struct User {
     #[serde(skip_serializing)]
     pub id: i32,
@@ -11,4 +12,7 @@ pub struct User {
     pub salt: String,
     pub is_admin: bool,
     pub is_read_only: bool,
+    pub totp_enabled: bool,
+    /// Service accounts authenticate with tokens only and cannot log in to the UI
+    pub is_service_account: bool,
 }
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Registry events webhooks can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    CratePublished,
+    CrateYanked,
+    CrateUnyanked,
+    CrateDeleted,
+    DocsBuilt,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// Key of the HMAC signature of the deliveries, which is never exposed
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    /// Crates the webhook is notified about, where `*` matches any characters
+    pub crate_pattern: String,
+    pub created: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// All attempts failed
+    Failed,
+}
+
+/// Delivery of an event to a webhook. Status and response of the last attempt
+/// are kept, such that failed deliveries are visible.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub payload: Value,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt: Option<String>,
+    pub last_attempt: Option<String>,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub created: String,
+}
+
+/// Delivery which is due, with everything needed to send it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event: String,
+    /// The JSON payload exactly as it is signed and sent
+    pub payload: String,
+    pub attempts: u32,
+}
+
+/// Result of a delivery attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryAttempt {
+    pub status: DeliveryStatus,
+    /// Time of the next attempt, if the delivery is still pending
+    pub next_attempt: Option<chrono::DateTime<chrono::Utc>>,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+}
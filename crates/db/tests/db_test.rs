@@ -7,12 +7,18 @@ use common::original_name::OriginalName;
 use common::prefetch::Prefetch;
 use common::publish_metadata::{PublishMetadata, RegistryDep};
 use common::version::Version;
-use db::password::hash_pwd;
-use db::provider::PrefetchState;
-use db::{DbProvider, DocQueueEntry, User, test_utils::*};
+use db::error::DbError;
+use db::provider::{DbResult, PrefetchState};
+use db::{
+    ActorKind, AuditAction, AuditActor, AuditEvent, AuditFilter, AuthMethod, AuthProvider,
+    DbProvider, DeliveryAttempt, DeliveryStatus, DocQueueEntry, EndpointScope, ExternalIdentity,
+    ExternalUser, PolicyAction, ServiceAccount, TokenScopes, WebhookEvent, test_utils::*,
+};
 use db_testcontainer::db_test;
+use sea_orm::prelude::async_trait::async_trait;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 mod image;
 
 #[db_test]
@@ -704,10 +710,7 @@ async fn test_add_crate_duplicate(test_db: &db::Database) {
 
 #[db_test]
 async fn test_add_crate_different_user(test_db: &db::Database) {
-    test_db
-        .add_user("user", "123", "123", false, false)
-        .await
-        .unwrap();
+    test_db.add_user("user", "123", false, false).await.unwrap();
     let pm = PublishMetadata::minimal("mycrate", "1.0.0");
     let created = Utc::now();
 
@@ -757,11 +760,11 @@ async fn test_add_empty_crate_exists(test_db: &db::Database) {
 #[db_test]
 async fn get_user_from_token_works(test_db: &db::Database) {
     test_db
-        .add_auth_token("test1", "mytoken1", "admin")
+        .add_auth_token("test1", "mytoken1", "admin", &TokenScopes::default(), None)
         .await
         .unwrap();
 
-    let user = test_db.get_user_from_token("mytoken1").await.unwrap();
+    let (user, _) = test_db.get_user_from_token("mytoken1").await.unwrap();
 
     assert_eq!("admin", user.name);
 }
@@ -769,11 +772,11 @@ async fn get_user_from_token_works(test_db: &db::Database) {
 #[db_test]
 async fn get_auth_tokens_returns_all_tokens(test_db: &db::Database) {
     test_db
-        .add_auth_token("test1", "mytoken1", "admin")
+        .add_auth_token("test1", "mytoken1", "admin", &TokenScopes::default(), None)
         .await
         .unwrap();
     test_db
-        .add_auth_token("test2", "mytoken2", "admin")
+        .add_auth_token("test2", "mytoken2", "admin", &TokenScopes::default(), None)
         .await
         .unwrap();
 
@@ -788,18 +791,139 @@ async fn get_auth_tokens_returns_all_tokens(test_db: &db::Database) {
 #[db_test]
 async fn auth_token_insert_and_read(test_db: &db::Database) {
     test_db
-        .add_auth_token("test", "mytoken", "admin")
+        .add_auth_token("test", "mytoken", "admin", &TokenScopes::default(), None)
         .await
         .unwrap();
-    let user = test_db.get_user_from_token("mytoken").await.unwrap();
+    let (user, _) = test_db.get_user_from_token("mytoken").await.unwrap();
 
     assert_eq!("admin", user.name);
 }
 
+#[db_test]
+async fn auth_token_scopes_are_stored(test_db: &db::Database) {
+    let scopes = TokenScopes {
+        endpoint_scopes: Some(vec![
+            EndpointScope::PublishNew,
+            EndpointScope::PublishUpdate,
+        ]),
+        crate_scopes: Some(vec!["acme-*".to_string()]),
+    };
+    test_db
+        .add_auth_token("ci", "mytoken", "admin", &scopes, None)
+        .await
+        .unwrap();
+
+    let (user, token) = test_db.get_user_from_token("mytoken").await.unwrap();
+    let tokens = test_db.get_auth_tokens("admin").await.unwrap();
+
+    assert_eq!("admin", user.name);
+    assert_eq!(scopes, token.scopes);
+    assert_eq!(TokenScopes::default(), tokens[0].scopes);
+    assert_eq!(scopes, tokens[1].scopes);
+}
+
+#[db_test]
+async fn get_user_from_token_rejects_expired_token(test_db: &db::Database) {
+    let in_future = Utc::now() + chrono::Duration::hours(1);
+    let in_past = Utc::now() - chrono::Duration::hours(1);
+    test_db
+        .add_auth_token(
+            "valid",
+            "validtoken",
+            "admin",
+            &TokenScopes::default(),
+            Some(in_future),
+        )
+        .await
+        .unwrap();
+    test_db
+        .add_auth_token(
+            "expired",
+            "expiredtoken",
+            "admin",
+            &TokenScopes::default(),
+            Some(in_past),
+        )
+        .await
+        .unwrap();
+
+    assert!(test_db.get_user_from_token("validtoken").await.is_ok());
+    assert!(matches!(
+        test_db.get_user_from_token("expiredtoken").await,
+        Err(DbError::TokenNotFound)
+    ));
+}
+
+#[db_test]
+async fn auth_token_usage_is_listed(test_db: &db::Database) {
+    test_db
+        .add_auth_token("test", "mytoken", "admin", &TokenScopes::default(), None)
+        .await
+        .unwrap();
+    let (_, token) = test_db.get_user_from_token("mytoken").await.unwrap();
+    let last_used = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+
+    test_db
+        .update_auth_token_usage(token.id, &last_used, Some("10.0.0.1".to_string()))
+        .await
+        .unwrap();
+    let tokens = test_db.get_auth_tokens("admin").await.unwrap();
+
+    assert_eq!(None, token.last_used);
+    assert!(!tokens[1].created.is_empty());
+    assert_eq!(Some("2025-01-02 03:04:05".to_string()), tokens[1].last_used);
+    assert_eq!(Some("10.0.0.1".to_string()), tokens[1].last_used_ip);
+}
+
+#[db_test]
+async fn cleanup_unused_auth_tokens_flags_and_deletes(test_db: &db::Database) {
+    test_db
+        .add_auth_token("unused", "unused", "admin", &TokenScopes::default(), None)
+        .await
+        .unwrap();
+    test_db
+        .add_auth_token("used", "used", "admin", &TokenScopes::default(), None)
+        .await
+        .unwrap();
+    let (_, unused) = test_db.get_user_from_token("unused").await.unwrap();
+    let (_, used) = test_db.get_user_from_token("used").await.unwrap();
+    let long_ago = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+    let cutoff = Utc::now() - chrono::Duration::days(30);
+    test_db
+        .update_auth_token_usage(unused.id, &long_ago, None)
+        .await
+        .unwrap();
+    test_db
+        .update_auth_token_usage(used.id, &Utc::now(), None)
+        .await
+        .unwrap();
+
+    let flagged = test_db
+        .cleanup_unused_auth_tokens(&cutoff, false)
+        .await
+        .unwrap();
+    let tokens = test_db.get_auth_tokens("admin").await.unwrap();
+    assert_eq!(1, flagged);
+    assert!(
+        tokens
+            .iter()
+            .any(|t| t.name == "unused" && t.flagged_unused)
+    );
+    assert!(tokens.iter().any(|t| t.name == "used" && !t.flagged_unused));
+
+    let deleted = test_db
+        .cleanup_unused_auth_tokens(&cutoff, true)
+        .await
+        .unwrap();
+    let tokens = test_db.get_auth_tokens("admin").await.unwrap();
+    assert_eq!(1, deleted);
+    assert!(tokens.iter().all(|t| t.name != "unused"));
+}
+
 #[db_test]
 async fn auth_token_insert_and_delete(test_db: &db::Database) {
     test_db
-        .add_auth_token("test", "mytoken", "admin")
+        .add_auth_token("test", "mytoken", "admin", &TokenScopes::default(), None)
         .await
         .unwrap();
 
@@ -811,7 +935,7 @@ async fn auth_token_insert_and_delete(test_db: &db::Database) {
 #[db_test]
 async fn get_user_from_token_no_token(test_db: &db::Database) {
     test_db
-        .add_auth_token("test", "mytoken", "admin")
+        .add_auth_token("test", "mytoken", "admin", &TokenScopes::default(), None)
         .await
         .unwrap();
 
@@ -822,7 +946,7 @@ async fn get_user_from_token_no_token(test_db: &db::Database) {
 async fn add_auth_token_no_user(test_db: &db::Database) {
     assert!(
         test_db
-            .add_auth_token("test", "mytoken", "nouser")
+            .add_auth_token("test", "mytoken", "nouser", &TokenScopes::default(), None)
             .await
             .is_err()
     );
@@ -830,12 +954,15 @@ async fn add_auth_token_no_user(test_db: &db::Database) {
 
 #[db_test]
 async fn delete_user_with_sessions(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
     test_db
-        .add_user("user", "pwd", "salt", false, false)
+        .add_session_token("user", "123", None)
+        .await
+        .unwrap();
+    test_db
+        .add_session_token("user", "abc", None)
         .await
         .unwrap();
-    test_db.add_session_token("user", "123").await.unwrap();
-    test_db.add_session_token("user", "abc").await.unwrap();
 
     test_db.delete_user("user").await.unwrap();
 
@@ -846,44 +973,28 @@ async fn delete_user_with_sessions(test_db: &db::Database) {
 
 #[db_test]
 async fn add_user_works(test_db: &db::Database) {
-    test_db
-        .add_user("user", "pwd", "salt", false, false)
-        .await
-        .unwrap();
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
 
-    let expected = User {
-        id: 2,
-        name: "user".to_owned(),
-        pwd: hash_pwd("pwd", "salt"),
-        salt: "salt".to_owned(),
-        is_admin: false,
-        is_read_only: false,
-    };
     let user = test_db.get_user("user").await.unwrap();
-    assert_eq!(expected, user);
+    assert_eq!(2, user.id);
+    assert_eq!("user", user.name);
+    assert!(user.pwd.starts_with("$argon2id$"));
+    assert_eq!("", user.salt);
+    assert!(!user.is_admin);
+    assert!(!user.is_read_only);
+    assert!(test_db.authenticate_user("user", "pwd").await.is_ok());
 }
 
 #[db_test]
 async fn add_user_duplicate(test_db: &db::Database) {
-    test_db
-        .add_user("user", "pwd", "salt", false, false)
-        .await
-        .unwrap();
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
 
-    assert!(
-        test_db
-            .add_user("user", "pwd", "salt", false, false)
-            .await
-            .is_err()
-    );
+    assert!(test_db.add_user("user", "pwd", false, false).await.is_err());
 }
 
 #[db_test]
 async fn get_users_works(test_db: &db::Database) {
-    test_db
-        .add_user("user", "123", "abc", false, false)
-        .await
-        .unwrap();
+    test_db.add_user("user", "123", false, false).await.unwrap();
 
     let users = test_db.get_users().await.unwrap();
 
@@ -911,10 +1022,391 @@ async fn change_pwd_works(test_db: &db::Database) {
     assert!(test_db.authenticate_user("admin", "abc").await.is_ok());
 }
 
+#[db_test]
+async fn change_admin_state_works(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+
+    test_db.change_admin_state("user", true).await.unwrap();
+    assert!(test_db.get_user("user").await.unwrap().is_admin);
+
+    test_db.change_admin_state("user", false).await.unwrap();
+    assert!(!test_db.get_user("user").await.unwrap().is_admin);
+}
+
+struct StubAuthProvider;
+
+#[async_trait]
+impl AuthProvider for StubAuthProvider {
+    async fn authenticate(&self, name: &str, pwd: &str) -> DbResult<Option<ExternalUser>> {
+        match (name, pwd) {
+            ("alice", "secret") => Ok(Some(ExternalUser {
+                name: "alice".to_string(),
+                is_read_only: Some(true),
+                ..ExternalUser::default()
+            })),
+            ("alice", _) => Err(DbError::PasswordMismatch),
+            ("admin", "down") => Err(DbError::AuthProviderError("unreachable".to_string())),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[db_test]
+async fn authenticate_user_with_auth_provider(test_db: &db::Database) {
+    let test_db = test_with_auth_provider(test_db, Arc::new(StubAuthProvider));
+
+    let user = test_db.authenticate_user("alice", "secret").await.unwrap();
+    assert_eq!("alice", user.name);
+    assert!(user.is_read_only);
+    assert!(test_db.authenticate_user("alice", "wrong").await.is_err());
+
+    // Users unknown to the provider are authenticated by Kellnr
+    assert!(test_db.authenticate_user("admin", "123").await.is_ok());
+    assert!(test_db.authenticate_user("admin", "wrong").await.is_err());
+
+    // If the provider fails, Kellnr still checks its own users
+    test_db.change_pwd("admin", "down").await.unwrap();
+    assert!(test_db.authenticate_user("admin", "down").await.is_ok());
+}
+
+#[db_test]
+async fn external_user_sync_creates_and_updates_user(test_db: &db::Database) {
+    test_db.add_group("readers").await.unwrap();
+    test_db.add_group("old").await.unwrap();
+    let mut external = ExternalUser {
+        name: "alice".to_string(),
+        identity: None,
+        is_admin: Some(true),
+        is_read_only: None,
+        groups: Some(vec!["old".to_string(), "unknown".to_string()]),
+    };
+
+    external.sync(test_db).await.unwrap();
+    let user = test_db.get_user("alice").await.unwrap();
+    assert!(user.is_admin);
+    assert!(!user.is_read_only);
+    assert!(test_db.is_group_user("old", "alice").await.unwrap());
+
+    external.is_admin = Some(false);
+    external.is_read_only = Some(true);
+    external.groups = Some(vec!["readers".to_string()]);
+    external.sync(test_db).await.unwrap();
+    let user = test_db.get_user("alice").await.unwrap();
+    assert!(!user.is_admin);
+    assert!(user.is_read_only);
+    assert!(test_db.is_group_user("readers", "alice").await.unwrap());
+    assert!(!test_db.is_group_user("old", "alice").await.unwrap());
+    assert_eq!(
+        vec!["readers".to_string()],
+        test_db
+            .get_user_groups("alice")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|g| g.name)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[db_test]
+async fn external_user_sync_rejects_service_account(test_db: &db::Database) {
+    test_db
+        .add_service_account("ci", None, false)
+        .await
+        .unwrap();
+    let external = ExternalUser {
+        name: "ci".to_string(),
+        is_admin: Some(true),
+        ..ExternalUser::default()
+    };
+
+    assert!(matches!(
+        external.sync(test_db).await,
+        Err(DbError::ServiceAccountLogin(_))
+    ));
+    assert!(!test_db.get_user("ci").await.unwrap().is_admin);
+}
+
+#[db_test]
+async fn external_user_sync_matches_identity_instead_of_name(test_db: &db::Database) {
+    let identity = ExternalIdentity {
+        issuer: "https://issuer".to_string(),
+        subject: "1234".to_string(),
+    };
+    test_db.add_user("bob", "pwd", false, false).await.unwrap();
+    let external = ExternalUser {
+        name: "alice".to_string(),
+        identity: Some(identity.clone()),
+        is_admin: Some(true),
+        ..ExternalUser::default()
+    };
+
+    // The first login creates and links the user
+    assert_eq!("alice", external.sync(test_db).await.unwrap());
+    assert_eq!(
+        vec![identity.clone()],
+        test_db.get_external_identities("alice").await.unwrap()
+    );
+
+    // After a rename at the provider, the linked user is still found
+    let renamed = ExternalUser {
+        name: "bob".to_string(),
+        ..external.clone()
+    };
+    assert_eq!("alice", renamed.sync(test_db).await.unwrap());
+    assert!(!test_db.get_user("bob").await.unwrap().is_admin);
+
+    // Other identities cannot take over existing users
+    let other = ExternalUser {
+        identity: Some(ExternalIdentity {
+            subject: "5678".to_string(),
+            ..identity.clone()
+        }),
+        ..renamed
+    };
+    assert!(matches!(
+        other.sync(test_db).await,
+        Err(DbError::ExternalIdentityNotLinked(_))
+    ));
+    assert!(!test_db.get_user("bob").await.unwrap().is_admin);
+
+    test_db
+        .delete_external_identity("alice", &identity)
+        .await
+        .unwrap();
+    assert_eq!(
+        None,
+        test_db
+            .get_external_identity_user(&identity.issuer, &identity.subject)
+            .await
+            .unwrap()
+    );
+}
+
+#[db_test]
+async fn audit_entries_are_filtered_newest_first(test_db: &db::Database) {
+    let user = AuditActor {
+        name: "alice".to_string(),
+        session_id: Some(7),
+        ..AuditActor::default()
+    };
+    let ci = AuditActor {
+        name: "ci".to_string(),
+        kind: ActorKind::ServiceAccount,
+        auth: AuthMethod::Token,
+        token_id: Some(3),
+        session_id: None,
+        ip: Some("10.0.0.1".to_string()),
+    };
+    let events = [
+        AuditEvent::new(ci.clone(), AuditAction::Publish, "crate1")
+            .after(serde_json::json!({ "version": "1.0.0" })),
+        AuditEvent::new(
+            user.clone(),
+            AuditAction::ChangeDownloadRestricted,
+            "crate1",
+        )
+        .before(serde_json::json!({ "download_restricted": false }))
+        .after(serde_json::json!({ "download_restricted": true })),
+        AuditEvent::new(user, AuditAction::ResetPassword, "bob"),
+    ];
+    for event in &events {
+        test_db.add_audit_entry(event).await.unwrap();
+    }
+
+    let crate1 = AuditFilter {
+        target: Some("crate1".to_string()),
+        ..AuditFilter::default()
+    };
+    let entries = test_db.get_audit_entries(&crate1, 10, 0).await.unwrap();
+    let by_service_account = AuditFilter {
+        actor_kind: Some("service_account".to_string()),
+        ..AuditFilter::default()
+    };
+    let published = test_db
+        .get_audit_entries(&by_service_account, 10, 0)
+        .await
+        .unwrap();
+    let future = AuditFilter {
+        from: Some(Utc::now() + chrono::Duration::hours(1)),
+        ..AuditFilter::default()
+    };
+
+    assert_eq!(2, test_db.count_audit_entries(&crate1).await.unwrap());
+    assert_eq!(
+        vec!["change_download_restricted", "publish"],
+        entries
+            .iter()
+            .map(|e| e.action.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(serde_json::json!({ "download_restricted": true })),
+        entries[0].after
+    );
+    assert_eq!(Some(7), entries[0].session_id);
+    let older = AuditFilter {
+        before_id: Some(entries[0].id),
+        ..crate1.clone()
+    };
+    assert_eq!(
+        vec![entries[1].clone()],
+        test_db.get_audit_entries(&older, 10, 0).await.unwrap()
+    );
+    assert_eq!(1, published.len());
+    assert_eq!("ci", published[0].actor);
+    assert_eq!("token", published[0].auth);
+    assert_eq!(Some(3), published[0].token_id);
+    assert_eq!(Some("10.0.0.1".to_string()), published[0].ip);
+    assert_eq!(
+        1,
+        test_db
+            .get_audit_entries(&AuditFilter::default(), 1, 2)
+            .await
+            .unwrap()
+            .len()
+    );
+    assert_eq!(0, test_db.count_audit_entries(&future).await.unwrap());
+}
+
+#[db_test]
+async fn webhook_events_are_queued_for_matching_webhooks(test_db: &db::Database) {
+    let all = test_db
+        .add_webhook(
+            "http://localhost/all",
+            "secret1",
+            &[WebhookEvent::CratePublished, WebhookEvent::CrateYanked],
+            "*",
+        )
+        .await
+        .unwrap();
+    let internal = test_db
+        .add_webhook(
+            "http://localhost/internal",
+            "secret2",
+            &[WebhookEvent::CratePublished],
+            "internal-*",
+        )
+        .await
+        .unwrap();
+
+    let published = test_db
+        .enqueue_webhook_event(
+            WebhookEvent::CratePublished,
+            &NormalizedName::from_unchecked_str("internal-foo"),
+            r#"{"event":"crate_published"}"#,
+        )
+        .await
+        .unwrap();
+    let yanked = test_db
+        .enqueue_webhook_event(
+            WebhookEvent::CrateYanked,
+            &NormalizedName::from_unchecked_str("other"),
+            r#"{"event":"crate_yanked"}"#,
+        )
+        .await
+        .unwrap();
+    let deleted = test_db
+        .enqueue_webhook_event(
+            WebhookEvent::CrateDeleted,
+            &NormalizedName::from_unchecked_str("internal-foo"),
+            "{}",
+        )
+        .await
+        .unwrap();
+
+    assert_eq!((2, 1, 0), (published, yanked, deleted));
+    let due = test_db
+        .get_due_webhook_deliveries(&Utc::now(), 10)
+        .await
+        .unwrap();
+    assert_eq!(3, due.len());
+    assert_eq!("http://localhost/all", due[0].url);
+    assert_eq!("secret1", due[0].secret);
+    assert_eq!("crate_published", due[0].event);
+    assert_eq!("http://localhost/internal", due[1].url);
+    assert_eq!(r#"{"event":"crate_yanked"}"#, due[2].payload);
+
+    // The first delivery is postponed, the second fails for good
+    test_db
+        .update_webhook_delivery(
+            due[0].id,
+            &DeliveryAttempt {
+                status: DeliveryStatus::Pending,
+                next_attempt: Some(Utc::now() + chrono::Duration::hours(1)),
+                response_status: Some(500),
+                error: None,
+            },
+        )
+        .await
+        .unwrap();
+    test_db
+        .update_webhook_delivery(
+            due[1].id,
+            &DeliveryAttempt {
+                status: DeliveryStatus::Failed,
+                next_attempt: None,
+                response_status: None,
+                error: Some("connection refused".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    let due_after = test_db
+        .get_due_webhook_deliveries(&Utc::now(), 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![due[2].id],
+        due_after.iter().map(|d| d.id).collect::<Vec<_>>()
+    );
+
+    let failed = test_db
+        .get_webhook_deliveries(internal, Some(DeliveryStatus::Failed), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(1, failed.len());
+    assert_eq!(1, failed[0].attempts);
+    assert_eq!(Some("connection refused".to_string()), failed[0].error);
+    assert_eq!(
+        serde_json::json!({ "event": "crate_published" }),
+        failed[0].payload
+    );
+    assert_eq!(
+        2,
+        test_db
+            .get_webhook_deliveries(all, None, 10, 0)
+            .await
+            .unwrap()
+            .len()
+    );
+
+    test_db.retry_webhook_delivery(due[1].id).await.unwrap();
+    let retried = test_db
+        .get_webhook_deliveries(internal, Some(DeliveryStatus::Pending), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(0, retried[0].attempts);
+
+    test_db.delete_webhook(all).await.unwrap();
+    assert_eq!(1, test_db.get_webhooks().await.unwrap().len());
+    assert!(
+        test_db
+            .get_webhook_deliveries(all, None, 10, 0)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        test_db.delete_webhook(all).await,
+        Err(DbError::WebhookNotFound(_))
+    ));
+}
+
 #[db_test]
 async fn clean_db_after_time(test_db: &db::Database) {
     test_db
-        .add_session_token("admin", "session_token")
+        .add_session_token("admin", "session_token", None)
         .await
         .unwrap();
     let (name, _) = test_db.validate_session("session_token").await.unwrap();
@@ -932,7 +1424,7 @@ async fn clean_db_after_time(test_db: &db::Database) {
 #[db_test]
 async fn delete_session_token_works(test_db: &db::Database) {
     test_db
-        .add_session_token("admin", "session_token")
+        .add_session_token("admin", "session_token", None)
         .await
         .unwrap();
     let (name, _) = test_db.validate_session("session_token").await.unwrap();
@@ -947,26 +1439,147 @@ async fn delete_session_token_works(test_db: &db::Database) {
 #[db_test]
 async fn delete_session_token_no_token(test_db: &db::Database) {
     test_db
-        .add_session_token("admin", "session_token")
+        .add_session_token("admin", "session_token", None)
+        .await
+        .unwrap();
+    let (name, _) = test_db.validate_session("session_token").await.unwrap();
+    assert_eq!("admin", name);
+
+    let r = test_db.delete_session_token("no_token").await;
+
+    assert!(r.is_ok());
+}
+
+#[db_test]
+async fn get_name_valid_user_and_token(test_db: &db::Database) {
+    test_db
+        .add_session_token("admin", "session_token", None)
+        .await
+        .unwrap();
+    let (name, _) = test_db.validate_session("session_token").await.unwrap();
+
+    assert_eq!("admin", name);
+}
+
+#[db_test]
+async fn session_expires_without_activity(test_db: &db::Database) {
+    test_db
+        .add_session_token("admin", "session_token", None)
+        .await
+        .unwrap();
+
+    let last_seen = Utc::now() - chrono::Duration::hours(9);
+    test_set_session_last_seen(test_db, "session_token", &last_seen)
+        .await
+        .unwrap();
+
+    assert!(test_db.validate_session("session_token").await.is_err());
+    assert!(test_db.get_sessions("admin", "").await.unwrap().is_empty());
+}
+
+#[db_test]
+async fn session_is_renewed_on_activity(test_db: &db::Database) {
+    test_db
+        .add_session_token("admin", "session_token", None)
+        .await
+        .unwrap();
+
+    let last_seen = Utc::now() - chrono::Duration::hours(7);
+    test_set_session_last_seen(test_db, "session_token", &last_seen)
+        .await
+        .unwrap();
+    assert!(test_db.validate_session("session_token").await.is_ok());
+
+    let sessions = test_db
+        .get_sessions("admin", "session_token")
+        .await
+        .unwrap();
+    assert_eq!(1, sessions.len());
+    assert!(sessions[0].last_seen > last_seen.format("%Y-%m-%d %H:%M:%S").to_string());
+}
+
+#[db_test]
+async fn get_sessions_lists_sessions_of_user(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    test_db
+        .add_session_token("admin", "token1", Some("Firefox".to_string()))
+        .await
+        .unwrap();
+    test_db
+        .add_session_token("admin", "token2", None)
+        .await
+        .unwrap();
+    test_db
+        .add_session_token("user", "token3", None)
+        .await
+        .unwrap();
+
+    let mut sessions = test_db.get_sessions("admin", "token2").await.unwrap();
+    sessions.sort_by_key(|s| s.id);
+
+    assert_eq!(2, sessions.len());
+    assert_eq!(Some("Firefox".to_string()), sessions[0].user_agent);
+    assert!(!sessions[0].current);
+    assert_eq!(None, sessions[1].user_agent);
+    assert!(sessions[1].current);
+    assert_eq!(sessions[0].created, sessions[0].last_seen);
+}
+
+#[db_test]
+async fn delete_session_of_other_user_fails(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    test_db
+        .add_session_token("admin", "token1", None)
+        .await
+        .unwrap();
+    let id = test_db.get_sessions("admin", "").await.unwrap()[0].id;
+
+    assert!(test_db.delete_session("user", id).await.is_err());
+    assert!(test_db.validate_session("token1").await.is_ok());
+
+    test_db.delete_session("admin", id).await.unwrap();
+    assert!(test_db.validate_session("token1").await.is_err());
+}
+
+#[db_test]
+async fn delete_user_sessions_logs_out_user(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    test_db
+        .add_session_token("user", "token1", None)
+        .await
+        .unwrap();
+    test_db
+        .add_session_token("user", "token2", None)
+        .await
+        .unwrap();
+    test_db
+        .add_session_token("admin", "token3", None)
         .await
         .unwrap();
-    let (name, _) = test_db.validate_session("session_token").await.unwrap();
-    assert_eq!("admin", name);
-
-    let r = test_db.delete_session_token("no_token").await;
 
-    assert!(r.is_ok());
+    assert_eq!(2, test_db.delete_user_sessions("user").await.unwrap());
+    assert!(test_db.validate_session("token1").await.is_err());
+    assert!(test_db.validate_session("token2").await.is_err());
+    assert!(test_db.validate_session("token3").await.is_ok());
 }
 
 #[db_test]
-async fn get_name_valid_user_and_token(test_db: &db::Database) {
+async fn cleanup_expired_sessions_deletes_expired_sessions(test_db: &db::Database) {
     test_db
-        .add_session_token("admin", "session_token")
+        .add_session_token("admin", "token1", None)
+        .await
+        .unwrap();
+    test_db
+        .add_session_token("admin", "token2", None)
+        .await
+        .unwrap();
+    let last_seen = Utc::now() - chrono::Duration::hours(9);
+    test_set_session_last_seen(test_db, "token1", &last_seen)
         .await
         .unwrap();
-    let (name, _) = test_db.validate_session("session_token").await.unwrap();
 
-    assert_eq!("admin", name);
+    assert_eq!(1, test_db.cleanup_expired_sessions().await.unwrap());
+    assert!(test_db.validate_session("token2").await.is_ok());
 }
 
 #[db_test]
@@ -979,11 +1592,8 @@ async fn bootstrap_db_inserts_admin(test_db: &db::Database) {
     let admin = test_db.get_user("admin").await.unwrap();
     assert_eq!(1, admin.id);
     assert_eq!("admin", admin.name);
-    assert_eq!(
-        "81d40d94fee4fb4eeb1a21bb7adb93c06aad35b929c1a2b024ae33b3a9b79e23",
-        admin.pwd
-    );
-    assert_eq!("salt", admin.salt);
+    assert!(admin.pwd.starts_with("$argon2id$"));
+    assert_eq!("", admin.salt);
     assert!(admin.is_admin);
 }
 
@@ -992,6 +1602,23 @@ async fn authenticate_user_valid(test_db: &db::Database) {
     assert!(test_db.authenticate_user("admin", "123").await.is_ok());
 }
 
+#[db_test]
+async fn authenticate_user_upgrades_legacy_hash(test_db: &db::Database) {
+    test_add_legacy_user(test_db, "legacy", "pwd", "salt")
+        .await
+        .unwrap();
+
+    assert!(test_db.authenticate_user("legacy", "wrong").await.is_err());
+    let user = test_db.get_user("legacy").await.unwrap();
+    assert_eq!("salt", user.salt);
+
+    assert!(test_db.authenticate_user("legacy", "pwd").await.is_ok());
+    let user = test_db.get_user("legacy").await.unwrap();
+    assert!(user.pwd.starts_with("$argon2id$"));
+    assert_eq!("", user.salt);
+    assert!(test_db.authenticate_user("legacy", "pwd").await.is_ok());
+}
+
 #[db_test]
 async fn authenticate_user_unknown_user(test_db: &db::Database) {
     assert!(test_db.authenticate_user("unknown", "123").await.is_err());
@@ -1390,6 +2017,45 @@ async fn search_in_crate_name_found_match(test_db: &db::Database) {
     assert_eq!(expected, search_results);
 }
 
+#[db_test]
+async fn search_in_crate_name_paged_returns_page_and_total(test_db: &db::Database) {
+    let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
+    for (name, version) in [
+        ("crate_a", "1.0.0"),
+        ("crate_a", "2.0.0"),
+        ("crate_b", "1.0.0"),
+        ("crate_c", "1.0.0"),
+        ("other", "1.0.0"),
+    ] {
+        test_add_crate(
+            test_db,
+            name,
+            "admin",
+            &Version::try_from(version).unwrap(),
+            &created,
+        )
+        .await
+        .unwrap();
+    }
+
+    let (total, first_page) = test_db
+        .search_in_crate_name_paged("crate", 2, 0)
+        .await
+        .unwrap();
+    let (_, second_page) = test_db
+        .search_in_crate_name_paged("crate", 2, 2)
+        .await
+        .unwrap();
+
+    assert_eq!(3, total);
+    assert_eq!(2, first_page.len());
+    assert_eq!("crate_a", first_page[0].name);
+    assert_eq!("2.0.0", first_page[0].version);
+    assert_eq!("crate_b", first_page[1].name);
+    assert_eq!(1, second_page.len());
+    assert_eq!("crate_c", second_page[0].name);
+}
+
 #[db_test]
 async fn get_crate_overview_list(test_db: &db::Database) {
     let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
@@ -1643,11 +2309,11 @@ async fn add_crate_and_get_crate_data(test_db: &db::Database) {
         ..Default::default()
     };
     test_db
-        .add_user("owner1", "pwd1", "salt1", false, false)
+        .add_user("owner1", "pwd1", false, false)
         .await
         .unwrap();
     test_db
-        .add_user("owner2", "pwd2", "salt2", false, false)
+        .add_user("owner2", "pwd2", false, false)
         .await
         .unwrap();
 
@@ -1898,7 +2564,7 @@ async fn update_docs_link(test_db: &db::Database) {
     let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
     let pm = PublishMetadata::minimal("crate1", "1.0.0");
     test_db
-        .add_user("owner1", "pwd", "salt", false, false)
+        .add_user("owner1", "pwd", false, false)
         .await
         .unwrap();
     test_db
@@ -2070,135 +2736,437 @@ async fn get_prefetch_data_with_full_data(test_db: &db::Database) {
     };
 
     test_db
-        .add_crate(&pm1, "cksum1_1", &created1, "admin")
+        .add_crate(&pm1, "cksum1_1", &created1, "admin")
+        .await
+        .unwrap();
+    test_db
+        .add_crate(&pm2, "cksum2_1", &created2, "admin")
+        .await
+        .unwrap();
+    test_db
+        .add_crate(&pm3, "cksum3_1", &created3, "admin")
+        .await
+        .unwrap();
+
+    let prefetch_data = test_db.get_prefetch_data("crate").await.unwrap();
+
+    assert_eq!(921, prefetch_data.data.len());
+    assert_eq!(
+        created3.format("%Y-%m-%d %H:%M:%S").to_string(),
+        prefetch_data.last_modified
+    );
+    assert_eq!(
+        "bd18a71d56aff9f39c05a9c819d0363b6d6e917f961a49c126ade402667d8568",
+        prefetch_data.etag
+    );
+}
+
+#[db_test]
+async fn delete_updates_etag(test_db: &db::Database) {
+    let created1 = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
+    let created2 = Utc.with_ymd_and_hms(2020, 10, 8, 13, 18, 00).unwrap();
+    let pm1 = PublishMetadata {
+        name: "crate".to_string(),
+        vers: "1.0.0".to_string(),
+        deps: vec![RegistryDep {
+            name: "dep1".to_string(),
+            version_req: "1.0.0".to_string(),
+            features: Some(vec!["feat1".to_string(), "feat2".to_string()]),
+            optional: true,
+            default_features: false,
+            target: Some("normal".to_string()),
+            kind: Some("kind1".to_string()),
+            registry: Some("registry1".to_string()),
+            explicit_name_in_toml: None,
+        }],
+        features: BTreeMap::from_iter(vec![
+            ("feature1".to_string(), vec!["dep1".to_string()]),
+            ("feature2".to_string(), vec!["dep1".to_string()]),
+        ]),
+        authors: Some(vec!["author1".to_string(), "author2".to_string()]),
+        description: Some("description1".to_string()),
+        homepage: Some("homepage1".to_string()),
+        documentation: Some("documentation1".to_string()),
+        readme: Some("readme1".to_string()),
+        repository: Some("repository1".to_string()),
+        keywords: vec!["keyword1".to_string()],
+        categories: vec!["category1".to_string()],
+        license: Some("license1".to_string()),
+        license_file: Some("license_file1".to_string()),
+        badges: None,
+        links: Some("links1".to_string()),
+        readme_file: Some("readme_file1".to_string()),
+    };
+    let pm2 = PublishMetadata {
+        name: "crate".to_string(),
+        vers: "2.0.0".to_string(),
+        deps: vec![RegistryDep {
+            name: "dep2".to_string(),
+            version_req: "2.0.0".to_string(),
+            features: Some(vec!["feat2".to_string(), "feat3".to_string()]),
+            optional: true,
+            default_features: false,
+            target: Some("normal".to_string()),
+            kind: Some("kind2".to_string()),
+            registry: Some("registry2".to_string()),
+            explicit_name_in_toml: None,
+        }],
+        features: BTreeMap::from_iter(vec![
+            ("feature3".to_string(), vec!["dep2".to_string()]),
+            ("feature4".to_string(), vec!["dep2".to_string()]),
+        ]),
+        authors: Some(vec!["author2".to_string(), "author3".to_string()]),
+        description: Some("description2".to_string()),
+        homepage: Some("homepage2".to_string()),
+        documentation: Some("documentation2".to_string()),
+        readme: Some("readme2".to_string()),
+        repository: Some("repository2".to_string()),
+        keywords: vec!["keyword2".to_string()],
+        categories: vec!["category2".to_string()],
+        license: Some("license2".to_string()),
+        license_file: Some("license_file2".to_string()),
+        badges: None,
+        links: Some("links2".to_string()),
+        readme_file: Some("readme_file2".to_string()),
+    };
+    test_db
+        .add_crate(&pm1, "cksum1_1", &created1, "admin")
+        .await
+        .unwrap();
+    test_db
+        .add_crate(&pm2, "cksum2_1", &created2, "admin")
+        .await
+        .unwrap();
+    let prefetch_data = test_db.get_prefetch_data("crate").await.unwrap();
+    let etag_before = prefetch_data.etag;
+
+    test_db
+        .delete_crate(
+            &NormalizedName::from_unchecked("crate".to_string()),
+            &Version::from_unchecked_str("1.0.0"),
+        )
+        .await
+        .unwrap();
+    let prefetch_data = test_db.get_prefetch_data("crate").await.unwrap();
+    let etag_after = prefetch_data.etag;
+
+    assert_ne!(etag_before, etag_after);
+}
+
+#[db_test]
+async fn is_cratesio_cache_up_to_date_not_found(test_db: &db::Database) {
+    let prefetch_state = test_db
+        .is_cratesio_cache_up_to_date(
+            &NormalizedName::from(OriginalName::try_from("crate").unwrap()),
+            Some("etag".to_string()),
+            Some("last_modified".to_string()),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(PrefetchState::NotFound, prefetch_state);
+}
+
+#[db_test]
+async fn cratesio_checksums_are_read_from_cached_index(test_db: &db::Database) {
+    test_db
+        .add_cratesio_prefetch_data(
+            &OriginalName::from_unchecked("My-Crate".to_string()),
+            "etag",
+            "last_modified",
+            None,
+            &[
+                IndexMetadata::minimal("My-Crate", "1.0.0", "cksum1"),
+                IndexMetadata::minimal("My-Crate", "2.0.0", "cksum2"),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let cksum = test_db
+        .get_cratesio_checksum(
+            &NormalizedName::from_unchecked_str("my-crate"),
+            &Version::try_from("2.0.0").unwrap(),
+        )
+        .await
+        .unwrap();
+    let missing = test_db
+        .get_cratesio_checksum(
+            &NormalizedName::from_unchecked_str("my-crate"),
+            &Version::try_from("3.0.0").unwrap(),
+        )
+        .await
+        .unwrap();
+    let versions = test_db.get_cratesio_crate_versions(10, 1).await.unwrap();
+
+    assert_eq!(Some("cksum2".to_string()), cksum);
+    assert_eq!(None, missing);
+    assert_eq!(1, versions.len());
+    assert_eq!("My-Crate", &*versions[0].name);
+    assert_eq!("2.0.0", versions[0].version);
+    assert_eq!("cksum2", versions[0].cksum);
+}
+
+#[db_test]
+async fn new_cratesio_versions_are_quarantined(test_db: &db::Database) {
+    let name = OriginalName::from_unchecked("crate".to_string());
+    let normalized = NormalizedName::from_unchecked_str("crate");
+    test_db
+        .add_cratesio_prefetch_data(
+            &name,
+            "etag",
+            "last_modified",
+            None,
+            &[IndexMetadata::minimal("crate", "1.0.0", "cksum")],
+        )
+        .await
+        .unwrap();
+    let old_release = IndexMetadata {
+        pubtime: Some("2020-01-01T00:00:00Z".to_string()),
+        ..IndexMetadata::minimal("crate", "1.1.0", "cksum")
+    };
+    test_db
+        .add_cratesio_prefetch_data(
+            &name,
+            "etag2",
+            "last_modified2",
+            None,
+            &[
+                IndexMetadata::minimal("crate", "1.0.0", "cksum"),
+                old_release,
+                IndexMetadata::minimal("crate", "2.0.0", "cksum"),
+            ],
+        )
+        .await
+        .unwrap();
+    let published_after = Utc::now() - chrono::Duration::days(1);
+
+    let quarantined = test_db
+        .get_quarantined_cratesio_versions(&normalized, &published_after)
+        .await
+        .unwrap();
+    let is_quarantined = async |version: &str| {
+        test_db
+            .is_quarantined_cratesio_version(
+                &normalized,
+                &Version::from_unchecked_str(version),
+                &published_after,
+            )
+            .await
+            .unwrap()
+    };
+    assert_eq!(Some(true), is_quarantined("2.0.0").await);
+    assert_eq!(Some(false), is_quarantined("1.1.0").await);
+    assert_eq!(Some(false), is_quarantined("1.0.0").await);
+    assert_eq!(None, is_quarantined("3.0.0").await);
+    assert!(!test_db.is_quarantine_exempt(&normalized).await.unwrap());
+    test_db.add_quarantine_exemption(&normalized).await.unwrap();
+    test_db.add_quarantine_exemption(&normalized).await.unwrap();
+    assert!(test_db.is_quarantine_exempt(&normalized).await.unwrap());
+    assert_eq!(Some(false), is_quarantined("2.0.0").await);
+    let exempt = test_db
+        .get_quarantined_cratesio_versions(&normalized, &published_after)
+        .await
+        .unwrap();
+    let exemptions = test_db.get_quarantine_exemptions().await.unwrap();
+    test_db
+        .delete_quarantine_exemption(&normalized)
+        .await
+        .unwrap();
+
+    assert_eq!(vec!["2.0.0".to_string()], quarantined);
+    assert!(exempt.is_empty());
+    assert_eq!(1, exemptions.len());
+    assert_eq!("crate", exemptions[0].name);
+    assert!(matches!(
+        test_db.delete_quarantine_exemption(&normalized).await,
+        Err(DbError::QuarantineExemptionNotFound(_))
+    ));
+}
+
+#[db_test]
+async fn cratesio_licenses_are_stored_per_version(test_db: &db::Database) {
+    let normalized = NormalizedName::from_unchecked_str("crate");
+    test_db
+        .add_cratesio_prefetch_data(
+            &OriginalName::from_unchecked("crate".to_string()),
+            "etag",
+            "last_modified",
+            None,
+            &[
+                IndexMetadata::minimal("crate", "1.0.0", "cksum"),
+                IndexMetadata::minimal("crate", "2.0.0", "cksum"),
+            ],
+        )
+        .await
+        .unwrap();
+
+    test_db
+        .set_cratesio_licenses(
+            &normalized,
+            &BTreeMap::from([
+                ("1.0.0".to_string(), "MIT".to_string()),
+                ("3.0.0".to_string(), "Apache-2.0".to_string()),
+            ]),
+        )
+        .await
+        .unwrap();
+    let licenses = test_db.get_cratesio_licenses(&normalized).await.unwrap();
+    let versions = test_db.get_cratesio_crate_versions(10, 0).await.unwrap();
+
+    assert_eq!(
+        BTreeMap::from([("1.0.0".to_string(), "MIT".to_string())]),
+        licenses
+    );
+    assert_eq!(Some("MIT".to_string()), versions[0].license);
+    assert_eq!(None, versions[1].license);
+}
+
+#[db_test]
+async fn proxy_policy_rules_are_added_and_deleted(test_db: &db::Database) {
+    let id = test_db
+        .add_proxy_policy_rule(
+            PolicyAction::Deny,
+            "evil-*",
+            Some("<2.0.0".to_string()),
+            &["GPL-3.0-only".to_string()],
+            Some("team".to_string()),
+            "Known malware",
+        )
+        .await
+        .unwrap();
+
+    let rules = test_db.get_proxy_policy_rules().await.unwrap();
+    test_db.delete_proxy_policy_rule(id).await.unwrap();
+
+    assert_eq!(1, rules.len());
+    assert_eq!(id, rules[0].id);
+    assert_eq!(PolicyAction::Deny, rules[0].action);
+    assert_eq!("evil-*", rules[0].crate_pattern);
+    assert_eq!(Some("<2.0.0".to_string()), rules[0].version_req);
+    assert_eq!(vec!["GPL-3.0-only".to_string()], rules[0].licenses);
+    assert_eq!(Some("team".to_string()), rules[0].group);
+    assert_eq!("Known malware", rules[0].reason);
+    assert!(test_db.get_proxy_policy_rules().await.unwrap().is_empty());
+    assert!(matches!(
+        test_db.delete_proxy_policy_rule(id).await,
+        Err(DbError::ProxyPolicyRuleNotFound(_))
+    ));
+}
+
+#[db_test]
+async fn cratesio_cached_files_are_ordered_by_last_download(test_db: &db::Database) {
+    let old = OriginalName::from_unchecked("Old".to_string());
+    let new = OriginalName::from_unchecked("new".to_string());
+    let version = Version::try_from("1.0.0").unwrap();
+    // Download times have a resolution of seconds
+    let second = std::time::Duration::from_secs(1);
+    test_db
+        .touch_cratesio_cached_file(&new, &version, 100)
         .await
         .unwrap();
+    std::thread::sleep(second);
     test_db
-        .add_crate(&pm2, "cksum2_1", &created2, "admin")
+        .touch_cratesio_cached_file(&old, &version, 200)
         .await
         .unwrap();
+    std::thread::sleep(second);
     test_db
-        .add_crate(&pm3, "cksum3_1", &created3, "admin")
+        .touch_cratesio_cached_file(&new, &version, 300)
         .await
         .unwrap();
 
-    let prefetch_data = test_db.get_prefetch_data("crate").await.unwrap();
+    let files = test_db.get_cratesio_cached_files(None).await.unwrap();
+    let expired = test_db
+        .get_cratesio_cached_files(Some(Utc::now() + chrono::Duration::days(1)))
+        .await
+        .unwrap();
+    test_db
+        .delete_cratesio_cached_file(&old, &version)
+        .await
+        .unwrap();
+    let remaining = test_db.get_cratesio_cached_files(None).await.unwrap();
 
-    assert_eq!(921, prefetch_data.data.len());
-    assert_eq!(
-        created3.format("%Y-%m-%d %H:%M:%S").to_string(),
-        prefetch_data.last_modified
-    );
-    assert_eq!(
-        "bd18a71d56aff9f39c05a9c819d0363b6d6e917f961a49c126ade402667d8568",
-        prefetch_data.etag
-    );
+    assert_eq!(2, files.len());
+    assert_eq!(("Old", 200), (files[0].name.as_str(), files[0].size));
+    assert_eq!(("new", 300), (files[1].name.as_str(), files[1].size));
+    assert!(files.iter().all(|f| !f.expired));
+    assert!(expired.iter().all(|f| f.expired));
+    assert_eq!(1, remaining.len());
+    assert_eq!("new", remaining[0].name);
 }
 
 #[db_test]
-async fn delete_updates_etag(test_db: &db::Database) {
-    let created1 = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
-    let created2 = Utc.with_ymd_and_hms(2020, 10, 8, 13, 18, 00).unwrap();
-    let pm1 = PublishMetadata {
-        name: "crate".to_string(),
-        vers: "1.0.0".to_string(),
-        deps: vec![RegistryDep {
-            name: "dep1".to_string(),
-            version_req: "1.0.0".to_string(),
-            features: Some(vec!["feat1".to_string(), "feat2".to_string()]),
-            optional: true,
-            default_features: false,
-            target: Some("normal".to_string()),
-            kind: Some("kind1".to_string()),
-            registry: Some("registry1".to_string()),
-            explicit_name_in_toml: None,
-        }],
-        features: BTreeMap::from_iter(vec![
-            ("feature1".to_string(), vec!["dep1".to_string()]),
-            ("feature2".to_string(), vec!["dep1".to_string()]),
-        ]),
-        authors: Some(vec!["author1".to_string(), "author2".to_string()]),
-        description: Some("description1".to_string()),
-        homepage: Some("homepage1".to_string()),
-        documentation: Some("documentation1".to_string()),
-        readme: Some("readme1".to_string()),
-        repository: Some("repository1".to_string()),
-        keywords: vec!["keyword1".to_string()],
-        categories: vec!["category1".to_string()],
-        license: Some("license1".to_string()),
-        license_file: Some("license_file1".to_string()),
-        badges: None,
-        links: Some("links1".to_string()),
-        readme_file: Some("readme_file1".to_string()),
-    };
-    let pm2 = PublishMetadata {
-        name: "crate".to_string(),
-        vers: "2.0.0".to_string(),
-        deps: vec![RegistryDep {
-            name: "dep2".to_string(),
-            version_req: "2.0.0".to_string(),
-            features: Some(vec!["feat2".to_string(), "feat3".to_string()]),
-            optional: true,
-            default_features: false,
-            target: Some("normal".to_string()),
-            kind: Some("kind2".to_string()),
-            registry: Some("registry2".to_string()),
-            explicit_name_in_toml: None,
-        }],
-        features: BTreeMap::from_iter(vec![
-            ("feature3".to_string(), vec!["dep2".to_string()]),
-            ("feature4".to_string(), vec!["dep2".to_string()]),
-        ]),
-        authors: Some(vec!["author2".to_string(), "author3".to_string()]),
-        description: Some("description2".to_string()),
-        homepage: Some("homepage2".to_string()),
-        documentation: Some("documentation2".to_string()),
-        readme: Some("readme2".to_string()),
-        repository: Some("repository2".to_string()),
-        keywords: vec!["keyword2".to_string()],
-        categories: vec!["category2".to_string()],
-        license: Some("license2".to_string()),
-        license_file: Some("license_file2".to_string()),
-        badges: None,
-        links: Some("links2".to_string()),
-        readme_file: Some("readme_file2".to_string()),
-    };
-    test_db
-        .add_crate(&pm1, "cksum1_1", &created1, "admin")
-        .await
-        .unwrap();
+async fn untracked_cratesio_cached_files_are_added(test_db: &db::Database) {
+    let tracked = OriginalName::from_unchecked("tracked".to_string());
+    let untracked = OriginalName::from_unchecked("Untracked".to_string());
+    let version = Version::try_from("1.0.0").unwrap();
     test_db
-        .add_crate(&pm2, "cksum2_1", &created2, "admin")
+        .touch_cratesio_cached_file(&tracked, &version, 100)
         .await
         .unwrap();
-    let prefetch_data = test_db.get_prefetch_data("crate").await.unwrap();
-    let etag_before = prefetch_data.etag;
 
-    test_db
-        .delete_crate(
-            &NormalizedName::from_unchecked("crate".to_string()),
-            &Version::from_unchecked_str("1.0.0"),
-        )
+    let added = test_db
+        .track_cratesio_cached_files(&[
+            (tracked.clone(), version.clone(), 999),
+            (untracked.clone(), version.clone(), 200),
+        ])
         .await
         .unwrap();
-    let prefetch_data = test_db.get_prefetch_data("crate").await.unwrap();
-    let etag_after = prefetch_data.etag;
+    let files = test_db.get_cratesio_cached_files(None).await.unwrap();
 
-    assert_ne!(etag_before, etag_after);
+    assert_eq!(1, added);
+    assert_eq!(
+        vec![("tracked", 100), ("Untracked", 200)],
+        files
+            .iter()
+            .map(|f| (f.name.as_str(), f.size))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!("untracked", files[1].normalized_name().to_string());
 }
 
 #[db_test]
-async fn is_cratesio_cache_up_to_date_not_found(test_db: &db::Database) {
-    let prefetch_state = test_db
-        .is_cratesio_cache_up_to_date(
-            &NormalizedName::from(OriginalName::try_from("crate").unwrap()),
-            Some("etag".to_string()),
-            Some("last_modified".to_string()),
-        )
+async fn cratesio_pins_are_added_and_deleted(test_db: &db::Database) {
+    let name = NormalizedName::from_unchecked_str("crate");
+    let version = Version::try_from("1.0.0").unwrap();
+
+    test_db.add_cratesio_pin(&name, &version).await.unwrap();
+    test_db.add_cratesio_pin(&name, &version).await.unwrap();
+    let pins = test_db.get_cratesio_pins().await.unwrap();
+    test_db.delete_cratesio_pin(&name, &version).await.unwrap();
+
+    assert_eq!(1, pins.len());
+    assert_eq!(
+        ("crate", "1.0.0"),
+        (pins[0].name.as_str(), pins[0].version.as_str())
+    );
+    assert!(test_db.get_cratesio_pins().await.unwrap().is_empty());
+    assert!(matches!(
+        test_db.delete_cratesio_pin(&name, &version).await,
+        Err(DbError::CratesIoPinNotFound(_))
+    ));
+}
+
+#[db_test]
+async fn cratesio_eviction_reports_are_listed_newest_first(test_db: &db::Database) {
+    let started = Utc::now();
+    let first = test_db
+        .add_cratesio_eviction_report(&started, &["a@1.0.0".to_string()], 100, 1000)
+        .await
+        .unwrap();
+    let second = test_db
+        .add_cratesio_eviction_report(&started, &[], 0, 1000)
         .await
         .unwrap();
 
-    assert_eq!(PrefetchState::NotFound, prefetch_state);
+    let reports = test_db.get_cratesio_eviction_reports(10).await.unwrap();
+    let latest = test_db.get_cratesio_eviction_reports(1).await.unwrap();
+
+    assert_eq!(vec![second.clone(), first.clone()], reports);
+    assert_eq!(vec!["a@1.0.0".to_string()], first.evicted);
+    assert_eq!((100, 1000), (first.reclaimed_bytes, first.remaining_bytes));
+    assert_eq!(vec![second], latest);
 }
 
 #[db_test]
@@ -2219,6 +3187,7 @@ async fn is_cratesio_cache_up_to_date_up_to_date(test_db: &db::Database) {
                 links: None,
                 v: Some(1),
                 features2: None,
+                pubtime: None,
             }],
         )
         .await
@@ -2248,6 +3217,7 @@ async fn is_cratesio_cache_up_to_date_needs_update(test_db: &db::Database) {
         links: None,
         v: Some(1),
         features2: None,
+        pubtime: None,
     }];
     test_db
         .add_cratesio_prefetch_data(
@@ -2270,6 +3240,7 @@ async fn is_cratesio_cache_up_to_date_needs_update(test_db: &db::Database) {
             links: None,
             v: Some(1),
             features2: None,
+            pubtime: None,
         },
         IndexMetadata {
             name: "crate".to_string(),
@@ -2281,6 +3252,7 @@ async fn is_cratesio_cache_up_to_date_needs_update(test_db: &db::Database) {
             links: None,
             v: Some(1),
             features2: None,
+            pubtime: None,
         },
     ];
     test_db
@@ -2564,3 +3536,296 @@ async fn test_delete_crate_rollback(test_db: &db::Database) {
     // Crate meta is deleted first, but the actions should be rolled back on error.
     assert_eq!(1, meta.len());
 }
+
+#[db_test]
+async fn get_crate_licenses_returns_license_of_all_versions(test_db: &db::Database) {
+    let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
+    let mut pm = PublishMetadata::minimal("crate1", "1.0.0");
+    pm.license = Some("MIT".to_string());
+    test_db
+        .add_crate(&pm, "cksum", &created, "admin")
+        .await
+        .unwrap();
+    let pm = PublishMetadata::minimal("crate1", "2.0.0");
+    test_db
+        .add_crate(&pm, "cksum", &created, "admin")
+        .await
+        .unwrap();
+
+    let licenses = test_db.get_crate_licenses().await.unwrap();
+
+    assert_eq!(2, licenses.len());
+    assert_eq!("crate1", licenses[0].name);
+    assert_eq!("1.0.0", licenses[0].version);
+    assert_eq!(Some("MIT".to_string()), licenses[0].license);
+    assert_eq!("2.0.0", licenses[1].version);
+    assert_eq!(None, licenses[1].license);
+}
+
+#[db_test]
+async fn trusted_publisher_add_get_and_delete(test_db: &db::Database) {
+    let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
+    let pm = PublishMetadata::minimal("crate1", "1.0.0");
+    test_db
+        .add_crate(&pm, "cksum", &created, "admin")
+        .await
+        .unwrap();
+    let crate_name = NormalizedName::from_unchecked("crate1".to_string());
+    let claims = BTreeMap::from([("repository".to_string(), "kellnr/kellnr".to_string())]);
+
+    let id = test_db
+        .add_trusted_publisher(
+            &crate_name,
+            "https://issuer",
+            "kellnr",
+            "file:///jwks.json",
+            &claims,
+        )
+        .await
+        .unwrap();
+    let by_crate = test_db.get_trusted_publishers(&crate_name).await.unwrap();
+    let by_issuer = test_db
+        .get_trusted_publishers_by_issuer("https://issuer")
+        .await
+        .unwrap();
+
+    assert_eq!(1, by_crate.len());
+    assert_eq!(id, by_crate[0].id);
+    assert_eq!("crate1", by_crate[0].crate_name);
+    assert_eq!("kellnr", by_crate[0].audience);
+    assert_eq!("file:///jwks.json", by_crate[0].jwks_url);
+    assert_eq!(claims, by_crate[0].claims);
+    assert_eq!(by_crate, by_issuer);
+
+    let other = NormalizedName::from_unchecked("other".to_string());
+    assert!(test_db.delete_trusted_publisher(&other, id).await.is_err());
+    test_db
+        .delete_trusted_publisher(&crate_name, id)
+        .await
+        .unwrap();
+    assert!(
+        test_db
+            .get_trusted_publishers(&crate_name)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[db_test]
+async fn trusted_publish_token_returns_crates_until_expired(test_db: &db::Database) {
+    let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
+    for name in ["crate1", "crate2"] {
+        let pm = PublishMetadata::minimal(name, "1.0.0");
+        test_db
+            .add_crate(&pm, "cksum", &created, "admin")
+            .await
+            .unwrap();
+    }
+    let mut ids = Vec::new();
+    for name in ["crate1", "crate2"] {
+        let id = test_db
+            .add_trusted_publisher(
+                &NormalizedName::from_unchecked(name.to_string()),
+                "https://issuer",
+                "kellnr",
+                "file:///jwks.json",
+                &BTreeMap::new(),
+            )
+            .await
+            .unwrap();
+        ids.push(id);
+    }
+    let in_future = Utc::now() + chrono::Duration::minutes(5);
+    let in_past = Utc::now() - chrono::Duration::minutes(5);
+
+    test_db
+        .add_trusted_publish_token("valid", &ids, &in_future)
+        .await
+        .unwrap();
+    test_db
+        .add_trusted_publish_token("expired", &ids[..1], &in_past)
+        .await
+        .unwrap();
+
+    let crates = test_db.get_trusted_publish_crates("valid").await.unwrap();
+    assert_eq!(
+        vec![
+            NormalizedName::from_unchecked("crate1".to_string()),
+            NormalizedName::from_unchecked("crate2".to_string())
+        ],
+        crates
+    );
+    assert!(test_db.get_trusted_publish_crates("expired").await.is_err());
+    assert!(test_db.get_trusted_publish_crates("unknown").await.is_err());
+
+    test_db.delete_trusted_publish_token("valid").await.unwrap();
+    assert!(test_db.get_trusted_publish_crates("valid").await.is_err());
+}
+
+#[db_test]
+async fn totp_is_enabled_after_setup_only(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    assert_eq!(None, test_db.get_totp_secret("user").await.unwrap());
+
+    test_db.set_totp_secret("user", "encrypted").await.unwrap();
+    assert_eq!(
+        Some("encrypted".to_string()),
+        test_db.get_totp_secret("user").await.unwrap()
+    );
+    assert!(!test_db.get_user("user").await.unwrap().totp_enabled);
+
+    let codes = vec!["code1".to_string(), "code2".to_string()];
+    test_db.enable_totp("user", &codes).await.unwrap();
+    assert!(test_db.get_user("user").await.unwrap().totp_enabled);
+}
+
+#[db_test]
+async fn recovery_codes_can_be_used_once(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    test_db
+        .add_user("other", "pwd", false, false)
+        .await
+        .unwrap();
+    test_db.set_totp_secret("user", "encrypted").await.unwrap();
+    let codes = vec!["code1".to_string(), "code2".to_string()];
+    test_db.enable_totp("user", &codes).await.unwrap();
+
+    assert!(!test_db.use_recovery_code("other", "code1").await.unwrap());
+    assert!(test_db.use_recovery_code("user", "code1").await.unwrap());
+    assert!(!test_db.use_recovery_code("user", "code1").await.unwrap());
+    assert!(!test_db.use_recovery_code("user", "unknown").await.unwrap());
+    assert!(test_db.use_recovery_code("user", "code2").await.unwrap());
+}
+
+#[db_test]
+async fn totp_failures_are_counted_until_reset(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    let since = Utc::now() - chrono::Duration::minutes(15);
+
+    assert_eq!(0, test_db.get_totp_failures("user", &since).await.unwrap());
+    assert_eq!(1, test_db.add_totp_failure("user", &since).await.unwrap());
+    assert_eq!(2, test_db.add_totp_failure("user", &since).await.unwrap());
+    assert_eq!(2, test_db.get_totp_failures("user", &since).await.unwrap());
+    // Failures before the window are not counted anymore
+    let future = Utc::now() + chrono::Duration::minutes(1);
+    assert_eq!(0, test_db.get_totp_failures("user", &future).await.unwrap());
+    assert_eq!(1, test_db.add_totp_failure("user", &future).await.unwrap());
+
+    test_db.reset_totp_failures("user").await.unwrap();
+    assert_eq!(0, test_db.get_totp_failures("user", &since).await.unwrap());
+}
+
+#[db_test]
+async fn totp_steps_can_be_used_once(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+
+    assert!(test_db.use_totp_step("user", 100).await.unwrap());
+    assert!(!test_db.use_totp_step("user", 100).await.unwrap());
+    assert!(!test_db.use_totp_step("user", 99).await.unwrap());
+    assert!(test_db.use_totp_step("user", 101).await.unwrap());
+
+    // A new secret starts with a new sequence of codes
+    test_db.set_totp_secret("user", "encrypted").await.unwrap();
+    assert!(test_db.use_totp_step("user", 50).await.unwrap());
+}
+
+#[db_test]
+async fn disable_totp_deletes_secret_and_recovery_codes(test_db: &db::Database) {
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    test_db.set_totp_secret("user", "encrypted").await.unwrap();
+    test_db
+        .enable_totp("user", &["code1".to_string()])
+        .await
+        .unwrap();
+
+    test_db.disable_totp("user").await.unwrap();
+
+    assert!(!test_db.get_user("user").await.unwrap().totp_enabled);
+    assert_eq!(None, test_db.get_totp_secret("user").await.unwrap());
+    assert!(!test_db.use_recovery_code("user", "code1").await.unwrap());
+}
+
+#[db_test]
+async fn service_accounts_authenticate_with_tokens_only(test_db: &db::Database) {
+    test_db
+        .add_service_account("ci", None, false)
+        .await
+        .unwrap();
+    test_db
+        .add_auth_token("ci token", "ci_token", "ci", &TokenScopes::default(), None)
+        .await
+        .unwrap();
+
+    let (user, _) = test_db.get_user_from_token("ci_token").await.unwrap();
+    assert_eq!("ci", user.name);
+    assert!(user.is_service_account);
+    assert!(matches!(
+        test_db.authenticate_user("ci", "").await,
+        Err(DbError::PasswordMismatch)
+    ));
+    assert!(matches!(
+        test_db.add_session_token("ci", "session", None).await,
+        Err(DbError::ServiceAccountLogin(_))
+    ));
+}
+
+#[db_test]
+async fn service_accounts_are_listed_with_group(test_db: &db::Database) {
+    test_db.add_group("devs").await.unwrap();
+    test_db.add_user("user", "pwd", false, false).await.unwrap();
+    test_db
+        .add_service_account("deploy", Some("devs".to_string()), true)
+        .await
+        .unwrap();
+    test_db
+        .add_service_account("ci", None, false)
+        .await
+        .unwrap();
+
+    let accounts = test_db.get_service_accounts().await.unwrap();
+
+    assert_eq!(
+        vec![
+            ServiceAccount {
+                name: "ci".to_string(),
+                group: None,
+                is_read_only: false,
+            },
+            ServiceAccount {
+                name: "deploy".to_string(),
+                group: Some("devs".to_string()),
+                is_read_only: true,
+            },
+        ],
+        accounts
+    );
+    assert!(test_db.get_service_account("user").await.is_err());
+    assert!(!test_db.get_user("user").await.unwrap().is_service_account);
+}
+
+#[db_test]
+async fn deleting_group_keeps_its_service_accounts(test_db: &db::Database) {
+    test_db.add_group("devs").await.unwrap();
+    test_db
+        .add_service_account("deploy", Some("devs".to_string()), false)
+        .await
+        .unwrap();
+
+    test_db.delete_group("devs").await.unwrap();
+
+    assert_eq!(
+        None,
+        test_db.get_service_account("deploy").await.unwrap().group
+    );
+}
+
+#[db_test]
+async fn service_account_needs_existing_group(test_db: &db::Database) {
+    assert!(matches!(
+        test_db
+            .add_service_account("deploy", Some("unknown".to_string()), false)
+            .await,
+        Err(DbError::GroupNotFound(_))
+    ));
+}
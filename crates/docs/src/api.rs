@@ -12,8 +12,10 @@ use axum::{
 };
 use common::original_name::OriginalName;
 use common::version::Version;
+use db::{EndpointScope, WebhookEvent};
 use error::api_error::ApiResult;
 use registry::kellnr_api::check_ownership;
+use registry::webhook;
 
 pub async fn docs_in_queue(State(db): DbState) -> ApiResult<Json<DocQueueResponse>> {
     let doc = db.get_doc_queue().await?;
@@ -29,12 +31,11 @@ pub async fn latest_docs(
     let opt_doc_version = get_latest_version_with_doc(&name, &settings);
     let res_db_version = db.get_max_version_from_name(&name).await;
 
-    if let Some(doc_version) = opt_doc_version {
-        if let Ok(db_version) = res_db_version {
-            if doc_version == db_version {
-                return Redirect::temporary(&compute_doc_url(&name, &db_version));
-            }
-        }
+    if let Some(doc_version) = opt_doc_version
+        && let Ok(db_version) = res_db_version
+        && doc_version == db_version
+    {
+        return Redirect::temporary(&compute_doc_url(&name, &db_version));
     }
 
     Redirect::temporary("/")
@@ -62,7 +63,7 @@ pub async fn publish_docs(
 
     // Check if user from token is an owner of the crate.
     // If not, he is not allowed to push the docs.
-    check_ownership(&normalized_name, &token, &db).await?;
+    check_ownership(&normalized_name, &token, EndpointScope::DocsUpload, &db).await?;
 
     let doc_path = settings.docs_path().join(&*package).join(crate_version);
 
@@ -76,6 +77,13 @@ pub async fn publish_docs(
         &compute_doc_url(&package, &version),
     )
     .await?;
+    webhook::notify(
+        db.as_ref(),
+        WebhookEvent::DocsBuilt,
+        &normalized_name,
+        Some(&version),
+    )
+    .await;
 
     Ok(Json(DocUploadResponse::new(
         "Successfully published docs.".to_string(),
@@ -6,9 +6,10 @@ use cargo::{
     util::command_prelude::CompileMode,
 };
 use common::{original_name::OriginalName, version::Version};
-use db::{Database, DbProvider, DocQueueEntry};
+use db::{Database, DbProvider, DocQueueEntry, WebhookEvent};
 use flate2::read::GzDecoder;
 use fs_extra::dir::{CopyOptions, copy};
+use registry::webhook;
 use std::path::{Path, PathBuf};
 use storage::kellnr_crate_storage::KellnrCrateStorage;
 use tar::Archive;
@@ -45,6 +46,13 @@ async fn inner_loop(
             let docs_link = compute_doc_url(&entry.normalized_name, &version);
             db.update_docs_link(&entry.normalized_name, &version, &docs_link)
                 .await?;
+            webhook::notify(
+                db,
+                WebhookEvent::DocsBuilt,
+                &entry.normalized_name,
+                Some(&version),
+            )
+            .await;
         }
         db.delete_doc_queue(entry.id).await?;
     }
@@ -64,7 +72,7 @@ async fn extract_docs(
     let version = Version::from_unchecked_str(&doc.version);
     let contents = cs.get(&orig_name, &version).await.ok_or_else(|| {
         error!("Failed to get crate from storage");
-        DocsError::CrateDoesNotExist(doc.normalized_name.to_string(), doc.version.to_string())
+        DocsError::CrateDoesNotExist(doc.normalized_name.to_string(), doc.version.clone())
     })?;
     let tar = GzDecoder::new(std::io::Cursor::new(contents));
     let mut archive = Archive::new(tar);
@@ -1,25 +1,32 @@
 use super::config_json::ConfigJson;
+use crate::proxy_policy::{ProxyError, ProxyPolicy, blocked_message, license};
 use appstate::{CratesIoPrefetchSenderState, DbState, SettingsState};
+use auth::token::OptionToken;
 use axum::Json;
 use axum::extract::{Path, State};
 use axum::http::HeaderMap;
+use chrono::DateTime;
 use common::cratesio_prefetch_msg::{CratesioPrefetchMsg, InsertData, UpdateData};
-use common::index_metadata::IndexMetadata;
-use common::normalized_name::NormalizedName;
+use common::index_metadata::{IndexMetadata, sparse_index_path};
 use common::original_name::OriginalName;
 use common::prefetch::Prefetch;
+use common::single_flight::SingleFlight;
 use db::provider::PrefetchState;
 use db::{ConString, Database, DbProvider};
 use hyper::StatusCode;
 use moka::future::Cache;
 use reqwest::{Client, ClientBuilder, Url};
 use serde::Deserialize;
+use settings::Proxy;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
 static UPDATE_INTERVAL_SECS: u64 = 60 * 120; // 2h background update interval
 static UPDATE_CACHE_TIMEOUT_SECS: u64 = 60 * 30; // 30 min cache timeout
+/// Running fetches of index files from the upstream registry, by URL
+static INDEX_FETCHES: SingleFlight<String, Result<Prefetch, StatusCode>> = SingleFlight::new();
 static CLIENT: std::sync::LazyLock<Client> = std::sync::LazyLock::new(|| {
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -41,26 +48,30 @@ pub async fn config_cratesio(State(settings): SettingsState) -> Json<ConfigJson>
 pub async fn prefetch_cratesio(
     Path((_a, _b, name)): Path<(String, String, OriginalName)>,
     headers: HeaderMap,
+    token: OptionToken,
     State(db): DbState,
+    State(settings): SettingsState,
     State(sender): CratesIoPrefetchSenderState,
-) -> Result<Prefetch, StatusCode> {
-    internal_prefetch_cratesio(name, headers, &db, &sender).await
+) -> Result<Prefetch, ProxyError> {
+    internal_prefetch_cratesio(name, headers, &token, &db, &settings.proxy, &sender).await
 }
 
 pub async fn prefetch_len2_cratesio(
     Path((_a, name)): Path<(String, OriginalName)>,
     headers: HeaderMap,
+    token: OptionToken,
     State(db): DbState,
+    State(settings): SettingsState,
     State(sender): CratesIoPrefetchSenderState,
-) -> Result<Prefetch, StatusCode> {
-    internal_prefetch_cratesio(name, headers, &db, &sender).await
+) -> Result<Prefetch, ProxyError> {
+    internal_prefetch_cratesio(name, headers, &token, &db, &settings.proxy, &sender).await
 }
 
 pub async fn init_cratesio_prefetch_thread(
     con_string: ConString,
     sender: flume::Sender<CratesioPrefetchMsg>,
     recv: flume::Receiver<CratesioPrefetchMsg>,
-    num_threads: usize,
+    proxy: Proxy,
     max_con: u32,
 ) {
     // Threads that takes messages to update the crates.io index
@@ -74,16 +85,24 @@ pub async fn init_cratesio_prefetch_thread(
         .time_to_live(Duration::from_secs(UPDATE_CACHE_TIMEOUT_SECS))
         .build();
 
-    for _ in 0..num_threads {
+    let offline = proxy.offline;
+    let proxy = Arc::new(proxy);
+    for _ in 0..proxy.num_threads {
         let recv2 = recv.clone();
         let db2 = db.clone();
         let cache2 = cache.clone();
+        let proxy2 = proxy.clone();
 
         tokio::spawn(async move {
-            cratesio_prefetch_thread(db2, cache2, recv2).await;
+            cratesio_prefetch_thread(db2, cache2, recv2, &proxy2).await;
         });
     }
 
+    if offline {
+        info!("The crates.io proxy is offline, only cached crates are served");
+        return;
+    }
+
     // Thread that periodically checks if the crates.io index needs to be updated.
     // It sends an update message to the thread above which then updates the index.
     tokio::spawn(async move {
@@ -97,9 +116,11 @@ pub async fn init_cratesio_prefetch_thread(
 async fn internal_prefetch_cratesio(
     name: OriginalName,
     headers: HeaderMap,
+    token: &OptionToken,
     db: &Arc<dyn DbProvider>,
+    proxy: &Proxy,
     sender: &flume::Sender<CratesioPrefetchMsg>,
-) -> Result<Prefetch, StatusCode> {
+) -> Result<Prefetch, ProxyError> {
     let if_modified_since = headers
         .get("if-modified-since")
         .map(|h| h.to_str().unwrap_or_default().to_string());
@@ -123,18 +144,171 @@ async fn internal_prefetch_cratesio(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+    let quarantined = match proxy.quarantine_cutoff() {
+        Some(cutoff) => db
+            .get_quarantined_cratesio_versions(&name.to_normalized(), &cutoff)
+            .await
+            .map_err(|e| {
+                error!("Could not get quarantined versions of {name}. Error {e}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?,
+        None => vec![],
+    };
+
+    let user = match token {
+        OptionToken::Some(token) => Some(token.user.as_str()),
+        OptionToken::None => None,
+    };
+    let policy = ProxyPolicy::for_user(db.as_ref(), user)
+        .await
+        .map_err(|e| {
+            error!("Could not get the proxy policy. Error {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
     match prefetch_state {
         PrefetchState::NeedsUpdate(p) => {
-            background_update(name.clone(), sender, if_modified_since, if_none_match);
+            if !proxy.offline {
+                background_update(name.clone(), sender, if_modified_since, if_none_match);
+            }
             trace!("Prefetching {name} from crates.io cache: Needs Update");
-            Ok(p)
+            apply_policy(p, &name, &policy, db, proxy, |m| {
+                quarantined.contains(&m.vers)
+            })
+            .await
+        }
+        PrefetchState::UpToDate if !quarantined.is_empty() || policy.affects(&name) => {
+            // The client may have got the index before versions were quarantined or blocked
+            let Ok(PrefetchState::NeedsUpdate(p)) = db
+                .is_cratesio_cache_up_to_date(&name.to_normalized(), None, None)
+                .await
+            else {
+                return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+            };
+            let etag = p.etag.clone();
+            let prefetch = apply_policy(p, &name, &policy, db, proxy, |m| {
+                quarantined.contains(&m.vers)
+            })
+            .await?;
+            if prefetch.etag == etag {
+                Err(StatusCode::NOT_MODIFIED.into())
+            } else {
+                Ok(prefetch)
+            }
         }
         PrefetchState::UpToDate => {
-            background_update(name.clone(), sender, if_modified_since, if_none_match);
+            if !proxy.offline {
+                background_update(name.clone(), sender, if_modified_since, if_none_match);
+            }
             trace!("Prefetching {name} from crates.io cache: Up to Date");
-            Err(StatusCode::NOT_MODIFIED)
+            Err(StatusCode::NOT_MODIFIED.into())
+        }
+        PrefetchState::NotFound if proxy.offline => {
+            debug!("Crate {name} is not cached and the crates.io proxy is offline");
+            Err(StatusCode::NOT_FOUND.into())
+        }
+        PrefetchState::NotFound => {
+            let prefetch = fetch_cratesio_prefetch(name.clone(), proxy, sender).await?;
+            // Only the publish time of crates.io is known for crates never seen before
+            let cutoff = match proxy.quarantine_cutoff() {
+                Some(cutoff) if !is_quarantine_exempt(db, &name).await? => Some(cutoff),
+                _ => None,
+            };
+            apply_policy(prefetch, &name, &policy, db, proxy, |m| {
+                cutoff.is_some_and(|cutoff| {
+                    m.pubtime
+                        .as_deref()
+                        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                        .is_some_and(|t| t > cutoff)
+                })
+            })
+            .await
+        }
+    }
+}
+
+/// Removes quarantined versions and versions blocked by the proxy policy from the index.
+/// If the policy blocks all versions, cargo gets an error with the reason instead.
+async fn apply_policy(
+    prefetch: Prefetch,
+    name: &OriginalName,
+    policy: &ProxyPolicy,
+    db: &Arc<dyn DbProvider>,
+    proxy: &Proxy,
+    is_quarantined: impl Fn(&IndexMetadata) -> bool,
+) -> Result<Prefetch, ProxyError> {
+    if !policy.affects(name) {
+        return Ok(remove_versions(prefetch, is_quarantined));
+    }
+
+    let data = String::from_utf8_lossy(&prefetch.data).into_owned();
+    let versions: Vec<String> = data
+        .lines()
+        .filter_map(|line| serde_json::from_str::<IndexMetadata>(line).ok())
+        .map(|m| m.vers)
+        .collect();
+    let versions: Vec<&str> = versions.iter().map(String::as_str).collect();
+    let licenses = policy.licenses(db.as_ref(), proxy, name, &versions).await;
+
+    let mut reason = None;
+    let prefetch = remove_versions(prefetch, |m| {
+        if is_quarantined(m) {
+            return true;
+        }
+        match policy.check(name, &m.vers, license(&licenses, &m.vers)) {
+            Ok(()) => false,
+            Err(r) => {
+                reason.get_or_insert(r);
+                true
+            }
         }
-        PrefetchState::NotFound => Ok(fetch_cratesio_prefetch(name, sender).await?),
+    });
+
+    match reason {
+        Some(reason) if prefetch.data.is_empty() => {
+            debug!("All versions of {name} are blocked by the proxy policy: {reason}");
+            Err(ProxyError::Blocked(blocked_message(name, None, &reason)))
+        }
+        _ => Ok(prefetch),
+    }
+}
+
+async fn is_quarantine_exempt(
+    db: &Arc<dyn DbProvider>,
+    name: &OriginalName,
+) -> Result<bool, StatusCode> {
+    db.is_quarantine_exempt(&name.to_normalized())
+        .await
+        .map_err(|e| {
+            error!("Could not get quarantine exemptions. Error {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Removes the index entries of quarantined or blocked versions. Filtered data gets
+/// an etag which never matches the cached one, such that cargo does not get a 304 and
+/// sees the versions as soon as the quarantine is over or the policy allows them.
+fn remove_versions(
+    prefetch: Prefetch,
+    mut is_removed: impl FnMut(&IndexMetadata) -> bool,
+) -> Prefetch {
+    let data = String::from_utf8_lossy(&prefetch.data);
+    let lines: Vec<&str> = data
+        .lines()
+        .filter(|line| !serde_json::from_str::<IndexMetadata>(line).is_ok_and(|m| is_removed(&m)))
+        .collect();
+    if lines.len() == data.lines().count() {
+        return prefetch;
+    }
+
+    let etag = match prefetch.etag.strip_suffix('"') {
+        Some(etag) => format!("{etag}-filtered\""),
+        None => format!("{}-filtered", prefetch.etag),
+    };
+    Prefetch {
+        data: lines.join("\n").into_bytes(),
+        etag,
+        last_modified: prefetch.last_modified,
     }
 }
 
@@ -173,21 +347,37 @@ async fn background_update_thread(db: impl DbProvider, sender: flume::Sender<Cra
     }
 }
 
-async fn fetch_cratesio_description(name: &str) -> Result<Option<String>, StatusCode> {
+/// Data of the crates.io API which is not part of the index
+#[derive(Debug, Default)]
+pub(crate) struct CratesIoInfo {
+    pub description: Option<String>,
+    /// License expression per version, empty if the license is unknown
+    pub licenses: BTreeMap<String, String>,
+}
+
+pub(crate) async fn fetch_cratesio_info(
+    name: &str,
+    proxy: &Proxy,
+) -> Result<CratesIoInfo, StatusCode> {
     #[derive(Deserialize)]
     struct Krate {
         description: Option<String>,
     }
     #[derive(Deserialize)]
+    struct Version {
+        num: String,
+        license: Option<String>,
+    }
+    #[derive(Deserialize)]
     struct MinimalCrate {
         #[serde(rename = "crate")]
         krate: Krate,
+        #[serde(default)]
+        versions: Vec<Version>,
     }
 
-    let url = Url::parse("https://crates.io/api/v1/crates/")
-        .unwrap()
-        .join(name)
-        .unwrap();
+    let url =
+        Url::parse(&proxy.crate_api_url(name)).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let response = CLIENT
         .get(url)
@@ -195,21 +385,29 @@ async fn fetch_cratesio_description(name: &str) -> Result<Option<String>, Status
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let desc = response
+    let info = response
         .json::<MinimalCrate>()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(desc.krate.description)
+    Ok(CratesIoInfo {
+        description: info.krate.description,
+        licenses: info
+            .versions
+            .into_iter()
+            .map(|v| (v.num, v.license.unwrap_or_default()))
+            .collect(),
+    })
 }
 
 async fn cratesio_prefetch_thread(
     db: Arc<impl DbProvider>,
     cache: Cache<OriginalName, String>,
     channel: flume::Receiver<CratesioPrefetchMsg>,
+    proxy: &Proxy,
 ) {
     loop {
-        if let Some((name, metadata, desc, etag, last_modified)) =
-            handle_cratesio_prefetch_msg(&cache, &channel, &db).await
+        if let Some((name, metadata, info, etag, last_modified)) =
+            handle_cratesio_prefetch_msg(&cache, &channel, &db, proxy).await
         {
             trace!("Update crates.io prefetch data for {name}");
             if let Err(e) = db
@@ -217,7 +415,7 @@ async fn cratesio_prefetch_thread(
                     &name,
                     &etag.unwrap_or_default(),
                     &last_modified.unwrap_or_default(),
-                    desc,
+                    info.description,
                     &metadata,
                 )
                 .await
@@ -225,6 +423,12 @@ async fn cratesio_prefetch_thread(
                 error!(
                     "Could not insert prefetch data from crates.io into database for {name}: {e}",
                 );
+            } else if !info.licenses.is_empty()
+                && let Err(e) = db
+                    .set_cratesio_licenses(&name.to_normalized(), &info.licenses)
+                    .await
+            {
+                error!("Could not insert licenses from crates.io into database for {name}: {e}");
             }
         }
     }
@@ -233,7 +437,8 @@ async fn cratesio_prefetch_thread(
 async fn convert_index_data(
     name: &OriginalName,
     data: String,
-) -> Option<(Vec<IndexMetadata>, Option<String>)> {
+    proxy: &Proxy,
+) -> Option<(Vec<IndexMetadata>, CratesIoInfo)> {
     let metadata: Result<Vec<IndexMetadata>, serde_json::Error> = data
         .lines()
         .map(serde_json::from_str::<IndexMetadata>)
@@ -241,12 +446,12 @@ async fn convert_index_data(
 
     match metadata {
         Ok(m) => {
-            let desc = fetch_cratesio_description(name).await.unwrap_or_else(|e| {
+            let info = fetch_cratesio_info(name, proxy).await.unwrap_or_else(|e| {
                 error!("Could not fetch description for from crates.io {name}: {e:?}",);
-                None
+                CratesIoInfo::default()
             });
 
-            Some((m, desc))
+            Some((m, info))
         }
         Err(e) => {
             error!("Could not parse prefetch data from crates.io for {name}: {e}",);
@@ -259,10 +464,11 @@ async fn handle_cratesio_prefetch_msg(
     cache: &Cache<OriginalName, String>,
     channel: &flume::Receiver<CratesioPrefetchMsg>,
     db: &Arc<impl DbProvider>,
+    proxy: &Proxy,
 ) -> Option<(
     OriginalName,
     Vec<IndexMetadata>,
-    Option<String>,
+    CratesIoInfo,
     Option<String>,
     Option<String>,
 )> {
@@ -271,7 +477,7 @@ async fn handle_cratesio_prefetch_msg(
             trace!("Inserting prefetch data from crates.io for {}", msg.name);
             let date = chrono::Utc::now().to_rfc3339();
             cache.insert(msg.name.clone(), date).await;
-            convert_index_data(&msg.name, msg.data)
+            convert_index_data(&msg.name, msg.data, proxy)
                 .await
                 .map(|(m, d)| (msg.name.clone(), m, d, msg.etag, msg.last_modified))
         }
@@ -284,7 +490,7 @@ async fn handle_cratesio_prefetch_msg(
                 cache
                     .insert(msg.name.clone(), chrono::Utc::now().to_rfc3339())
                     .await;
-                fetch_index_data(msg.name, msg.etag, msg.last_modified).await
+                fetch_index_data(msg.name, msg.etag, msg.last_modified, proxy).await
             }
         }
         Ok(CratesioPrefetchMsg::IncDownloadCnt(msg)) => {
@@ -292,9 +498,13 @@ async fn handle_cratesio_prefetch_msg(
                 "Incrementing download count for {} {}",
                 msg.name, msg.version
             );
-            db.increase_cached_download_counter(&msg.name, &msg.version)
+            db.increase_cached_download_counter(&msg.name.to_normalized(), &msg.version)
                 .await
                 .unwrap_or_else(|e| warn!("Failed to increase download counter: {e}"));
+            let size = i64::try_from(msg.size).unwrap_or(i64::MAX);
+            db.touch_cratesio_cached_file(&msg.name, &msg.version, size)
+                .await
+                .unwrap_or_else(|e| warn!("Failed to track download of cached crate: {e}"));
             None
         }
         Err(e) => {
@@ -308,17 +518,15 @@ async fn fetch_index_data(
     name: OriginalName,
     etag: Option<String>,
     last_modified: Option<String>,
+    proxy: &Proxy,
 ) -> Option<(
     OriginalName,
     Vec<IndexMetadata>,
-    Option<String>,
+    CratesIoInfo,
     Option<String>,
     Option<String>,
 )> {
-    let url = match Url::parse("https://index.crates.io/")
-        .unwrap()
-        .join(&crate_sub_path(&name.to_normalized()))
-    {
+    let url = match Url::parse(&proxy.index_file_url(&sparse_index_path(&name.to_normalized()))) {
         Ok(url) => url,
         Err(e) => {
             error!("Could not parse crates.io url for {name}: {e}");
@@ -380,7 +588,7 @@ async fn fetch_index_data(
                     }
                 };
 
-                convert_index_data(&name, data)
+                convert_index_data(&name, data, proxy)
                     .await
                     .map(|(m, d)| (name, m, d, etag, last_modified))
             }
@@ -394,15 +602,25 @@ async fn fetch_index_data(
     }
 }
 
+/// Fetches the index file of a crate from the upstream registry. Concurrent requests
+/// for the same crate wait for and share a single fetch.
 async fn fetch_cratesio_prefetch(
     name: OriginalName,
+    proxy: &Proxy,
     sender: &flume::Sender<CratesioPrefetchMsg>,
 ) -> Result<Prefetch, StatusCode> {
-    let url = Url::parse("https://index.crates.io/")
-        .unwrap()
-        .join(&crate_sub_path(&name.to_normalized()))
+    let url = Url::parse(&proxy.index_file_url(&sparse_index_path(&name.to_normalized())))
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    INDEX_FETCHES
+        .run(url.to_string(), || fetch_index_file(name, url, sender))
+        .await
+}
 
+async fn fetch_index_file(
+    name: OriginalName,
+    url: Url,
+    sender: &flume::Sender<CratesioPrefetchMsg>,
+) -> Result<Prefetch, StatusCode> {
     let response = CLIENT.get(url).send().await;
 
     match response {
@@ -465,22 +683,6 @@ async fn fetch_cratesio_prefetch(
     }
 }
 
-fn crate_sub_path(name: &NormalizedName) -> String {
-    match name.len() {
-        1 => format!("1/{name}"),
-        2 => format!("2/{name}"),
-        3 => {
-            let first_char = &name[0..1];
-            format!("3/{first_char}/{name}")
-        }
-        _ => {
-            let first_two = &name[0..2];
-            let second_two = &name[2..4];
-            format!("{first_two}/{second_two}/{name}")
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -493,14 +695,19 @@ mod tests {
         routing::get,
     };
     use db::mock::MockDb;
+    use db::{PolicyAction, ProxyPolicyRule};
     use http_body_util::BodyExt;
     use settings::{Protocol, Settings};
     use std::mem;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tower::ServiceExt;
 
     #[tokio::test]
     async fn fetch_cratesio_description_works() {
-        let desc = fetch_cratesio_description("rocket").await.unwrap();
+        let desc = fetch_cratesio_info("rocket", &Proxy::default())
+            .await
+            .unwrap()
+            .description;
         assert_eq!(
             Some(
                 "Web framework with a focus on usability, security, extensibility, and speed.\n"
@@ -512,8 +719,11 @@ mod tests {
 
     #[tokio::test]
     async fn fetch_cratesio_description_not_existent_crate() {
-        let desc = fetch_cratesio_description("does_not_exists123").await;
-        assert_eq!(Err(StatusCode::INTERNAL_SERVER_ERROR), desc);
+        let desc = fetch_cratesio_info("does_not_exists123", &Proxy::default()).await;
+        assert_eq!(
+            Err(StatusCode::INTERNAL_SERVER_ERROR),
+            desc.map(|i| i.description)
+        );
     }
 
     #[tokio::test]
@@ -552,6 +762,340 @@ mod tests {
         assert_eq!(status, StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn fetch_prefetch_from_configured_upstream() {
+        let upstream = Router::new().route(
+            "/index/ro/ck/rocket",
+            get(|| async { ([(header::ETAG, "upstream-etag")], "{}\n") }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let index_url = format!("http://{}/index/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+
+        let r = app_with_proxy(Proxy {
+            index_url,
+            ..Proxy::default()
+        })
+        .oneshot(
+            Request::get("/api/v1/cratesio/ro/ck/rocket")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!("upstream-etag", r.headers()[header::ETAG]);
+        let prefetch = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(b"{}\n", &prefetch[..]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_upstream_fetch() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let upstream = Router::new().route(
+            "/index/co/al/coalesced",
+            get(|| async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                "{}\n"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let index_url = format!("http://{}/index/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+        let app = app_with_proxy(Proxy {
+            index_url,
+            ..Proxy::default()
+        });
+        let request = || {
+            app.clone().oneshot(
+                Request::get("/api/v1/cratesio/co/al/coalesced")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let (r1, r2, r3) = tokio::join!(request(), request(), request());
+
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+        for r in [r1, r2, r3] {
+            let r = r.unwrap();
+            assert_eq!(StatusCode::OK, r.status());
+            let prefetch = r.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(b"{}\n", &prefetch[..]);
+        }
+    }
+
+    #[tokio::test]
+    async fn new_crate_is_quarantined_by_publish_time() {
+        let index = [
+            ("1.0.0", "2020-01-01T00:00:00Z".to_string()),
+            ("2.0.0", chrono::Utc::now().to_rfc3339()),
+        ]
+        .map(|(vers, pubtime)| {
+            IndexMetadata {
+                pubtime: Some(pubtime),
+                ..IndexMetadata::minimal("rocket", vers, "cksum")
+            }
+            .to_json()
+            .unwrap()
+        })
+        .join("\n");
+        let released = index.lines().next().unwrap().to_string();
+        let upstream = Router::new().route(
+            "/index/ro/ck/rocket",
+            get(|| async { ([(header::ETAG, "\"upstream-etag\"")], index) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let index_url = format!("http://{}/index/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+
+        let r = app_with_proxy(Proxy {
+            index_url,
+            quarantine_days: 7,
+            ..Proxy::default()
+        })
+        .oneshot(
+            Request::get("/api/v1/cratesio/ro/ck/rocket")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!("\"upstream-etag-filtered\"", r.headers()[header::ETAG]);
+        let prefetch = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(released.as_bytes(), &prefetch[..]);
+    }
+
+    #[tokio::test]
+    async fn quarantined_versions_are_removed_from_cached_index() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_cratesio_cache_up_to_date()
+            .returning(|_, _, _| {
+                let index = [
+                    IndexMetadata::minimal("rocket", "1.0.0", "cksum"),
+                    IndexMetadata::minimal("rocket", "2.0.0", "cksum"),
+                ];
+                Ok(PrefetchState::NeedsUpdate(Prefetch {
+                    data: IndexMetadata::serialize_indices(&index)
+                        .unwrap()
+                        .into_bytes(),
+                    etag: "cached-etag".to_string(),
+                    last_modified: "date".to_string(),
+                }))
+            });
+        mock_db
+            .expect_get_quarantined_cratesio_versions()
+            .returning(|_, _| Ok(vec!["2.0.0".to_string()]));
+        let (sender, _receiver) = flume::unbounded::<CratesioPrefetchMsg>();
+        let proxy = Proxy {
+            enabled: true,
+            quarantine_days: 7,
+            ..Proxy::default()
+        };
+
+        let r = cached_app(mock_db, sender, proxy, vec![])
+            .oneshot(
+                Request::get("/api/v1/cratesio/ro/ck/rocket")
+                    .header(header::IF_NONE_MATCH, "cached-etag")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!("cached-etag-filtered", r.headers()[header::ETAG]);
+        let prefetch = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            IndexMetadata::minimal("rocket", "1.0.0", "cksum")
+                .to_json()
+                .unwrap()
+                .as_bytes(),
+            &prefetch[..]
+        );
+    }
+
+    fn cached_index(versions: &[&str]) -> PrefetchState {
+        let index: Vec<IndexMetadata> = versions
+            .iter()
+            .map(|vers| IndexMetadata::minimal("rocket", vers, "cksum"))
+            .collect();
+        PrefetchState::NeedsUpdate(Prefetch {
+            data: IndexMetadata::serialize_indices(&index)
+                .unwrap()
+                .into_bytes(),
+            etag: "cached-etag".to_string(),
+            last_modified: "date".to_string(),
+        })
+    }
+
+    fn deny_rule(version_req: Option<&str>) -> ProxyPolicyRule {
+        ProxyPolicyRule {
+            id: 1,
+            action: PolicyAction::Deny,
+            crate_pattern: "rocket".to_string(),
+            version_req: version_req.map(ToString::to_string),
+            licenses: vec![],
+            group: None,
+            reason: "known vulnerability".to_string(),
+            created: "2026-10-18 12:00:00".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn blocked_versions_are_removed_from_index() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_cratesio_cache_up_to_date()
+            .returning(|_, _, _| Ok(cached_index(&["1.0.0", "2.0.0"])));
+        let (sender, _receiver) = flume::unbounded::<CratesioPrefetchMsg>();
+        let proxy = Proxy {
+            enabled: true,
+            ..Proxy::default()
+        };
+
+        let r = cached_app(mock_db, sender, proxy, vec![deny_rule(Some("<2"))])
+            .oneshot(
+                Request::get("/api/v1/cratesio/ro/ck/rocket")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!("cached-etag-filtered", r.headers()[header::ETAG]);
+        let prefetch = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            IndexMetadata::minimal("rocket", "2.0.0", "cksum")
+                .to_json()
+                .unwrap()
+                .as_bytes(),
+            &prefetch[..]
+        );
+    }
+
+    #[tokio::test]
+    async fn blocked_crate_returns_reason() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_cratesio_cache_up_to_date()
+            .returning(|_, _, _| Ok(cached_index(&["1.0.0", "2.0.0"])));
+        let (sender, _receiver) = flume::unbounded::<CratesioPrefetchMsg>();
+        let proxy = Proxy {
+            enabled: true,
+            ..Proxy::default()
+        };
+
+        let r = cached_app(mock_db, sender, proxy, vec![deny_rule(None)])
+            .oneshot(
+                Request::get("/api/v1/cratesio/ro/ck/rocket")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            "crate `rocket` is blocked by the proxy policy of kellnr: known vulnerability",
+            String::from_utf8_lossy(&body)
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_serves_cached_index_without_update() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_cratesio_cache_up_to_date()
+            .returning(|_, _, _| {
+                Ok(PrefetchState::NeedsUpdate(Prefetch {
+                    data: b"{}\n".to_vec(),
+                    etag: "cached-etag".to_string(),
+                    last_modified: "date".to_string(),
+                }))
+            });
+        let (sender, receiver) = flume::unbounded::<CratesioPrefetchMsg>();
+
+        let r = offline_app(mock_db, sender)
+            .oneshot(
+                Request::get("/api/v1/cratesio/ro/ck/rocket")
+                    .header(header::IF_NONE_MATCH, "old-etag")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!("cached-etag", r.headers()[header::ETAG]);
+        assert!(receiver.is_empty());
+    }
+
+    #[tokio::test]
+    async fn offline_returns_404_for_uncached_crate() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_cratesio_cache_up_to_date()
+            .returning(|_, _, _| Ok(PrefetchState::NotFound));
+        let (sender, receiver) = flume::unbounded::<CratesioPrefetchMsg>();
+
+        let r = offline_app(mock_db, sender)
+            .oneshot(
+                Request::get("/api/v1/cratesio/ro/ck/rocket")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+        assert!(receiver.is_empty());
+    }
+
+    /// The upstream URL is not reachable, such that any request to it fails the test.
+    fn offline_app(mock_db: MockDb, sender: flume::Sender<CratesioPrefetchMsg>) -> Router {
+        let proxy = Proxy {
+            enabled: true,
+            offline: true,
+            index_url: "http://127.0.0.1:1".to_string(),
+            ..Proxy::default()
+        };
+        cached_app(mock_db, sender, proxy, vec![])
+    }
+
+    fn cached_app(
+        mut mock_db: MockDb,
+        sender: flume::Sender<CratesioPrefetchMsg>,
+        proxy: Proxy,
+        rules: Vec<ProxyPolicyRule>,
+    ) -> Router {
+        let settings = Settings {
+            proxy,
+            ..Settings::default()
+        };
+        mock_db
+            .expect_get_proxy_policy_rules()
+            .returning(move || Ok(rules.clone()));
+        let state = AppStateData {
+            db: Arc::new(mock_db),
+            settings: Arc::new(settings),
+            cratesio_prefetch_sender: sender,
+            ..appstate::test_state()
+        };
+
+        Router::new()
+            .route("/api/v1/cratesio/{_}/{_}/{name}", get(prefetch_cratesio))
+            .with_state(state)
+    }
+
     #[tokio::test]
     async fn config_returns_config_json() {
         let r = app()
@@ -580,12 +1124,17 @@ mod tests {
     }
 
     fn app() -> Router {
+        app_with_proxy(Proxy::default())
+    }
+
+    fn app_with_proxy(proxy: Proxy) -> Router {
         let settings = Settings {
             origin: settings::Origin {
                 protocol: Protocol::Http,
                 hostname: "test.api.com".to_string(),
                 port: 1234,
             },
+            proxy,
             ..Settings::default()
         };
 
@@ -605,6 +1154,18 @@ mod tests {
             .expect_is_cratesio_cache_up_to_date()
             .returning(move |_, _, _| Ok(PrefetchState::NotFound));
 
+        mock_db
+            .expect_get_quarantined_cratesio_versions()
+            .returning(|_, _| Ok(vec![]));
+
+        mock_db
+            .expect_is_quarantine_exempt()
+            .returning(|_| Ok(false));
+
+        mock_db
+            .expect_get_proxy_policy_rules()
+            .returning(|| Ok(vec![]));
+
         let (sender, receiver) = flume::unbounded::<CratesioPrefetchMsg>();
         // Make receiver undroppable, else the sender will fail, as the receiver is dropped when
         // this function goes out of scope
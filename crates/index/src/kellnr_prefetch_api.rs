@@ -7,11 +7,12 @@ use axum::{
 };
 use common::{normalized_name::NormalizedName, original_name::OriginalName, prefetch::Prefetch};
 use db::DbProvider;
+use settings::constants::KELLNR_API_PATH;
 use std::sync::Arc;
 
 #[allow(clippy::unused_async)] // part of the router
 pub async fn config_kellnr(State(settings): SettingsState) -> Json<ConfigJson> {
-    Json(ConfigJson::from((&(*settings), "crates", true)))
+    Json(ConfigJson::from((&(*settings), KELLNR_API_PATH, true)))
 }
 
 pub async fn prefetch_kellnr(
@@ -24,7 +25,7 @@ pub async fn prefetch_kellnr(
 }
 
 pub async fn prefetch_len2_kellnr(
-    Path((_a, package)): Path<(String, OriginalName)>,
+    Path(package): Path<OriginalName>,
     headers: HeaderMap,
     State(db): DbState,
 ) -> Result<Prefetch, StatusCode> {
@@ -115,6 +116,24 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn prefetch_len2_returns_prefetch_data() {
+        let r = app()
+            .oneshot(
+                Request::get("/api/v1/index/2/metadata")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!(
+            vec![0x1, 0x2, 0x3],
+            r.into_body().collect().await.unwrap().to_bytes()
+        );
+    }
+
     #[tokio::test]
     async fn prefetch_returns_not_modified() {
         let r = app()
@@ -176,7 +195,8 @@ mod tests {
         let kellnr_prefetch = Router::new()
             .route("/config.json", get(config_kellnr))
             .route("/{a}/{b}/{name}", get(prefetch_kellnr))
-            .route("/{a}/{name}", get(prefetch_len2_kellnr));
+            .route("/1/{name}", get(prefetch_len2_kellnr))
+            .route("/2/{name}", get(prefetch_len2_kellnr));
 
         let state = AppStateData {
             db: Arc::new(mock_db),
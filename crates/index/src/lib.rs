@@ -1,3 +1,4 @@
 mod config_json;
 pub mod cratesio_prefetch_api;
 pub mod kellnr_prefetch_api;
+pub mod proxy_policy;
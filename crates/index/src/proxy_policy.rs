@@ -0,0 +1,434 @@
+use crate::cratesio_prefetch_api::fetch_cratesio_info;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use common::original_name::OriginalName;
+use common::util::wildcard_match;
+use db::provider::DbResult;
+use db::{DbProvider, PolicyAction, ProxyPolicyRule};
+use semver::VersionReq;
+use settings::Proxy;
+use spdx::{Expression, LicenseItem, LicenseReq, ParseMode};
+use std::collections::BTreeMap;
+use tracing::{error, warn};
+
+/// Error of a request to the crates.io proxy. Blocked crates get a message which
+/// is shown to the user by cargo.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProxyError {
+    Status(StatusCode),
+    Blocked(String),
+}
+
+impl From<StatusCode> for ProxyError {
+    fn from(status: StatusCode) -> Self {
+        Self::Status(status)
+    }
+}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status) => status.into_response(),
+            Self::Blocked(msg) => (StatusCode::FORBIDDEN, msg).into_response(),
+        }
+    }
+}
+
+/// Message for cargo if a crate version is blocked.
+pub fn blocked_message(name: &str, version: Option<&str>, reason: &str) -> String {
+    match version {
+        Some(version) => {
+            format!("crate `{name}@{version}` is blocked by the proxy policy of kellnr: {reason}")
+        }
+        None => format!("crate `{name}` is blocked by the proxy policy of kellnr: {reason}"),
+    }
+}
+
+/// Rules of the crates.io proxy policy which apply to a user.
+#[derive(Debug, Clone, Default)]
+pub struct ProxyPolicy {
+    rules: Vec<ProxyPolicyRule>,
+}
+
+impl ProxyPolicy {
+    pub fn new(rules: Vec<ProxyPolicyRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Loads the rules without group and the rules of the groups of the user.
+    /// Anonymous requests, e.g. if no authentication is required, only get the
+    /// rules without group. Therefore rules with a group can only be added if
+    /// authentication is required.
+    pub async fn for_user(db: &dyn DbProvider, user: Option<&str>) -> DbResult<Self> {
+        let rules = db.get_proxy_policy_rules().await?;
+        if rules.iter().all(|r| r.group.is_none()) {
+            return Ok(Self { rules });
+        }
+
+        let groups = match user {
+            Some(user) => db.get_user_groups(user).await?,
+            None => vec![],
+        };
+        let rules = rules
+            .into_iter()
+            .filter(|r| {
+                r.group
+                    .as_ref()
+                    .is_none_or(|group| groups.iter().any(|g| &g.name == group))
+            })
+            .collect();
+        Ok(Self { rules })
+    }
+
+    /// Rules which apply to the members of the group, or to anyone without group.
+    pub fn for_group(rules: Vec<ProxyPolicyRule>, group: Option<&str>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter(|r| r.group.is_none() || r.group.as_deref() == group)
+            .collect();
+        Self { rules }
+    }
+
+    /// Whether versions of the crate may be blocked. If any allow rule exists,
+    /// crates not matched by an allow rule are blocked as well.
+    pub fn affects(&self, name: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|r| r.action == PolicyAction::Allow || matches_name(r, name))
+    }
+
+    /// Whether the licenses of the crate versions are needed to check them.
+    pub fn needs_licenses(&self, name: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|r| !r.licenses.is_empty() && matches_name(r, name))
+    }
+
+    /// Checks the crate version against the rules and returns why it is blocked.
+    /// Deny rules take precedence, and if allow rules exist, one has to match.
+    pub fn check(&self, name: &str, version: &str, license: Option<&str>) -> Result<(), String> {
+        if let Some(deny) = self
+            .rules
+            .iter()
+            .filter(|r| r.action == PolicyAction::Deny)
+            .find(|r| matches(r, name, version, license))
+        {
+            return Err(deny.reason.clone());
+        }
+
+        let mut allow = self
+            .rules
+            .iter()
+            .filter(|r| r.action == PolicyAction::Allow)
+            .peekable();
+        if allow.peek().is_some() && !allow.any(|r| matches(r, name, version, license)) {
+            return Err("not on the list of approved crates".to_string());
+        }
+        Ok(())
+    }
+
+    /// Checks a single crate version before it is downloaded.
+    pub async fn check_download(
+        &self,
+        db: &dyn DbProvider,
+        proxy: &Proxy,
+        name: &OriginalName,
+        version: &str,
+    ) -> Result<(), ProxyError> {
+        if !self.affects(name) {
+            return Ok(());
+        }
+        let licenses = self.licenses(db, proxy, name, &[version]).await;
+        self.check(name, version, license(&licenses, version))
+            .map_err(|reason| {
+                warn!("Download of {name} ({version}) refused by the proxy policy: {reason}");
+                ProxyError::Blocked(blocked_message(name, Some(version), &reason))
+            })
+    }
+
+    /// Licenses of the crate versions if the rules need them. Licenses which are not
+    /// cached yet are fetched from the upstream API, unknown licenses are empty.
+    pub async fn licenses(
+        &self,
+        db: &dyn DbProvider,
+        proxy: &Proxy,
+        name: &OriginalName,
+        versions: &[&str],
+    ) -> BTreeMap<String, String> {
+        if !self.needs_licenses(name) {
+            return BTreeMap::new();
+        }
+        let normalized = name.to_normalized();
+        let cached = db
+            .get_cratesio_licenses(&normalized)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Could not get licenses of {name}. Error {e}");
+                BTreeMap::new()
+            });
+        if proxy.offline || versions.iter().all(|v| cached.contains_key(*v)) {
+            return cached;
+        }
+
+        match fetch_cratesio_info(name, proxy).await {
+            Ok(info) => {
+                if let Err(e) = db.set_cratesio_licenses(&normalized, &info.licenses).await {
+                    error!("Could not store licenses of {name}. Error {e}");
+                }
+                info.licenses
+            }
+            Err(e) => {
+                warn!("Could not fetch licenses of {name} from crates.io: {e}");
+                cached
+            }
+        }
+    }
+}
+
+/// Whether the version requirement of a rule is valid, e.g. `<1.2.3`
+pub fn is_valid_version_req(version_req: &str) -> bool {
+    VersionReq::parse(version_req).is_ok()
+}
+
+/// License of the version, if known
+pub fn license<'a>(licenses: &'a BTreeMap<String, String>, version: &str) -> Option<&'a str> {
+    licenses
+        .get(version)
+        .map(String::as_str)
+        .filter(|l| !l.is_empty())
+}
+
+fn matches_name(rule: &ProxyPolicyRule, name: &str) -> bool {
+    wildcard_match(&rule.crate_pattern.to_lowercase(), &name.to_lowercase())
+}
+
+fn matches(rule: &ProxyPolicyRule, name: &str, version: &str, license: Option<&str>) -> bool {
+    matches_name(rule, name) && matches_version(rule, version) && matches_license(rule, license)
+}
+
+/// Version requirements only match pre-releases of the same version, e.g. `<1.2.0`
+/// does not match `1.1.0-rc.1`. Deny rules match them as if they were released, so
+/// that pre-releases of denied versions are blocked as well.
+fn matches_version(rule: &ProxyPolicyRule, version: &str) -> bool {
+    let Some(version_req) = &rule.version_req else {
+        return true;
+    };
+    let (Ok(req), Ok(version)) = (
+        VersionReq::parse(version_req),
+        semver::Version::parse(version),
+    ) else {
+        return false;
+    };
+    if req.matches(&version) {
+        return true;
+    }
+    rule.action == PolicyAction::Deny
+        && !version.pre.is_empty()
+        && req.matches(&semver::Version::new(
+            version.major,
+            version.minor,
+            version.patch,
+        ))
+}
+
+/// An allow rule matches if the license expression can be satisfied by the licenses
+/// of the rule only, a deny rule if it cannot be satisfied without them. Unknown or
+/// invalid licenses never match allow rules and always match deny rules, such that
+/// versions are blocked if their license cannot be checked.
+fn matches_license(rule: &ProxyPolicyRule, license: Option<&str>) -> bool {
+    if rule.licenses.is_empty() {
+        return true;
+    }
+    // Crates often use the deprecated "/" as "OR", which is accepted in lax mode
+    let Some(expression) = license.and_then(|l| Expression::parse_mode(l, ParseMode::LAX).ok())
+    else {
+        return rule.action == PolicyAction::Deny;
+    };
+
+    match rule.action {
+        PolicyAction::Allow => expression.evaluate(|req| is_listed(req, &rule.licenses)),
+        PolicyAction::Deny => !expression.evaluate(|req| !is_listed(req, &rule.licenses)),
+    }
+}
+
+fn is_listed(req: &LicenseReq, licenses: &[String]) -> bool {
+    let name = match &req.license {
+        LicenseItem::Spdx { id, .. } => id.name.to_string(),
+        LicenseItem::Other(other) => other.to_string(),
+    };
+    let full = req.to_string();
+    licenses
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case(&name) || l.eq_ignore_ascii_case(&full))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: PolicyAction, pattern: &str) -> ProxyPolicyRule {
+        ProxyPolicyRule {
+            id: 1,
+            action,
+            crate_pattern: pattern.to_string(),
+            version_req: None,
+            licenses: vec![],
+            group: None,
+            reason: format!("{action:?} {pattern}"),
+            created: String::new(),
+        }
+    }
+
+    #[test]
+    fn no_rules_allow_everything() {
+        let policy = ProxyPolicy::default();
+
+        assert!(!policy.affects("serde"));
+        assert_eq!(Ok(()), policy.check("serde", "1.0.0", None));
+    }
+
+    #[test]
+    fn deny_rule_blocks_matching_versions() {
+        let policy = ProxyPolicy::new(vec![ProxyPolicyRule {
+            version_req: Some("<1.2.0".to_string()),
+            ..rule(PolicyAction::Deny, "Evil-*")
+        }]);
+
+        assert!(policy.affects("evil-crate"));
+        assert!(!policy.affects("serde"));
+        assert_eq!(
+            Err("Deny Evil-*".to_string()),
+            policy.check("evil-crate", "1.1.0", None)
+        );
+        assert_eq!(Ok(()), policy.check("evil-crate", "1.2.0", None));
+        assert_eq!(Ok(()), policy.check("serde", "1.0.0", None));
+    }
+
+    #[test]
+    fn deny_rule_blocks_pre_releases_of_matching_versions() {
+        let policy = ProxyPolicy::new(vec![ProxyPolicyRule {
+            version_req: Some("<1.2.0".to_string()),
+            ..rule(PolicyAction::Deny, "evil")
+        }]);
+        let allow = ProxyPolicy::new(vec![ProxyPolicyRule {
+            version_req: Some("<1.2.0".to_string()),
+            ..rule(PolicyAction::Allow, "good")
+        }]);
+
+        assert!(policy.check("evil", "1.1.0-rc.1", None).is_err());
+        assert!(policy.check("evil", "0.1.0-alpha", None).is_err());
+        assert_eq!(Ok(()), policy.check("evil", "1.2.0-rc.1", None));
+        assert_eq!(Ok(()), policy.check("evil", "1.3.0-beta", None));
+        assert!(allow.check("good", "1.1.0-rc.1", None).is_err());
+    }
+
+    #[test]
+    fn allow_rules_restrict_to_approved_crates() {
+        let policy = ProxyPolicy::new(vec![
+            rule(PolicyAction::Allow, "serde*"),
+            rule(PolicyAction::Deny, "serde_yaml"),
+        ]);
+
+        assert!(policy.affects("tokio"));
+        assert_eq!(Ok(()), policy.check("serde_json", "1.0.0", None));
+        assert_eq!(
+            Err("Deny serde_yaml".to_string()),
+            policy.check("serde_yaml", "0.9.0", None)
+        );
+        assert_eq!(
+            Err("not on the list of approved crates".to_string()),
+            policy.check("tokio", "1.0.0", None)
+        );
+    }
+
+    #[test]
+    fn license_rules_evaluate_expressions() {
+        let policy = ProxyPolicy::new(vec![
+            ProxyPolicyRule {
+                licenses: vec!["MIT".to_string(), "Apache-2.0".to_string()],
+                ..rule(PolicyAction::Allow, "*")
+            },
+            ProxyPolicyRule {
+                licenses: vec!["GPL-3.0-only".to_string()],
+                ..rule(PolicyAction::Deny, "*")
+            },
+        ]);
+
+        assert!(policy.needs_licenses("serde"));
+        assert_eq!(Ok(()), policy.check("a", "1.0.0", Some("MIT/Apache-2.0")));
+        assert_eq!(
+            Ok(()),
+            policy.check("a", "1.0.0", Some("MIT OR GPL-3.0-only"))
+        );
+        assert_eq!(
+            Err("Deny *".to_string()),
+            policy.check("a", "1.0.0", Some("MIT AND GPL-3.0-only"))
+        );
+        assert!(policy.check("a", "1.0.0", Some("BSD-3-Clause")).is_err());
+        assert!(policy.check("a", "1.0.0", None).is_err());
+    }
+
+    #[test]
+    fn deny_license_rule_blocks_unknown_licenses() {
+        let policy = ProxyPolicy::new(vec![ProxyPolicyRule {
+            licenses: vec!["GPL-3.0-only".to_string()],
+            ..rule(PolicyAction::Deny, "*")
+        }]);
+
+        assert_eq!(Ok(()), policy.check("a", "1.0.0", Some("MIT")));
+        assert_eq!(Err("Deny *".to_string()), policy.check("a", "1.0.0", None));
+        assert!(policy.check("a", "1.0.0", Some("not a license (")).is_err());
+    }
+
+    #[test]
+    fn group_rules_only_apply_to_members() {
+        let rules = vec![ProxyPolicyRule {
+            group: Some("team".to_string()),
+            ..rule(PolicyAction::Deny, "*")
+        }];
+
+        assert!(
+            ProxyPolicy::for_group(rules.clone(), Some("team"))
+                .check("a", "1.0.0", None)
+                .is_err()
+        );
+        assert!(
+            ProxyPolicy::for_group(rules, None)
+                .check("a", "1.0.0", None)
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn groups_of_user_are_read_once() {
+        let mut db = db::mock::MockDb::new();
+        db.expect_get_proxy_policy_rules().returning(|| {
+            Ok(vec![
+                rule(PolicyAction::Deny, "a"),
+                ProxyPolicyRule {
+                    group: Some("team".to_string()),
+                    ..rule(PolicyAction::Deny, "b")
+                },
+                ProxyPolicyRule {
+                    group: Some("other".to_string()),
+                    ..rule(PolicyAction::Deny, "c")
+                },
+            ])
+        });
+        db.expect_get_user_groups()
+            .with(mockall::predicate::eq("user"))
+            .times(1)
+            .returning(|_| {
+                Ok(vec![db::Group {
+                    id: 1,
+                    name: "team".to_string(),
+                }])
+            });
+
+        let user = ProxyPolicy::for_user(&db, Some("user")).await.unwrap();
+        let anonymous = ProxyPolicy::for_user(&db, None).await.unwrap();
+
+        assert!(user.affects("a") && user.affects("b") && !user.affects("c"));
+        assert!(anonymous.affects("a") && !anonymous.affects("b"));
+    }
+}
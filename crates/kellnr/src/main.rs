@@ -1,4 +1,5 @@
-use appstate::AppStateData;
+use appstate::rate_limit::RateLimiter;
+use appstate::{AppStateData, jwks_cache, prewarm_jobs, synced_users};
 use axum_extra::extract::cookie::Key;
 use common::cratesio_prefetch_msg::CratesioPrefetchMsg;
 use db::{ConString, Database, DbProvider, PgConString, SqliteConString};
@@ -36,13 +37,25 @@ async fn main() {
     // Create the database connection. Has to be done after the index and storage
     // as the needed folders for the sqlite database my not been created before that.
     let con_string = get_connect_string(&settings);
-    let db = Database::new(&con_string, settings.registry.max_db_connections)
+    let mut db = Database::new(&con_string, settings.registry.max_db_connections)
         .await
         .expect("Failed to create database");
+    if settings.ldap.enabled {
+        let ldap = auth::ldap::LdapAuthProvider::new(settings.ldap.clone());
+        db = db.with_auth_provider(Arc::new(ldap));
+    }
     let db = Arc::new(db) as Arc<dyn DbProvider>;
+    auth::token_cleanup::unused_token_cleanup(db.clone(), settings.tokens.clone());
+    web_ui::session::expired_session_cleanup(db.clone());
+    registry::webhook::delivery_worker(db.clone(), settings.webhooks.clone());
 
     // Crates.io Proxy
     let cratesio_storage: Arc<CratesIoCrateStorage> = init_cratesio_storage(&settings).into();
+    registry::cratesio_eviction::eviction_worker(
+        db.clone(),
+        cratesio_storage.clone(),
+        settings.proxy.clone(),
+    );
     let (cratesio_prefetch_sender, cratesio_prefetch_receiver) =
         flume::unbounded::<CratesioPrefetchMsg>();
 
@@ -50,7 +63,7 @@ async fn main() {
         get_connect_string(&settings),
         cratesio_prefetch_sender.clone(),
         cratesio_prefetch_receiver,
-        settings.proxy.num_threads,
+        settings.proxy.clone(),
         settings.registry.max_db_connections,
     )
     .await;
@@ -61,6 +74,10 @@ async fn main() {
     let signing_key = Key::generate();
     let max_docs_size = settings.docs.max_size;
     let max_crate_size = settings.registry.max_crate_size as usize;
+    let rate_limiter = Arc::new(RateLimiter::new(settings.rate_limit.clone()));
+    let jwks_cache = Arc::new(jwks_cache());
+    let synced_users = Arc::new(synced_users());
+    let prewarm_jobs = Arc::new(prewarm_jobs());
     let state = AppStateData {
         db,
         signing_key,
@@ -68,6 +85,10 @@ async fn main() {
         crate_storage,
         cratesio_storage,
         cratesio_prefetch_sender,
+        rate_limiter,
+        jwks_cache,
+        synced_users,
+        prewarm_jobs,
     };
 
     // Create router using the route module
@@ -77,7 +98,13 @@ async fn main() {
     let listener = TcpListener::bind(addr)
         .await
         .unwrap_or_else(|_| panic!("Failed to bind to {addr}"));
-    axum::serve(listener, app).await.unwrap();
+    // The client address is needed to record the last client IP of a token
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 fn init_tracing(settings: &Settings) {
@@ -0,0 +1,10 @@
+use appstate::AppStateData;
+use axum::{Router, routing::get};
+use web_ui::audit;
+
+/// Creates the audit log routes
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/", get(audit::list))
+        .route("/export", get(audit::export))
+}
@@ -1,7 +1,7 @@
 use appstate::AppStateData;
 use axum::{
     Router,
-    routing::{delete, get, put},
+    routing::{delete, get, post, put},
 };
 use web_ui::crate_access;
 
@@ -28,4 +28,16 @@ pub fn create_routes() -> Router<AppStateData> {
             "/{crate_name}/access_data",
             put(crate_access::set_access_data),
         )
+        .route(
+            "/{crate_name}/trusted_publishers",
+            get(crate_access::list_trusted_publishers),
+        )
+        .route(
+            "/{crate_name}/trusted_publishers",
+            post(crate_access::add_trusted_publisher),
+        )
+        .route(
+            "/{crate_name}/trusted_publishers/{id}",
+            delete(crate_access::delete_trusted_publisher),
+        )
 }
@@ -1,8 +1,11 @@
 use appstate::AppStateData;
-use auth::auth_req_token;
-use axum::{Router, middleware, routing::get};
+use auth::{auth_req_token, token_quota};
+use axum::{
+    Router, middleware,
+    routing::{get, post},
+};
 use index::cratesio_prefetch_api;
-use registry::cratesio_api;
+use registry::{cratesio_api, cratesio_prewarm};
 
 /// Creates the crates.io API routes
 pub fn create_routes(state: AppStateData) -> Router<AppStateData> {
@@ -17,9 +20,14 @@ pub fn create_routes(state: AppStateData) -> Router<AppStateData> {
             get(cratesio_prefetch_api::prefetch_len2_cratesio),
         )
         .route("/", get(cratesio_api::search))
+        .route("/prewarm", post(cratesio_prewarm::prewarm))
+        .route("/prewarm/{id}", get(cratesio_prewarm::prewarm_status))
         .route(
             "/dl/{package}/{version}/download",
-            get(cratesio_api::download),
+            get(cratesio_api::download).layer(middleware::from_fn_with_state(
+                state.clone(),
+                token_quota::download_quota,
+            )),
         )
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
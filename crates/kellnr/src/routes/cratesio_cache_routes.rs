@@ -0,0 +1,16 @@
+use appstate::AppStateData;
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+use web_ui::cratesio_cache;
+
+/// Creates the routes of the eviction of cached crates.io crates
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/pins", get(cratesio_cache::list_pins))
+        .route("/pins", post(cratesio_cache::add_pin))
+        .route("/pins/{name}/{version}", delete(cratesio_cache::delete_pin))
+        .route("/reports", get(cratesio_cache::reports))
+        .route("/evict", post(cratesio_cache::evict))
+}
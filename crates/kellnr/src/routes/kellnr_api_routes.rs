@@ -1,5 +1,5 @@
 use appstate::AppStateData;
-use auth::auth_req_token;
+use auth::{auth_req_token, token_quota};
 use axum::{
     Router,
     extract::DefaultBodyLimit,
@@ -17,8 +17,14 @@ pub fn create_routes(state: AppStateData, max_crate_size: usize) -> Router<AppSt
             "/{a}/{b}/{package}",
             get(kellnr_prefetch_api::prefetch_kellnr),
         )
+        // Index paths of crate names with one or two characters start with
+        // a static "1" or "2", which keeps "/{crate_name}/{version}" free.
         .route(
-            "/{a}/{package}",
+            "/1/{package}",
+            get(kellnr_prefetch_api::prefetch_len2_kellnr),
+        )
+        .route(
+            "/2/{package}",
             get(kellnr_prefetch_api::prefetch_len2_kellnr),
         )
         .route("/{crate_name}/owners", delete(kellnr_api::remove_owner))
@@ -52,14 +58,35 @@ pub fn create_routes(state: AppStateData, max_crate_size: usize) -> Router<AppSt
             "/{crate_name}/crate_versions",
             get(kellnr_api::list_crate_versions),
         )
+        .route("/{crate_name}", get(kellnr_api::crate_info))
+        .route(
+            "/{crate_name}/versions",
+            get(kellnr_api::crate_info_versions),
+        )
+        .route(
+            "/{crate_name}/{version}",
+            get(kellnr_api::crate_info_version),
+        )
+        .route(
+            "/{crate_name}/{version}/dependencies",
+            get(kellnr_api::crate_info_dependencies),
+        )
         .route("/", get(kellnr_api::search))
         .route(
             "/dl/{package}/{version}/download",
-            get(kellnr_api::download),
+            get(kellnr_api::download).layer(middleware::from_fn_with_state(
+                state.clone(),
+                token_quota::download_quota,
+            )),
         )
         .route(
             "/new",
-            put(kellnr_api::publish).layer(DefaultBodyLimit::max(max_crate_size * 1_000_000)),
+            put(kellnr_api::publish)
+                .layer(DefaultBodyLimit::max(max_crate_size * 1_000_000))
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    token_quota::publish_quota,
+                )),
         )
         .route("/new_empty", put(kellnr_api::add_empty_crate))
         .route("/{crate_name}/{version}/yank", delete(kellnr_api::yank))
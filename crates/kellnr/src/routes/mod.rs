@@ -7,13 +7,20 @@ use std::path::Path;
 use tower_http::services::{ServeDir, ServeFile};
 use web_ui::session;
 
+mod audit_routes;
 mod crate_access_routes;
 mod cratesio_api_routes;
+mod cratesio_cache_routes;
 mod docs_routes;
 mod group_routes;
 mod kellnr_api_routes;
+mod proxy_policy_routes;
+mod quarantine_routes;
+mod service_account_routes;
+mod trusted_publishing_routes;
 mod ui_routes;
 mod user_routes;
+mod webhook_routes;
 
 /// Creates and returns the complete application router with all routes configured
 pub fn create_router(
@@ -41,7 +48,19 @@ pub fn create_router(
         .nest("/api/v1/ui", ui_routes::create_routes(state.clone()))
         .nest("/api/v1/user", user_routes::create_routes())
         .nest("/api/v1/group", group_routes::create_routes())
+        .nest(
+            "/api/v1/service_accounts",
+            service_account_routes::create_routes(),
+        )
         .nest("/api/v1/crate_access", crate_access_routes::create_routes())
+        .nest("/api/v1/audit", audit_routes::create_routes())
+        .nest("/api/v1/webhooks", webhook_routes::create_routes())
+        .nest("/api/v1/quarantine", quarantine_routes::create_routes())
+        .nest("/api/v1/proxy_policy", proxy_policy_routes::create_routes())
+        .nest(
+            "/api/v1/cratesio_cache",
+            cratesio_cache_routes::create_routes(),
+        )
         .nest("/api/v1/docs", docs_routes::create_ui_routes(state.clone()))
         .nest(
             "/api/v1/docs",
@@ -51,6 +70,10 @@ pub fn create_router(
             "/api/v1/crates",
             kellnr_api_routes::create_routes(state.clone(), max_crate_size),
         )
+        .nest(
+            "/api/v1/trusted_publishing",
+            trusted_publishing_routes::create_routes(),
+        )
         .nest(
             "/api/v1/cratesio",
             cratesio_api_routes::create_routes(state.clone()),
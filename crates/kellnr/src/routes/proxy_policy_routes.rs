@@ -0,0 +1,15 @@
+use appstate::AppStateData;
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+use web_ui::proxy_policy;
+
+/// Creates the routes of the policy for crates.io crates served by the proxy
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/rules", get(proxy_policy::list))
+        .route("/rules", post(proxy_policy::add))
+        .route("/rules/{id}", delete(proxy_policy::delete))
+        .route("/blocked", get(proxy_policy::blocked))
+}
@@ -0,0 +1,14 @@
+use appstate::AppStateData;
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+use web_ui::quarantine;
+
+/// Creates the routes of the crates.io quarantine exemptions
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/exemptions", get(quarantine::list))
+        .route("/exemptions", post(quarantine::add))
+        .route("/exemptions/{name}", delete(quarantine::delete))
+}
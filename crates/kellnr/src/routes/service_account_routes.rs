@@ -0,0 +1,17 @@
+use appstate::AppStateData;
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+use web_ui::service_account;
+
+/// Creates the service account routes
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/", get(service_account::list))
+        .route("/add", post(service_account::add))
+        .route("/delete/{name}", delete(service_account::delete))
+        .route("/{name}/tokens", get(service_account::list_tokens))
+        .route("/{name}/tokens", post(service_account::add_token))
+        .route("/{name}/tokens/{id}", delete(service_account::delete_token))
+}
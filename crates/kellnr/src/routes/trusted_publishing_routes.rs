@@ -0,0 +1,13 @@
+use appstate::AppStateData;
+use axum::{
+    Router,
+    routing::{delete, put},
+};
+use registry::trusted_publishing;
+
+/// Creates the trusted publishing routes
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/tokens", put(trusted_publishing::exchange_token))
+        .route("/tokens", delete(trusted_publishing::revoke_token))
+}
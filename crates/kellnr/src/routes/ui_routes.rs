@@ -1,7 +1,7 @@
 use appstate::AppStateData;
 use axum::{
     Router, middleware,
-    routing::{delete, get},
+    routing::{delete, get, post},
 };
 use web_ui::session;
 use web_ui::ui;
@@ -18,6 +18,8 @@ pub fn create_routes(state: AppStateData) -> Router<AppStateData> {
         .route("/delete_version", delete(ui::delete_version))
         .route("/delete_crate", delete(ui::delete_crate))
         .route("/settings", get(ui::settings))
+        .route("/license_violations", get(ui::license_violations))
+        .route("/verify_cratesio_cache", post(ui::verify_cratesio_cache))
         .route_layer(middleware::from_fn_with_state(
             state,
             session::session_auth_when_required,
@@ -3,21 +3,41 @@ use axum::{
     Router,
     routing::{delete, get, post},
 };
-use web_ui::user;
+use web_ui::{totp, user};
 
 /// Creates the user routes
 pub fn create_routes() -> Router<AppStateData> {
     Router::new()
         .route("/login", post(user::login))
+        .route("/login/totp", post(user::login_totp))
+        .route("/oidc", get(user::oidc_info))
+        .route("/oidc/login", get(user::oidc_login))
+        .route("/oidc/callback", get(user::oidc_callback))
         .route("/logout", get(user::logout))
         .route("/change_pwd", post(user::change_pwd))
         .route("/add", post(user::add))
         .route("/delete/{name}", delete(user::delete))
         .route("/reset_pwd/{name}", post(user::reset_pwd))
         .route("/read_only/{name}", post(user::read_only))
+        .route(
+            "/identities/{name}",
+            get(user::list_identities)
+                .post(user::add_identity)
+                .delete(user::delete_identity),
+        )
+        .route("/logout_user/{name}", post(user::logout_user))
         .route("/add_token", post(user::add_token))
         .route("/delete_token/{id}", delete(user::delete_token))
         .route("/list_tokens", get(user::list_tokens))
         .route("/list_users", get(user::list_users))
+        .route("/list_sessions", get(user::list_sessions))
+        .route("/delete_session/{id}", delete(user::delete_session))
         .route("/login_state", get(user::login_state))
+        .route("/lockouts", get(user::list_lockouts))
+        .route("/lockouts/{kind}/{key}", delete(user::clear_lockout))
+        .route("/totp", get(totp::status))
+        .route("/totp/setup", post(totp::setup))
+        .route("/totp/enable", post(totp::enable))
+        .route("/totp/disable", post(totp::disable))
+        .route("/totp/reset/{name}", post(totp::reset))
 }
@@ -0,0 +1,16 @@
+use appstate::AppStateData;
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+use web_ui::webhook;
+
+/// Creates the webhook routes
+pub fn create_routes() -> Router<AppStateData> {
+    Router::new()
+        .route("/", get(webhook::list))
+        .route("/", post(webhook::add))
+        .route("/{id}", delete(webhook::delete))
+        .route("/{id}/deliveries", get(webhook::deliveries))
+        .route("/deliveries/{id}/retry", post(webhook::retry))
+}
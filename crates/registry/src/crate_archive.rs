@@ -0,0 +1,246 @@
+use crate::registry_error::RegistryError;
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::{Component, Path};
+use std::sync::Arc;
+use tar::{Archive, EntryType};
+
+/// Limits of the unpacked archive, such that a small upload cannot unpack to an
+/// arbitrary amount of data. The total limit is the same as on crates.io.
+const MAX_UNPACKED_SIZE: u64 = 512 * 1024 * 1024;
+const MAX_ENTRY_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Runs [`validate_crate_archive`] on the blocking thread pool, as unpacking the
+/// archive can take a while.
+pub async fn validate_crate_archive_blocking(
+    data: Arc<[u8]>,
+    name: String,
+    version: String,
+) -> Result<(), RegistryError> {
+    tokio::task::spawn_blocking(move || validate_crate_archive(&data, &name, &version))
+        .await
+        .map_err(|e| invalid(format!("validation failed: {e}")))?
+}
+
+/// Checks that the uploaded `.crate` file is a gzip compressed tarball whose entries
+/// all live below `{name}-{version}/` and which contains a `Cargo.toml` matching the
+/// name and version of the publish metadata.
+pub fn validate_crate_archive(data: &[u8], name: &str, version: &str) -> Result<(), RegistryError> {
+    let prefix = format!("{name}-{version}");
+    // The limit also bounds the data of the tar headers, which are not counted below
+    let mut archive = Archive::new(GzDecoder::new(data).take(MAX_UNPACKED_SIZE));
+    let entries = archive
+        .entries()
+        .map_err(|e| invalid(format!("not a valid gzip compressed tarball: {e}")))?;
+
+    let mut cargo_toml = None;
+    let mut unpacked_size: u64 = 0;
+    for entry in entries {
+        let mut entry =
+            entry.map_err(|e| invalid(format!("not a valid gzip compressed tarball: {e}")))?;
+
+        let size = entry.size();
+        unpacked_size = unpacked_size.saturating_add(size);
+        if size > MAX_ENTRY_SIZE || unpacked_size > MAX_UNPACKED_SIZE {
+            return Err(invalid(format!(
+                "unpacked size exceeds the limit of {MAX_ENTRY_SIZE} bytes per file and {MAX_UNPACKED_SIZE} bytes in total"
+            )));
+        }
+
+        if matches!(
+            entry.header().entry_type(),
+            EntryType::Symlink | EntryType::Link
+        ) {
+            return Err(invalid(format!(
+                "links are not allowed: {}",
+                String::from_utf8_lossy(&entry.path_bytes())
+            )));
+        }
+
+        let path = entry
+            .path()
+            .map_err(|e| invalid(format!("invalid entry path: {e}")))?
+            .into_owned();
+        check_entry_path(&path, &prefix)?;
+
+        if path == Path::new(&prefix).join("Cargo.toml") {
+            let mut content = String::new();
+            entry
+                .by_ref()
+                .take(MAX_ENTRY_SIZE)
+                .read_to_string(&mut content)
+                .map_err(|e| invalid(format!("failed to read Cargo.toml: {e}")))?;
+            cargo_toml = Some(content);
+        }
+    }
+
+    let cargo_toml = cargo_toml.ok_or_else(|| invalid(format!("missing {prefix}/Cargo.toml")))?;
+    check_cargo_toml(&cargo_toml, name, version)
+}
+
+fn check_entry_path(path: &Path, prefix: &str) -> Result<(), RegistryError> {
+    let mut components = path.components();
+    let escapes = components.any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes || !path.starts_with(prefix) {
+        return Err(invalid(format!(
+            "entry {} is outside of {prefix}/",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+fn check_cargo_toml(content: &str, name: &str, version: &str) -> Result<(), RegistryError> {
+    let manifest: toml::Table = content
+        .parse()
+        .map_err(|e| invalid(format!("failed to parse Cargo.toml: {e}")))?;
+    let package = manifest
+        .get("package")
+        .and_then(toml::Value::as_table)
+        .ok_or_else(|| invalid("Cargo.toml has no [package] section".to_string()))?;
+
+    let toml_name = package.get("name").and_then(toml::Value::as_str);
+    if toml_name != Some(name) {
+        return Err(invalid(format!(
+            "package name {} in Cargo.toml does not match {name}",
+            toml_name.unwrap_or_default()
+        )));
+    }
+
+    let toml_version = package.get("version").and_then(toml::Value::as_str);
+    if toml_version != Some(version) {
+        return Err(invalid(format!(
+            "package version {} in Cargo.toml does not match {version}",
+            toml_version.unwrap_or_default()
+        )));
+    }
+
+    Ok(())
+}
+
+fn invalid(reason: String) -> RegistryError {
+    RegistryError::InvalidCrateArchive(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tar::{Builder, Header};
+
+    fn build_archive(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in entries {
+            let mut header = Header::new_gnu();
+            // Write the raw name, as `set_path` refuses paths containing `..`
+            let name = &mut header.as_gnu_mut().unwrap().name;
+            name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    const CARGO_TOML: &str = "[package]\nname = \"foo\"\nversion = \"1.0.0\"\n";
+
+    #[test]
+    fn valid_archive() {
+        let data = build_archive(&[
+            ("foo-1.0.0/Cargo.toml", CARGO_TOML),
+            ("foo-1.0.0/src/lib.rs", ""),
+        ]);
+
+        assert!(validate_crate_archive(&data, "foo", "1.0.0").is_ok());
+    }
+
+    #[test]
+    fn valid_test_data_archive() {
+        let data = std::fs::read("../test_data/full-toml-0.1.4.crate").unwrap();
+
+        assert!(validate_crate_archive(&data, "full-toml", "0.1.4").is_ok());
+    }
+
+    #[test]
+    fn no_gzip() {
+        let result = validate_crate_archive(&[0x00, 0x11, 0x22, 0x33], "foo", "1.0.0");
+
+        assert!(matches!(result, Err(RegistryError::InvalidCrateArchive(_))));
+    }
+
+    #[test]
+    fn missing_cargo_toml() {
+        let data = build_archive(&[("foo-1.0.0/src/lib.rs", "")]);
+
+        let result = validate_crate_archive(&data, "foo", "1.0.0");
+
+        assert!(
+            matches!(result, Err(RegistryError::InvalidCrateArchive(r)) if r.contains("missing"))
+        );
+    }
+
+    #[test]
+    fn name_mismatch() {
+        let data = build_archive(&[("bar-1.0.0/Cargo.toml", CARGO_TOML)]);
+
+        assert!(validate_crate_archive(&data, "bar", "1.0.0").is_err());
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let data = build_archive(&[("foo-2.0.0/Cargo.toml", CARGO_TOML)]);
+
+        assert!(validate_crate_archive(&data, "foo", "2.0.0").is_err());
+    }
+
+    #[test]
+    fn entry_outside_prefix() {
+        let data = build_archive(&[
+            ("foo-1.0.0/Cargo.toml", CARGO_TOML),
+            ("other/src/lib.rs", ""),
+        ]);
+
+        assert!(validate_crate_archive(&data, "foo", "1.0.0").is_err());
+    }
+
+    #[test]
+    fn entry_with_parent_dir() {
+        let data = build_archive(&[
+            ("foo-1.0.0/Cargo.toml", CARGO_TOML),
+            ("foo-1.0.0/../../etc/passwd", ""),
+        ]);
+
+        assert!(validate_crate_archive(&data, "foo", "1.0.0").is_err());
+    }
+
+    #[test]
+    fn entry_above_size_limit() {
+        // Only the header is written, as the size is checked before the content is read
+        let mut header = Header::new_gnu();
+        header.set_path("foo-1.0.0/src/lib.rs").unwrap();
+        header.set_size(MAX_ENTRY_SIZE + 1);
+        header.set_cksum();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(header.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let result = validate_crate_archive(&data, "foo", "1.0.0");
+
+        assert!(
+            matches!(result, Err(RegistryError::InvalidCrateArchive(r)) if r.contains("limit"))
+        );
+    }
+
+    #[test]
+    fn entry_with_absolute_path() {
+        let data = build_archive(&[
+            ("foo-1.0.0/Cargo.toml", CARGO_TOML),
+            ("/foo-1.0.0/src/lib.rs", ""),
+        ]);
+
+        assert!(validate_crate_archive(&data, "foo", "1.0.0").is_err());
+    }
+}
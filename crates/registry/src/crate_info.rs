@@ -0,0 +1,164 @@
+use common::crate_data::{CrateData, CrateRegistryDep, CrateVersionData};
+use serde::{Deserialize, Serialize};
+use settings::constants::KELLNR_API_PATH;
+use std::collections::BTreeMap;
+
+/// Response of `GET /api/v1/crates/{name}`, shaped like the crates.io API.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateResponse {
+    #[serde(rename = "crate")]
+    pub krate: CrateInfo,
+    pub versions: Vec<VersionInfo>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateInfo {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub documentation: Option<String>,
+    pub max_version: String,
+    pub newest_version: String,
+    pub downloads: i64,
+    pub updated_at: String,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub owners: Vec<String>,
+    pub authors: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub dl_path: String,
+    pub checksum: String,
+    pub downloads: i64,
+    pub created_at: String,
+    pub license: Option<String>,
+    pub documentation: Option<String>,
+    pub yanked: bool,
+    pub links: Option<String>,
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionList {
+    pub versions: Vec<VersionInfo>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub version: VersionInfo,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyInfo {
+    pub crate_id: String,
+    pub req: String,
+    pub optional: bool,
+    pub default_features: bool,
+    pub features: Vec<String>,
+    pub target: Option<String>,
+    pub kind: String,
+    pub registry: Option<String>,
+    pub explicit_name_in_toml: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyList {
+    pub dependencies: Vec<DependencyInfo>,
+}
+
+impl From<CrateData> for CrateResponse {
+    fn from(data: CrateData) -> Self {
+        let versions: Vec<VersionInfo> = data
+            .versions
+            .iter()
+            .map(|v| VersionInfo::new(&data.name, v))
+            .collect();
+        let documentation = data
+            .versions
+            .iter()
+            .find(|v| v.version == data.max_version)
+            .and_then(|v| v.documentation.clone());
+
+        Self {
+            krate: CrateInfo {
+                id: data.name.clone(),
+                name: data.name,
+                description: data.description,
+                homepage: data.homepage,
+                repository: data.repository,
+                documentation,
+                newest_version: data.max_version.clone(),
+                max_version: data.max_version,
+                downloads: data.total_downloads,
+                updated_at: data.last_updated,
+                keywords: data.keywords.clone(),
+                categories: data.categories.clone(),
+                owners: data.owners,
+                authors: data.authors,
+            },
+            versions,
+            keywords: data.keywords,
+            categories: data.categories,
+        }
+    }
+}
+
+impl VersionInfo {
+    pub fn new(crate_name: &str, data: &CrateVersionData) -> Self {
+        Self {
+            krate: crate_name.to_string(),
+            num: data.version.clone(),
+            dl_path: format!(
+                "/api/v1/{KELLNR_API_PATH}/dl/{crate_name}/{}/download",
+                data.version
+            ),
+            checksum: data.checksum.clone(),
+            downloads: data.downloads,
+            created_at: data.created.clone(),
+            license: data.license.clone(),
+            documentation: data.documentation.clone(),
+            yanked: data.yanked,
+            links: data.links.clone(),
+            features: data.features.clone(),
+        }
+    }
+}
+
+impl From<Vec<VersionInfo>> for VersionList {
+    fn from(versions: Vec<VersionInfo>) -> Self {
+        Self { versions }
+    }
+}
+
+impl From<CrateRegistryDep> for DependencyInfo {
+    fn from(dep: CrateRegistryDep) -> Self {
+        Self {
+            crate_id: dep.name,
+            req: dep.version_req,
+            optional: dep.optional,
+            default_features: dep.default_features,
+            features: dep.features.unwrap_or_default(),
+            target: dep.target,
+            kind: dep.kind.unwrap_or_else(|| "normal".to_string()),
+            registry: dep.registry,
+            explicit_name_in_toml: dep.explicit_name_in_toml,
+        }
+    }
+}
+
+impl From<Vec<CrateRegistryDep>> for DependencyList {
+    fn from(deps: Vec<CrateRegistryDep>) -> Self {
+        Self {
+            dependencies: deps.into_iter().map(DependencyInfo::from).collect(),
+        }
+    }
+}
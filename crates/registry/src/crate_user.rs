@@ -1,10 +1,36 @@
+use db::User;
 use serde::{Deserialize, Serialize};
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrateUserKind {
+    #[default]
+    User,
+    ServiceAccount,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CrateUser {
     pub id: i32,
     pub login: String,
     pub name: Option<String>,
+    #[serde(default)]
+    pub kind: CrateUserKind,
+}
+
+impl From<&User> for CrateUser {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            login: user.name.clone(),
+            name: None,
+            kind: if user.is_service_account {
+                CrateUserKind::ServiceAccount
+            } else {
+                CrateUserKind::User
+            },
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
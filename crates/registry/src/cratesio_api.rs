@@ -1,5 +1,9 @@
+use crate::cratesio_verify::{
+    checksum, expected_checksum, log_mismatch, publish_time, upstream_metadata,
+};
 use crate::{registry_error::RegistryError, search_params::SearchParams};
-use appstate::{CrateIoStorageState, CratesIoPrefetchSenderState, SettingsState};
+use appstate::{CrateIoStorageState, CratesIoPrefetchSenderState, DbState, SettingsState};
+use auth::token::OptionToken;
 use axum::{
     extract::{Path, Request, State},
     http::StatusCode,
@@ -9,14 +13,23 @@ use axum::{
 use common::{
     cratesio_prefetch_msg::{CratesioPrefetchMsg, DownloadData},
     original_name::OriginalName,
+    single_flight::SingleFlight,
     version::Version,
 };
+use db::DbProvider;
+use db::provider::DbResult;
 use error::api_error::ApiResult;
+use index::proxy_policy::{ProxyError, ProxyPolicy};
 use reqwest::{Client, ClientBuilder, Url};
+use settings::Proxy;
 use std::{error::Error, sync::Arc};
-use tracing::{error, trace, warn};
+use storage::cratesio_crate_storage::CratesIoCrateStorage;
+use tracing::{debug, error, trace, warn};
+
+/// Running downloads of crate files from the upstream registry, by download URL
+static CRATE_FETCHES: SingleFlight<String, Result<(), UpstreamError>> = SingleFlight::new();
 
-static CLIENT: std::sync::LazyLock<Client> = std::sync::LazyLock::new(|| {
+pub(crate) static CLIENT: std::sync::LazyLock<Client> = std::sync::LazyLock::new(|| {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         reqwest::header::USER_AGENT,
@@ -43,11 +56,17 @@ pub async fn cratesio_enabled(
     }
 }
 
-pub async fn search(params: SearchParams) -> ApiResult<String> {
-    let url = Url::parse(&format!(
-        "https://crates.io/api/v1/crates?q={}&per_page={}",
-        params.q, params.per_page.0
-    ))
+pub async fn search(params: SearchParams, State(settings): SettingsState) -> ApiResult<String> {
+    if settings.proxy.offline {
+        return Err(RegistryError::ProxyOffline.into());
+    }
+    let url = Url::parse_with_params(
+        &settings.proxy.search_api_url(),
+        &[
+            ("q", params.q.to_string()),
+            ("per_page", params.per_page.0.to_string()),
+        ],
+    )
     .map_err(RegistryError::UrlParseError)?;
 
     let response = CLIENT
@@ -63,15 +82,49 @@ pub async fn search(params: SearchParams) -> ApiResult<String> {
 
 pub async fn download(
     Path((package, version)): Path<(OriginalName, Version)>,
+    token: OptionToken,
+    State(settings): SettingsState,
     State(crate_storage): CrateIoStorageState,
     State(sender): CratesIoPrefetchSenderState,
-) -> Result<Vec<u8>, StatusCode> {
+    State(db): DbState,
+) -> Result<Vec<u8>, ProxyError> {
     trace!("Downloading crate: {package} ({version})");
 
-    if let Some(file) = crate_storage.get(&package, &version).await {
+    let user = match &token {
+        OptionToken::Some(token) => Some(token.user.as_str()),
+        OptionToken::None => None,
+    };
+    ProxyPolicy::for_user(db.as_ref(), user)
+        .await
+        .map_err(|e| {
+            error!("Failed to get the proxy policy: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .check_download(db.as_ref(), &settings.proxy, &package, &version.to_string())
+        .await?;
+
+    let file = crate_storage.get(&package, &version).await;
+    if file.is_none() && settings.proxy.offline {
+        debug!("Crate {package} ({version}) is not cached and the proxy is offline");
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    let quarantined = is_quarantined(db.as_ref(), &settings.proxy, &package, &version)
+        .await
+        .map_err(|e| {
+            error!("Failed to check the quarantine of {package} ({version}): {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if quarantined {
+        warn!("Download of {package} ({version}) refused, the version is quarantined");
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    if let Some(file) = file {
         let msg = DownloadData {
-            name: package.into(),
+            name: package,
             version,
+            size: file.len(),
         };
         if let Err(e) = sender.send(CratesioPrefetchMsg::IncDownloadCnt(msg)) {
             warn!("Failed to send IncDownloadCnt message: {e}");
@@ -79,37 +132,143 @@ pub async fn download(
 
         Ok(file)
     } else {
-        let target = format!("https://static.crates.io/crates/{package}/{version}/download");
-
-        let res = match CLIENT.get(target).send().await {
-            Ok(resp) if resp.status() != 200 => Err(StatusCode::NOT_FOUND),
-            Ok(resp) => Ok(resp),
-            Err(e) => {
-                error!("Encountered error... {e}");
-                Err(StatusCode::NOT_FOUND)
-            }
-        }?;
-
-        let crate_data = res.bytes().await.map_err(log_return_error)?;
-        let crate_data: Arc<[u8]> = Arc::from(crate_data.iter().as_slice());
-        let _save = crate_storage
-            .put(&package, &version, crate_data.clone())
-            .await
-            .map_err(|e| {
-                error!("Failed to save crate to disk: {e}");
-                StatusCode::UNPROCESSABLE_ENTITY
-            })?;
+        fetch_upstream_crate(
+            db.as_ref(),
+            &settings.proxy,
+            &crate_storage,
+            &package,
+            &version,
+        )
+        .await
+        .map_err(StatusCode::from)?;
 
         crate_storage
             .get(&package, &version)
             .await
-            .ok_or(StatusCode::NOT_FOUND)
+            .ok_or(StatusCode::NOT_FOUND.into())
+    }
+}
+
+/// Whether the version is in quarantine. Versions not in the cached index yet are
+/// checked against the upstream index. As in the cached index, versions without a
+/// publish time are not quarantined. Versions which are not in the upstream index or
+/// cannot be checked, e.g. because the proxy is offline, are quarantined.
+pub(crate) async fn is_quarantined(
+    db: &dyn DbProvider,
+    proxy: &Proxy,
+    package: &OriginalName,
+    version: &Version,
+) -> DbResult<bool> {
+    let Some(cutoff) = proxy.quarantine_cutoff() else {
+        return Ok(false);
+    };
+    let quarantined = db
+        .is_quarantined_cratesio_version(&package.to_normalized(), version, &cutoff)
+        .await?;
+    Ok(match quarantined {
+        Some(quarantined) => quarantined,
+        None if proxy.offline => true,
+        None => match upstream_metadata(proxy, package, version).await {
+            Some(metadata) => publish_time(&metadata).is_some_and(|published| published > cutoff),
+            None => true,
+        },
+    })
+}
+
+/// Reason why a crate could not be fetched from the upstream registry.
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum UpstreamError {
+    #[error("not available on crates.io")]
+    NotFound,
+    #[error("checksum does not match the crates.io index")]
+    ChecksumMismatch,
+    #[error("not in the crates.io index")]
+    NotInIndex,
+    #[error("could not be stored")]
+    Storage,
+}
+
+impl From<UpstreamError> for StatusCode {
+    fn from(e: UpstreamError) -> Self {
+        match e {
+            UpstreamError::NotFound | UpstreamError::NotInIndex => StatusCode::NOT_FOUND,
+            UpstreamError::ChecksumMismatch => StatusCode::BAD_GATEWAY,
+            UpstreamError::Storage => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+/// Downloads the crate from the upstream registry into the crates.io storage.
+/// Only crates matching the upstream index are stored and served. Concurrent
+/// requests for the same version wait for and share a single download.
+pub(crate) async fn fetch_upstream_crate(
+    db: &dyn DbProvider,
+    proxy: &Proxy,
+    crate_storage: &CratesIoCrateStorage,
+    package: &OriginalName,
+    version: &Version,
+) -> Result<(), UpstreamError> {
+    let target = proxy.crate_download_url(package, &version.to_string());
+    CRATE_FETCHES
+        .run(target.clone(), || {
+            download_and_store(db, proxy, crate_storage, target, package, version)
+        })
+        .await
+}
+
+async fn download_and_store(
+    db: &dyn DbProvider,
+    proxy: &Proxy,
+    crate_storage: &CratesIoCrateStorage,
+    target: String,
+    package: &OriginalName,
+    version: &Version,
+) -> Result<(), UpstreamError> {
+    let res = match CLIENT.get(target).send().await {
+        Ok(resp) if resp.status() != 200 => Err(UpstreamError::NotFound),
+        Ok(resp) => Ok(resp),
+        Err(e) => {
+            error!("Encountered error... {e}");
+            Err(UpstreamError::NotFound)
+        }
+    }?;
+
+    let crate_data = res.bytes().await.map_err(log_return_error)?;
+
+    let actual = checksum(&crate_data);
+    match expected_checksum(db, proxy, package, version).await {
+        Some(expected) if expected == actual => {}
+        Some(expected) => {
+            log_mismatch(package, &version.to_string(), &expected, &actual);
+            return Err(UpstreamError::ChecksumMismatch);
+        }
+        None => {
+            warn!(
+                target: "security",
+                "Crate {package} ({version}) is not in the upstream index and cannot be verified"
+            );
+            return Err(UpstreamError::NotInIndex);
+        }
     }
+
+    let size = i64::try_from(crate_data.len()).unwrap_or(i64::MAX);
+    let crate_data: Arc<[u8]> = Arc::from(crate_data.iter().as_slice());
+    crate_storage
+        .put(package, version, crate_data)
+        .await
+        .map_err(|e| {
+            error!("Failed to save crate to disk: {e}");
+            UpstreamError::Storage
+        })?;
+    db.touch_cratesio_cached_file(package, version, size)
+        .await
+        .unwrap_or_else(|e| warn!("Failed to track cached crate {package} ({version}): {e}"));
+    Ok(())
 }
 
-fn log_return_error<E: Error>(e: E) -> StatusCode {
+fn log_return_error<E: Error>(e: E) -> UpstreamError {
     error!("Failure while crate download...: {e}");
-    StatusCode::NOT_FOUND
+    UpstreamError::NotFound
 }
 
 #[cfg(test)]
@@ -117,14 +276,19 @@ mod tests {
     use super::*;
     use appstate::AppStateData;
     use axum::body::Body;
+    use axum::extract::Query;
     use axum::http::Request;
     use axum::routing::get;
     use axum::{Router, middleware};
+    use common::index_metadata::IndexMetadata;
     use common::util::generate_rand_string;
     use db::mock::MockDb;
+    use db::{PolicyAction, ProxyPolicyRule};
     use http_body_util::BodyExt;
     use settings::Settings;
+    use std::collections::HashMap;
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use storage::cached_crate_storage::DynStorage;
     use storage::cratesio_crate_storage::CratesIoCrateStorage;
     use storage::fs_storage::FSStorage;
@@ -222,6 +386,327 @@ mod tests {
         assert_eq!(12778, body.len());
     }
 
+    #[tokio::test]
+    async fn download_from_configured_upstream() {
+        let mut settings = get_settings();
+        configure_upstream(&mut settings, &checksum(b"foo-1.0.0")).await;
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/foo/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::OK);
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(b"foo-1.0.0", &body[..]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_downloads_share_one_upstream_fetch() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let upstream = Router::new()
+            .route(
+                "/files/foo/1.0.0/download",
+                get(|| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    "foo-1.0.0"
+                }),
+            )
+            .route(
+                "/index/3/f/foo",
+                get(|| async {
+                    IndexMetadata::minimal("foo", "1.0.0", &checksum(b"foo-1.0.0"))
+                        .to_json()
+                        .unwrap()
+                }),
+            );
+        let url = serve(upstream).await;
+        let mut settings = get_settings();
+        settings.proxy.download_url = format!("{url}/files");
+        settings.proxy.index_url = format!("{url}/index");
+        let kellnr = TestKellnr::new(settings);
+        let request = || {
+            kellnr.client.clone().oneshot(
+                Request::get("/api/v1/cratesio/foo/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let (r1, r2, r3) = tokio::join!(request(), request(), request());
+
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+        for r in [r1, r2, r3] {
+            let r = r.unwrap();
+            assert_eq!(r.status(), StatusCode::OK);
+            let body = r.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(b"foo-1.0.0", &body[..]);
+        }
+    }
+
+    #[tokio::test]
+    async fn download_with_checksum_mismatch_is_rejected() {
+        let mut settings = get_settings();
+        configure_upstream(&mut settings, &checksum(b"something else")).await;
+        let crate_file = PathBuf::from(settings.crates_io_path()).join("foo-1.0.0.crate");
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/foo/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::BAD_GATEWAY);
+        assert!(!crate_file.exists());
+    }
+
+    #[tokio::test]
+    async fn download_of_quarantined_version_is_refused() {
+        let mut settings = get_settings();
+        settings.proxy.quarantine_days = 7;
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/quarantined/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn download_of_blocked_version_is_refused_with_reason() {
+        let kellnr = TestKellnr::new(get_settings());
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/blocked/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            "crate `blocked@1.0.0` is blocked by the proxy policy of kellnr: known vulnerability",
+            String::from_utf8_lossy(&body)
+        );
+    }
+
+    #[tokio::test]
+    async fn download_checks_upstream_publish_time_of_uncached_version() {
+        let published = (chrono::Utc::now() - chrono::Duration::days(30)).to_rfc3339();
+        let upstream = Router::new()
+            .route(
+                "/fr/es/fresh",
+                get(|| async move { index_line("fresh", Some(chrono::Utc::now().to_rfc3339())) }),
+            )
+            .route(
+                "/ag/ed/aged",
+                get(move || async move { index_line("aged", Some(published)) }),
+            )
+            .route(
+                "/un/kn/unknown",
+                get(|| async { index_line("unknown", None) }),
+            );
+        let mut settings = get_settings();
+        settings.proxy.quarantine_days = 7;
+        settings.proxy.index_url = serve(upstream).await;
+        // The crate files are not available upstream
+        settings
+            .proxy
+            .download_url
+            .clone_from(&settings.proxy.index_url);
+        let kellnr = TestKellnr::new(settings);
+
+        let status = |name: &str| {
+            let client = kellnr.client.clone();
+            let uri = format!("/api/v1/cratesio/{name}/1.0.0/download");
+            async move {
+                client
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(StatusCode::FORBIDDEN, status("fresh").await);
+        assert_eq!(StatusCode::FORBIDDEN, status("missing").await);
+        // Not quarantined, but not available upstream
+        assert_eq!(StatusCode::NOT_FOUND, status("aged").await);
+        assert_eq!(StatusCode::NOT_FOUND, status("unknown").await);
+    }
+
+    #[tokio::test]
+    async fn offline_download_of_uncached_version_does_not_fetch_upstream() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let upstream = Router::new().fallback(move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            index_line("fresh", None)
+        });
+        let mut settings = get_settings();
+        settings.proxy.quarantine_days = 7;
+        settings.proxy.offline = true;
+        settings.proxy.index_url = serve(upstream).await;
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/fresh/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+        assert_eq!(0, requests.load(Ordering::SeqCst));
+    }
+
+    fn index_line(name: &str, pubtime: Option<String>) -> String {
+        serde_json::to_string(&IndexMetadata {
+            pubtime,
+            ..IndexMetadata::minimal(name, "1.0.0", "cksum")
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn search_on_configured_upstream() {
+        let upstream = Router::new().route(
+            "/api/v1/crates",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                format!("{}:{}", query["q"], query["per_page"])
+            }),
+        );
+        let mut settings = get_settings();
+        settings.proxy.api_url = format!("{}/api/v1", serve(upstream).await);
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio?q=serde&per_page=5")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::OK);
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(b"serde:5", &body[..]);
+    }
+
+    #[tokio::test]
+    async fn offline_does_not_download_uncached_crate() {
+        let upstream = Router::new().route(
+            "/files/{package}/{version}/download",
+            get(|| async { "crate" }),
+        );
+        let mut settings = get_settings();
+        settings.proxy.offline = true;
+        settings.proxy.download_url = format!("{}/files", serve(upstream).await);
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/foo/1.0.0/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn offline_search_is_unavailable() {
+        let mut settings = get_settings();
+        settings.proxy.offline = true;
+        let kellnr = TestKellnr::new(settings);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio?q=serde")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    /// Points the proxy to a stand-in upstream, which serves `{crate}-{version}` as crate
+    /// files and an index with the given checksum for all versions.
+    async fn configure_upstream(settings: &mut Settings, cksum: &str) {
+        let cksum = cksum.to_string();
+        let upstream = Router::new()
+            .route(
+                "/files/{package}/{version}/download",
+                get(
+                    |Path((package, version)): Path<(String, String)>| async move {
+                        format!("{package}-{version}")
+                    },
+                ),
+            )
+            .route(
+                "/index/3/f/foo",
+                get(|| async move {
+                    IndexMetadata::minimal("foo", "1.0.0", &cksum)
+                        .to_json()
+                        .unwrap()
+                }),
+            );
+        let url = serve(upstream).await;
+        settings.proxy.download_url = format!("{url}/files");
+        settings.proxy.index_url = format!("{url}/index");
+    }
+
+    /// Serves the stand-in upstream registry on a local port and returns its URL.
+    async fn serve(upstream: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+        url
+    }
+
     #[tokio::test]
     async fn cratesio_disabled_returns_404() {
         let mut settings = get_settings();
@@ -253,9 +738,9 @@ mod tests {
                 session_age_seconds: 10,
                 ..settings::Registry::default()
             },
-            proxy: settings::Proxy {
+            proxy: Proxy {
                 enabled: true,
-                ..settings::Proxy::default()
+                ..Proxy::default()
             },
             ..Settings::default()
         }
@@ -283,6 +768,29 @@ mod tests {
         let mut db = MockDb::new();
         db.expect_increase_cached_download_counter()
             .returning(|_, _| Ok(()));
+        db.expect_get_cratesio_checksum().returning(|_, _| Ok(None));
+        db.expect_touch_cratesio_cached_file()
+            .returning(|_, _, _| Ok(()));
+        db.expect_is_quarantined_cratesio_version()
+            .returning(|name, _, _| {
+                Ok(match name.to_string().as_str() {
+                    "quarantined" => Some(true),
+                    "fresh" | "aged" | "unknown" | "missing" => None,
+                    _ => Some(false),
+                })
+            });
+        db.expect_get_proxy_policy_rules().returning(|| {
+            Ok(vec![ProxyPolicyRule {
+                id: 1,
+                action: PolicyAction::Deny,
+                crate_pattern: "blocked".to_string(),
+                version_req: Some("<2".to_string()),
+                licenses: vec![],
+                group: None,
+                reason: "known vulnerability".to_string(),
+                created: "2026-10-18 12:00:00".to_string(),
+            }])
+        });
 
         let state = AppStateData {
             settings: settings.into(),
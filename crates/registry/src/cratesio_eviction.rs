@@ -0,0 +1,332 @@
+use chrono::Utc;
+use common::original_name::OriginalName;
+use common::version::Version;
+use db::error::DbError;
+use db::{CratesIoEvictionReport, DbProvider};
+use settings::Proxy;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use storage::cratesio_crate_storage::CratesIoCrateStorage;
+use tracing::{error, info};
+
+/// Interval between eviction runs, if a size or age limit is set.
+const EVICTION_INTERVAL: Duration = Duration::from_hours(1);
+
+/// Evicts crate files from the crates.io storage at a fixed interval, if a size
+/// or age limit for the cache is set.
+pub fn eviction_worker(db: Arc<dyn DbProvider>, storage: Arc<CratesIoCrateStorage>, proxy: Proxy) {
+    if !proxy.enabled || !proxy.eviction_enabled() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        track_cached_crates(db.as_ref(), &storage).await;
+        loop {
+            if let Err(e) = evict_cached_crates(db.as_ref(), &storage, &proxy).await {
+                error!("Eviction of cached crates.io crates failed: {e}");
+            }
+            tokio::time::sleep(EVICTION_INTERVAL).await;
+        }
+    });
+}
+
+/// Tracks the crate files of the storage which were cached without being tracked,
+/// e.g. before the eviction existed, such that they can be evicted as well.
+pub async fn track_cached_crates(db: &dyn DbProvider, storage: &CratesIoCrateStorage) {
+    let files = match storage.crate_files().await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to list cached crates.io crates: {e}");
+            return;
+        }
+    };
+    let files: Vec<_> = files
+        .into_iter()
+        .map(|(name, version, size)| (name, version, i64::try_from(size).unwrap_or(i64::MAX)))
+        .collect();
+    match db.track_cratesio_cached_files(&files).await {
+        Ok(0) => {}
+        Ok(added) => info!("Tracking {added} cached crates.io crates for the eviction"),
+        Err(e) => error!("Failed to track cached crates.io crates: {e}"),
+    }
+}
+
+/// Evicts the least recently downloaded crate files until the cache fits into the
+/// size limit, and all files which were not downloaded within the age limit.
+/// Pinned versions are never evicted and do not count towards the size limit.
+/// The result of the run is stored as a report.
+pub async fn evict_cached_crates(
+    db: &dyn DbProvider,
+    storage: &CratesIoCrateStorage,
+    proxy: &Proxy,
+) -> Result<CratesIoEvictionReport, DbError> {
+    let started = Utc::now();
+    let files = db
+        .get_cratesio_cached_files(proxy.cache_age_cutoff())
+        .await?;
+    let pins: BTreeSet<(String, String)> = db
+        .get_cratesio_pins()
+        .await?
+        .into_iter()
+        .map(|p| (p.name, p.version))
+        .collect();
+    let files: Vec<_> = files
+        .into_iter()
+        .filter(|f| !pins.contains(&(f.normalized_name().to_string(), f.version.clone())))
+        .collect();
+
+    let max_size = i64::try_from(proxy.max_cache_size).unwrap_or(i64::MAX);
+    let mut remaining: i64 = files.iter().map(|f| f.size).sum();
+    let mut reclaimed = 0;
+    let mut evicted = vec![];
+    // Files are ordered by their last download, such that the first file which is
+    // neither expired nor needed to fit into the size limit ends the eviction
+    for file in &files {
+        let name = OriginalName::from_unchecked(file.name.clone());
+        let over_budget = max_size > 0 && remaining > max_size;
+        if !file.expired && !over_budget {
+            break;
+        }
+        let Ok(version) = Version::try_from(&file.version) else {
+            continue;
+        };
+
+        if let Err(e) = storage.delete(&name, &version).await {
+            error!("Failed to evict {name} ({version}): {e}");
+            continue;
+        }
+        db.delete_cratesio_cached_file(&name, &version).await?;
+        remaining -= file.size;
+        reclaimed += file.size;
+        evicted.push(format!("{name}@{version}"));
+    }
+
+    let report = db
+        .add_cratesio_eviction_report(&started, &evicted, reclaimed, remaining)
+        .await?;
+    info!(
+        "Evicted {} cached crates.io crates, reclaimed {reclaimed} bytes, {remaining} bytes remain",
+        evicted.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::util::generate_rand_string;
+    use db::mock::MockDb;
+    use db::{CachedCratesIoFile, CratesIoPin};
+    use settings::Settings;
+    use storage::cached_crate_storage::DynStorage;
+    use storage::fs_storage::FSStorage;
+
+    struct TestStorage {
+        settings: Settings,
+        storage: CratesIoCrateStorage,
+    }
+
+    impl TestStorage {
+        async fn new(files: &[(&str, &str)]) -> Self {
+            let settings = Settings {
+                registry: settings::Registry {
+                    data_dir: "/tmp/".to_string() + &generate_rand_string(10),
+                    ..settings::Registry::default()
+                },
+                ..Settings::default()
+            };
+            std::fs::create_dir_all(settings.crates_io_path()).unwrap();
+            let fs = Box::new(FSStorage::new(&settings.crates_io_path()).unwrap()) as DynStorage;
+            let storage = CratesIoCrateStorage::new(&settings, fs);
+            for (name, version) in files {
+                storage
+                    .put(
+                        &OriginalName::from_unchecked((*name).to_string()),
+                        &Version::try_from(*version).unwrap(),
+                        Arc::from(&b"data"[..]),
+                    )
+                    .await
+                    .unwrap();
+            }
+            Self { settings, storage }
+        }
+
+        async fn contains(&self, name: &str, version: &str) -> bool {
+            self.storage
+                .get(
+                    &OriginalName::from_unchecked(name.to_string()),
+                    &Version::try_from(version).unwrap(),
+                )
+                .await
+                .is_some()
+        }
+    }
+
+    impl Drop for TestStorage {
+        fn drop(&mut self) {
+            rm_rf::remove(&self.settings.registry.data_dir).unwrap();
+        }
+    }
+
+    fn cached_file(name: &str, version: &str, size: i64, expired: bool) -> CachedCratesIoFile {
+        CachedCratesIoFile {
+            name: name.to_string(),
+            version: version.to_string(),
+            size,
+            last_downloaded: "2026-10-18 12:00:00".to_string(),
+            expired,
+        }
+    }
+
+    fn mock_db(files: Vec<CachedCratesIoFile>, pins: &[(&str, &str)]) -> MockDb {
+        let pins: Vec<CratesIoPin> = pins
+            .iter()
+            .map(|(name, version)| CratesIoPin {
+                name: (*name).to_string(),
+                version: (*version).to_string(),
+                created: "2026-10-18 12:00:00".to_string(),
+            })
+            .collect();
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_cratesio_cached_files()
+            .returning(move |_| Ok(files.clone()));
+        mock_db
+            .expect_get_cratesio_pins()
+            .returning(move || Ok(pins.clone()));
+        mock_db
+            .expect_delete_cratesio_cached_file()
+            .returning(|_, _| Ok(()));
+        mock_db.expect_add_cratesio_eviction_report().returning(
+            |_, evicted, reclaimed_bytes, remaining_bytes| {
+                Ok(CratesIoEvictionReport {
+                    id: 1,
+                    started: "2026-10-18 12:00:00".to_string(),
+                    finished: "2026-10-18 12:00:01".to_string(),
+                    evicted: evicted.to_vec(),
+                    reclaimed_bytes,
+                    remaining_bytes,
+                })
+            },
+        );
+        mock_db
+    }
+
+    #[tokio::test]
+    async fn least_recently_downloaded_files_are_evicted_until_cache_fits() {
+        let storage = TestStorage::new(&[("a", "1.0.0"), ("b", "1.0.0"), ("c", "1.0.0")]).await;
+        let mock_db = mock_db(
+            vec![
+                cached_file("a", "1.0.0", 100, false),
+                cached_file("b", "1.0.0", 100, false),
+                cached_file("c", "1.0.0", 100, false),
+            ],
+            &[],
+        );
+        let proxy = Proxy {
+            max_cache_size: 150,
+            ..Proxy::default()
+        };
+
+        let report = evict_cached_crates(&mock_db, &storage.storage, &proxy)
+            .await
+            .unwrap();
+
+        assert_eq!(vec!["a@1.0.0", "b@1.0.0"], report.evicted);
+        assert_eq!((200, 100), (report.reclaimed_bytes, report.remaining_bytes));
+        assert!(!storage.contains("a", "1.0.0").await);
+        assert!(!storage.contains("b", "1.0.0").await);
+        assert!(storage.contains("c", "1.0.0").await);
+    }
+
+    #[tokio::test]
+    async fn pinned_files_are_kept() {
+        let storage = TestStorage::new(&[("Pinned", "1.0.0"), ("old", "1.0.0")]).await;
+        let mock_db = mock_db(
+            vec![
+                cached_file("Pinned", "1.0.0", 100, true),
+                cached_file("old", "1.0.0", 100, true),
+            ],
+            &[("pinned", "1.0.0")],
+        );
+        let proxy = Proxy {
+            max_cache_age_days: 30,
+            ..Proxy::default()
+        };
+
+        let report = evict_cached_crates(&mock_db, &storage.storage, &proxy)
+            .await
+            .unwrap();
+
+        assert_eq!(vec!["old@1.0.0"], report.evicted);
+        assert_eq!((100, 0), (report.reclaimed_bytes, report.remaining_bytes));
+        assert!(storage.contains("Pinned", "1.0.0").await);
+    }
+
+    #[tokio::test]
+    async fn pinned_files_do_not_count_towards_size_limit() {
+        let storage = TestStorage::new(&[("pinned", "1.0.0"), ("a", "1.0.0")]).await;
+        let mock_db = mock_db(
+            vec![
+                cached_file("pinned", "1.0.0", 1000, false),
+                cached_file("a", "1.0.0", 100, false),
+            ],
+            &[("pinned", "1.0.0")],
+        );
+        let proxy = Proxy {
+            max_cache_size: 150,
+            ..Proxy::default()
+        };
+
+        let report = evict_cached_crates(&mock_db, &storage.storage, &proxy)
+            .await
+            .unwrap();
+
+        assert!(report.evicted.is_empty());
+        assert!(storage.contains("a", "1.0.0").await);
+    }
+
+    #[tokio::test]
+    async fn untracked_files_are_tracked_with_their_file_name() {
+        let storage = TestStorage::new(&[("Foo-Bar", "1.0.0-rc.1"), ("a", "2.0.0")]).await;
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_track_cratesio_cached_files()
+            .withf(|files| {
+                let mut files: Vec<_> = files
+                    .iter()
+                    .map(|(name, version, size)| (name.to_string(), version.to_string(), *size))
+                    .collect();
+                files.sort();
+                files
+                    == vec![
+                        ("Foo-Bar".to_string(), "1.0.0-rc.1".to_string(), 4),
+                        ("a".to_string(), "2.0.0".to_string(), 4),
+                    ]
+            })
+            .times(1)
+            .returning(|files| Ok(files.len() as u64));
+
+        track_cached_crates(&mock_db, &storage.storage).await;
+    }
+
+    #[tokio::test]
+    async fn nothing_is_evicted_within_limits() {
+        let storage = TestStorage::new(&[("a", "1.0.0")]).await;
+        let mock_db = mock_db(vec![cached_file("a", "1.0.0", 100, false)], &[]);
+        let proxy = Proxy {
+            max_cache_size: 1000,
+            max_cache_age_days: 30,
+            ..Proxy::default()
+        };
+
+        let report = evict_cached_crates(&mock_db, &storage.storage, &proxy)
+            .await
+            .unwrap();
+
+        assert!(report.evicted.is_empty());
+        assert_eq!((0, 100), (report.reclaimed_bytes, report.remaining_bytes));
+        assert!(storage.contains("a", "1.0.0").await);
+    }
+}
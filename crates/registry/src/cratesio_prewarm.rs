@@ -0,0 +1,493 @@
+use crate::cratesio_api::{fetch_upstream_crate, is_quarantined};
+use crate::registry_error::RegistryError;
+use appstate::{AppState, AppStateData};
+use auth::token::Token;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use common::cratesio_prefetch_msg::{CratesioPrefetchMsg, UpdateData};
+use common::original_name::OriginalName;
+use common::prewarm::{PackageProgress, PackageStatus, PrewarmJob};
+use common::version::Version;
+use db::EndpointScope;
+use error::api_error::ApiResult;
+use futures_util::StreamExt;
+use futures_util::stream;
+use index::proxy_policy::{ProxyError, ProxyPolicy};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use tracing::{error, info};
+
+/// Sources of crates.io packages in a `Cargo.lock`
+const CRATES_IO_SOURCES: [&str; 2] = [
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// Number of packages of a job which are downloaded at once.
+const JOB_DOWNLOADS: usize = 4;
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+/// Parses a JSON list of name/version pairs or the crates.io packages of a `Cargo.lock`.
+/// Workspace members, git dependencies and packages of other registries are skipped.
+fn parse_packages(body: &str, is_json: bool) -> Result<Vec<(String, String)>, RegistryError> {
+    let packages: Vec<(String, String)> = if is_json {
+        serde_json::from_str::<Vec<Package>>(body)
+            .map_err(|e| RegistryError::InvalidPrewarmRequest(e.to_string()))?
+            .into_iter()
+            .map(|p| (p.name, p.version))
+            .collect()
+    } else {
+        toml::from_str::<CargoLock>(body)
+            .map_err(|e| RegistryError::InvalidPrewarmRequest(e.to_string()))?
+            .package
+            .into_iter()
+            .filter(|p| {
+                p.source
+                    .as_deref()
+                    .is_some_and(|s| CRATES_IO_SOURCES.contains(&s))
+            })
+            .map(|p| (p.name, p.version))
+            .collect()
+    };
+
+    // Duplicates are downloaded once
+    let mut seen = BTreeSet::new();
+    Ok(packages
+        .into_iter()
+        .filter(|p| seen.insert(p.clone()))
+        .collect())
+}
+
+/// Starts to download the crates.io packages of a `Cargo.lock`, or of a JSON list of
+/// name/version pairs, into the proxy cache. The progress can be polled with the id
+/// of the returned job.
+#[allow(clippy::unused_async)] // part of the router
+pub async fn prewarm(
+    token: Token,
+    headers: HeaderMap,
+    State(state): AppState,
+    body: String,
+) -> ApiResult<(StatusCode, Json<PrewarmJob>)> {
+    if state.settings.proxy.offline {
+        return Err(RegistryError::ProxyOffline.into());
+    }
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    let packages = parse_packages(&body, is_json)?;
+
+    let job = state
+        .prewarm_jobs
+        .add(&token.user, packages)
+        .ok_or(RegistryError::TooManyPrewarmJobs)?;
+    info!(
+        "Pre-warming the crates.io cache with {} packages for {}",
+        job.total, token.user
+    );
+
+    let id = job.id;
+    let packages = job.packages.clone();
+    tokio::spawn(async move {
+        run_job(id, &packages, &token, &state).await;
+    });
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Returns the progress of a job, which can only be seen by its creator or an admin.
+#[allow(clippy::unused_async)] // part of the router
+pub async fn prewarm_status(
+    token: Token,
+    Path(id): Path<u64>,
+    State(state): AppState,
+) -> ApiResult<Json<PrewarmJob>> {
+    state
+        .prewarm_jobs
+        .get(id)
+        .filter(|job| token.is_admin || job.user == token.user)
+        .map(Json)
+        .ok_or_else(|| RegistryError::PrewarmJobNotFound(id).into())
+}
+
+async fn run_job(id: u64, packages: &[PackageProgress], token: &Token, state: &AppStateData) {
+    // Fetch the index entries in the prefetch threads, while the crate files are downloaded
+    let names: BTreeSet<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    for name in names {
+        if let Ok(name) = OriginalName::try_from(name) {
+            let msg = CratesioPrefetchMsg::Update(UpdateData {
+                name,
+                etag: None,
+                last_modified: None,
+            });
+            if let Err(e) = state.cratesio_prefetch_sender.send(msg) {
+                error!("Could not send update message: {e}");
+            }
+        }
+    }
+
+    let policy = ProxyPolicy::for_user(state.db.as_ref(), Some(&token.user))
+        .await
+        .map_err(|e| {
+            error!("Could not get the proxy policy: {e}");
+            "the proxy policy could not be checked".to_string()
+        });
+
+    let jobs = &state.prewarm_jobs;
+    stream::iter(packages.iter().enumerate())
+        .for_each_concurrent(JOB_DOWNLOADS, |(index, package)| {
+            let policy = &policy;
+            async move {
+                let result = match policy {
+                    Ok(policy) => {
+                        let _permit = jobs.download_permit().await;
+                        prewarm_package(&package.name, &package.version, token, policy, state).await
+                    }
+                    Err(e) => Err(e.clone()),
+                };
+                jobs.update(id, |job| job.set_result(index, result));
+            }
+        })
+        .await;
+
+    jobs.update(id, |job| {
+        job.finished = true;
+        info!(
+            "Pre-warmed the crates.io cache with {} packages, {} failed",
+            job.total, job.failed
+        );
+    });
+}
+
+async fn prewarm_package(
+    name: &str,
+    version: &str,
+    token: &Token,
+    policy: &ProxyPolicy,
+    state: &AppStateData,
+) -> Result<PackageStatus, String> {
+    let name = OriginalName::try_from(name).map_err(|e| e.to_string())?;
+    let version = Version::try_from(version).map_err(|e| e.to_string())?;
+    let db = state.db.as_ref();
+    let proxy = &state.settings.proxy;
+
+    if !token
+        .scopes
+        .allows(EndpointScope::Download, &name.to_normalized())
+    {
+        return Err(format!(
+            "the token scopes do not allow {}",
+            EndpointScope::Download
+        ));
+    }
+    policy
+        .check_download(db, proxy, &name, &version.to_string())
+        .await
+        .map_err(|e| match e {
+            ProxyError::Blocked(msg) => msg,
+            ProxyError::Status(status) => status.to_string(),
+        })?;
+    if is_quarantined(db, proxy, &name, &version)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("the version is quarantined".to_string());
+    }
+
+    if state.cratesio_storage.get(&name, &version).await.is_some() {
+        return Ok(PackageStatus::Cached);
+    }
+    fetch_upstream_crate(db, proxy, &state.cratesio_storage, &name, &version)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(PackageStatus::Downloaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::{get, post};
+    use common::prewarm::PrewarmJobs;
+    use db::mock::MockDb;
+    use db::{AuthToken, PolicyAction, ProxyPolicyRule, TokenScopes, User};
+    use http_body_util::BodyExt;
+    use settings::Settings;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn token(user: &str, scopes: TokenScopes) -> Token {
+        Token {
+            value: "token".to_string(),
+            user: user.to_string(),
+            is_admin: false,
+            is_read_only: false,
+            trusted_crates: None,
+            scopes,
+            auth_token_id: Some(1),
+            is_service_account: false,
+            client_ip: None,
+        }
+    }
+
+    fn state(mock_db: MockDb, settings: Settings) -> AppStateData {
+        AppStateData {
+            db: Arc::new(mock_db),
+            settings: Arc::new(settings),
+            ..appstate::test_state()
+        }
+    }
+
+    /// Users authenticated by tokens named after them
+    fn token_db() -> MockDb {
+        let mut mock_db = MockDb::new();
+        mock_db.expect_get_user_from_token().returning(|token| {
+            Ok((
+                User {
+                    name: token.to_string(),
+                    is_admin: token == "admin",
+                    ..User::default()
+                },
+                AuthToken::default(),
+            ))
+        });
+        mock_db
+            .expect_update_auth_token_usage()
+            .returning(|_, _, _| Ok(()));
+        mock_db
+            .expect_get_proxy_policy_rules()
+            .returning(|| Ok(vec![]));
+        mock_db
+    }
+
+    fn app(state: AppStateData) -> Router {
+        Router::new()
+            .route("/prewarm", post(prewarm))
+            .route("/prewarm/{id}", get(prewarm_status))
+            .with_state(state)
+    }
+
+    fn prewarm_request(user: &str) -> Request<Body> {
+        Request::post("/prewarm")
+            .header(header::AUTHORIZATION, user)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("[]"))
+            .unwrap()
+    }
+
+    fn status_request(user: &str, id: u64) -> Request<Body> {
+        Request::get(format!("/prewarm/{id}"))
+            .header(header::AUTHORIZATION, user)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn job_is_only_visible_to_its_creator_and_admins() {
+        let app = app(state(token_db(), Settings::default()));
+
+        let r = app.clone().oneshot(prewarm_request("alice")).await.unwrap();
+        assert_eq!(StatusCode::ACCEPTED, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+
+        let status = async |user: &str| {
+            app.clone()
+                .oneshot(status_request(user, id))
+                .await
+                .unwrap()
+                .status()
+        };
+        assert_eq!(StatusCode::OK, status("alice").await);
+        assert_eq!(StatusCode::OK, status("admin").await);
+        assert_eq!(StatusCode::NOT_FOUND, status("bob").await);
+    }
+
+    #[tokio::test]
+    async fn prewarm_is_refused_if_too_many_jobs_are_running() {
+        let state = AppStateData {
+            prewarm_jobs: Arc::new(PrewarmJobs::new(1, 1)),
+            ..state(token_db(), Settings::default())
+        };
+        state
+            .prewarm_jobs
+            .add("bob", vec![("a".into(), "1.0.0".into())]);
+
+        let r = app(state).oneshot(prewarm_request("alice")).await.unwrap();
+
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, r.status());
+    }
+
+    #[tokio::test]
+    async fn prewarm_is_refused_if_proxy_is_offline() {
+        let mut settings = Settings::default();
+        settings.proxy.offline = true;
+
+        let r = app(state(token_db(), settings))
+            .oneshot(prewarm_request("alice"))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, r.status());
+    }
+
+    #[tokio::test]
+    async fn package_needs_download_scope() {
+        let state = state(MockDb::new(), Settings::default());
+        let token = token(
+            "alice",
+            TokenScopes {
+                endpoint_scopes: Some(vec![EndpointScope::PublishUpdate]),
+                crate_scopes: None,
+            },
+        );
+
+        let r = prewarm_package("serde", "1.0.0", &token, &ProxyPolicy::default(), &state).await;
+
+        assert_eq!(Err("the token scopes do not allow download".to_string()), r);
+    }
+
+    #[tokio::test]
+    async fn package_blocked_by_proxy_policy_is_refused() {
+        let state = state(MockDb::new(), Settings::default());
+        let policy = ProxyPolicy::new(vec![ProxyPolicyRule {
+            id: 1,
+            action: PolicyAction::Deny,
+            crate_pattern: "evil".to_string(),
+            version_req: None,
+            licenses: vec![],
+            group: None,
+            reason: "malware".to_string(),
+            created: String::new(),
+        }]);
+
+        let r = prewarm_package(
+            "evil",
+            "1.0.0",
+            &token("alice", TokenScopes::default()),
+            &policy,
+            &state,
+        )
+        .await;
+
+        assert_eq!(
+            Err(index::proxy_policy::blocked_message(
+                "evil",
+                Some("1.0.0"),
+                "malware"
+            )),
+            r
+        );
+    }
+
+    #[tokio::test]
+    async fn quarantined_package_is_refused() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_quarantined_cratesio_version()
+            .returning(|_, _, _| Ok(Some(true)));
+        let mut settings = Settings::default();
+        settings.proxy.quarantine_days = 7;
+        let state = state(mock_db, settings);
+
+        let r = prewarm_package(
+            "fresh",
+            "1.0.0",
+            &token("alice", TokenScopes::default()),
+            &ProxyPolicy::default(),
+            &state,
+        )
+        .await;
+
+        assert_eq!(Err("the version is quarantined".to_string()), r);
+    }
+
+    #[test]
+    fn parse_cargo_lock_keeps_crates_io_packages() {
+        let lock = r#"
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.210"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "c8e3592472072e6e22e0a54d5904d9febf8508f65fb8552499a1abc7d1078c3a"
+
+[[package]]
+name = "tokio"
+version = "1.40.0"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "internal"
+version = "0.2.0"
+source = "sparse+https://kellnr.example.com/api/v1/crates/"
+
+[[package]]
+name = "forked"
+version = "0.3.0"
+source = "git+https://github.com/example/forked#0123456789abcdef"
+"#;
+
+        let packages = parse_packages(lock, false).unwrap();
+
+        assert_eq!(
+            vec![
+                ("serde".to_string(), "1.0.210".to_string()),
+                ("tokio".to_string(), "1.40.0".to_string()),
+            ],
+            packages
+        );
+    }
+
+    #[test]
+    fn parse_json_list_removes_duplicates() {
+        let body = r#"[
+            {"name": "serde", "version": "1.0.0"},
+            {"name": "serde", "version": "1.0.0"},
+            {"name": "serde", "version": "1.0.1"}
+        ]"#;
+
+        let packages = parse_packages(body, true).unwrap();
+
+        assert_eq!(
+            vec![
+                ("serde".to_string(), "1.0.0".to_string()),
+                ("serde".to_string(), "1.0.1".to_string()),
+            ],
+            packages
+        );
+    }
+
+    #[test]
+    fn parse_invalid_body_is_rejected() {
+        assert!(parse_packages("[[package]", false).is_err());
+        assert!(parse_packages("{}", true).is_err());
+    }
+}
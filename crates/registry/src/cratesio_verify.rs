@@ -0,0 +1,223 @@
+use crate::cratesio_api::CLIENT;
+use chrono::{DateTime, Utc};
+use common::index_metadata::{IndexMetadata, sparse_index_path};
+use common::original_name::OriginalName;
+use common::version::Version;
+use db::DbProvider;
+use db::error::DbError;
+use serde::Serialize;
+use settings::Proxy;
+use sha2::{Digest, Sha256};
+use storage::cratesio_crate_storage::CratesIoCrateStorage;
+use tracing::{error, info, warn};
+
+/// Number of index entries read from the database at once.
+const VERIFY_BATCH_SIZE: u64 = 1000;
+
+/// Hex encoded SHA-256 of the crate file, as in the `cksum` field of the index.
+pub fn checksum(crate_data: &[u8]) -> String {
+    hex::encode(Sha256::digest(crate_data))
+}
+
+/// Checksum of the crate version in the upstream index. The cached index is used if
+/// possible, else the index file is fetched, as the prefetched data is stored
+/// asynchronously and might be missing on the first download of a crate.
+pub(crate) async fn expected_checksum(
+    db: &dyn DbProvider,
+    proxy: &Proxy,
+    name: &OriginalName,
+    version: &Version,
+) -> Option<String> {
+    match db
+        .get_cratesio_checksum(&name.to_normalized(), version)
+        .await
+    {
+        Ok(Some(cksum)) => return Some(cksum),
+        Ok(None) => {}
+        Err(e) => error!("Failed to read checksum of {name} ({version}): {e}"),
+    }
+
+    upstream_metadata(proxy, name, version)
+        .await
+        .map(|metadata| metadata.cksum)
+}
+
+/// Publish time of the crate version, which is missing for versions published
+/// before crates.io recorded it.
+pub(crate) fn publish_time(metadata: &IndexMetadata) -> Option<DateTime<Utc>> {
+    metadata
+        .pubtime
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Entry of the crate version in the upstream index, or `None` if the version is
+/// not in the index or the index file could not be fetched.
+pub(crate) async fn upstream_metadata(
+    proxy: &Proxy,
+    name: &OriginalName,
+    version: &Version,
+) -> Option<IndexMetadata> {
+    let url = proxy.index_file_url(&sparse_index_path(&name.to_normalized()));
+    let index = match CLIENT
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+    {
+        Ok(response) => response.text().await.ok()?,
+        Err(e) => {
+            warn!("Failed to fetch index of {name}: {e}");
+            return None;
+        }
+    };
+    let version = version.to_string();
+    index
+        .lines()
+        .filter_map(|line| serde_json::from_str::<IndexMetadata>(line).ok())
+        .find(|metadata| metadata.vers == version)
+}
+
+/// Logs a crate file which does not match the index, as it was tampered with
+/// upstream, in transit or in the storage.
+pub(crate) fn log_mismatch(name: &str, version: &str, expected: &str, actual: &str) {
+    error!(
+        target: "security",
+        "Checksum mismatch of crates.io crate {name} ({version}): expected {expected}, got {actual}"
+    );
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    /// Number of verified crate files
+    pub checked: u64,
+    /// Crate files which did not match the index and were removed
+    pub removed: Vec<String>,
+}
+
+/// Verifies all crate files in the cratesio storage against the cached index.
+/// Mismatching files are removed, such that they are downloaded and verified again.
+pub async fn verify_cached_crates(
+    db: &dyn DbProvider,
+    storage: &CratesIoCrateStorage,
+) -> Result<VerifyReport, DbError> {
+    let mut report = VerifyReport::default();
+    let mut offset = 0;
+    loop {
+        let versions = db
+            .get_cratesio_crate_versions(VERIFY_BATCH_SIZE, offset)
+            .await?;
+        for cached in &versions {
+            let Ok(version) = Version::try_from(&cached.version) else {
+                continue;
+            };
+            let Some(crate_data) = storage.get(&cached.name, &version).await else {
+                continue;
+            };
+            report.checked += 1;
+
+            let actual = checksum(&crate_data);
+            if actual != cached.cksum {
+                log_mismatch(&cached.name, &cached.version, &cached.cksum, &actual);
+                if let Err(e) = storage.delete(&cached.name, &version).await {
+                    error!("Failed to remove {} ({version}): {e}", cached.name);
+                }
+                db.delete_cratesio_cached_file(&cached.name, &version)
+                    .await?;
+                report.removed.push(format!("{}-{version}", cached.name));
+            }
+        }
+        if (versions.len() as u64) < VERIFY_BATCH_SIZE {
+            break;
+        }
+        offset += VERIFY_BATCH_SIZE;
+    }
+
+    info!(
+        "Verified {} cached crates.io crates, removed {} with mismatching checksum",
+        report.checked,
+        report.removed.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::util::generate_rand_string;
+    use db::CachedCrateVersion;
+    use db::mock::MockDb;
+    use mockall::predicate::*;
+    use settings::Settings;
+    use std::sync::Arc;
+    use storage::cached_crate_storage::DynStorage;
+    use storage::fs_storage::FSStorage;
+
+    #[test]
+    fn checksum_is_hex_sha256() {
+        assert_eq!(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+            checksum(b"hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn mismatching_crates_are_removed() {
+        let settings = Settings {
+            registry: settings::Registry {
+                data_dir: "/tmp/".to_string() + &generate_rand_string(10),
+                ..settings::Registry::default()
+            },
+            ..Settings::default()
+        };
+        std::fs::create_dir_all(settings.crates_io_path()).unwrap();
+        let fs = Box::new(FSStorage::new(&settings.crates_io_path()).unwrap()) as DynStorage;
+        let storage = CratesIoCrateStorage::new(&settings, fs);
+        let name = OriginalName::from_unchecked("foo".to_string());
+        for (version, data) in [("1.0.0", b"good"), ("2.0.0", b"evil")] {
+            let version = Version::try_from(version).unwrap();
+            storage
+                .put(&name, &version, Arc::from(&data[..]))
+                .await
+                .unwrap();
+        }
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_cratesio_crate_versions()
+            .with(eq(VERIFY_BATCH_SIZE), eq(0))
+            .returning(|_, _| {
+                Ok(["1.0.0", "2.0.0", "3.0.0"]
+                    .into_iter()
+                    .map(|version| CachedCrateVersion {
+                        name: OriginalName::from_unchecked("foo".to_string()),
+                        version: version.to_string(),
+                        cksum: checksum(b"good"),
+                        license: None,
+                    })
+                    .collect())
+            });
+        mock_db
+            .expect_delete_cratesio_cached_file()
+            .withf(|name, version| name.to_string() == "foo" && version.to_string() == "2.0.0")
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let report = verify_cached_crates(&mock_db, &storage).await.unwrap();
+
+        assert_eq!(
+            VerifyReport {
+                checked: 2,
+                removed: vec!["foo-2.0.0".to_string()],
+            },
+            report
+        );
+        assert!(
+            storage
+                .get(&name, &Version::try_from("2.0.0").unwrap())
+                .await
+                .is_none()
+        );
+        rm_rf::remove(&settings.registry.data_dir).unwrap();
+    }
+}
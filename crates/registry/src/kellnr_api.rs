@@ -1,41 +1,91 @@
+use crate::crate_archive::validate_crate_archive_blocking;
+use crate::license_policy::check_license;
 use crate::pub_data::{EmptyCrateData, PubData};
 use crate::pub_success::{EmptyCrateSuccess, PubDataSuccess};
 use crate::registry_error::RegistryError;
 use crate::search_params::SearchParams;
+use crate::webhook;
 use crate::yank_success::YankSuccess;
-use crate::{crate_group, crate_user, crate_version};
+use crate::{crate_group, crate_info, crate_user, crate_version};
 use appstate::AppState;
 use appstate::DbState;
-use auth::token;
+use auth::{audit, token};
 use axum::Json;
 use axum::extract::Path;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::Redirect;
 use chrono::Utc;
+use common::crate_data::{CrateData, CrateVersionData};
 use common::normalized_name::NormalizedName;
 use common::original_name::OriginalName;
 use common::search_result;
 use common::search_result::{Crate, SearchResult};
 use common::version::Version;
-use db::DbProvider;
+use db::error::DbError;
+use db::{AuditAction, AuditEvent, DbProvider, EndpointScope, WebhookEvent};
 use error::api_error::{ApiError, ApiResult};
+use serde_json::json;
 use std::convert::TryFrom;
 use std::sync::Arc;
 use tracing::warn;
 
+/// Checks if the scopes of the token allow the endpoint for the crate.
+pub fn check_token_scope(
+    crate_name: &NormalizedName,
+    token: &token::Token,
+    scope: EndpointScope,
+) -> Result<(), ApiError> {
+    if token.scopes.allows(scope, crate_name) {
+        Ok(())
+    } else {
+        Err(RegistryError::TokenScopeViolation(scope, crate_name.to_string()).into())
+    }
+}
+
 pub async fn check_ownership(
     crate_name: &NormalizedName,
     token: &token::Token,
+    scope: EndpointScope,
     db: &Arc<dyn DbProvider>,
 ) -> Result<(), ApiError> {
-    if token.is_admin || db.is_owner(crate_name, &token.user).await? {
+    check_token_scope(crate_name, token, scope)?;
+
+    // Tokens issued by trusted publishing can only publish, see `publish`
+    if token.trusted_crates.is_none()
+        && (token.is_admin || db.is_owner(crate_name, &token.user).await?)
+    {
         Ok(())
     } else {
         Err(RegistryError::NotOwner.into())
     }
 }
 
+/// Checks if the token may publish a new version of an existing crate and
+/// returns the owner the new version is added for.
+async fn check_publish_ownership(
+    crate_name: &NormalizedName,
+    token: &token::Token,
+    db: &Arc<dyn DbProvider>,
+) -> Result<String, ApiError> {
+    match &token.trusted_crates {
+        // Tokens issued by trusted publishing do not belong to a user,
+        // so the version is added in the name of an existing owner.
+        Some(crates) if crates.contains(crate_name) => db
+            .get_crate_owners(crate_name)
+            .await?
+            .into_iter()
+            .next()
+            .map(|owner| owner.name)
+            .ok_or_else(|| RegistryError::NotOwner.into()),
+        Some(_) => Err(RegistryError::NotOwner.into()),
+        None => {
+            check_ownership(crate_name, token, EndpointScope::PublishUpdate, db).await?;
+            Ok(token.user.clone())
+        }
+    }
+}
+
 pub fn check_can_modify(token: &token::Token) -> Result<(), ApiError> {
     if !token.is_admin && token.is_read_only {
         Err(RegistryError::ReadOnlyModify.into())
@@ -49,6 +99,12 @@ pub async fn check_download_auth(
     token: &token::OptionToken,
     db: &Arc<dyn DbProvider>,
 ) -> ApiResult<()> {
+    // A token scoped to other endpoints or crates cannot download,
+    // even if downloads of the crate are not restricted.
+    if let token::OptionToken::Some(token) = token {
+        check_token_scope(crate_name, token, EndpointScope::Download)?;
+    }
+
     if !db.is_download_restricted(crate_name).await? {
         return Ok(());
     }
@@ -86,10 +142,13 @@ pub async fn remove_owner(
     check_can_modify(&token)?;
 
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     for user in &input.users {
         db.delete_owner(&crate_name, user).await?;
+        let event = AuditEvent::new(token.audit_actor(), AuditAction::RemoveOwner, &crate_name)
+            .before(json!({ "owner": user }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(Json(crate_user::CrateUserResponse::from(
@@ -108,9 +167,17 @@ pub async fn remove_crate_user(
     check_can_modify(&token)?;
 
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     db.delete_crate_user(&crate_name, &name).await?;
+    let event = AuditEvent::new(
+        token.audit_actor(),
+        AuditAction::RemoveCrateUser,
+        &crate_name,
+    )
+    .before(json!({ "user": name }));
+    audit::record(db.as_ref(), event).await;
+
     Ok(Json(crate_user::CrateUserResponse::from(
         "Removed users from crate.",
     )))
@@ -122,9 +189,17 @@ pub async fn remove_crate_group(
     Path((crate_name, name)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_group::CrateGroupResponse>> {
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     db.delete_crate_group(&crate_name, &name).await?;
+    let event = AuditEvent::new(
+        token.audit_actor(),
+        AuditAction::RemoveCrateGroup,
+        &crate_name,
+    )
+    .before(json!({ "group": name }));
+    audit::record(db.as_ref(), event).await;
+
     Ok(Json(crate_group::CrateGroupResponse::from(
         "Removed groups from crate.",
     )))
@@ -142,10 +217,13 @@ pub async fn add_owner(
     check_can_modify(&token)?;
 
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     for user in &input.users {
         db.add_owner(&crate_name, user).await?;
+        let event = AuditEvent::new(token.audit_actor(), AuditAction::AddOwner, &crate_name)
+            .after(json!({ "owner": user }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(Json(crate_user::CrateUserResponse::from(
@@ -163,11 +241,7 @@ pub async fn list_owners(
         .get_crate_owners(&crate_name)
         .await?
         .iter()
-        .map(|u| crate_user::CrateUser {
-            id: u.id,
-            login: u.name.clone(),
-            name: None,
-        })
+        .map(crate_user::CrateUser::from)
         .collect();
 
     Ok(Json(crate_user::CrateUserList::from(owners)))
@@ -184,10 +258,13 @@ pub async fn add_crate_user(
     check_can_modify(&token)?;
 
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     if !db.is_crate_user(&crate_name, &name).await? {
         db.add_crate_user(&crate_name, &name).await?;
+        let event = AuditEvent::new(token.audit_actor(), AuditAction::AddCrateUser, &crate_name)
+            .after(json!({ "user": name }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(Json(crate_user::CrateUserResponse::from(
@@ -201,17 +278,13 @@ pub async fn list_crate_users(
     State(db): DbState,
 ) -> ApiResult<Json<crate_user::CrateUserList>> {
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     let users: Vec<crate_user::CrateUser> = db
         .get_crate_users(&crate_name)
         .await?
         .iter()
-        .map(|u| crate_user::CrateUser {
-            id: u.id,
-            login: u.name.clone(),
-            name: None,
-        })
+        .map(crate_user::CrateUser::from)
         .collect();
 
     Ok(Json(crate_user::CrateUserList::from(users)))
@@ -223,10 +296,13 @@ pub async fn add_crate_group(
     Path((crate_name, name)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_group::CrateGroupResponse>> {
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     if !db.is_crate_group(&crate_name, &name).await? {
         db.add_crate_group(&crate_name, &name).await?;
+        let event = AuditEvent::new(token.audit_actor(), AuditAction::AddCrateGroup, &crate_name)
+            .after(json!({ "group": name }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(Json(crate_group::CrateGroupResponse::from(
@@ -240,7 +316,7 @@ pub async fn list_crate_groups(
     State(db): DbState,
 ) -> ApiResult<Json<crate_group::CrateGroupList>> {
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::ChangeOwners, &db).await?;
 
     let groups: Vec<crate_group::CrateGroup> = db
         .get_crate_groups(&crate_name)
@@ -273,10 +349,95 @@ pub async fn list_crate_versions(
     Ok(Json(crate_version::CrateVersionList::from(versions)))
 }
 
+async fn get_crate_data(
+    crate_name: &NormalizedName,
+    token: &token::OptionToken,
+    db: &Arc<dyn DbProvider>,
+) -> ApiResult<CrateData> {
+    check_download_auth(crate_name, token, db).await?;
+
+    let mut crate_data = match db.get_crate_data(crate_name).await {
+        Ok(crate_data) => crate_data,
+        Err(DbError::CrateNotFound(_)) => return Err(RegistryError::CrateNotFound.into()),
+        Err(e) => return Err(e.into()),
+    };
+
+    // Newest version first, as crates.io does
+    crate_data.versions.sort_by(|a, b| {
+        Version::from_unchecked_str(&b.version).cmp(&Version::from_unchecked_str(&a.version))
+    });
+
+    Ok(crate_data)
+}
+
+fn find_version(crate_data: CrateData, version: &Version) -> ApiResult<CrateVersionData> {
+    crate_data
+        .versions
+        .into_iter()
+        .find(|v| v.version == version.to_string())
+        .ok_or_else(|| {
+            RegistryError::CrateVersionNotFound(crate_data.name, version.to_string()).into()
+        })
+}
+
+pub async fn crate_info(
+    token: token::OptionToken,
+    Path(crate_name): Path<OriginalName>,
+    State(db): DbState,
+) -> ApiResult<Json<crate_info::CrateResponse>> {
+    let crate_data = get_crate_data(&crate_name.to_normalized(), &token, &db).await?;
+    Ok(Json(crate_info::CrateResponse::from(crate_data)))
+}
+
+pub async fn crate_info_versions(
+    token: token::OptionToken,
+    Path(crate_name): Path<OriginalName>,
+    State(db): DbState,
+) -> ApiResult<Json<crate_info::VersionList>> {
+    let crate_data = get_crate_data(&crate_name.to_normalized(), &token, &db).await?;
+
+    let versions = crate_data
+        .versions
+        .iter()
+        .map(|v| crate_info::VersionInfo::new(&crate_data.name, v))
+        .collect::<Vec<_>>();
+
+    Ok(Json(crate_info::VersionList::from(versions)))
+}
+
+pub async fn crate_info_version(
+    token: token::OptionToken,
+    Path((crate_name, version)): Path<(OriginalName, Version)>,
+    State(db): DbState,
+) -> ApiResult<Json<crate_info::VersionResponse>> {
+    let crate_data = get_crate_data(&crate_name.to_normalized(), &token, &db).await?;
+    let name = crate_data.name.clone();
+    let version_data = find_version(crate_data, &version)?;
+
+    Ok(Json(crate_info::VersionResponse {
+        version: crate_info::VersionInfo::new(&name, &version_data),
+    }))
+}
+
+pub async fn crate_info_dependencies(
+    token: token::OptionToken,
+    Path((crate_name, version)): Path<(OriginalName, Version)>,
+    State(db): DbState,
+) -> ApiResult<Json<crate_info::DependencyList>> {
+    let crate_data = get_crate_data(&crate_name.to_normalized(), &token, &db).await?;
+    let version_data = find_version(crate_data, &version)?;
+
+    Ok(Json(crate_info::DependencyList::from(
+        version_data.dependencies,
+    )))
+}
+
 pub async fn search(State(db): DbState, params: SearchParams) -> ApiResult<Json<SearchResult>> {
-    let crates = db
-        .search_in_crate_name(&params.q, false)
-        .await?
+    let (total, crates) = db
+        .search_in_crate_name_paged(&params.q, params.per_page.0 as u64, params.offset() as u64)
+        .await?;
+
+    let crates = crates
         .into_iter()
         .map(|c| Crate {
             name: c.name,
@@ -285,12 +446,11 @@ pub async fn search(State(db): DbState, params: SearchParams) -> ApiResult<Json<
                 .description
                 .unwrap_or_else(|| "No description set".to_string()),
         })
-        .take(params.per_page.0)
         .collect::<Vec<Crate>>();
 
     Ok(Json(SearchResult {
         meta: search_result::Meta {
-            total: crates.len() as i32,
+            total: total as i32,
         },
         crates,
     }))
@@ -323,13 +483,15 @@ pub async fn add_empty_crate(
     token: token::Token,
     Json(data): Json<EmptyCrateData>,
 ) -> ApiResult<Json<EmptyCrateSuccess>> {
-    // Only admins can create empty crate placeholders
-    if !token.is_admin {
+    // Only admins can create empty crate placeholders, trusted publishing tokens
+    // can only publish the crates they were issued for
+    if !token.is_admin || token.trusted_crates.is_some() {
         return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
     }
     let db = state.db;
     let orig_name = OriginalName::try_from(&data.name)?;
     let normalized_name = orig_name.to_normalized();
+    check_token_scope(&normalized_name, &token, EndpointScope::PublishNew)?;
 
     if let Some(id) = db.get_crate_id(&normalized_name).await? {
         let version = match db.get_max_version_from_id(id).await {
@@ -343,6 +505,13 @@ pub async fn add_empty_crate(
 
     // Add crate to DB
     db.add_empty_crate(&data.name, &created).await?;
+    let event = AuditEvent::new(
+        token.audit_actor(),
+        AuditAction::AddEmptyCrate,
+        normalized_name,
+    );
+    audit::record(db.as_ref(), event).await;
+
     Ok(Json(EmptyCrateSuccess::new()))
 }
 
@@ -366,9 +535,9 @@ pub async fn publish(
     // If not, he is not allowed push a new version.
     // Check if crate with same version already exists.
     let id = db.get_crate_id(&normalized_name).await?;
-    match (id, settings.registry.new_crates_restricted) {
+    let owner = match (id, settings.registry.new_crates_restricted) {
         (Some(id), _) => {
-            check_ownership(&normalized_name, &token, &db).await?;
+            let owner = check_publish_ownership(&normalized_name, &token, &db).await?;
             if db.crate_version_exists(id, &pub_data.metadata.vers).await? {
                 return Err(RegistryError::CrateExists(
                     pub_data.metadata.name,
@@ -376,14 +545,20 @@ pub async fn publish(
                 )
                 .into());
             }
+            owner
         }
-        (None, true) => {
-            if !token.is_admin {
-                return Err(RegistryError::NewCratesRestricted.into());
-            }
+        // Trusted publishing only allows new versions of existing crates
+        (None, _) if token.trusted_crates.is_some() => {
+            return Err(RegistryError::NotOwner.into());
         }
-        _ => (),
-    }
+        (None, true) if !token.is_admin => {
+            return Err(RegistryError::NewCratesRestricted.into());
+        }
+        (None, _) => {
+            check_token_scope(&normalized_name, &token, EndpointScope::PublishNew)?;
+            token.user.clone()
+        }
+    };
 
     // Check if required crate fields aren't present in crate
     // Skip serializing if no fields to check
@@ -413,6 +588,18 @@ pub async fn publish(
         }
     }
 
+    // Check if the license of the crate is allowed by the license policy
+    check_license(pub_data.metadata.license.as_deref(), &settings.policy)?;
+
+    // Check that the crate file is a valid archive matching the metadata.
+    // The archive gets unpacked later on, e.g. for the docs generation.
+    validate_crate_archive_blocking(
+        pub_data.cratedata.clone(),
+        pub_data.metadata.name.clone(),
+        pub_data.metadata.vers.clone(),
+    )
+    .await?;
+
     // Set SHA256 from crate file
     let version = Version::try_from(&pub_data.metadata.vers)?;
     let cksum = cs
@@ -423,13 +610,23 @@ pub async fn publish(
 
     // Add crate to DB
     if let Err(e) = db
-        .add_crate(&pub_data.metadata, &cksum, &created, &token.user)
+        .add_crate(&pub_data.metadata, &cksum, &created, &owner)
         .await
     {
         // On DB error rollback storage insert and bail.
         let _ = cs.delete(&orig_name, &version).await;
         return Err(e.into());
     }
+    let event = AuditEvent::new(token.audit_actor(), AuditAction::Publish, &normalized_name)
+        .after(json!({ "version": version, "owner": owner }));
+    audit::record(db.as_ref(), event).await;
+    webhook::notify(
+        db.as_ref(),
+        WebhookEvent::CratePublished,
+        &normalized_name,
+        Some(&version),
+    )
+    .await;
 
     // Add crate to queue for doc extraction if there is no documentation value set already
     if settings.docs.enabled && pub_data.metadata.documentation.is_none() {
@@ -455,9 +652,19 @@ pub async fn yank(
     check_can_modify(&token)?;
 
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::Yank, &db).await?;
 
     db.yank_crate(&crate_name, &version).await?;
+    let event = AuditEvent::new(token.audit_actor(), AuditAction::Yank, &crate_name)
+        .after(json!({ "version": version }));
+    audit::record(db.as_ref(), event).await;
+    webhook::notify(
+        db.as_ref(),
+        WebhookEvent::CrateYanked,
+        &crate_name,
+        Some(&version),
+    )
+    .await;
 
     Ok(Json(YankSuccess::new()))
 }
@@ -473,9 +680,19 @@ pub async fn unyank(
     check_can_modify(&token)?;
 
     let crate_name = crate_name.to_normalized();
-    check_ownership(&crate_name, &token, &db).await?;
+    check_ownership(&crate_name, &token, EndpointScope::Yank, &db).await?;
 
     db.unyank_crate(&crate_name, &version).await?;
+    let event = AuditEvent::new(token.audit_actor(), AuditAction::Unyank, &crate_name)
+        .after(json!({ "version": version }));
+    audit::record(db.as_ref(), event).await;
+    webhook::notify(
+        db.as_ref(),
+        WebhookEvent::CrateUnyanked,
+        &crate_name,
+        Some(&version),
+    )
+    .await;
 
     Ok(Json(YankSuccess::new()))
 }
@@ -483,6 +700,7 @@ pub async fn unyank(
 #[cfg(test)]
 mod reg_api_tests {
     use super::*;
+    use crate::trusted_publishing;
     use appstate::AppStateData;
     use axum::Router;
     use axum::body::Body;
@@ -490,7 +708,7 @@ mod reg_api_tests {
     use axum::http::StatusCode;
     use axum::routing::{delete, get, put};
     use db::mock::MockDb;
-    use db::{ConString, Database, SqliteConString, test_utils};
+    use db::{ConString, Database, SqliteConString, TokenScopes, test_utils};
     use error::api_error::ErrorDetails;
     use http_body_util::BodyExt;
     use hyper::header;
@@ -499,6 +717,7 @@ mod reg_api_tests {
     use rand::distr::Alphanumeric;
     use rand::rng;
     use settings::Settings;
+    use std::collections::BTreeMap;
     use std::iter;
     use std::path::PathBuf;
     use storage::cached_crate_storage::DynStorage;
@@ -587,7 +806,7 @@ mod reg_api_tests {
             .unwrap();
         kellnr
             .db
-            .add_user("user", "123", "123", false, false)
+            .add_user("user", "123", false, false)
             .await
             .unwrap();
         let add_owner = crate_user::CrateUserRequest {
@@ -653,6 +872,56 @@ mod reg_api_tests {
         assert_eq!("admin", owners.users[0].login);
     }
 
+    #[tokio::test]
+    async fn publish_invalid_crate_archive() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::new(settings).await;
+
+        let metadata = serde_json::to_vec(&common::publish_metadata::PublishMetadata::minimal(
+            "test_lib", "0.2.0",
+        ))
+        .unwrap();
+        let cratedata = vec![0x00, 0x11, 0x22, 0x33];
+        let mut body = Vec::new();
+        body.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        body.extend_from_slice(&metadata);
+        body.extend_from_slice(&(cratedata.len() as u32).to_le_bytes());
+        body.extend_from_slice(&cratedata);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let response_status = r.status();
+        let error: ErrorDetails =
+            serde_json::from_slice(r.into_body().collect().await.unwrap().to_bytes().as_ref())
+                .expect("Cannot deserialize error message");
+
+        assert_eq!(StatusCode::BAD_REQUEST, response_status);
+        assert!(
+            error.errors[0]
+                .detail
+                .starts_with("ERROR: Invalid crate archive")
+        );
+        assert!(
+            kellnr
+                .db
+                .get_crate_id(&NormalizedName::from_unchecked("test_lib".to_string()))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
     #[tokio::test]
     async fn publish_garbage() {
         let settings = get_settings();
@@ -756,175 +1025,231 @@ mod reg_api_tests {
         assert_eq!(r.status(), StatusCode::NOT_FOUND);
     }
 
+    async fn publish_test_lib(kellnr: &TestKellnr) {
+        let valid_pub_package = read("../test_data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
     #[tokio::test]
-    async fn search_verify_query_and_default() {
-        let mut mock_db = MockDb::new();
-        mock_db
-            .expect_search_in_crate_name()
-            .with(eq("foo"), eq(false))
-            .returning(|_, _| Ok(vec![]));
+    async fn crate_info_returns_crate_and_versions() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::new(settings).await;
+        publish_test_lib(&kellnr).await;
 
-        let kellnr = app_search(Arc::new(mock_db));
         let r = kellnr
+            .client
+            .clone()
             .oneshot(
-                Request::get("/api/v1/crates?q=foo")
+                Request::get("/api/v1/crates/test_lib")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
+        assert_eq!(StatusCode::OK, r.status());
         let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_ok());
+        let info = serde_json::from_slice::<crate_info::CrateResponse>(&result_msg).unwrap();
+        assert_eq!("test_lib", info.krate.name);
+        assert_eq!("0.2.0", info.krate.max_version);
+        assert_eq!(vec!["admin".to_string()], info.krate.owners);
+        assert_eq!(1, info.versions.len());
+        assert_eq!("0.2.0", info.versions[0].num);
+        assert_eq!(
+            "/api/v1/crates/dl/test_lib/0.2.0/download",
+            info.versions[0].dl_path
+        );
     }
 
     #[tokio::test]
-    async fn search_verify_per_page() {
-        let mut mock_db = MockDb::new();
-        mock_db
-            .expect_search_in_crate_name()
-            .with(eq("foo"), eq(false))
-            .returning(|_, _| Ok(vec![]));
+    async fn crate_info_not_existing_crate() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::new(settings).await;
 
-        let kellnr = app_search(Arc::new(mock_db));
         let r = kellnr
+            .client
+            .clone()
             .oneshot(
-                Request::get("/api/v1/crates?q=foo&per_page=20")
+                Request::get("/api/v1/crates/does_not_exist")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_ok());
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
     }
 
     #[tokio::test]
-    async fn search_verify_per_page_out_of_range() {
+    async fn crate_info_versions_returns_versions() {
         let settings = get_settings();
-        let kellnr = TestKellnr::fake(settings).await;
+        let kellnr = TestKellnr::new(settings).await;
+        publish_test_lib(&kellnr).await;
+
         let r = kellnr
             .client
             .clone()
             .oneshot(
-                Request::get("/api/v1/crates?q=foo&per_page=200")
+                Request::get("/api/v1/crates/test_lib/versions")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
+        assert_eq!(StatusCode::OK, r.status());
         let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_err());
+        let versions = serde_json::from_slice::<crate_info::VersionList>(&result_msg).unwrap();
+        assert_eq!(1, versions.versions.len());
+        assert_eq!("test_lib", versions.versions[0].krate);
+        assert!(!versions.versions[0].yanked);
     }
 
     #[tokio::test]
-    async fn yank_success() {
+    async fn crate_info_version_returns_version() {
         let settings = get_settings();
-        let kellnr = TestKellnr::fake(settings).await;
-        // Use valid crate publish data to test.
-        let valid_pub_package = read("../test_data/pub_data.bin")
-            .await
-            .expect("Cannot open valid package file.");
-        let _ = kellnr
+        let kellnr = TestKellnr::new(settings).await;
+        publish_test_lib(&kellnr).await;
+
+        let r = kellnr
             .client
             .clone()
             .oneshot(
-                Request::put("/api/v1/crates/new")
-                    .header(header::CONTENT_TYPE, "application/json")
-                    .header(header::AUTHORIZATION, TOKEN)
-                    .body(Body::from(valid_pub_package))
+                Request::get("/api/v1/crates/test_lib/0.2.0")
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
+        assert_eq!(StatusCode::OK, r.status());
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        let version = serde_json::from_slice::<crate_info::VersionResponse>(&result_msg).unwrap();
+        assert_eq!("0.2.0", version.version.num);
+        assert!(!version.version.checksum.is_empty());
+    }
+
+    #[tokio::test]
+    async fn crate_info_version_not_existing_version() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::new(settings).await;
+        publish_test_lib(&kellnr).await;
+
         let r = kellnr
             .client
             .clone()
             .oneshot(
-                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
-                    .header(header::AUTHORIZATION, TOKEN)
+                Request::get("/api/v1/crates/test_lib/9.9.9")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<YankSuccess>(&result_msg).is_ok());
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
     }
 
     #[tokio::test]
-    async fn yank_error() {
+    async fn crate_info_dependencies_returns_dependencies() {
         let settings = get_settings();
-        let kellnr = TestKellnr::fake(settings).await;
+        let kellnr = TestKellnr::new(settings).await;
+        publish_test_lib(&kellnr).await;
 
         let r = kellnr
             .client
             .clone()
             .oneshot(
-                Request::delete("/api/v1/crates/test/0.1.0/yank")
-                    .header(header::AUTHORIZATION, TOKEN)
+                Request::get("/api/v1/crates/test_lib/0.2.0/dependencies")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
+        assert_eq!(StatusCode::OK, r.status());
         let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<ErrorDetails>(&result_msg).is_ok());
+        assert!(serde_json::from_slice::<crate_info::DependencyList>(&result_msg).is_ok());
     }
 
     #[tokio::test]
-    async fn unyank_success() {
+    async fn crate_info_restricted_download_requires_token() {
         let settings = get_settings();
-        let kellnr = TestKellnr::fake(settings).await;
-        // Use valid crate publish data to test.
-        let valid_pub_package = read("../test_data/pub_data.bin")
+        let kellnr = TestKellnr::new(settings).await;
+        publish_test_lib(&kellnr).await;
+        kellnr
+            .db
+            .change_download_restricted(
+                &NormalizedName::from_unchecked("test_lib".to_string()),
+                true,
+            )
             .await
-            .expect("Cannot open valid package file.");
-        let _ = kellnr
+            .unwrap();
+
+        let r = kellnr
             .client
             .clone()
             .oneshot(
-                Request::put("/api/v1/crates/new")
-                    .header(header::CONTENT_TYPE, "application/json")
-                    .header(header::AUTHORIZATION, TOKEN)
-                    .body(Body::from(valid_pub_package))
+                Request::get("/api/v1/crates/test_lib")
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, r.status());
 
         let r = kellnr
             .client
             .clone()
             .oneshot(
-                Request::put("/api/v1/crates/test_lib/0.2.0/unyank")
-                    .header(header::AUTHORIZATION, TOKEN)
+                Request::get("/api/v1/crates/test_lib")
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
 
-        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<YankSuccess>(&result_msg).is_ok());
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/crates/test_lib")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, r.status());
     }
 
     #[tokio::test]
-    async fn unyank_error() {
-        let settings = get_settings();
-        let kellnr = TestKellnr::fake(settings).await;
+    async fn search_verify_query_and_default() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_search_in_crate_name_paged()
+            .with(eq("foo"), eq(10), eq(0))
+            .returning(|_, _, _| Ok((0, vec![])));
 
+        let kellnr = app_search(Arc::new(mock_db));
         let r = kellnr
-            .client
-            .clone()
             .oneshot(
-                Request::put("/api/v1/crates/test/0.1.0/unyank")
-                    .header(header::AUTHORIZATION, TOKEN)
+                Request::get("/api/v1/crates?q=foo")
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -932,31 +1257,255 @@ mod reg_api_tests {
             .unwrap();
 
         let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        assert!(serde_json::from_slice::<ErrorDetails>(&result_msg).is_ok());
+        assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_ok());
     }
 
     #[tokio::test]
-    async fn add_empty_package() {
-        let settings = get_settings();
-        let kellnr = TestKellnr::fake(settings).await;
+    async fn search_verify_per_page() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_search_in_crate_name_paged()
+            .with(eq("foo"), eq(20), eq(0))
+            .returning(|_, _, _| Ok((0, vec![])));
+
+        let kellnr = app_search(Arc::new(mock_db));
         let r = kellnr
-            .client
-            .clone()
             .oneshot(
-                Request::put("/api/v1/crates/new_empty")
-                    .header(header::CONTENT_TYPE, "application/json")
-                    .header(header::AUTHORIZATION, TOKEN)
-                    .body(Body::from("{\"name\": \"next-crate\"}"))
+                Request::get("/api/v1/crates?q=foo&per_page=20")
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        // Get the empty success results message.
-        let response_status = r.status();
         let result_msg = r.into_body().collect().await.unwrap().to_bytes();
-        let _success: EmptyCrateSuccess =
-            serde_json::from_slice(&result_msg).expect("Cannot deserialize success message");
+        assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_ok());
+    }
+
+    #[tokio::test]
+    async fn search_verify_page_and_total() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_search_in_crate_name_paged()
+            .with(eq("foo"), eq(2), eq(4))
+            .returning(|_, _, _| {
+                Ok((
+                    7,
+                    vec![common::crate_overview::CrateOverview {
+                        name: "foo5".to_string(),
+                        version: "1.0.0".to_string(),
+                        ..Default::default()
+                    }],
+                ))
+            });
+
+        let kellnr = app_search(Arc::new(mock_db));
+        let r = kellnr
+            .oneshot(
+                Request::get("/api/v1/crates?q=foo&per_page=2&page=3")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        let result = serde_json::from_slice::<SearchResult>(&result_msg).unwrap();
+        assert_eq!(7, result.meta.total);
+        assert_eq!(1, result.crates.len());
+        assert_eq!("foo5", result.crates[0].name);
+    }
+
+    #[tokio::test]
+    async fn search_verify_page_zero() {
+        let kellnr = app_search(Arc::new(MockDb::new()));
+        let r = kellnr
+            .oneshot(
+                Request::get("/api/v1/crates?q=foo&page=0")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+
+    #[tokio::test]
+    async fn search_verify_page_too_large() {
+        let kellnr = app_search(Arc::new(MockDb::new()));
+        let r = kellnr
+            .oneshot(
+                Request::get(format!(
+                    "/api/v1/crates?q=foo&per_page=100&page={}",
+                    usize::MAX
+                ))
+                .body(Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+
+    #[tokio::test]
+    async fn search_verify_per_page_out_of_range() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::fake(settings).await;
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/crates?q=foo&per_page=200")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_err());
+    }
+
+    #[tokio::test]
+    async fn yank_success() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::fake(settings).await;
+        // Use valid crate publish data to test.
+        let valid_pub_package = read("../test_data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        let _ = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        assert!(serde_json::from_slice::<YankSuccess>(&result_msg).is_ok());
+    }
+
+    #[tokio::test]
+    async fn yank_error() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::fake(settings).await;
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test/0.1.0/yank")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        assert!(serde_json::from_slice::<ErrorDetails>(&result_msg).is_ok());
+    }
+
+    #[tokio::test]
+    async fn unyank_success() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::fake(settings).await;
+        // Use valid crate publish data to test.
+        let valid_pub_package = read("../test_data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        let _ = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/test_lib/0.2.0/unyank")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        assert!(serde_json::from_slice::<YankSuccess>(&result_msg).is_ok());
+    }
+
+    #[tokio::test]
+    async fn unyank_error() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::fake(settings).await;
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/test/0.1.0/unyank")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        assert!(serde_json::from_slice::<ErrorDetails>(&result_msg).is_ok());
+    }
+
+    #[tokio::test]
+    async fn add_empty_package() {
+        let settings = get_settings();
+        let kellnr = TestKellnr::fake(settings).await;
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new_empty")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from("{\"name\": \"next-crate\"}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Get the empty success results message.
+        let response_status = r.status();
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        let _success: EmptyCrateSuccess =
+            serde_json::from_slice(&result_msg).expect("Cannot deserialize success message");
 
         assert_eq!(StatusCode::OK, response_status);
         let normalized_name = OriginalName::try_from("next-crate")
@@ -1370,6 +1919,41 @@ mod reg_api_tests {
         );
     }
 
+    #[tokio::test]
+    async fn publish_crate_without_license_with_license_policy() {
+        let valid_pub_package = read("../test_data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        let mut settings = get_settings();
+        settings.policy.allowed_licenses = vec!["MIT".to_string()];
+
+        let kellnr = TestKellnr::fake(settings).await;
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let response_status = r.status();
+        let msg = r.into_body().collect().await.unwrap().to_bytes();
+
+        let error: ErrorDetails =
+            serde_json::from_slice(&msg).expect("Cannot deserialize error message");
+
+        assert_eq!(StatusCode::BAD_REQUEST, response_status);
+        assert_eq!(
+            "ERROR: The license policy requires a license for the crate",
+            error.errors[0].detail
+        );
+    }
+
     #[tokio::test]
     async fn publish_crate_with_missing_multiple_required_fields() {
         let valid_pub_package = read("../test_data/pub_data.bin")
@@ -1492,6 +2076,507 @@ mod reg_api_tests {
         );
     }
 
+    const OIDC_ISSUER: &str = "https://token.actions.example.com";
+    const OIDC_AUDIENCE: &str = "kellnr";
+
+    fn trusted_publishing_settings() -> Settings {
+        Settings {
+            trusted_publishing: settings::TrustedPublishing {
+                enabled: true,
+                ..settings::TrustedPublishing::default()
+            },
+            ..get_settings()
+        }
+    }
+
+    async fn add_trusted_publisher(kellnr: &TestKellnr, crate_name: &str) {
+        let jwks = std::fs::canonicalize("../test_data/trusted_publishing/jwks.json").unwrap();
+        let crate_name = OriginalName::try_from(crate_name).unwrap();
+        kellnr
+            .db
+            .add_empty_crate(&crate_name, &Utc::now())
+            .await
+            .unwrap();
+        kellnr
+            .db
+            .add_owner(&crate_name.to_normalized(), "admin")
+            .await
+            .unwrap();
+        kellnr
+            .db
+            .add_trusted_publisher(
+                &crate_name.to_normalized(),
+                OIDC_ISSUER,
+                OIDC_AUDIENCE,
+                &format!("file://{}", jwks.display()),
+                &BTreeMap::from([
+                    ("repository".to_string(), "kellnr/test_lib".to_string()),
+                    ("ref".to_string(), "refs/tags/v*".to_string()),
+                ]),
+            )
+            .await
+            .unwrap();
+    }
+
+    fn oidc_token(claims: &serde_json::Value) -> String {
+        let pem = std::fs::read("../test_data/trusted_publishing/private_key.pem").unwrap();
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        jsonwebtoken::encode(
+            &header,
+            claims,
+            &jsonwebtoken::EncodingKey::from_rsa_pem(&pem).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn valid_oidc_claims() -> serde_json::Value {
+        serde_json::json!({
+            "iss": OIDC_ISSUER,
+            "aud": OIDC_AUDIENCE,
+            "exp": Utc::now().timestamp() + 300,
+            "repository": "kellnr/test_lib",
+            "ref": "refs/tags/v0.2.0",
+        })
+    }
+
+    async fn exchange_oidc_token(kellnr: &TestKellnr, jwt: &str) -> axum::response::Response {
+        let body = serde_json::to_vec(&trusted_publishing::TokenRequest {
+            jwt: jwt.to_string(),
+        })
+        .unwrap();
+        kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/trusted_publishing/tokens")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn publish_test_lib_with(kellnr: &TestKellnr, token: &str) -> StatusCode {
+        let valid_pub_package = read("../test_data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, token)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn trusted_publishing_disabled() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        add_trusted_publisher(&kellnr, "test_lib").await;
+
+        let r = exchange_oidc_token(&kellnr, &oidc_token(&valid_oidc_claims())).await;
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+
+    #[tokio::test]
+    async fn trusted_publishing_token_publishes_crate() {
+        let kellnr = TestKellnr::new(trusted_publishing_settings()).await;
+        add_trusted_publisher(&kellnr, "test_lib").await;
+
+        let r = exchange_oidc_token(&kellnr, &oidc_token(&valid_oidc_claims())).await;
+        assert_eq!(StatusCode::OK, r.status());
+        let response: trusted_publishing::TokenResponse =
+            serde_json::from_slice(r.into_body().collect().await.unwrap().to_bytes().as_ref())
+                .unwrap();
+        let status = publish_test_lib_with(&kellnr, &response.token).await;
+
+        assert_eq!(StatusCode::OK, status);
+        let test_lib = NormalizedName::from_unchecked("test_lib".to_string());
+        let owners = kellnr.db.get_crate_owners(&test_lib).await.unwrap();
+        assert_eq!(1, owners.len());
+        assert_eq!("admin", owners[0].name);
+    }
+
+    #[tokio::test]
+    async fn trusted_publishing_token_can_only_publish() {
+        let kellnr = TestKellnr::new(trusted_publishing_settings()).await;
+        add_trusted_publisher(&kellnr, "test_lib").await;
+        let r = exchange_oidc_token(&kellnr, &oidc_token(&valid_oidc_claims())).await;
+        let response: trusted_publishing::TokenResponse =
+            serde_json::from_slice(r.into_body().collect().await.unwrap().to_bytes().as_ref())
+                .unwrap();
+        assert_eq!(
+            StatusCode::OK,
+            publish_test_lib_with(&kellnr, &response.token).await
+        );
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
+                    .header(header::AUTHORIZATION, &response.token)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+
+    #[tokio::test]
+    async fn trusted_publishing_token_cannot_publish_other_crates() {
+        let kellnr = TestKellnr::new(trusted_publishing_settings()).await;
+        add_trusted_publisher(&kellnr, "other_lib").await;
+        kellnr
+            .db
+            .add_empty_crate("test_lib", &Utc::now())
+            .await
+            .unwrap();
+        let r = exchange_oidc_token(&kellnr, &oidc_token(&valid_oidc_claims())).await;
+        let response: trusted_publishing::TokenResponse =
+            serde_json::from_slice(r.into_body().collect().await.unwrap().to_bytes().as_ref())
+                .unwrap();
+
+        let status = publish_test_lib_with(&kellnr, &response.token).await;
+
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+
+    #[tokio::test]
+    async fn trusted_publishing_revoked_token() {
+        let kellnr = TestKellnr::new(trusted_publishing_settings()).await;
+        add_trusted_publisher(&kellnr, "test_lib").await;
+        let r = exchange_oidc_token(&kellnr, &oidc_token(&valid_oidc_claims())).await;
+        let response: trusted_publishing::TokenResponse =
+            serde_json::from_slice(r.into_body().collect().await.unwrap().to_bytes().as_ref())
+                .unwrap();
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/trusted_publishing/tokens")
+                    .header(header::AUTHORIZATION, &response.token)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, r.status());
+
+        let status = publish_test_lib_with(&kellnr, &response.token).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+
+    #[tokio::test]
+    async fn trusted_publishing_rejects_invalid_oidc_tokens() {
+        let kellnr = TestKellnr::new(trusted_publishing_settings()).await;
+        add_trusted_publisher(&kellnr, "test_lib").await;
+        let mut wrong_claims = valid_oidc_claims();
+        wrong_claims["repository"] = "attacker/test_lib".into();
+        let mut wrong_ref = valid_oidc_claims();
+        wrong_ref["ref"] = "refs/heads/main".into();
+        let mut wrong_audience = valid_oidc_claims();
+        wrong_audience["aud"] = "crates.io".into();
+        let mut expired = valid_oidc_claims();
+        expired["exp"] = (Utc::now().timestamp() - 300).into();
+        let mut unknown_issuer = valid_oidc_claims();
+        unknown_issuer["iss"] = "https://unknown.example.com".into();
+
+        for claims in [
+            wrong_claims,
+            wrong_ref,
+            wrong_audience,
+            expired,
+            unknown_issuer,
+        ] {
+            let r = exchange_oidc_token(&kellnr, &oidc_token(&claims)).await;
+            assert_eq!(StatusCode::FORBIDDEN, r.status(), "{claims}");
+        }
+
+        // Signed with a key which is not part of the JWKS
+        let jwt = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &valid_oidc_claims(),
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        let r = exchange_oidc_token(&kellnr, &jwt).await;
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+
+        let r = exchange_oidc_token(&kellnr, "no jwt").await;
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+
+    async fn add_scoped_token(
+        kellnr: &TestKellnr,
+        token: &str,
+        endpoint_scopes: Option<Vec<EndpointScope>>,
+        crate_scopes: Option<Vec<&str>>,
+    ) {
+        let scopes = TokenScopes {
+            endpoint_scopes,
+            crate_scopes: crate_scopes.map(|c| c.iter().map(ToString::to_string).collect()),
+        };
+        kellnr
+            .db
+            .add_auth_token("scoped", token, "admin", &scopes, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn scoped_token_publishes_matching_crates_only() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        add_scoped_token(&kellnr, "acme_token", None, Some(vec!["acme-*"])).await;
+        add_scoped_token(
+            &kellnr,
+            "update_token",
+            Some(vec![EndpointScope::PublishUpdate]),
+            Some(vec!["test_*"]),
+        )
+        .await;
+        add_scoped_token(
+            &kellnr,
+            "new_token",
+            Some(vec![EndpointScope::PublishNew]),
+            Some(vec!["test_*"]),
+        )
+        .await;
+
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            publish_test_lib_with(&kellnr, "acme_token").await
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            publish_test_lib_with(&kellnr, "update_token").await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            publish_test_lib_with(&kellnr, "new_token").await
+        );
+    }
+
+    #[tokio::test]
+    async fn download_scoped_token_can_only_download() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        assert_eq!(StatusCode::OK, publish_test_lib_with(&kellnr, TOKEN).await);
+        add_scoped_token(
+            &kellnr,
+            "download_token",
+            Some(vec![EndpointScope::Download]),
+            None,
+        )
+        .await;
+
+        let yank = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
+                    .header(header::AUTHORIZATION, "download_token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let download = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/crates/test_lib/0.2.0/download")
+                    .header(header::AUTHORIZATION, "download_token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, yank.status());
+        assert_eq!(StatusCode::OK, download.status());
+    }
+
+    #[tokio::test]
+    async fn add_empty_needs_publish_new_scope_for_crate() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        add_scoped_token(
+            &kellnr,
+            "download_token",
+            Some(vec![EndpointScope::Download]),
+            None,
+        )
+        .await;
+        add_scoped_token(&kellnr, "acme_token", None, Some(vec!["acme-*"])).await;
+        let add_empty = |token: &'static str, name: &'static str| {
+            kellnr.client.clone().oneshot(
+                Request::put("/api/v1/crates/new_empty")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, token)
+                    .body(Body::from(format!("{{\"name\": \"{name}\"}}")))
+                    .unwrap(),
+            )
+        };
+
+        let download_only = add_empty("download_token", "placeholder").await.unwrap();
+        let other_crate = add_empty("acme_token", "other-crate").await.unwrap();
+        let matching_crate = add_empty("acme_token", "acme-crate").await.unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, download_only.status());
+        assert_eq!(StatusCode::FORBIDDEN, other_crate.status());
+        assert_eq!(StatusCode::OK, matching_crate.status());
+        let placeholder = NormalizedName::from_unchecked_str("placeholder");
+        assert_eq!(None, kellnr.db.get_crate_id(&placeholder).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn publish_scoped_token_cannot_download() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        assert_eq!(StatusCode::OK, publish_test_lib_with(&kellnr, TOKEN).await);
+        add_scoped_token(
+            &kellnr,
+            "publish_token",
+            Some(vec![EndpointScope::PublishUpdate]),
+            None,
+        )
+        .await;
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/crates/test_lib/0.2.0/download")
+                    .header(header::AUTHORIZATION, "publish_token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+
+    #[tokio::test]
+    async fn token_usage_is_recorded() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+
+        assert_eq!(StatusCode::OK, publish_test_lib_with(&kellnr, TOKEN).await);
+
+        let tokens = kellnr.db.get_auth_tokens("admin").await.unwrap();
+        let token = tokens.iter().find(|t| t.name == "test").unwrap();
+        assert!(token.last_used.is_some());
+    }
+
+    #[tokio::test]
+    async fn publish_and_yank_are_audited() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        assert_eq!(StatusCode::OK, publish_test_lib_with(&kellnr, TOKEN).await);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, r.status());
+
+        let filter = db::AuditFilter {
+            target: Some("test_lib".to_string()),
+            ..db::AuditFilter::default()
+        };
+        let entries = kellnr.db.get_audit_entries(&filter, 10, 0).await.unwrap();
+        let tokens = kellnr.db.get_auth_tokens("admin").await.unwrap();
+        let token = tokens.iter().find(|t| t.name == "test").unwrap();
+
+        assert_eq!(
+            vec!["yank", "publish"],
+            entries
+                .iter()
+                .map(|e| e.action.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("admin", entries[0].actor);
+        assert_eq!("user", entries[0].actor_kind);
+        assert_eq!("token", entries[0].auth);
+        assert_eq!(Some(token.id), entries[0].token_id);
+        assert_eq!(Some(json!({ "version": "0.2.0" })), entries[0].after);
+    }
+
+    #[tokio::test]
+    async fn publish_queues_webhook_deliveries() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        kellnr
+            .db
+            .add_webhook(
+                "http://localhost/hook",
+                "secret",
+                &[WebhookEvent::CratePublished],
+                "test_*",
+            )
+            .await
+            .unwrap();
+        kellnr
+            .db
+            .add_webhook(
+                "http://localhost/other",
+                "secret",
+                &[WebhookEvent::CratePublished],
+                "other",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, publish_test_lib_with(&kellnr, TOKEN).await);
+
+        let due = kellnr
+            .db
+            .get_due_webhook_deliveries(&Utc::now(), 10)
+            .await
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&due[0].payload).unwrap();
+        assert_eq!(1, due.len());
+        assert_eq!("http://localhost/hook", due[0].url);
+        assert_eq!("crate_published", payload["event"]);
+        assert_eq!("test_lib", payload["crate"]);
+        assert_eq!("0.2.0", payload["version"]);
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_rejected() {
+        let kellnr = TestKellnr::new(get_settings()).await;
+        kellnr
+            .db
+            .add_auth_token(
+                "expired",
+                "expired_token",
+                "admin",
+                &TokenScopes::default(),
+                Some(Utc::now() - chrono::Duration::minutes(1)),
+            )
+            .await
+            .unwrap();
+
+        let status = publish_test_lib_with(&kellnr, "expired_token").await;
+
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+
     struct TestKellnr {
         path: PathBuf,
         client: Router,
@@ -1554,25 +2639,41 @@ mod reg_api_tests {
         let db = Database::new(&con_string, 10).await.unwrap();
         let storage = Box::new(FSStorage::new(&settings.crates_path()).unwrap()) as DynStorage;
         let cs = KellnrCrateStorage::new(&settings, storage);
-        db.add_auth_token("test", TOKEN, "admin").await.unwrap();
-        db.add_user("ro_dummy", "ro", "", false, true)
-            .await
-            .unwrap();
-        db.add_auth_token("test ro", RO_TOKEN, "ro_dummy")
-            .await
-            .unwrap();
-        db.add_user("ro_dummy_admin", "roa", "", true, true)
+        db.add_auth_token("test", TOKEN, "admin", &TokenScopes::default(), None)
             .await
             .unwrap();
-        db.add_auth_token("test admin ro", RO_ADMIN_TOKEN, "ro_dummy_admin")
-            .await
-            .unwrap();
-        db.add_user("non_admin", "na", "", false, false)
-            .await
-            .unwrap();
-        db.add_auth_token("test non admin", NON_ADMIN_TOKEN, "non_admin")
+        db.add_user("ro_dummy", "ro", false, true).await.unwrap();
+        db.add_auth_token(
+            "test ro",
+            RO_TOKEN,
+            "ro_dummy",
+            &TokenScopes::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        db.add_user("ro_dummy_admin", "roa", true, true)
             .await
             .unwrap();
+        db.add_auth_token(
+            "test admin ro",
+            RO_ADMIN_TOKEN,
+            "ro_dummy_admin",
+            &TokenScopes::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        db.add_user("non_admin", "na", false, false).await.unwrap();
+        db.add_auth_token(
+            "test non admin",
+            NON_ADMIN_TOKEN,
+            "non_admin",
+            &TokenScopes::default(),
+            None,
+        )
+        .await
+        .unwrap();
 
         let state = AppStateData {
             db: Arc::new(db),
@@ -1585,6 +2686,13 @@ mod reg_api_tests {
             .route("/{crate_name}/owners", delete(remove_owner))
             .route("/{crate_name}/owners", put(add_owner))
             .route("/{crate_name}/owners", get(list_owners))
+            .route("/{crate_name}", get(crate_info))
+            .route("/{crate_name}/versions", get(crate_info_versions))
+            .route("/{crate_name}/{version}", get(crate_info_version))
+            .route(
+                "/{crate_name}/{version}/dependencies",
+                get(crate_info_dependencies),
+            )
             .route("/", get(search))
             .route("/{package}/{version}/download", get(download))
             .route("/new_empty", put(add_empty_crate))
@@ -1592,8 +2700,13 @@ mod reg_api_tests {
             .route("/{crate_name}/{version}/yank", delete(yank))
             .route("/{crate_name}/{version}/unyank", put(unyank));
 
+        let trusted_publishing_routes = Router::new()
+            .route("/tokens", put(trusted_publishing::exchange_token))
+            .route("/tokens", delete(trusted_publishing::revoke_token));
+
         Router::new()
             .nest("/api/v1/crates", routes)
+            .nest("/api/v1/trusted_publishing", trusted_publishing_routes)
             .with_state(state)
     }
 
@@ -1,10 +1,18 @@
+pub mod crate_archive;
 pub mod crate_group;
+pub mod crate_info;
 pub mod crate_user;
 pub mod crate_version;
 pub mod cratesio_api;
+pub mod cratesio_eviction;
+pub mod cratesio_prewarm;
+pub mod cratesio_verify;
 pub mod kellnr_api;
+pub mod license_policy;
 pub mod pub_data;
 mod pub_success;
 pub mod registry_error;
 pub mod search_params;
+pub mod trusted_publishing;
+pub mod webhook;
 mod yank_success;
@@ -0,0 +1,138 @@
+use crate::registry_error::RegistryError;
+use settings::Policy;
+use spdx::{Expression, LicenseItem, LicenseReq, ParseMode};
+
+/// Checks the `license` field of a crate against the allowed and denied licenses
+/// of the policy. The field is parsed as an SPDX expression, e.g. `MIT OR Apache-2.0`,
+/// and is accepted if the expression can be satisfied by allowed licenses only.
+pub fn check_license(license: Option<&str>, policy: &Policy) -> Result<(), RegistryError> {
+    if !policy.license_policy_enabled() {
+        return Ok(());
+    }
+
+    let license = match license.map(str::trim) {
+        Some(license) if !license.is_empty() => license,
+        _ if policy.allowed_licenses.is_empty() => return Ok(()),
+        _ => return Err(RegistryError::MissingLicense),
+    };
+
+    // Crates often use the deprecated "/" as "OR", which is accepted in lax mode
+    let expression = Expression::parse_mode(license, ParseMode::LAX)
+        .map_err(|e| RegistryError::InvalidLicenseExpression(license.to_string(), e.to_string()))?;
+
+    expression
+        .evaluate_with_failures(|req| is_accepted(req, policy))
+        .map_err(|failures| {
+            let mut offending: Vec<String> = failures.iter().map(|f| f.req.to_string()).collect();
+            offending.sort();
+            offending.dedup();
+            RegistryError::LicensePolicyViolation(license.to_string(), offending)
+        })
+}
+
+fn is_accepted(req: &LicenseReq, policy: &Policy) -> bool {
+    let name = match &req.license {
+        LicenseItem::Spdx { id, .. } => id.name.to_string(),
+        LicenseItem::Other(other) => other.to_string(),
+    };
+    let full = req.to_string();
+    let matches = |licenses: &[String]| {
+        licenses
+            .iter()
+            .any(|l| l.eq_ignore_ascii_case(&name) || l.eq_ignore_ascii_case(&full))
+    };
+
+    !matches(&policy.denied_licenses)
+        && (policy.allowed_licenses.is_empty() || matches(&policy.allowed_licenses))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allowed: &[&str], denied: &[&str]) -> Policy {
+        Policy {
+            allowed_licenses: allowed.iter().map(ToString::to_string).collect(),
+            denied_licenses: denied.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn no_policy_accepts_everything() {
+        let policy = Policy::default();
+
+        assert!(check_license(None, &policy).is_ok());
+        assert!(check_license(Some("GPL-3.0-only"), &policy).is_ok());
+        assert!(check_license(Some("not a license"), &policy).is_ok());
+    }
+
+    #[test]
+    fn allowed_license_is_accepted() {
+        let policy = policy(&["MIT", "Apache-2.0"], &[]);
+
+        assert!(check_license(Some("MIT"), &policy).is_ok());
+        assert!(check_license(Some("MIT OR Apache-2.0"), &policy).is_ok());
+        assert!(check_license(Some("MIT/Apache-2.0"), &policy).is_ok());
+    }
+
+    #[test]
+    fn or_expression_needs_one_allowed_license() {
+        let policy = policy(&["MIT"], &[]);
+
+        assert!(check_license(Some("MIT OR GPL-3.0-only"), &policy).is_ok());
+    }
+
+    #[test]
+    fn and_expression_lists_offending_licenses() {
+        let policy = policy(&["MIT"], &[]);
+
+        let result = check_license(Some("MIT AND GPL-3.0-only"), &policy);
+
+        assert!(matches!(
+            result,
+            Err(RegistryError::LicensePolicyViolation(_, offending)) if offending == vec!["GPL-3.0-only"]
+        ));
+    }
+
+    #[test]
+    fn denied_license_is_rejected() {
+        let policy = policy(&[], &["GPL-3.0-only", "AGPL-3.0-only"]);
+
+        assert!(check_license(Some("MIT"), &policy).is_ok());
+        assert!(check_license(Some("MIT OR AGPL-3.0-only"), &policy).is_ok());
+        let result = check_license(Some("AGPL-3.0-only"), &policy);
+        assert!(matches!(
+            result,
+            Err(RegistryError::LicensePolicyViolation(_, offending)) if offending == vec!["AGPL-3.0-only"]
+        ));
+    }
+
+    #[test]
+    fn deny_overrules_allow() {
+        let policy = policy(&["MIT"], &["mit"]);
+
+        assert!(check_license(Some("MIT"), &policy).is_err());
+    }
+
+    #[test]
+    fn missing_license_with_allow_list() {
+        let allow_policy = policy(&["MIT"], &[]);
+        let deny_policy = policy(&[], &["MIT"]);
+
+        assert!(matches!(
+            check_license(None, &allow_policy),
+            Err(RegistryError::MissingLicense)
+        ));
+        assert!(check_license(None, &deny_policy).is_ok());
+    }
+
+    #[test]
+    fn invalid_expression() {
+        let policy = policy(&["MIT"], &[]);
+
+        assert!(matches!(
+            check_license(Some("MIT OR OR"), &policy),
+            Err(RegistryError::InvalidLicenseExpression(_, _))
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use db::EndpointScope;
 use error::api_error::ApiError;
 use hyper::StatusCode;
 use thiserror::Error;
@@ -30,22 +31,60 @@ pub enum RegistryError {
     NotCrateUser,
     #[error("Crate not found")]
     CrateNotFound,
+    #[error("Crate version not found: {0}-{1}")]
+    CrateVersionNotFound(String, String),
     #[error("Required field(s) not defined for crate {0}, missing: {1:?}, requires: {2:?}")]
     MissingRequiredFields(String, Vec<String>, Vec<String>),
     #[error("Read-only users cannot modify the registry")]
     ReadOnlyModify,
     #[error("New crates publishing has been restricted")]
     NewCratesRestricted,
+    #[error("Invalid crate archive: {0}")]
+    InvalidCrateArchive(String),
+    #[error("The license policy requires a license for the crate")]
+    MissingLicense,
+    #[error("Invalid SPDX license expression \"{0}\": {1}")]
+    InvalidLicenseExpression(String, String),
+    #[error("License \"{0}\" violates the license policy, offending license(s): {1:?}")]
+    LicensePolicyViolation(String, Vec<String>),
+    #[error("Trusted publishing is disabled")]
+    TrustedPublishingDisabled,
+    #[error("Invalid OIDC token: {0}")]
+    InvalidOidcToken(String),
+    #[error("No trusted publisher matches the OIDC token")]
+    NoMatchingTrustedPublisher,
+    #[error("Not a token issued by trusted publishing")]
+    NotTrustedPublishToken,
+    #[error("The token scopes do not allow {0} for crate {1}")]
+    TokenScopeViolation(EndpointScope, String),
+    #[error("The crates.io proxy is offline and serves cached crates only")]
+    ProxyOffline,
+    #[error("Invalid Cargo.lock or package list: {0}")]
+    InvalidPrewarmRequest(String),
+    #[error("Pre-warm job not found: {0}")]
+    PrewarmJobNotFound(u64),
+    #[error("Too many pre-warm jobs are running, try again later")]
+    TooManyPrewarmJobs,
 }
 
 impl From<RegistryError> for ApiError {
     fn from(e: RegistryError) -> Self {
         match e {
-            RegistryError::CrateNotFound => ApiError::from_err(&e, StatusCode::NOT_FOUND),
+            RegistryError::CrateNotFound
+            | RegistryError::CrateVersionNotFound(_, _)
+            | RegistryError::PrewarmJobNotFound(_) => ApiError::from_err(&e, StatusCode::NOT_FOUND),
             RegistryError::DownloadUnauthorized => ApiError::from_err(&e, StatusCode::UNAUTHORIZED),
-            RegistryError::NotOwner | RegistryError::NotCrateUser => {
+            RegistryError::NotOwner
+            | RegistryError::NotCrateUser
+            | RegistryError::TrustedPublishingDisabled
+            | RegistryError::NoMatchingTrustedPublisher
+            | RegistryError::TokenScopeViolation(_, _) => {
                 ApiError::from_err(&e, StatusCode::FORBIDDEN)
             }
+            RegistryError::ProxyOffline => ApiError::from_err(&e, StatusCode::SERVICE_UNAVAILABLE),
+            RegistryError::TooManyPrewarmJobs => {
+                ApiError::from_err(&e, StatusCode::TOO_MANY_REQUESTS)
+            }
             _ => ApiError::from_err(&e, StatusCode::BAD_REQUEST),
         }
     }
@@ -6,10 +6,37 @@ use std::{collections::HashMap, convert::TryFrom};
 pub struct SearchParams {
     pub q: OriginalName,
     pub per_page: PerPage,
+    pub page: Page,
+}
+
+impl SearchParams {
+    /// Number of search results to skip for the requested page.
+    /// Pages beyond the addressable results are clamped, but rejected by the extractor.
+    pub fn offset(&self) -> usize {
+        self.page
+            .0
+            .saturating_sub(1)
+            .saturating_mul(self.per_page.0)
+    }
 }
 
 pub struct PerPage(pub usize);
 
+/// Page of the search results, starting with 1
+pub struct Page(pub usize);
+
+impl TryFrom<usize> for Page {
+    type Error = &'static str;
+
+    fn try_from(page: usize) -> Result<Self, Self::Error> {
+        if page == 0 {
+            Err("page has to be greater than 0.")
+        } else {
+            Ok(Self(page))
+        }
+    }
+}
+
 impl TryFrom<usize> for PerPage {
     type Error = &'static str;
 
@@ -64,7 +91,26 @@ where
         let per_page =
             PerPage::try_from(per_page).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
 
-        Ok(Self { q, per_page })
+        let page = query_params
+            .get("page")
+            .unwrap_or(&"1".to_string())
+            .parse::<usize>()
+            .map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid value for page: {e}"),
+                )
+            })?;
+        let page = Page::try_from(page).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        if (page.0 - 1)
+            .checked_mul(per_page.0)
+            .and_then(|offset| i64::try_from(offset).ok())
+            .is_none()
+        {
+            return Err((StatusCode::BAD_REQUEST, "page is too large.".to_owned()));
+        }
+
+        Ok(Self { q, per_page, page })
     }
 }
 
@@ -83,4 +129,30 @@ mod tests {
         let result = PerPage::try_from(20);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn page_try_from_zero() {
+        let result = Page::try_from(0);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn offset_of_page() {
+        let params = SearchParams {
+            q: OriginalName::from_unchecked("foo".to_string()),
+            per_page: PerPage(20),
+            page: Page(3),
+        };
+        assert_eq!(40, params.offset());
+    }
+
+    #[test]
+    fn offset_of_huge_page_does_not_overflow() {
+        let params = SearchParams {
+            q: OriginalName::from_unchecked("foo".to_string()),
+            per_page: PerPage(100),
+            page: Page(usize::MAX),
+        };
+        assert_eq!(usize::MAX, params.offset());
+    }
 }
@@ -0,0 +1,247 @@
+use crate::registry_error::RegistryError;
+use appstate::{AppState, DbState, JwksCache};
+use auth::token::{self, generate_trusted_publish_token};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use common::util::wildcard_match;
+use db::TrustedPublisher;
+use error::api_error::ApiResult;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{DecodingKey, Validation, decode, decode_header};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRequest {
+    pub jwt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+struct UnverifiedClaims {
+    iss: String,
+}
+
+/// Exchanges the OIDC token of a CI system for a short-lived publish token.
+/// The publish token is valid for all crates with a trust rule matching the OIDC token.
+pub async fn exchange_token(
+    State(state): AppState,
+    Json(request): Json<TokenRequest>,
+) -> ApiResult<Json<TokenResponse>> {
+    let settings = &state.settings.trusted_publishing;
+    if !settings.enabled {
+        return Err(RegistryError::TrustedPublishingDisabled.into());
+    }
+
+    // The issuer is needed to find the trust rules, which hold the keys to verify the token
+    let issuer = unverified_issuer(&request.jwt)?;
+    let publishers = state.db.get_trusted_publishers_by_issuer(&issuer).await?;
+
+    let mut failed_urls = HashSet::new();
+    let mut publisher_ids = Vec::new();
+    for publisher in &publishers {
+        if failed_urls.contains(publisher.jwks_url.as_str()) {
+            continue;
+        }
+        let jwks = match cached_jwks(&state.jwks_cache, publisher).await {
+            Ok(jwks) => jwks,
+            Err(e) => {
+                warn!("Failed to load JWKS {}: {e}", publisher.jwks_url);
+                failed_urls.insert(publisher.jwks_url.as_str());
+                continue;
+            }
+        };
+
+        match verify(&request.jwt, publisher, &jwks) {
+            Ok(true) => publisher_ids.push(publisher.id),
+            Ok(false) => debug!(
+                "Claims do not match trusted publisher {} of crate {}",
+                publisher.id, publisher.crate_name
+            ),
+            Err(e) => debug!(
+                "OIDC token not valid for trusted publisher {} of crate {}: {e}",
+                publisher.id, publisher.crate_name
+            ),
+        }
+    }
+
+    if publisher_ids.is_empty() {
+        return Err(RegistryError::NoMatchingTrustedPublisher.into());
+    }
+
+    let token = generate_trusted_publish_token();
+    let expires = Utc::now() + Duration::seconds(settings.token_ttl_seconds as i64);
+    state
+        .db
+        .add_trusted_publish_token(&token, &publisher_ids, &expires)
+        .await?;
+
+    Ok(Json(TokenResponse { token }))
+}
+
+/// Revokes the trusted publishing token used to authenticate the request,
+/// e.g. at the end of a CI job.
+pub async fn revoke_token(token: token::Token, State(db): DbState) -> ApiResult<StatusCode> {
+    if token.trusted_crates.is_none() {
+        return Err(RegistryError::NotTrustedPublishToken.into());
+    }
+
+    db.delete_trusted_publish_token(&token.value).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn unverified_issuer(jwt: &str) -> Result<String, RegistryError> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.set_required_spec_claims(&["iss"]);
+
+    decode::<UnverifiedClaims>(jwt, &DecodingKey::from_secret(&[]), &validation)
+        .map(|data| data.claims.iss)
+        .map_err(|e| RegistryError::InvalidOidcToken(e.to_string()))
+}
+
+/// Returns the JWKS of the publisher's issuer from the cache, if it was loaded from
+/// the same URL before, and loads it otherwise.
+async fn cached_jwks(cache: &JwksCache, publisher: &TrustedPublisher) -> Result<JwkSet, String> {
+    if let Some((url, jwks)) = cache.get(&publisher.issuer)
+        && url == publisher.jwks_url
+    {
+        return Ok(jwks);
+    }
+
+    let jwks = load_jwks(&publisher.jwks_url).await?;
+    cache.insert(
+        publisher.issuer.clone(),
+        (publisher.jwks_url.clone(), jwks.clone()),
+    );
+    Ok(jwks)
+}
+
+/// Loads a JWKS from an `https://` URL or a local `file://` path.
+async fn load_jwks(url: &str) -> Result<JwkSet, String> {
+    let content = if let Some(path) = url.strip_prefix("file://") {
+        tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| e.to_string())?
+    } else if url.starts_with("https://") {
+        reqwest::get(url)
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?
+    } else {
+        return Err("only https:// and file:// URLs are supported".to_string());
+    };
+
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+/// Verifies signature, issuer, audience and expiry of the token and checks its claims.
+fn verify(
+    jwt: &str,
+    publisher: &TrustedPublisher,
+    jwks: &JwkSet,
+) -> Result<bool, jsonwebtoken::errors::Error> {
+    let header = decode_header(jwt)?;
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or(jsonwebtoken::errors::ErrorKind::InvalidKeyFormat)?;
+
+    // The algorithm has to fit the key type, so a token cannot
+    // be signed with a symmetric key derived from the public key
+    let key = DecodingKey::from_jwk(jwk)?;
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&publisher.issuer]);
+    validation.set_audience(&[&publisher.audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+    let data = decode::<Map<String, Value>>(jwt, &key, &validation)?;
+    Ok(claims_match(&publisher.claims, &data.claims))
+}
+
+/// All expected claims have to be present in the token. A `*` in an expected
+/// value matches any characters, e.g. `refs/tags/v*` for all version tags.
+fn claims_match(expected: &BTreeMap<String, String>, claims: &Map<String, Value>) -> bool {
+    expected
+        .iter()
+        .all(|(name, pattern)| match claims.get(name) {
+            Some(Value::String(value)) => wildcard_match(pattern, value),
+            Some(value @ (Value::Bool(_) | Value::Number(_))) => {
+                wildcard_match(pattern, &value.to_string())
+            }
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn claims_match_requires_all_claims() {
+        let expected = BTreeMap::from([
+            ("repository".to_string(), "kellnr/kellnr".to_string()),
+            ("ref".to_string(), "refs/tags/*".to_string()),
+        ]);
+
+        assert!(claims_match(
+            &expected,
+            &claims(json!({"repository": "kellnr/kellnr", "ref": "refs/tags/v1", "sub": "x"}))
+        ));
+        assert!(!claims_match(
+            &expected,
+            &claims(json!({"repository": "kellnr/kellnr"}))
+        ));
+        assert!(!claims_match(
+            &expected,
+            &claims(json!({"repository": "other/kellnr", "ref": "refs/tags/v1"}))
+        ));
+    }
+
+    #[test]
+    fn claims_match_non_string_values() {
+        let expected = BTreeMap::from([
+            ("run_attempt".to_string(), "1".to_string()),
+            ("protected".to_string(), "true".to_string()),
+        ]);
+
+        assert!(claims_match(
+            &expected,
+            &claims(json!({"run_attempt": 1, "protected": true}))
+        ));
+        assert!(!claims_match(
+            &expected,
+            &claims(json!({"run_attempt": [1], "protected": true}))
+        ));
+    }
+
+    #[test]
+    fn unverified_issuer_needs_a_jwt() {
+        assert!(matches!(
+            unverified_issuer("not a jwt"),
+            Err(RegistryError::InvalidOidcToken(_))
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+use chrono::Utc;
+use common::normalized_name::NormalizedName;
+use common::version::Version;
+use db::{DbProvider, DeliveryAttempt, DeliveryStatus, PendingDelivery, WebhookEvent};
+use hmac::{Hmac, Mac};
+use reqwest::header;
+use serde_json::json;
+use settings::Webhooks;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// Number of deliveries sent per poll of the outbox.
+const DELIVERY_BATCH_SIZE: u64 = 100;
+
+/// Queues the event for all webhooks which subscribed to it. Failures are logged,
+/// as the registry action itself already succeeded.
+pub async fn notify(
+    db: &dyn DbProvider,
+    event: WebhookEvent,
+    crate_name: &NormalizedName,
+    version: Option<&Version>,
+) {
+    let payload = json!({
+        "event": event,
+        "crate": crate_name.to_string(),
+        "version": version,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+    if let Err(e) = db
+        .enqueue_webhook_event(event, crate_name, &payload.to_string())
+        .await
+    {
+        error!("Failed to queue webhook event for {crate_name}: {e}");
+    }
+}
+
+/// Periodically sends the due deliveries of the webhook outbox. Failed deliveries
+/// are retried with exponential backoff until `max_attempts` is reached.
+pub fn delivery_worker(db: Arc<dyn DbProvider>, settings: Webhooks) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(settings.timeout_seconds))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create webhook client, webhooks are not delivered: {e}");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = deliver_due(db.as_ref(), &client, &settings).await {
+                error!("Webhook delivery failed: {e}");
+            }
+            tokio::time::sleep(Duration::from_secs(settings.poll_interval_seconds)).await;
+        }
+    });
+}
+
+async fn deliver_due(
+    db: &dyn DbProvider,
+    client: &reqwest::Client,
+    settings: &Webhooks,
+) -> Result<(), db::error::DbError> {
+    let due = db
+        .get_due_webhook_deliveries(&Utc::now(), DELIVERY_BATCH_SIZE)
+        .await?;
+    for delivery in due {
+        let attempt = deliver(client, &delivery, settings).await;
+        db.update_webhook_delivery(delivery.id, &attempt).await?;
+    }
+    Ok(())
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    delivery: &PendingDelivery,
+    settings: &Webhooks,
+) -> DeliveryAttempt {
+    let response = client
+        .post(&delivery.url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("X-Kellnr-Event", &delivery.event)
+        .header("X-Kellnr-Delivery", delivery.id)
+        .header(
+            "X-Kellnr-Signature",
+            signature(&delivery.secret, &delivery.payload),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    let (response_status, error) = match response {
+        Ok(r) if r.status().is_success() => {
+            return DeliveryAttempt {
+                status: DeliveryStatus::Delivered,
+                next_attempt: None,
+                response_status: Some(r.status().as_u16()),
+                error: None,
+            };
+        }
+        Ok(r) => (
+            Some(r.status().as_u16()),
+            Some(format!("Unexpected response status {}", r.status())),
+        ),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    let attempts = delivery.attempts + 1;
+    warn!(
+        "Webhook delivery {} to {} failed (attempt {attempts}): {}",
+        delivery.id,
+        delivery.url,
+        error.as_deref().unwrap_or_default()
+    );
+    let (status, next_attempt) = if attempts >= settings.max_attempts {
+        (DeliveryStatus::Failed, None)
+    } else {
+        let delay = chrono::Duration::seconds(retry_delay(settings, attempts) as i64);
+        (DeliveryStatus::Pending, Some(Utc::now() + delay))
+    };
+    DeliveryAttempt {
+        status,
+        next_attempt,
+        response_status,
+        error,
+    }
+}
+
+/// Seconds to wait after the given number of failed attempts.
+/// The delay doubles with every attempt, up to `max_retry_seconds`.
+fn retry_delay(settings: &Webhooks, attempts: u32) -> u64 {
+    let factor = 2_u64.saturating_pow(attempts.saturating_sub(1));
+    settings
+        .initial_retry_seconds
+        .saturating_mul(factor)
+        .min(settings.max_retry_seconds)
+}
+
+/// Value of the `X-Kellnr-Signature` header: the hex encoded HMAC-SHA256 of the
+/// payload, keyed with the secret of the webhook.
+pub fn signature(secret: &str, payload: &str) -> String {
+    // HMAC accepts keys of any length
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use db::mock::MockDb;
+    use mockall::predicate::*;
+    use std::sync::Mutex;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    /// Starts a local receiver which answers all requests with the status.
+    async fn receiver(status: StatusCode) -> (String, Received) {
+        let received = Received::default();
+        let record = move |State(received): State<Received>, headers: HeaderMap, body: String| {
+            received.lock().unwrap().push((headers, body));
+            async move { status }
+        };
+        let app = Router::new()
+            .route("/hook", post(record))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    fn pending(url: &str, attempts: u32) -> PendingDelivery {
+        PendingDelivery {
+            id: 7,
+            url: url.to_string(),
+            secret: "secret".to_string(),
+            event: "crate_published".to_string(),
+            payload: r#"{"event":"crate_published","crate":"foo"}"#.to_string(),
+            attempts,
+        }
+    }
+
+    fn mock_db(
+        delivery: PendingDelivery,
+        expected: impl Fn(&DeliveryAttempt) -> bool + Send + 'static,
+    ) -> MockDb {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_due_webhook_deliveries()
+            .returning(move |_, _| Ok(vec![delivery.clone()]));
+        mock_db
+            .expect_update_webhook_delivery()
+            .with(eq(7), function(expected))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mock_db
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256_of_payload() {
+        assert_eq!(
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+            signature("key", "The quick brown fox jumps over the lazy dog")
+        );
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_max() {
+        let settings = Webhooks {
+            initial_retry_seconds: 30,
+            max_retry_seconds: 100,
+            ..Webhooks::default()
+        };
+
+        assert_eq!(30, retry_delay(&settings, 1));
+        assert_eq!(60, retry_delay(&settings, 2));
+        assert_eq!(100, retry_delay(&settings, 3));
+        assert_eq!(100, retry_delay(&settings, 64));
+    }
+
+    #[tokio::test]
+    async fn delivery_is_signed_and_marked_delivered() {
+        let (url, received) = receiver(StatusCode::NO_CONTENT).await;
+        let mock_db = mock_db(pending(&url, 0), |a| {
+            a.status == DeliveryStatus::Delivered && a.response_status == Some(204)
+        });
+
+        deliver_due(&mock_db, &reqwest::Client::new(), &Webhooks::default())
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[0];
+        assert_eq!(r#"{"event":"crate_published","crate":"foo"}"#, body);
+        assert_eq!("crate_published", headers["x-kellnr-event"]);
+        assert_eq!("7", headers["x-kellnr-delivery"]);
+        assert_eq!(
+            signature("secret", body).as_str(),
+            headers["x-kellnr-signature"]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_is_retried_later() {
+        let (url, _) = receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let mock_db = mock_db(pending(&url, 1), |a| {
+            a.status == DeliveryStatus::Pending
+                && a.response_status == Some(500)
+                && a.next_attempt
+                    .is_some_and(|next| next > Utc::now() + chrono::Duration::seconds(50))
+        });
+
+        deliver_due(&mock_db, &reqwest::Client::new(), &Webhooks::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn delivery_fails_after_max_attempts() {
+        let (url, _) = receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let settings = Webhooks {
+            max_attempts: 2,
+            ..Webhooks::default()
+        };
+        let mock_db = mock_db(pending(&url, 1), |a| {
+            a.status == DeliveryStatus::Failed && a.next_attempt.is_none() && a.error.is_some()
+        });
+
+        deliver_due(&mock_db, &reqwest::Client::new(), &settings)
+            .await
+            .unwrap();
+    }
+}
@@ -1,7 +1,16 @@
 // Minimal size of a crate and its documentation.
 pub const MIN_BODY_CRATE_AND_DOC_BYTES: usize = 10;
 
+// Path of the kellnr crate API below `/api/v1`, e.g. downloads are served from `/api/v1/crates/dl`.
+pub const KELLNR_API_PATH: &str = "crates";
+
 // Name of the cookie set on the client to identify a current session.
 pub const COOKIE_SESSION_ID: &str = "kellnr_session_id";
 
 pub const COOKIE_SESSION_USER: &str = "kellnr_session_user";
+
+// Name of the cookie which holds the state of a running OpenID Connect login.
+pub const COOKIE_OIDC_STATE: &str = "kellnr_oidc_state";
+
+// Name of the cookie which holds a login waiting for the TOTP code.
+pub const COOKIE_TOTP_LOGIN: &str = "kellnr_totp_login";
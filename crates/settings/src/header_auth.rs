@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct HeaderAuth {
+    pub enabled: bool,
+    pub trusted_proxies: Vec<String>,
+    pub user_header: String,
+    pub groups_header: String,
+    pub admin_groups: Vec<String>,
+    pub read_only_groups: Vec<String>,
+    pub sync_groups: bool,
+}
+
+impl Default for HeaderAuth {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            trusted_proxies: Vec::new(),
+            user_header: "X-Forwarded-User".to_string(),
+            groups_header: "X-Forwarded-Groups".to_string(),
+            admin_groups: Vec::new(),
+            read_only_groups: Vec::new(),
+            sync_groups: false,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Ldap {
+    pub enabled: bool,
+    pub url: String,
+    pub start_tls: bool,
+    pub user_dn_template: String,
+    pub bind_dn: String,
+    #[serde(skip_serializing, default)]
+    pub bind_pwd: String,
+    pub search_base: String,
+    pub search_filter: String,
+    pub group_attribute: String,
+    pub admin_groups: Vec<String>,
+    pub read_only_groups: Vec<String>,
+    pub sync_groups: bool,
+}
+
+impl Default for Ldap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "ldap://localhost:389".to_string(),
+            start_tls: false,
+            user_dn_template: String::new(),
+            bind_dn: String::new(),
+            bind_pwd: String::new(),
+            search_base: String::new(),
+            search_filter: "(uid={username})".to_string(),
+            group_attribute: "memberOf".to_string(),
+            admin_groups: Vec::new(),
+            read_only_groups: Vec::new(),
+            sync_groups: false,
+        }
+    }
+}
@@ -1,26 +1,46 @@
 pub mod constants;
 mod deserialize_with;
 pub mod docs;
+pub mod header_auth;
+pub mod ldap;
 pub mod local;
 pub mod log;
+pub mod oidc;
 pub mod origin;
+pub mod password_hashing;
+pub mod policy;
 pub mod postgresql;
 pub mod protocol;
 pub mod proxy;
+pub mod rate_limit;
 pub mod registry;
 pub mod s3;
 pub mod settings;
 pub mod setup;
+pub mod tokens;
+pub mod totp;
+pub mod trusted_publishing;
+pub mod webhooks;
 
 pub use docs::Docs;
+pub use header_auth::HeaderAuth;
+pub use ldap::Ldap;
 pub use local::Local;
 pub use log::LogFormat;
 pub use log::LogLevel;
+pub use oidc::Oidc;
 pub use origin::Origin;
+pub use password_hashing::PasswordHashing;
+pub use policy::Policy;
 pub use postgresql::Postgresql;
 pub use protocol::Protocol;
 pub use proxy::Proxy;
+pub use rate_limit::RateLimit;
 pub use registry::Registry;
 pub use settings::Settings;
 pub use settings::get_settings;
 pub use setup::Setup;
+pub use tokens::Tokens;
+pub use totp::Totp;
+pub use trusted_publishing::TrustedPublishing;
+pub use webhooks::Webhooks;
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Oidc {
+    pub enabled: bool,
+    pub issuer_url: String,
+    pub client_id: String,
+    #[serde(skip_serializing, default)]
+    pub client_secret: String,
+    pub redirect_url: String,
+    pub scopes: Vec<String>,
+    pub username_claim: String,
+    pub groups_claim: String,
+    pub admin_groups: Vec<String>,
+    pub read_only_groups: Vec<String>,
+    pub sync_groups: bool,
+}
+
+impl Default for Oidc {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            issuer_url: String::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            redirect_url: String::new(),
+            scopes: vec![
+                "openid".to_string(),
+                "profile".to_string(),
+                "email".to_string(),
+            ],
+            username_claim: "preferred_username".to_string(),
+            groups_claim: "groups".to_string(),
+            admin_groups: Vec::new(),
+            read_only_groups: Vec::new(),
+            sync_groups: false,
+        }
+    }
+}
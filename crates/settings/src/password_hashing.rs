@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Parameters of the Argon2id password hashing.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct PasswordHashing {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashing {
+    fn default() -> Self {
+        // Recommended minimum of the OWASP password storage cheat sheet
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Default)]
+pub struct Policy {
+    pub allowed_licenses: Vec<String>,
+    pub denied_licenses: Vec<String>,
+}
+
+impl Policy {
+    pub fn license_policy_enabled(&self) -> bool {
+        !self.allowed_licenses.is_empty() || !self.denied_licenses.is_empty()
+    }
+}
@@ -1,16 +1,135 @@
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
 pub struct Proxy {
     pub enabled: bool,
+    /// Serve only cached crates, without any traffic to the upstream registry
+    pub offline: bool,
     pub num_threads: usize,
+    /// Sparse index of the upstream registry
+    pub index_url: String,
+    /// Crate downloads of the upstream registry. `{crate}` and `{version}` are replaced,
+    /// else `/{crate}/{version}/download` is appended like cargo does.
+    pub download_url: String,
+    /// Web API of the upstream registry, used for search and crate descriptions
+    pub api_url: String,
+    /// Days new versions of crates.io crates are hidden, 0 disables the quarantine
+    pub quarantine_days: u32,
+    /// Bytes of cached crate files kept, least recently downloaded files are evicted
+    /// beyond it, 0 disables the limit
+    pub max_cache_size: u64,
+    /// Days a cached crate file is kept after its last download, 0 disables the limit
+    pub max_cache_age_days: u32,
 }
 
 impl Default for Proxy {
     fn default() -> Self {
         Self {
             enabled: false,
+            offline: false,
             num_threads: 10,
+            index_url: "https://index.crates.io".to_string(),
+            download_url: "https://static.crates.io/crates".to_string(),
+            api_url: "https://crates.io/api/v1".to_string(),
+            quarantine_days: 0,
+            max_cache_size: 0,
+            max_cache_age_days: 0,
         }
     }
 }
+
+impl Proxy {
+    /// URL of the index file at the path below the sparse index root, e.g. `ro/ck/rocket`.
+    pub fn index_file_url(&self, path: &str) -> String {
+        format!("{}/{path}", self.index_url.trim_end_matches('/'))
+    }
+
+    pub fn crate_download_url(&self, name: &str, version: &str) -> String {
+        if self.download_url.contains("{crate}") || self.download_url.contains("{version}") {
+            self.download_url
+                .replace("{crate}", name)
+                .replace("{version}", version)
+        } else {
+            format!(
+                "{}/{name}/{version}/download",
+                self.download_url.trim_end_matches('/')
+            )
+        }
+    }
+
+    pub fn crate_api_url(&self, name: &str) -> String {
+        format!("{}/crates/{name}", self.api_url.trim_end_matches('/'))
+    }
+
+    pub fn search_api_url(&self) -> String {
+        format!("{}/crates", self.api_url.trim_end_matches('/'))
+    }
+
+    /// Versions published after the returned time are quarantined, if the quarantine is enabled.
+    pub fn quarantine_cutoff(&self) -> Option<DateTime<Utc>> {
+        (self.quarantine_days > 0)
+            .then(|| Utc::now() - Duration::days(i64::from(self.quarantine_days)))
+    }
+
+    /// Whether cached crate files are evicted by size or age.
+    pub fn eviction_enabled(&self) -> bool {
+        self.max_cache_size > 0 || self.max_cache_age_days > 0
+    }
+
+    /// Cached crate files last downloaded before the returned time are evicted, if the
+    /// age limit is enabled.
+    pub fn cache_age_cutoff(&self) -> Option<DateTime<Utc>> {
+        (self.max_cache_age_days > 0)
+            .then(|| Utc::now() - Duration::days(i64::from(self.max_cache_age_days)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_download_url_appends_path_like_cargo() {
+        let proxy = Proxy {
+            download_url: "http://mirror/api/v1/cratesio/dl/".to_string(),
+            ..Proxy::default()
+        };
+
+        assert_eq!(
+            "http://mirror/api/v1/cratesio/dl/serde/1.0.0/download",
+            proxy.crate_download_url("serde", "1.0.0")
+        );
+    }
+
+    #[test]
+    fn crate_download_url_replaces_markers() {
+        let proxy = Proxy {
+            download_url: "http://mirror/files/{crate}-{version}.crate".to_string(),
+            ..Proxy::default()
+        };
+
+        assert_eq!(
+            "http://mirror/files/serde-1.0.0.crate",
+            proxy.crate_download_url("serde", "1.0.0")
+        );
+    }
+
+    #[test]
+    fn default_urls_point_to_crates_io() {
+        let proxy = Proxy::default();
+
+        assert_eq!(
+            "https://index.crates.io/ro/ck/rocket",
+            proxy.index_file_url("ro/ck/rocket")
+        );
+        assert_eq!(
+            "https://static.crates.io/crates/adler/1.0.2/download",
+            proxy.crate_download_url("adler", "1.0.2")
+        );
+        assert_eq!(
+            "https://crates.io/api/v1/crates/rocket",
+            proxy.crate_api_url("rocket")
+        );
+    }
+}
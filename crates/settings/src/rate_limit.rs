@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct RateLimit {
+    pub enabled: bool,
+    pub max_failed_logins: u32,
+    pub lockout_threshold: u32,
+    pub lockout_minutes: u64,
+    pub publish_per_minute: u32,
+    pub download_per_minute: u32,
+    pub trusted_proxies: Vec<String>,
+    pub client_ip_header: String,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_failed_logins: 5,
+            lockout_threshold: 10,
+            lockout_minutes: 15,
+            publish_per_minute: 0,
+            download_per_minute: 0,
+            trusted_proxies: Vec::new(),
+            client_ip_header: "X-Forwarded-For".to_string(),
+        }
+    }
+}
@@ -4,14 +4,24 @@ use std::path::PathBuf;
 use std::{convert::TryFrom, env, path::Path};
 
 use crate::docs::Docs;
+use crate::header_auth::HeaderAuth;
+use crate::ldap::Ldap;
 use crate::local::Local;
 use crate::log::Log;
+use crate::oidc::Oidc;
 use crate::origin::Origin;
+use crate::password_hashing::PasswordHashing;
+use crate::policy::Policy;
 use crate::postgresql::Postgresql;
 use crate::proxy::Proxy;
+use crate::rate_limit::RateLimit;
 use crate::registry::Registry;
 use crate::s3::S3;
 use crate::setup::Setup;
+use crate::tokens::Tokens;
+use crate::totp::Totp;
+use crate::trusted_publishing::TrustedPublishing;
+use crate::webhooks::Webhooks;
 
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Default, Clone)]
 pub struct Settings {
@@ -24,6 +34,16 @@ pub struct Settings {
     pub origin: Origin,
     pub postgresql: Postgresql,
     pub s3: S3,
+    pub policy: Policy,
+    pub trusted_publishing: TrustedPublishing,
+    pub tokens: Tokens,
+    pub password_hashing: PasswordHashing,
+    pub oidc: Oidc,
+    pub ldap: Ldap,
+    pub header_auth: HeaderAuth,
+    pub totp: Totp,
+    pub rate_limit: RateLimit,
+    pub webhooks: Webhooks,
 }
 
 impl TryFrom<&Path> for Settings {
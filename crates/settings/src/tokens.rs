@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Tokens {
+    pub usage_update_interval_seconds: u64,
+    pub max_unused_days: u64,
+    pub delete_unused: bool,
+}
+
+impl Default for Tokens {
+    fn default() -> Self {
+        Self {
+            usage_update_interval_seconds: 60 * 5,
+            max_unused_days: 0,
+            delete_unused: false,
+        }
+    }
+}
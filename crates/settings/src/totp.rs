@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Totp {
+    pub required_for_admins: bool,
+    #[serde(skip_serializing, default)]
+    pub encryption_key: String,
+    pub issuer: String,
+}
+
+impl Default for Totp {
+    fn default() -> Self {
+        Self {
+            required_for_admins: false,
+            encryption_key: String::new(),
+            issuer: "Kellnr".to_string(),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct TrustedPublishing {
+    pub enabled: bool,
+    pub token_ttl_seconds: u64,
+}
+
+impl Default for TrustedPublishing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token_ttl_seconds: 60 * 30,
+        }
+    }
+}
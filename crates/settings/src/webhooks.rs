@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Webhooks {
+    pub max_attempts: u32,
+    pub initial_retry_seconds: u64,
+    pub max_retry_seconds: u64,
+    pub timeout_seconds: u64,
+    pub poll_interval_seconds: u64,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_retry_seconds: 30,
+            max_retry_seconds: 60 * 60,
+            timeout_seconds: 10,
+            poll_interval_seconds: 5,
+        }
+    }
+}
@@ -33,6 +33,32 @@ impl CachedCrateStorage {
         format!("{name}-{version}.crate")
     }
 
+    /// Name and version of a crate file. Names and versions may both contain `-`,
+    /// so the first split into a valid name and version is used.
+    fn parse_file_name(file_name: &str) -> Option<(OriginalName, Version)> {
+        let stem = file_name.strip_suffix(".crate")?;
+        stem.match_indices('-').find_map(|(i, _)| {
+            let name = OriginalName::try_from(&stem[..i]).ok()?;
+            let version = Version::try_from(&stem[i + 1..]).ok()?;
+            Some((name, version))
+        })
+    }
+
+    /// Crate files in the storage with their size in bytes. The names are the
+    /// case-sensitive names of the files, as the crates were requested.
+    pub async fn crate_files(&self) -> Result<Vec<(OriginalName, Version, u64)>, StorageError> {
+        Ok(self
+            .storage
+            .list()
+            .await?
+            .into_iter()
+            .filter_map(|(key, size)| {
+                let (name, version) = Self::parse_file_name(&key)?;
+                Some((name, version, size))
+            })
+            .collect())
+    }
+
     pub async fn delete(&self, name: &OriginalName, version: &Version) -> Result<(), StorageError> {
         let crate_file = Self::file_name(name, version);
         self.storage.delete(&crate_file).await?;
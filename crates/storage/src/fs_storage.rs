@@ -2,6 +2,7 @@ use crate::storage::Storage;
 use crate::storage_error::StorageError;
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures_util::TryStreamExt;
 use object_store::{ObjectStore, PutMode, local::LocalFileSystem, path::Path};
 use std::fs::DirBuilder;
 
@@ -29,6 +30,14 @@ impl Storage for FSStorage {
         self.storage().delete(&Path::from(key)).await?;
         Ok(())
     }
+
+    async fn list(&self) -> Result<Vec<(String, u64)>, StorageError> {
+        let objects: Vec<_> = self.storage().list(None).try_collect().await?;
+        Ok(objects
+            .into_iter()
+            .map(|o| (o.location.to_string(), o.size))
+            .collect())
+    }
 }
 
 impl FSStorage {
@@ -1,6 +1,7 @@
 use crate::{storage::Storage, storage_error::StorageError};
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures_util::TryStreamExt;
 use object_store::{
     ObjectStore, PutMode,
     aws::{AmazonS3, AmazonS3Builder},
@@ -37,6 +38,14 @@ impl Storage for S3Storage {
         self.storage().delete(&path).await?;
         Ok(())
     }
+
+    async fn list(&self) -> Result<Vec<(String, u64)>, StorageError> {
+        let objects: Vec<_> = self.storage().list(None).try_collect().await?;
+        Ok(objects
+            .into_iter()
+            .map(|o| (o.location.to_string(), o.size))
+            .collect())
+    }
 }
 
 impl S3Storage {
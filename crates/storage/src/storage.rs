@@ -7,4 +7,6 @@ pub trait Storage {
     async fn get(&self, key: &str) -> Result<Bytes, StorageError>;
     async fn put(&self, key: &str, object: Bytes) -> Result<(), StorageError>;
     async fn delete(&self, key: &str) -> Result<(), StorageError>;
+    /// Keys of all objects with their size in bytes
+    async fn list(&self) -> Result<Vec<(String, u64)>, StorageError>;
 }
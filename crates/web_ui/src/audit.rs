@@ -0,0 +1,318 @@
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::{AppStateData, DbState};
+use auth::token::client_ip;
+use axum::body::Body;
+use axum::extract::{FromRequestParts, Query, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, RequestPartsExt};
+use axum_extra::extract::PrivateCookieJar;
+use db::error::DbError;
+use db::{AuditAction, AuditActor, AuditEntry, AuditEvent, AuditFilter, AuthMethod};
+use futures_util::stream;
+use serde::{Deserialize, Serialize};
+use settings::constants::COOKIE_SESSION_ID;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+/// Number of entries read from the database at once for the export.
+const EXPORT_BATCH_SIZE: u64 = 1000;
+
+/// How a UI request was authenticated, to record it in the audit log.
+pub struct AuditContext {
+    auth: AuthMethod,
+    session_id: Option<i64>,
+    ip: Option<IpAddr>,
+}
+
+impl AuditContext {
+    /// Creates an event for an action of the user. Service accounts cannot log in
+    /// to the UI, so the actor is always a user.
+    pub fn event(&self, user: &MaybeUser, action: AuditAction, target: impl Display) -> AuditEvent {
+        let actor = AuditActor {
+            name: user.name().to_string(),
+            auth: self.auth,
+            session_id: self.session_id,
+            ip: self.ip.map(|ip| ip.to_string()),
+            ..AuditActor::default()
+        };
+        AuditEvent::new(actor, action, target)
+    }
+}
+
+impl FromRequestParts<AppStateData> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppStateData,
+    ) -> Result<Self, Self::Rejection> {
+        let header_auth = &state.settings.header_auth;
+        if header_auth.enabled && parts.headers.contains_key(&header_auth.user_header) {
+            return Ok(Self {
+                auth: AuthMethod::ReverseProxy,
+                session_id: None,
+                ip: client_ip(state, &parts.headers, &parts.extensions),
+            });
+        }
+
+        // The id identifies the session in the log without exposing its token
+        let jar: PrivateCookieJar = parts.extract_with_state(state).await.unwrap();
+        let session_id = match jar.get(COOKIE_SESSION_ID) {
+            Some(cookie) => state.db.get_session_id(cookie.value()).await.ok(),
+            None => None,
+        };
+        Ok(Self {
+            auth: AuthMethod::Session,
+            session_id,
+            ip: client_ip(state, &parts.headers, &parts.extensions),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PageParams {
+    page: Option<u64>,
+    page_size: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct AuditPage {
+    entries: Vec<AuditEntry>,
+    page: u64,
+    page_size: u64,
+    total: u64,
+}
+
+/// Returns the matching audit log entries, newest first.
+pub async fn list(
+    user: MaybeUser,
+    Query(filter): Query<AuditFilter>,
+    Query(params): Query<PageParams>,
+    State(db): DbState,
+) -> Result<Json<AuditPage>, RouteError> {
+    user.assert_admin()?;
+
+    let page = params.page.unwrap_or(0);
+    let page_size = params.page_size.unwrap_or(50).clamp(1, EXPORT_BATCH_SIZE);
+    let offset = page
+        .checked_mul(page_size)
+        .ok_or(RouteError::Status(StatusCode::BAD_REQUEST))?;
+    let entries = db.get_audit_entries(&filter, page_size, offset).await?;
+    let total = db.count_audit_entries(&filter).await?;
+
+    Ok(Json(AuditPage {
+        entries,
+        page,
+        page_size,
+        total,
+    }))
+}
+
+/// Exports the matching audit log entries as JSON Lines, newest first.
+#[allow(clippy::unused_async)] // part of the router
+pub async fn export(
+    user: MaybeUser,
+    Query(filter): Query<AuditFilter>,
+    State(db): DbState,
+) -> Result<Response, RouteError> {
+    user.assert_admin()?;
+
+    // Entries are read in batches by id, so that new entries do not shift the
+    // batches and the body is sent while the batches are read.
+    let batches = stream::try_unfold(Some(filter), move |filter| {
+        let db = db.clone();
+        async move {
+            let Some(filter) = filter else {
+                return Ok(None);
+            };
+            let entries = db.get_audit_entries(&filter, EXPORT_BATCH_SIZE, 0).await?;
+            let next = match entries.last() {
+                Some(last) if entries.len() as u64 == EXPORT_BATCH_SIZE => Some(AuditFilter {
+                    before_id: Some(last.id),
+                    ..filter
+                }),
+                _ => None,
+            };
+            let mut lines = String::new();
+            for entry in &entries {
+                // An entry consists of strings and JSON values only
+                lines.push_str(&serde_json::to_string(entry).unwrap_or_default());
+                lines.push('\n');
+            }
+            Ok::<_, DbError>(Some((lines, next)))
+        }
+    });
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/x-ndjson"),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_static("attachment; filename=\"audit.jsonl\""),
+            ),
+        ],
+        Body::from_stream(batches),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::{admin_db, encode_cookies, test_app};
+    use axum::Router;
+    use axum::http::Request;
+    use axum::routing::get;
+    use db::mock::MockDb;
+    use http_body_util::BodyExt;
+    use mockall::predicate::*;
+    use settings::constants::COOKIE_SESSION_ID;
+    use tower::ServiceExt;
+
+    fn app(mock_db: MockDb) -> Router {
+        test_app(
+            mock_db,
+            Router::new()
+                .route("/list", get(list))
+                .route("/export", get(export)),
+        )
+    }
+
+    fn entry(id: i64) -> AuditEntry {
+        AuditEntry {
+            id,
+            actor: "admin".to_string(),
+            action: "delete_user".to_string(),
+            target: "bob".to_string(),
+            ..AuditEntry::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn export_writes_one_entry_per_line() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_get_audit_entries()
+            .with(
+                eq(AuditFilter {
+                    action: Some("delete_user".to_string()),
+                    ..AuditFilter::default()
+                }),
+                eq(EXPORT_BATCH_SIZE),
+                eq(0),
+            )
+            .returning(|_, _, _| Ok(vec![entry(2), entry(1)]));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::get("/export?action=delete_user")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let lines: Vec<AuditEntry> = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(vec![entry(2), entry(1)], lines);
+    }
+
+    #[tokio::test]
+    async fn export_reads_batches_by_id() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_get_audit_entries()
+            .with(eq(AuditFilter::default()), eq(EXPORT_BATCH_SIZE), eq(0))
+            .returning(|_, _, _| Ok((2..=1001).rev().map(entry).collect()));
+        mock_db
+            .expect_get_audit_entries()
+            .with(
+                eq(AuditFilter {
+                    before_id: Some(2),
+                    ..AuditFilter::default()
+                }),
+                eq(EXPORT_BATCH_SIZE),
+                eq(0),
+            )
+            .returning(|_, _, _| Ok(vec![entry(1)]));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::get("/export")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(1001, body.lines().count());
+        assert_eq!(
+            entry(1),
+            serde_json::from_str(body.lines().last().unwrap()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn list_rejects_too_large_page() {
+        let r = app(admin_db())
+            .oneshot(
+                Request::get(format!("/list?page={}&page_size=2", u64::MAX))
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+
+    #[tokio::test]
+    async fn export_needs_admin() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .returning(|_| Ok(("user".to_string(), false)));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::get("/export")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+}
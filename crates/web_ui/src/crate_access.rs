@@ -1,12 +1,18 @@
+use crate::audit::AuditContext;
 use crate::error::RouteError;
 use crate::session::MaybeUser;
 use appstate::DbState;
+use auth::audit;
 use axum::Json;
 use axum::extract::{Path, State};
+use axum::http::StatusCode;
 use common::original_name::OriginalName;
+use db::{AuditAction, TrustedPublisher};
 use registry::crate_group::{CrateGroup, CrateGroupList};
 use registry::crate_user::{CrateUser, CrateUserList};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccessData {
@@ -25,11 +31,7 @@ pub async fn list_users(
         .get_crate_users(&crate_name)
         .await?
         .iter()
-        .map(|u| CrateUser {
-            id: u.id,
-            login: u.name.clone(),
-            name: None,
-        })
+        .map(CrateUser::from)
         .collect();
 
     Ok(Json(CrateUserList::from(users)))
@@ -37,6 +39,7 @@ pub async fn list_users(
 
 pub async fn add_user(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
@@ -50,6 +53,10 @@ pub async fn add_user(
 
     if !db.is_crate_user(&crate_name, &name).await? {
         db.add_crate_user(&crate_name, &name).await?;
+        let event = audit_ctx
+            .event(&user, AuditAction::AddCrateUser, &crate_name)
+            .after(json!({ "user": name }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(())
@@ -57,13 +64,19 @@ pub async fn add_user(
 
 pub async fn delete_user(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
 
     let crate_name = crate_name.to_normalized();
-    Ok(db.delete_crate_user(&crate_name, &name).await?)
+    db.delete_crate_user(&crate_name, &name).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::RemoveCrateUser, &crate_name)
+        .before(json!({ "user": name }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
 
 pub async fn list_groups(
@@ -89,6 +102,7 @@ pub async fn list_groups(
 
 pub async fn add_group(
     group: MaybeUser,
+    audit_ctx: AuditContext,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
@@ -97,6 +111,10 @@ pub async fn add_group(
     let crate_name = crate_name.to_normalized();
     if !db.is_crate_group(&crate_name, &name).await? {
         db.add_crate_group(&crate_name, &name).await?;
+        let event = audit_ctx
+            .event(&group, AuditAction::AddCrateGroup, &crate_name)
+            .after(json!({ "group": name }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(())
@@ -104,13 +122,19 @@ pub async fn add_group(
 
 pub async fn delete_group(
     group: MaybeUser,
+    audit_ctx: AuditContext,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     group.assert_admin()?;
 
     let crate_name = crate_name.to_normalized();
-    Ok(db.delete_crate_group(&crate_name, &name).await?)
+    db.delete_crate_group(&crate_name, &name).await?;
+    let event = audit_ctx
+        .event(&group, AuditAction::RemoveCrateGroup, &crate_name)
+        .before(json!({ "group": name }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
 
 pub async fn get_access_data(
@@ -128,6 +152,7 @@ pub async fn get_access_data(
 
 pub async fn set_access_data(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(db): DbState,
     Path(crate_name): Path<OriginalName>,
     Json(input): Json<AccessData>,
@@ -135,10 +160,168 @@ pub async fn set_access_data(
     user.assert_admin()?;
 
     let crate_name = crate_name.to_normalized();
+    let before = db.is_download_restricted(&crate_name).await?;
     db.change_download_restricted(&crate_name, input.download_restricted)
         .await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::ChangeDownloadRestricted, &crate_name)
+        .before(json!({ "download_restricted": before }))
+        .after(json!({ "download_restricted": input.download_restricted }));
+    audit::record(db.as_ref(), event).await;
 
     Ok(Json(AccessData {
         download_restricted: db.is_download_restricted(&crate_name).await?,
     }))
 }
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewTrustedPublisher {
+    pub issuer: String,
+    pub audience: String,
+    /// URL of the JWKS to verify OIDC tokens, or `file://` path of a local JWKS
+    pub jwks_url: String,
+    #[serde(default)]
+    pub claims: BTreeMap<String, String>,
+}
+
+/// Claims which identify the repository or pipeline of a CI system. A trust rule has to
+/// match at least one of them, otherwise every token of the issuer would be trusted.
+const IDENTITY_CLAIMS: [&str; 3] = ["sub", "repository", "project_path"];
+
+impl NewTrustedPublisher {
+    pub fn validate(&self) -> Result<(), RouteError> {
+        let jwks_url_valid = ["https://", "file://"]
+            .iter()
+            .any(|scheme| self.jwks_url.starts_with(scheme));
+        let identity_matched = IDENTITY_CLAIMS.iter().any(|claim| {
+            self.claims
+                .get(*claim)
+                .is_some_and(|pattern| !pattern.chars().all(|c| c == '*'))
+        });
+        if self.issuer.is_empty()
+            || self.audience.is_empty()
+            || !jwks_url_valid
+            || !identity_matched
+        {
+            return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+        }
+        Ok(())
+    }
+}
+
+pub async fn list_trusted_publishers(
+    user: MaybeUser,
+    Path(crate_name): Path<OriginalName>,
+    State(db): DbState,
+) -> Result<Json<Vec<TrustedPublisher>>, RouteError> {
+    user.assert_admin()?;
+
+    let crate_name = crate_name.to_normalized();
+    Ok(Json(db.get_trusted_publishers(&crate_name).await?))
+}
+
+pub async fn add_trusted_publisher(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(crate_name): Path<OriginalName>,
+    State(db): DbState,
+    Json(input): Json<NewTrustedPublisher>,
+) -> Result<Json<TrustedPublisher>, RouteError> {
+    user.assert_admin()?;
+
+    input.validate()?;
+
+    let normalized_name = crate_name.to_normalized();
+    if db.get_crate_id(&normalized_name).await?.is_none() {
+        return Err(RouteError::Status(StatusCode::NOT_FOUND));
+    }
+
+    let id = db
+        .add_trusted_publisher(
+            &normalized_name,
+            &input.issuer,
+            &input.audience,
+            &input.jwks_url,
+            &input.claims,
+        )
+        .await?;
+
+    let publisher = TrustedPublisher {
+        id,
+        crate_name: crate_name.to_string(),
+        issuer: input.issuer,
+        audience: input.audience,
+        jwks_url: input.jwks_url,
+        claims: input.claims,
+    };
+    let event = audit_ctx
+        .event(&user, AuditAction::AddTrustedPublisher, &normalized_name)
+        .after(json!(publisher));
+    audit::record(db.as_ref(), event).await;
+
+    Ok(Json(publisher))
+}
+
+pub async fn delete_trusted_publisher(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path((crate_name, id)): Path<(OriginalName, i32)>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let crate_name = crate_name.to_normalized();
+    db.delete_trusted_publisher(&crate_name, id).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::DeleteTrustedPublisher, &crate_name)
+        .before(json!({ "id": id }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publisher(jwks_url: &str, claims: &[(&str, &str)]) -> NewTrustedPublisher {
+        NewTrustedPublisher {
+            issuer: "https://token.actions.githubusercontent.com".to_string(),
+            audience: "kellnr".to_string(),
+            jwks_url: jwks_url.to_string(),
+            claims: claims
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn trusted_publisher_needs_identity_claim() {
+        let url = "https://token.actions.githubusercontent.com/.well-known/jwks";
+
+        assert!(
+            publisher(url, &[("repository", "kellnr/kellnr")])
+                .validate()
+                .is_ok()
+        );
+        assert!(publisher(url, &[]).validate().is_err());
+        assert!(
+            publisher(url, &[("ref", "refs/tags/*")])
+                .validate()
+                .is_err()
+        );
+        assert!(publisher(url, &[("sub", "*")]).validate().is_err());
+    }
+
+    #[test]
+    fn trusted_publisher_needs_https_jwks_url() {
+        let claims = [("sub", "repo:kellnr/kellnr:*")];
+
+        assert!(publisher("file:///jwks.json", &claims).validate().is_ok());
+        assert!(
+            publisher("http://example.com/jwks", &claims)
+                .validate()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::audit::AuditContext;
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::{AppState, DbState};
+use auth::audit;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use common::original_name::OriginalName;
+use common::version::Version;
+use db::{AuditAction, CratesIoEvictionReport, CratesIoPin};
+use registry::cratesio_eviction;
+use serde::Deserialize;
+use tracing::error;
+
+/// Number of eviction reports shown.
+const MAX_REPORTS: u64 = 50;
+
+/// Lists the crates.io versions which are never evicted from the cache.
+pub async fn list_pins(
+    user: MaybeUser,
+    State(db): DbState,
+) -> Result<Json<Vec<CratesIoPin>>, RouteError> {
+    user.assert_admin()?;
+    Ok(Json(db.get_cratesio_pins().await?))
+}
+
+#[derive(Deserialize)]
+pub struct NewPin {
+    pub name: OriginalName,
+    pub version: Version,
+}
+
+pub async fn add_pin(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(db): DbState,
+    Json(pin): Json<NewPin>,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let name = pin.name.to_normalized();
+    db.add_cratesio_pin(&name, &pin.version).await?;
+    let target = format!("{name}@{}", pin.version);
+    let event = audit_ctx.event(&user, AuditAction::AddCratesioPin, target);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+pub async fn delete_pin(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path((name, version)): Path<(OriginalName, Version)>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let name = name.to_normalized();
+    db.delete_cratesio_pin(&name, &version).await?;
+    let target = format!("{name}@{version}");
+    let event = audit_ctx.event(&user, AuditAction::DeleteCratesioPin, target);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+/// Lists the latest runs of the eviction, newest first.
+pub async fn reports(
+    user: MaybeUser,
+    State(db): DbState,
+) -> Result<Json<Vec<CratesIoEvictionReport>>, RouteError> {
+    user.assert_admin()?;
+    Ok(Json(db.get_cratesio_eviction_reports(MAX_REPORTS).await?))
+}
+
+/// Starts an eviction run with the configured size and age limits. The run is
+/// done in the background and its result is stored as a report.
+pub async fn evict(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(state): AppState,
+) -> Result<StatusCode, RouteError> {
+    user.assert_admin()?;
+    if !state.settings.proxy.eviction_enabled() {
+        return Err(RouteError::Status(StatusCode::CONFLICT));
+    }
+
+    let event = audit_ctx.event(&user, AuditAction::EvictCratesioCache, "crates.io");
+    audit::record(state.db.as_ref(), event).await;
+
+    tokio::spawn(async move {
+        if let Err(e) = cratesio_eviction::evict_cached_crates(
+            state.db.as_ref(),
+            &state.cratesio_storage,
+            &state.settings.proxy,
+        )
+        .await
+        {
+            error!("Failed to evict cached crates.io crates: {e}");
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::{admin_db, encode_cookies, test_app};
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use axum::routing::{delete, post};
+    use common::normalized_name::NormalizedName;
+    use db::error::DbError;
+    use db::mock::MockDb;
+    use mockall::predicate::*;
+    use settings::constants::COOKIE_SESSION_ID;
+    use tower::ServiceExt;
+
+    fn app(mock_db: MockDb) -> Router {
+        test_app(
+            mock_db,
+            Router::new()
+                .route("/pins", post(add_pin))
+                .route("/pins/{name}/{version}", delete(delete_pin))
+                .route("/evict", post(evict)),
+        )
+    }
+
+    #[tokio::test]
+    async fn add_pin_normalizes_crate_name() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_add_cratesio_pin()
+            .with(
+                eq(NormalizedName::from_unchecked_str("my_crate")),
+                eq(Version::try_from("1.0.0").unwrap()),
+            )
+            .returning(|_, _| Ok(()));
+        mock_db.expect_add_audit_entry().returning(|_| Ok(()));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::post("/pins")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"name":"My_Crate","version":"1.0.0"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
+    #[tokio::test]
+    async fn delete_unknown_pin_returns_404() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_delete_cratesio_pin()
+            .returning(|name, version| {
+                Err(DbError::CratesIoPinNotFound(format!("{name}@{version}")))
+            });
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::delete("/pins/serde/1.0.0")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+
+    #[tokio::test]
+    async fn evict_without_limits_is_refused() {
+        let r = app(admin_db())
+            .oneshot(
+                Request::post("/evict")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::CONFLICT, r.status());
+    }
+}
@@ -1,3 +1,4 @@
+use appstate::rate_limit::Throttled;
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
@@ -10,17 +11,34 @@ pub enum RouteError {
     Status(StatusCode),
     AuthenticationFailure,
     UserNotFound(String),
+    Throttled(Throttled),
 }
 
 impl From<db::error::DbError> for RouteError {
     fn from(err: db::error::DbError) -> Self {
         match err {
             db::error::DbError::PasswordMismatch => Self::AuthenticationFailure,
+            db::error::DbError::TrustedPublisherNotFound(_)
+            | db::error::DbError::WebhookNotFound(_)
+            | db::error::DbError::WebhookDeliveryNotFound(_)
+            | db::error::DbError::QuarantineExemptionNotFound(_)
+            | db::error::DbError::ProxyPolicyRuleNotFound(_)
+            | db::error::DbError::CratesIoPinNotFound(_)
+            | db::error::DbError::ExternalIdentityNotFound(_) => {
+                Self::Status(StatusCode::NOT_FOUND)
+            }
+            db::error::DbError::ServiceAccountLogin(_) => Self::Status(StatusCode::FORBIDDEN),
             _ => Self::DbError(err),
         }
     }
 }
 
+impl From<Throttled> for RouteError {
+    fn from(throttled: Throttled) -> Self {
+        Self::Throttled(throttled)
+    }
+}
+
 impl IntoResponse for RouteError {
     fn into_response(self) -> Response {
         match self {
@@ -38,6 +56,10 @@ impl IntoResponse for RouteError {
                 tracing::warn!("User not found: {name}");
                 StatusCode::NOT_FOUND.into_response()
             }
+            RouteError::Throttled(throttled) => {
+                tracing::warn!("Login throttled: {throttled}");
+                throttled.into_response()
+            }
         }
     }
 }
@@ -1,11 +1,14 @@
+use crate::audit::AuditContext;
 use crate::error::RouteError;
 use crate::session::MaybeUser;
 use appstate::DbState;
+use auth::audit;
 use axum::Json;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
-use db::{self, Group};
+use db::{self, AuditAction, Group};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Serialize)]
 pub struct NewTokenResponse {
@@ -24,12 +27,16 @@ pub async fn list_groups(
 
 pub async fn delete(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path(name): Path<String>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
 
-    Ok(db.delete_group(&name).await?)
+    db.delete_group(&name).await?;
+    let event = audit_ctx.event(&user, AuditAction::DeleteGroup, &name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
 
 #[derive(Deserialize)]
@@ -48,6 +55,7 @@ impl NewGroup {
 
 pub async fn add(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(db): DbState,
     Json(new_group): Json<NewGroup>,
 ) -> Result<(), RouteError> {
@@ -55,7 +63,10 @@ pub async fn add(
 
     new_group.validate()?;
 
-    Ok(db.add_group(&new_group.name).await?)
+    db.add_group(&new_group.name).await?;
+    let event = audit_ctx.event(&user, AuditAction::AddGroup, &new_group.name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -97,6 +108,7 @@ pub async fn list_users(
 
 pub async fn add_user(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path((group_name, name)): Path<(String, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
@@ -104,6 +116,10 @@ pub async fn add_user(
 
     if !db.is_group_user(&group_name, &name).await? {
         db.add_group_user(&group_name, &name).await?;
+        let event = audit_ctx
+            .event(&user, AuditAction::AddGroupUser, &group_name)
+            .after(json!({ "user": name }));
+        audit::record(db.as_ref(), event).await;
     }
 
     Ok(())
@@ -111,10 +127,16 @@ pub async fn add_user(
 
 pub async fn delete_user(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path((group_name, name)): Path<(String, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
 
-    Ok(db.delete_group_user(&group_name, &name).await?)
+    db.delete_group_user(&group_name, &name).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::RemoveGroupUser, &group_name)
+        .before(json!({ "user": name }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
@@ -0,0 +1,138 @@
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::rate_limit::is_trusted_proxy;
+use appstate::{AppStateData, SyncedUsers};
+use axum::extract::ConnectInfo;
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use db::ExternalUser;
+use settings::HeaderAuth;
+use std::net::SocketAddr;
+use tracing::warn;
+
+/// User of the request which was already looked up, such that the
+/// middleware and the extractors do not look it up again.
+#[derive(Clone)]
+struct ProxyUser(Option<MaybeUser>);
+
+/// Returns the user set by a trusted reverse proxy in the user header, or `None` if
+/// header authentication is disabled or the header is missing. Requests with the header
+/// from peers which are not trusted are rejected, as the header could be forged.
+pub async fn proxy_user(
+    parts: &mut Parts,
+    state: &AppStateData,
+) -> Result<Option<MaybeUser>, RouteError> {
+    if let Some(ProxyUser(user)) = parts.extensions.get::<ProxyUser>() {
+        return Ok(user.clone());
+    }
+    let user = lookup_proxy_user(parts, state).await?;
+    parts.extensions.insert(ProxyUser(user.clone()));
+    Ok(user)
+}
+
+async fn lookup_proxy_user(
+    parts: &Parts,
+    state: &AppStateData,
+) -> Result<Option<MaybeUser>, RouteError> {
+    let settings = &state.settings.header_auth;
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let Some(name) = parts.headers.get(&settings.user_header) else {
+        return Ok(None);
+    };
+
+    let peer = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if !peer.is_some_and(|ip| is_trusted_proxy(&settings.trusted_proxies, ip)) {
+        warn!("Rejected user header from untrusted peer {peer:?}");
+        return Err(RouteError::Status(StatusCode::FORBIDDEN));
+    }
+
+    let name = name.to_str().unwrap_or_default().trim();
+    if name.is_empty() {
+        return Err(RouteError::Status(StatusCode::UNAUTHORIZED));
+    }
+    let groups = parts
+        .headers
+        .get(&settings.groups_header)
+        .and_then(|g| g.to_str().ok())
+        .unwrap_or_default();
+
+    if needs_sync(&state.synced_users, name, groups) {
+        external_user(name.to_string(), groups, settings)
+            .sync(state.db.as_ref())
+            .await?;
+        state
+            .synced_users
+            .insert(name.to_string(), groups.to_string());
+    }
+
+    let user = state.db.get_user(name).await?;
+    Ok(Some(if user.is_admin {
+        MaybeUser::Admin(user.name)
+    } else {
+        MaybeUser::Normal(user.name)
+    }))
+}
+
+fn needs_sync(synced_users: &SyncedUsers, name: &str, groups: &str) -> bool {
+    synced_users
+        .get(&name.to_string())
+        .is_none_or(|synced| synced != groups)
+}
+
+fn external_user(name: String, groups: &str, settings: &HeaderAuth) -> ExternalUser {
+    let groups: Vec<String> = groups
+        .split(',')
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(ToString::to_string)
+        .collect();
+    ExternalUser::from_groups(
+        name,
+        None,
+        groups,
+        &settings.admin_groups,
+        &settings.read_only_groups,
+        settings.sync_groups,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_user_maps_group_header() {
+        let settings = HeaderAuth {
+            admin_groups: vec!["admins".to_string()],
+            sync_groups: true,
+            ..HeaderAuth::default()
+        };
+
+        let user = external_user("alice".to_string(), "devs, admins,", &settings);
+
+        assert_eq!(
+            ExternalUser {
+                name: "alice".to_string(),
+                identity: None,
+                is_admin: Some(true),
+                is_read_only: None,
+                groups: Some(vec!["devs".to_string(), "admins".to_string()]),
+            },
+            user
+        );
+    }
+
+    #[test]
+    fn needs_sync_again_if_groups_change() {
+        let synced_users = appstate::synced_users();
+        assert!(needs_sync(&synced_users, "alice", "a"));
+        synced_users.insert("alice".to_string(), "a".to_string());
+        assert!(!needs_sync(&synced_users, "alice", "a"));
+        assert!(needs_sync(&synced_users, "alice", "a,b"));
+    }
+}
@@ -1,17 +1,50 @@
+pub mod audit;
 pub mod crate_access;
+pub mod cratesio_cache;
 pub mod error;
 pub mod group;
+pub mod header_auth;
+pub mod oidc;
+pub mod proxy_policy;
+pub mod quarantine;
+pub mod service_account;
 pub mod session;
+pub mod totp;
 pub mod ui;
 pub mod user;
+pub mod webhook;
 
 #[cfg(test)]
 mod test_helper {
+    use appstate::AppStateData;
+    use axum::Router;
     use cookie::{Cookie, CookieJar};
+    use db::mock::MockDb;
     use std::borrow::Cow;
+    use std::sync::Arc;
 
     pub(crate) const TEST_KEY: &[u8] = &[1; 64];
 
+    /// Database with a valid session of the admin.
+    pub(crate) fn admin_db() -> MockDb {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .returning(|_| Ok(("admin".to_string(), true)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
+        mock_db
+    }
+
+    /// Serves the routes with the database and the key of the session cookies.
+    pub(crate) fn test_app(mock_db: MockDb, routes: Router<AppStateData>) -> Router {
+        let state = AppStateData {
+            db: Arc::new(mock_db),
+            signing_key: TEST_KEY.try_into().unwrap(),
+            ..appstate::test_state()
+        };
+        routes.with_state(state)
+    }
+
     // there has to be a better way to set cookies, i really don't like importing cookie crate just to do this
     pub(crate) fn encode_cookies<
         const N: usize,
@@ -22,9 +55,9 @@ mod test_helper {
     ) -> String {
         let mut clear = CookieJar::new();
         let mut jar = clear.private_mut(&TEST_KEY.try_into().unwrap());
-        cookies
-            .into_iter()
-            .for_each(|(k, v)| jar.add(Cookie::new(k, v)));
+        for (k, v) in cookies {
+            jar.add(Cookie::new(k, v));
+        }
         clear
             .iter()
             .map(|c| c.encoded().to_string())
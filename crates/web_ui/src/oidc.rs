@@ -0,0 +1,639 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use common::util::generate_rand_string;
+use db::{ExternalIdentity, ExternalUser};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{DecodingKey, Validation, decode, decode_header};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use settings::Oidc;
+use sha2::{Digest, Sha256};
+
+/// Endpoints of the OIDC provider, loaded from its discovery document.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// State of a running login, which is kept in a private cookie
+/// until the provider redirects the user back to Kellnr.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginState {
+    pub state: String,
+    pub nonce: String,
+    pub verifier: String,
+}
+
+impl LoginState {
+    pub fn new() -> Self {
+        Self {
+            state: generate_rand_string(32),
+            nonce: generate_rand_string(32),
+            verifier: generate_rand_string(64),
+        }
+    }
+}
+
+impl Default for LoginState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+pub async fn discover(issuer_url: &str) -> Result<ProviderMetadata, String> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer_url.trim_end_matches('/')
+    );
+    get_json(&url).await
+}
+
+/// URL of the provider the user is redirected to for the login.
+pub fn authorization_url(
+    metadata: &ProviderMetadata,
+    settings: &Oidc,
+    login: &LoginState,
+) -> Result<String, String> {
+    let challenge = pkce_challenge(&login.verifier);
+    Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", settings.client_id.as_str()),
+            ("redirect_uri", settings.redirect_url.as_str()),
+            ("scope", settings.scopes.join(" ").as_str()),
+            ("state", login.state.as_str()),
+            ("nonce", login.nonce.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map(String::from)
+    .map_err(|e| e.to_string())
+}
+
+/// PKCE code challenge with the `S256` method, see RFC 7636.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Exchanges the authorization code for an ID token.
+pub async fn exchange_code(
+    metadata: &ProviderMetadata,
+    settings: &Oidc,
+    code: &str,
+    verifier: &str,
+) -> Result<String, String> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", settings.redirect_url.as_str()),
+        ("client_id", settings.client_id.as_str()),
+        ("code_verifier", verifier),
+    ];
+    if !settings.client_secret.is_empty() {
+        form.push(("client_secret", settings.client_secret.as_str()));
+    }
+
+    let response: TokenResponse = reqwest::Client::new()
+        .post(&metadata.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(response.id_token)
+}
+
+/// Verifies signature, issuer, audience, expiry and nonce of the ID token and returns its claims.
+pub async fn verify_id_token(
+    id_token: &str,
+    metadata: &ProviderMetadata,
+    settings: &Oidc,
+    nonce: &str,
+) -> Result<Map<String, Value>, String> {
+    let jwks: JwkSet = get_json(&metadata.jwks_uri).await?;
+    let header = decode_header(id_token).map_err(|e| e.to_string())?;
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or("No matching key found in JWKS")?;
+
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| e.to_string())?;
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&metadata.issuer]);
+    validation.set_audience(&[&settings.client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = decode::<Map<String, Value>>(id_token, &key, &validation)
+        .map_err(|e| e.to_string())?
+        .claims;
+    match claims.get("nonce") {
+        Some(Value::String(n)) if n == nonce => Ok(claims),
+        _ => Err("Nonce of the ID token does not match".to_string()),
+    }
+}
+
+pub fn username(claims: &Map<String, Value>, settings: &Oidc) -> Option<String> {
+    match claims.get(&settings.username_claim) {
+        Some(Value::String(name)) if !name.trim().is_empty() => Some(name.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Identity of the user at the provider. The `sub` claim is unique and never
+/// reassigned within the issuer, unlike the name of the user.
+pub fn identity(
+    claims: &Map<String, Value>,
+    metadata: &ProviderMetadata,
+) -> Option<ExternalIdentity> {
+    match claims.get("sub") {
+        Some(Value::String(sub)) if !sub.is_empty() => Some(ExternalIdentity {
+            issuer: metadata.issuer.clone(),
+            subject: sub.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Groups of the user, which may be a single string or a list of strings.
+pub fn groups(claims: &Map<String, Value>, settings: &Oidc) -> Vec<String> {
+    match claims.get(&settings.groups_claim) {
+        Some(Value::String(group)) => vec![group.clone()],
+        Some(Value::Array(groups)) => groups
+            .iter()
+            .filter_map(|g| g.as_str().map(ToString::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Maps the groups of the provider to the roles and groups of the user in Kellnr.
+pub fn external_user(
+    name: String,
+    identity: ExternalIdentity,
+    groups: &[String],
+    settings: &Oidc,
+) -> ExternalUser {
+    ExternalUser::from_groups(
+        name,
+        Some(identity),
+        groups.to_vec(),
+        &settings.admin_groups,
+        &settings.read_only_groups,
+        settings.sync_groups,
+    )
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, String> {
+    reqwest::get(url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::TEST_KEY;
+    use crate::user;
+    use appstate::AppStateData;
+    use axum::extract::{Form, State};
+    use axum::routing::{get, post};
+    use axum::{Json, Router, body::Body};
+    use axum_extra::extract::cookie::Key;
+    use db::error::DbError;
+    use db::mock::MockDb;
+    use hyper::{Request, StatusCode, header};
+    use mockall::predicate::*;
+    use serde_json::json;
+    use settings::Settings;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    #[derive(Default)]
+    struct IssuerState {
+        url: String,
+        nonce: String,
+        code_challenge: String,
+        claims: Map<String, Value>,
+    }
+
+    type SharedIssuer = Arc<Mutex<IssuerState>>;
+
+    /// Serves discovery document, JWKS and token endpoint of a local OIDC provider.
+    async fn start_mock_issuer(claims: Value) -> SharedIssuer {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let issuer = Arc::new(Mutex::new(IssuerState {
+            url,
+            claims: claims.as_object().unwrap().clone(),
+            ..IssuerState::default()
+        }));
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(issuer.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        issuer
+    }
+
+    async fn discovery(State(issuer): State<SharedIssuer>) -> Json<Value> {
+        let url = issuer.lock().unwrap().url.clone();
+        Json(json!({
+            "issuer": url,
+            "authorization_endpoint": format!("{url}/authorize"),
+            "token_endpoint": format!("{url}/token"),
+            "jwks_uri": format!("{url}/jwks"),
+        }))
+    }
+
+    async fn jwks() -> String {
+        std::fs::read_to_string("../test_data/trusted_publishing/jwks.json").unwrap()
+    }
+
+    async fn token(
+        State(issuer): State<SharedIssuer>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        let issuer = issuer.lock().unwrap();
+        if form["code"] != "code" || pkce_challenge(&form["code_verifier"]) != issuer.code_challenge
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let mut claims = issuer.claims.clone();
+        claims.insert("iss".to_string(), json!(issuer.url));
+        claims.insert("nonce".to_string(), json!(issuer.nonce));
+        let pem = std::fs::read("../test_data/trusted_publishing/private_key.pem").unwrap();
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        let id_token = jsonwebtoken::encode(
+            &header,
+            &claims,
+            &jsonwebtoken::EncodingKey::from_rsa_pem(&pem).unwrap(),
+        )
+        .unwrap();
+        Ok(Json(
+            json!({ "id_token": id_token, "token_type": "Bearer" }),
+        ))
+    }
+
+    fn oidc_settings(issuer_url: &str) -> Oidc {
+        Oidc {
+            enabled: true,
+            issuer_url: issuer_url.to_string(),
+            client_id: "kellnr".to_string(),
+            redirect_url: "http://kellnr/api/v1/user/oidc/callback".to_string(),
+            admin_groups: vec!["admins".to_string()],
+            ..Oidc::default()
+        }
+    }
+
+    fn app(db: MockDb, oidc: Oidc) -> Router {
+        Router::new()
+            .route("/login", get(user::oidc_login))
+            .route("/callback", get(user::oidc_callback))
+            .with_state(AppStateData {
+                db: Arc::new(db),
+                signing_key: Key::from(TEST_KEY),
+                settings: Arc::new(Settings {
+                    oidc,
+                    ..Settings::default()
+                }),
+                ..appstate::test_state()
+            })
+    }
+
+    fn valid_claims() -> Value {
+        json!({
+            "aud": "kellnr",
+            "exp": chrono::Utc::now().timestamp() + 300,
+            "sub": "1234",
+            "preferred_username": "alice",
+            "groups": ["admins", "developers"],
+        })
+    }
+
+    fn admin(name: &str, totp_enabled: bool) -> db::User {
+        db::User {
+            name: name.to_string(),
+            is_admin: true,
+            totp_enabled,
+            ..db::User::default()
+        }
+    }
+
+    /// Starts the login and returns the state parameter and the cookie of the login.
+    async fn start_login(app: &Router, issuer: &SharedIssuer) -> (String, String) {
+        let r = app
+            .clone()
+            .oneshot(Request::get("/login").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::SEE_OTHER, r.status());
+
+        let location = r.headers()[header::LOCATION].to_str().unwrap();
+        let location = Url::parse(location).unwrap();
+        let params: HashMap<_, _> = location.query_pairs().into_owned().collect();
+        assert_eq!("code", params["response_type"]);
+        assert_eq!("S256", params["code_challenge_method"]);
+        {
+            let mut issuer = issuer.lock().unwrap();
+            issuer.nonce.clone_from(&params["nonce"]);
+            issuer.code_challenge.clone_from(&params["code_challenge"]);
+        }
+
+        let cookie = r.headers()[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        (params["state"].clone(), cookie)
+    }
+
+    #[tokio::test]
+    async fn login_provisions_user_and_issues_session() {
+        let issuer = start_mock_issuer(valid_claims()).await;
+        let url = issuer.lock().unwrap().url.clone();
+        let mut db = MockDb::new();
+        db.expect_get_external_identity_user()
+            .with(eq(url.clone()), eq("1234"))
+            .returning(|_, _| Ok(None));
+        db.expect_get_user()
+            .with(eq("alice"))
+            .times(1)
+            .returning(|name| Err(DbError::UserNotFound(name.to_string())));
+        db.expect_add_user()
+            .with(eq("alice"), always(), eq(true), eq(false))
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let identity = ExternalIdentity {
+            issuer: url.clone(),
+            subject: "1234".to_string(),
+        };
+        db.expect_add_external_identity()
+            .with(eq("alice"), eq(identity))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_get_user()
+            .with(eq("alice"))
+            .returning(|name| Ok(admin(name, false)));
+        db.expect_add_session_token()
+            .with(eq("alice"), always(), always())
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let app = app(db, oidc_settings(&url));
+
+        let (state, cookie) = start_login(&app, &issuer).await;
+        let r = app
+            .oneshot(
+                Request::get(format!("/callback?code=code&state={state}"))
+                    .header(header::COOKIE, cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::SEE_OTHER, r.status());
+        assert_eq!("/", r.headers()[header::LOCATION]);
+        assert!(
+            r.headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .any(|c| c.to_str().unwrap().starts_with("kellnr_session_id="))
+        );
+    }
+
+    #[tokio::test]
+    async fn login_as_existing_user_without_link_is_rejected() {
+        let issuer = start_mock_issuer(valid_claims()).await;
+        let url = issuer.lock().unwrap().url.clone();
+        let mut db = MockDb::new();
+        db.expect_get_external_identity_user()
+            .returning(|_, _| Ok(None));
+        db.expect_get_user()
+            .with(eq("alice"))
+            .returning(|name| Ok(admin(name, false)));
+        let app = app(db, oidc_settings(&url));
+
+        let (state, cookie) = start_login(&app, &issuer).await;
+        let r = app
+            .oneshot(
+                Request::get(format!("/callback?code=code&state={state}"))
+                    .header(header::COOKIE, cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, r.status());
+    }
+
+    #[tokio::test]
+    async fn login_of_linked_user_with_totp_requires_code() {
+        let issuer = start_mock_issuer(valid_claims()).await;
+        let url = issuer.lock().unwrap().url.clone();
+        let mut db = MockDb::new();
+        // The linked user has another name than the claim of the provider
+        db.expect_get_external_identity_user()
+            .with(eq(url.clone()), eq("1234"))
+            .returning(|_, _| Ok(Some("alice.smith".to_string())));
+        db.expect_get_user()
+            .with(eq("alice.smith"))
+            .returning(|name| Ok(admin(name, true)));
+        let app = app(db, oidc_settings(&url));
+
+        let (state, cookie) = start_login(&app, &issuer).await;
+        let r = app
+            .oneshot(
+                Request::get(format!("/callback?code=code&state={state}"))
+                    .header(header::COOKIE, cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::SEE_OTHER, r.status());
+        assert_eq!("/login?totp=true", r.headers()[header::LOCATION]);
+        let cookies: Vec<_> = r
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|c| c.to_str().unwrap())
+            .collect();
+        assert!(cookies.iter().any(|c| c.starts_with("kellnr_totp_login=")));
+        assert!(!cookies.iter().any(|c| c.starts_with("kellnr_session_id=")));
+    }
+
+    #[tokio::test]
+    async fn login_with_wrong_state_is_rejected() {
+        let issuer = start_mock_issuer(valid_claims()).await;
+        let url = issuer.lock().unwrap().url.clone();
+        let app = app(MockDb::new(), oidc_settings(&url));
+
+        let (_, cookie) = start_login(&app, &issuer).await;
+        let r = app
+            .oneshot(
+                Request::get("/callback?code=code&state=other")
+                    .header(header::COOKIE, cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, r.status());
+    }
+
+    #[tokio::test]
+    async fn login_with_token_for_other_client_is_rejected() {
+        let mut claims = valid_claims();
+        claims["aud"] = json!("other");
+        let issuer = start_mock_issuer(claims).await;
+        let url = issuer.lock().unwrap().url.clone();
+        let app = app(MockDb::new(), oidc_settings(&url));
+
+        let (state, cookie) = start_login(&app, &issuer).await;
+        let r = app
+            .oneshot(
+                Request::get(format!("/callback?code=code&state={state}"))
+                    .header(header::COOKIE, cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, r.status());
+    }
+
+    #[tokio::test]
+    async fn existing_user_gets_mapped_roles_and_groups() {
+        let settings = Oidc {
+            admin_groups: vec!["admins".to_string()],
+            read_only_groups: vec!["readers".to_string()],
+            sync_groups: true,
+            ..Oidc::default()
+        };
+        let identity = ExternalIdentity {
+            issuer: "https://issuer".to_string(),
+            subject: "1234".to_string(),
+        };
+        let mut db = MockDb::new();
+        db.expect_get_external_identity_user()
+            .with(eq("https://issuer"), eq("1234"))
+            .returning(|_, _| Ok(Some("alice".to_string())));
+        db.expect_get_user()
+            .with(eq("alice"))
+            .returning(|name| Ok(admin(name, false)));
+        db.expect_change_admin_state()
+            .with(eq("alice"), eq(false))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_change_read_only_state()
+            .with(eq("alice"), eq(true))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_get_groups().returning(|| {
+            Ok(vec![
+                db::Group {
+                    id: 1,
+                    name: "readers".to_string(),
+                },
+                db::Group {
+                    id: 2,
+                    name: "old".to_string(),
+                },
+            ])
+        });
+        db.expect_is_group_user()
+            .returning(|group, _| Ok(group == "old"));
+        db.expect_add_group_user()
+            .with(eq("readers"), eq("alice"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_delete_group_user()
+            .with(eq("old"), eq("alice"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let name = external_user(
+            "alice".to_string(),
+            identity,
+            &["readers".to_string()],
+            &settings,
+        )
+        .sync(&db)
+        .await
+        .unwrap();
+
+        assert_eq!("alice", name);
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc_example() {
+        assert_eq!(
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk")
+        );
+    }
+
+    #[test]
+    fn groups_claim_can_be_string_or_list() {
+        let settings = Oidc::default();
+        let list = json!({"groups": ["a", "b", 1]});
+        let single = json!({"groups": "a"});
+
+        assert_eq!(vec!["a", "b"], groups(list.as_object().unwrap(), &settings));
+        assert_eq!(vec!["a"], groups(single.as_object().unwrap(), &settings));
+        assert!(groups(&Map::new(), &settings).is_empty());
+    }
+
+    #[test]
+    fn external_user_maps_configured_groups_only() {
+        let settings = Oidc {
+            admin_groups: vec!["admins".to_string()],
+            sync_groups: true,
+            ..Oidc::default()
+        };
+        let groups = vec!["readers".to_string()];
+
+        let identity = ExternalIdentity {
+            issuer: "https://issuer".to_string(),
+            subject: "1234".to_string(),
+        };
+
+        let user = external_user("alice".to_string(), identity.clone(), &groups, &settings);
+
+        assert_eq!(
+            ExternalUser {
+                name: "alice".to_string(),
+                identity: Some(identity),
+                is_admin: Some(false),
+                is_read_only: None,
+                groups: Some(groups),
+            },
+            user
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::audit::AuditContext;
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::{DbState, SettingsState};
+use auth::audit;
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use db::{AuditAction, PolicyAction, ProxyPolicyRule};
+use index::proxy_policy::{ProxyPolicy, is_valid_version_req};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Number of cached versions read from the database at once to find the blocked ones.
+const BLOCKED_BATCH_SIZE: u64 = 1000;
+
+/// Lists the rules of the policy for crates.io crates served by the proxy.
+pub async fn list(
+    user: MaybeUser,
+    State(db): DbState,
+) -> Result<Json<Vec<ProxyPolicyRule>>, RouteError> {
+    user.assert_admin()?;
+    Ok(Json(db.get_proxy_policy_rules().await?))
+}
+
+#[derive(Deserialize)]
+pub struct NewRule {
+    pub action: PolicyAction,
+    pub crate_pattern: String,
+    pub version_req: Option<String>,
+    #[serde(default)]
+    pub licenses: Vec<String>,
+    pub group: Option<String>,
+    pub reason: String,
+}
+
+/// Adds a rule. Rules with a group are refused if no authentication is required,
+/// as requests without a token do not belong to a group and only get the rules without group.
+pub async fn add(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(db): DbState,
+    State(settings): SettingsState,
+    Json(rule): Json<NewRule>,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let crate_pattern = rule.crate_pattern.trim();
+    let reason = rule.reason.trim();
+    let version_req = rule
+        .version_req
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let licenses: Vec<String> = rule
+        .licenses
+        .iter()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let group = rule.group.filter(|g| !g.is_empty());
+    if crate_pattern.is_empty()
+        || reason.is_empty()
+        || version_req
+            .as_deref()
+            .is_some_and(|v| !is_valid_version_req(v))
+    {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+    if group.is_some() && !settings.registry.auth_required {
+        return Err(RouteError::Status(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+
+    db.add_proxy_policy_rule(
+        rule.action,
+        crate_pattern,
+        version_req.clone(),
+        &licenses,
+        group.clone(),
+        reason,
+    )
+    .await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddProxyPolicyRule, crate_pattern)
+        .after(json!({
+            "action": rule.action,
+            "version_req": version_req,
+            "licenses": licenses,
+            "group": group,
+            "reason": reason,
+        }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+pub async fn delete(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    db.delete_proxy_policy_rule(id).await?;
+    let event = audit_ctx.event(&user, AuditAction::DeleteProxyPolicyRule, id);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct BlockedParams {
+    /// Shows what members of the group cannot use, else what anyone cannot use,
+    /// including requests without a token if no authentication is required.
+    pub group: Option<String>,
+}
+
+/// A cached crates.io version which is blocked by the policy.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct BlockedVersion {
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    pub reason: String,
+}
+
+/// Lists the cached crates.io versions which are blocked by the policy and why.
+/// Rules with a group do not apply to requests without a token, see `ProxyPolicy::for_user`.
+pub async fn blocked(
+    user: MaybeUser,
+    Query(params): Query<BlockedParams>,
+    State(db): DbState,
+) -> Result<Json<Vec<BlockedVersion>>, RouteError> {
+    user.assert_admin()?;
+
+    let rules = db.get_proxy_policy_rules().await?;
+    let policy = ProxyPolicy::for_group(rules, params.group.as_deref());
+
+    let mut blocked = vec![];
+    let mut offset = 0;
+    loop {
+        let versions = db
+            .get_cratesio_crate_versions(BLOCKED_BATCH_SIZE, offset)
+            .await?;
+        for version in &versions {
+            let license = version.license.as_deref().filter(|l| !l.is_empty());
+            if let Err(reason) = policy.check(&version.name, &version.version, license) {
+                blocked.push(BlockedVersion {
+                    name: version.name.to_string(),
+                    version: version.version.clone(),
+                    license: license.map(ToString::to_string),
+                    reason,
+                });
+            }
+        }
+        if (versions.len() as u64) < BLOCKED_BATCH_SIZE {
+            break;
+        }
+        offset += BLOCKED_BATCH_SIZE;
+    }
+
+    Ok(Json(blocked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::{admin_db, encode_cookies, test_app};
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use axum::routing::{get, post};
+    use common::original_name::OriginalName;
+    use db::CachedCrateVersion;
+    use db::mock::MockDb;
+    use http_body_util::BodyExt;
+    use settings::constants::COOKIE_SESSION_ID;
+    use tower::ServiceExt;
+
+    fn app(mock_db: MockDb) -> Router {
+        test_app(
+            mock_db,
+            Router::new()
+                .route("/rules", post(add))
+                .route("/blocked", get(blocked)),
+        )
+    }
+
+    #[tokio::test]
+    async fn add_rejects_invalid_version_req() {
+        let r = app(admin_db())
+            .oneshot(
+                Request::post("/rules")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"action":"deny","crate_pattern":"serde","version_req":"one","reason":"r"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+
+    #[tokio::test]
+    async fn add_rejects_group_rule_without_required_auth() {
+        let r = app(admin_db())
+            .oneshot(
+                Request::post("/rules")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"action":"deny","crate_pattern":"serde","group":"team","reason":"r"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, r.status());
+    }
+
+    #[tokio::test]
+    async fn blocked_lists_versions_of_group_with_reason() {
+        let mut mock_db = admin_db();
+        mock_db.expect_get_proxy_policy_rules().returning(|| {
+            Ok(vec![ProxyPolicyRule {
+                id: 1,
+                action: PolicyAction::Allow,
+                crate_pattern: "serde*".to_string(),
+                version_req: None,
+                licenses: vec![],
+                group: Some("team".to_string()),
+                reason: "approved crates".to_string(),
+                created: "2026-10-18 12:00:00".to_string(),
+            }])
+        });
+        mock_db
+            .expect_get_cratesio_crate_versions()
+            .returning(|_, _| {
+                Ok(["serde", "tokio"]
+                    .map(|name| CachedCrateVersion {
+                        name: OriginalName::from_unchecked(name.to_string()),
+                        version: "1.0.0".to_string(),
+                        cksum: "cksum".to_string(),
+                        license: Some("MIT".to_string()),
+                    })
+                    .to_vec())
+            });
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::get("/blocked?group=team")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let blocked: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json!([{
+                "name": "tokio",
+                "version": "1.0.0",
+                "license": "MIT",
+                "reason": "not on the list of approved crates",
+            }]),
+            blocked
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::audit::AuditContext;
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::DbState;
+use auth::audit;
+use axum::Json;
+use axum::extract::{Path, State};
+use common::original_name::OriginalName;
+use db::{AuditAction, QuarantineExemption};
+use serde::Deserialize;
+
+/// Lists the crates.io crates whose new versions are not quarantined.
+pub async fn list(
+    user: MaybeUser,
+    State(db): DbState,
+) -> Result<Json<Vec<QuarantineExemption>>, RouteError> {
+    user.assert_admin()?;
+    Ok(Json(db.get_quarantine_exemptions().await?))
+}
+
+#[derive(Deserialize)]
+pub struct NewExemption {
+    pub name: OriginalName,
+}
+
+pub async fn add(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(db): DbState,
+    Json(exemption): Json<NewExemption>,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let name = exemption.name.to_normalized();
+    db.add_quarantine_exemption(&name).await?;
+    let event = audit_ctx.event(&user, AuditAction::AddQuarantineExemption, &name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+pub async fn delete(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<OriginalName>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let name = name.to_normalized();
+    db.delete_quarantine_exemption(&name).await?;
+    let event = audit_ctx.event(&user, AuditAction::DeleteQuarantineExemption, &name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::{admin_db, encode_cookies, test_app};
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use axum::routing::{delete, post};
+    use common::normalized_name::NormalizedName;
+    use db::error::DbError;
+    use db::mock::MockDb;
+    use mockall::predicate::*;
+    use settings::constants::COOKIE_SESSION_ID;
+    use tower::ServiceExt;
+
+    fn app(mock_db: MockDb) -> Router {
+        test_app(
+            mock_db,
+            Router::new()
+                .route("/", post(add))
+                .route("/{name}", delete(super::delete)),
+        )
+    }
+
+    #[tokio::test]
+    async fn add_normalizes_crate_name() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_add_quarantine_exemption()
+            .with(eq(NormalizedName::from_unchecked_str("my_crate")))
+            .returning(|_| Ok(()));
+        mock_db.expect_add_audit_entry().returning(|_| Ok(()));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::post("/")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"name":"My_Crate"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
+    #[tokio::test]
+    async fn delete_unknown_exemption_returns_404() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_delete_quarantine_exemption()
+            .returning(|name| Err(DbError::QuarantineExemptionNotFound(name.to_string())));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::delete("/serde")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+}
@@ -0,0 +1,367 @@
+use crate::audit::AuditContext;
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use crate::user::{self, NewTokenResponse};
+use appstate::DbState;
+use auth::{audit, token};
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use db::error::DbError;
+use db::{AuditAction, AuthToken, DbProvider, ServiceAccount};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Lists the service accounts the user can manage.
+pub async fn list(
+    user: MaybeUser,
+    State(db): DbState,
+) -> Result<Json<Vec<ServiceAccount>>, RouteError> {
+    let mut accounts = Vec::new();
+    for account in db.get_service_accounts().await? {
+        if can_manage(&user, account.group.as_deref(), db.as_ref()).await? {
+            accounts.push(account);
+        }
+    }
+    Ok(Json(accounts))
+}
+
+#[derive(Deserialize)]
+pub struct NewServiceAccount {
+    pub name: String,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub is_read_only: bool,
+}
+
+impl NewServiceAccount {
+    pub fn validate(&self) -> Result<(), RouteError> {
+        if self.name.is_empty() || self.group.as_ref().is_some_and(String::is_empty) {
+            return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+        }
+        Ok(())
+    }
+}
+
+pub async fn add(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(db): DbState,
+    Json(new_account): Json<NewServiceAccount>,
+) -> Result<(), RouteError> {
+    new_account.validate()?;
+    if !can_manage(&user, new_account.group.as_deref(), db.as_ref()).await? {
+        return Err(RouteError::InsufficientPrivileges);
+    }
+
+    db.add_service_account(
+        &new_account.name,
+        new_account.group.clone(),
+        new_account.is_read_only,
+    )
+    .await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddServiceAccount, &new_account.name)
+        .after(json!({ "group": new_account.group, "read_only": new_account.is_read_only }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+pub async fn delete(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<String>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    assert_can_manage(&user, &name, db.as_ref()).await?;
+
+    db.delete_user(&name).await?;
+    let event = audit_ctx.event(&user, AuditAction::DeleteServiceAccount, &name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+pub async fn list_tokens(
+    user: MaybeUser,
+    Path(name): Path<String>,
+    State(db): DbState,
+) -> Result<Json<Vec<AuthToken>>, RouteError> {
+    assert_can_manage(&user, &name, db.as_ref()).await?;
+
+    Ok(Json(db.get_auth_tokens(&name).await?))
+}
+
+pub async fn add_token(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<String>,
+    State(db): DbState,
+    Json(auth_token): Json<token::NewTokenReqData>,
+) -> Result<Json<NewTokenResponse>, RouteError> {
+    assert_can_manage(&user, &name, db.as_ref()).await?;
+
+    let token_name = auth_token.name.clone();
+    let token = user::create_token(db.as_ref(), &name, auth_token).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddToken, &name)
+        .after(json!({ "token": token_name }));
+    audit::record(db.as_ref(), event).await;
+
+    Ok(token.into())
+}
+
+pub async fn delete_token(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path((name, id)): Path<(String, i32)>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    assert_can_manage(&user, &name, db.as_ref()).await?;
+
+    user::remove_token(db.as_ref(), &name, id).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::DeleteToken, &name)
+        .before(json!({ "token_id": id }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+/// Admins manage all service accounts, other users the ones of their groups.
+/// Read-only users cannot manage service accounts, as they could create
+/// accounts with more permissions than they have.
+async fn can_manage(
+    user: &MaybeUser,
+    group: Option<&str>,
+    db: &dyn DbProvider,
+) -> Result<bool, RouteError> {
+    Ok(match (user, group) {
+        (MaybeUser::Admin(_), _) => true,
+        (MaybeUser::Normal(name), Some(group)) => {
+            db.is_group_user(group, name).await? && !db.get_user(name).await?.is_read_only
+        }
+        (MaybeUser::Normal(_), None) => false,
+    })
+}
+
+async fn assert_can_manage(
+    user: &MaybeUser,
+    name: &str,
+    db: &dyn DbProvider,
+) -> Result<(), RouteError> {
+    let account = db.get_service_account(name).await.map_err(|e| match e {
+        DbError::UserNotFound(name) => RouteError::UserNotFound(name),
+        e => e.into(),
+    })?;
+
+    if can_manage(user, account.group.as_deref(), db).await? {
+        Ok(())
+    } else {
+        Err(RouteError::InsufficientPrivileges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::{encode_cookies, test_app};
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use axum::routing::{get, post};
+    use db::User;
+    use db::mock::MockDb;
+    use http_body_util::BodyExt;
+    use mockall::predicate::*;
+    use settings::constants::COOKIE_SESSION_ID;
+    use tower::ServiceExt;
+
+    fn app(mock_db: MockDb) -> Router {
+        test_app(
+            mock_db,
+            Router::new()
+                .route("/", get(list))
+                .route("/add", post(add))
+                .route("/{name}/tokens", get(list_tokens)),
+        )
+    }
+
+    /// Session of a user, who is a member of the group `team` only.
+    fn session_db(name: &'static str, is_admin: bool, is_read_only: bool) -> MockDb {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .returning(move |_| Ok((name.to_string(), is_admin)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
+        mock_db.expect_get_user().returning(move |name| {
+            Ok(User {
+                name: name.to_string(),
+                is_admin,
+                is_read_only,
+                ..User::default()
+            })
+        });
+        mock_db
+            .expect_is_group_user()
+            .returning(|group, _| Ok(group == "team"));
+        mock_db
+    }
+
+    fn expect_account(mock_db: &mut MockDb, name: &'static str, group: Option<&'static str>) {
+        mock_db
+            .expect_get_service_account()
+            .with(eq(name))
+            .returning(move |name| {
+                Ok(ServiceAccount {
+                    name: name.to_string(),
+                    group: group.map(ToString::to_string),
+                    is_read_only: false,
+                })
+            });
+    }
+
+    fn add_request(body: &'static str) -> Request<Body> {
+        Request::post("/add")
+            .header(
+                header::COOKIE,
+                encode_cookies([(COOKIE_SESSION_ID, "token")]),
+            )
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    fn get_request(uri: &str) -> Request<Body> {
+        Request::get(uri)
+            .header(
+                header::COOKIE,
+                encode_cookies([(COOKIE_SESSION_ID, "token")]),
+            )
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn admin_adds_account_without_group() {
+        let mut mock_db = session_db("admin", true, false);
+        mock_db
+            .expect_add_service_account()
+            .with(eq("ci"), eq(None), eq(false))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock_db.expect_add_audit_entry().returning(|_| Ok(()));
+
+        let r = app(mock_db)
+            .oneshot(add_request(r#"{"name":"ci"}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
+    #[tokio::test]
+    async fn group_member_adds_account_of_own_group() {
+        let mut mock_db = session_db("alice", false, false);
+        mock_db
+            .expect_add_service_account()
+            .with(eq("ci"), eq(Some("team".to_string())), eq(false))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock_db.expect_add_audit_entry().returning(|_| Ok(()));
+
+        let r = app(mock_db)
+            .oneshot(add_request(r#"{"name":"ci","group":"team"}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
+    #[tokio::test]
+    async fn group_member_cannot_add_account_of_other_group_or_without_group() {
+        for body in [r#"{"name":"ci","group":"other"}"#, r#"{"name":"ci"}"#] {
+            let r = app(session_db("alice", false, false))
+                .oneshot(add_request(body))
+                .await
+                .unwrap();
+
+            assert_eq!(StatusCode::FORBIDDEN, r.status());
+        }
+    }
+
+    #[tokio::test]
+    async fn read_only_group_member_cannot_add_account() {
+        let r = app(session_db("reader", false, true))
+            .oneshot(add_request(r#"{"name":"ci","group":"team"}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+
+    #[tokio::test]
+    async fn group_member_manages_accounts_of_own_group_only() {
+        let mut mock_db = session_db("alice", false, false);
+        expect_account(&mut mock_db, "team-ci", Some("team"));
+        expect_account(&mut mock_db, "other-ci", Some("other"));
+        expect_account(&mut mock_db, "admin-ci", None);
+        mock_db
+            .expect_get_auth_tokens()
+            .with(eq("team-ci"))
+            .returning(|_| Ok(Vec::new()));
+        let app = app(mock_db);
+
+        let r = app
+            .clone()
+            .oneshot(get_request("/team-ci/tokens"))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, r.status());
+        for name in ["other-ci", "admin-ci"] {
+            let r = app
+                .clone()
+                .oneshot(get_request(&format!("/{name}/tokens")))
+                .await
+                .unwrap();
+            assert_eq!(StatusCode::FORBIDDEN, r.status());
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_account_returns_404() {
+        let mut mock_db = session_db("admin", true, false);
+        mock_db
+            .expect_get_service_account()
+            .returning(|name| Err(DbError::UserNotFound(name.to_string())));
+
+        let r = app(mock_db)
+            .oneshot(get_request("/unknown/tokens"))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+
+    #[tokio::test]
+    async fn list_returns_manageable_accounts_only() {
+        let mut mock_db = session_db("alice", false, false);
+        mock_db.expect_get_service_accounts().returning(|| {
+            Ok(["team", "other"]
+                .into_iter()
+                .map(|group| ServiceAccount {
+                    name: format!("{group}-ci"),
+                    group: Some(group.to_string()),
+                    is_read_only: false,
+                })
+                .collect())
+        });
+
+        let r = app(mock_db).oneshot(get_request("/")).await.unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let accounts: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(1, accounts.len());
+        assert_eq!("team-ci", accounts[0]["name"]);
+    }
+}
@@ -1,8 +1,15 @@
 use crate::error::RouteError;
+use crate::header_auth::proxy_user;
+use axum::response::IntoResponse;
 use axum::{RequestPartsExt, extract::State};
 use axum::{extract::Request, http::request::Parts, middleware::Next, response::Response};
 use axum_extra::extract::PrivateCookieJar;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use cookie::time;
+use db::DbProvider;
 use settings::constants;
+use std::sync::Arc;
+use tracing::{error, info};
 
 pub trait Name {
     fn name(&self) -> String;
@@ -39,7 +46,7 @@ impl Name for AnyUser {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum MaybeUser {
     // Consider using a db model or something?
     Normal(String),
@@ -66,6 +73,22 @@ impl MaybeUser {
             MaybeUser::Admin(_) => Ok(()),
         }
     }
+
+    /// Admins without TOTP have no admin rights, if TOTP is required for admins.
+    async fn from_session(
+        state: &appstate::AppStateData,
+        name: String,
+        is_admin: bool,
+    ) -> Result<Self, RouteError> {
+        if !is_admin {
+            return Ok(Self::Normal(name));
+        }
+        if state.settings.totp.required_for_admins && !state.db.get_user(&name).await?.totp_enabled
+        {
+            return Ok(Self::Normal(name));
+        }
+        Ok(Self::Admin(name))
+    }
 }
 
 impl axum::extract::FromRequestParts<appstate::AppStateData> for MaybeUser {
@@ -75,14 +98,14 @@ impl axum::extract::FromRequestParts<appstate::AppStateData> for MaybeUser {
         parts: &mut Parts,
         state: &appstate::AppStateData,
     ) -> Result<Self, Self::Rejection> {
+        if let Some(user) = proxy_user(parts, state).await? {
+            return Ok(user);
+        }
         let jar: PrivateCookieJar = parts.extract_with_state(state).await.unwrap();
         let session_cookie = jar.get(constants::COOKIE_SESSION_ID);
         match session_cookie {
             Some(cookie) => match state.db.validate_session(cookie.value()).await {
-                // admin
-                Ok((name, true)) => Ok(Self::Admin(name)),
-                // not admin
-                Ok((name, false)) => Ok(Self::Normal(name)),
+                Ok((name, is_admin)) => Self::from_session(state, name, is_admin).await,
                 Err(_) => Err(RouteError::Status(axum::http::StatusCode::UNAUTHORIZED)),
             },
             None => Err(RouteError::Status(axum::http::StatusCode::UNAUTHORIZED)),
@@ -97,14 +120,14 @@ impl axum::extract::OptionalFromRequestParts<appstate::AppStateData> for MaybeUs
         parts: &mut Parts,
         state: &appstate::AppStateData,
     ) -> Result<Option<Self>, Self::Rejection> {
+        if let Some(user) = proxy_user(parts, state).await? {
+            return Ok(Some(user));
+        }
         let jar: PrivateCookieJar = parts.extract_with_state(state).await.unwrap();
         let session_cookie = jar.get(constants::COOKIE_SESSION_ID);
         match session_cookie {
             Some(cookie) => match state.db.validate_session(cookie.value()).await {
-                // admin
-                Ok((name, true)) => Ok(Some(Self::Admin(name))),
-                // not admin
-                Ok((name, false)) => Ok(Some(Self::Normal(name))),
+                Ok((name, is_admin)) => Self::from_session(state, name, is_admin).await.map(Some),
                 Err(_) => Err(RouteError::Status(axum::http::StatusCode::UNAUTHORIZED)),
             },
             None => Ok(None),
@@ -114,29 +137,73 @@ impl axum::extract::OptionalFromRequestParts<appstate::AppStateData> for MaybeUs
 
 /// Middleware that checks if a user is logged in when `settings.registry.auth_required` is `true`
 /// If the user is not logged in, a 401 is returned.
+/// The cookie of a valid session is renewed, as the session itself is renewed on activity.
+/// Users authenticated by a trusted reverse proxy need no session.
 pub async fn session_auth_when_required(
     State(state): State<appstate::AppStateData>,
     jar: PrivateCookieJar,
     request: Request,
     next: Next,
 ) -> Result<Response, RouteError> {
-    if !state.settings.registry.auth_required {
-        // If "auth_required" is "false", pass through.
-        return Ok(next.run(request).await);
+    let (mut parts, body) = request.into_parts();
+    if proxy_user(&mut parts, &state).await?.is_some() {
+        return Ok(next.run(Request::from_parts(parts, body)).await);
     }
+    let request = Request::from_parts(parts, body);
+
     let session_cookie = jar.get(constants::COOKIE_SESSION_ID);
-    match session_cookie {
+    let session_token = match session_cookie {
         Some(cookie) => match state.db.validate_session(cookie.value()).await {
             // user is logged in
-            Ok(_) => Ok(next.run(request).await),
+            Ok(_) => Some(cookie.value().to_owned()),
             // user is not logged in
-            Err(_) => Err(RouteError::Status(axum::http::StatusCode::UNAUTHORIZED)),
+            Err(_) => None,
         },
         // user is not logged in
-        None => Err(RouteError::Status(axum::http::StatusCode::UNAUTHORIZED)),
+        None => None,
+    };
+
+    match session_token {
+        Some(token) => {
+            let response = next.run(request).await;
+            let jar = jar.add(session_cookie_with_age(
+                token,
+                state.settings.registry.session_age_seconds,
+            ));
+            Ok((jar, response).into_response())
+        }
+        None if state.settings.registry.auth_required => {
+            Err(RouteError::Status(axum::http::StatusCode::UNAUTHORIZED))
+        }
+        // If "auth_required" is "false", pass through.
+        None => Ok(next.run(request).await),
     }
 }
 
+/// Creates the session cookie, which expires together with the session if it is not used.
+pub fn session_cookie_with_age(token: String, session_age_seconds: u64) -> Cookie<'static> {
+    Cookie::build((constants::COOKIE_SESSION_ID, token))
+        .max_age(time::Duration::seconds(session_age_seconds as i64))
+        .same_site(SameSite::Strict)
+        .path("/")
+        .build()
+}
+
+/// Periodically deletes sessions which expired, as expired sessions
+/// are otherwise only deleted when they are used again.
+pub fn expired_session_cleanup(db: Arc<dyn DbProvider>) {
+    tokio::spawn(async move {
+        loop {
+            match db.cleanup_expired_sessions().await {
+                Ok(0) => {}
+                Ok(count) => info!("Deleted {count} expired session(s)"),
+                Err(e) => error!("Expired session cleanup failed: {e}"),
+            }
+            tokio::time::sleep(std::time::Duration::from_hours(1)).await;
+        }
+    });
+}
+
 #[cfg(test)]
 mod session_tests {
     use super::*;
@@ -145,7 +212,7 @@ mod session_tests {
     use axum::{Router, body::Body, routing::get};
     use axum_extra::extract::cookie::Key;
     use db::DbProvider;
-    use db::{error::DbError, mock::MockDb};
+    use db::{User, error::DbError, mock::MockDb};
     use hyper::{Request, StatusCode, header};
     use mockall::predicate::*;
     use settings::Settings;
@@ -168,7 +235,10 @@ mod session_tests {
     async fn any_endpoint(_user: MaybeUser) {}
 
     fn app(db: Arc<dyn DbProvider>) -> Router {
-        let settings = Settings::default();
+        app_with_settings(db, Settings::default())
+    }
+
+    fn app_with_settings(db: Arc<dyn DbProvider>, settings: Settings) -> Router {
         let storage = Box::new(FSStorage::new(&settings.crates_path()).unwrap()) as DynStorage;
         Router::new()
             .route("/admin", get(admin_endpoint))
@@ -266,6 +336,72 @@ mod session_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn admin_without_totp_is_normal_user_if_totp_required() -> Result {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .with(eq("1234"))
+            .returning(|_st| Ok(("admin".to_string(), true)));
+        mock_db
+            .expect_get_user()
+            .with(eq("admin"))
+            .returning(|name| {
+                Ok(User {
+                    name: name.to_string(),
+                    is_admin: true,
+                    ..User::default()
+                })
+            });
+        let mut settings = Settings::default();
+        settings.totp.required_for_admins = true;
+
+        let r = app_with_settings(Arc::new(mock_db), settings)
+            .oneshot(
+                Request::get("/admin")
+                    .header(header::COOKIE, c1234())
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn admin_with_totp_is_admin_if_totp_required() -> Result {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .with(eq("1234"))
+            .returning(|_st| Ok(("admin".to_string(), true)));
+        mock_db
+            .expect_get_user()
+            .with(eq("admin"))
+            .returning(|name| {
+                Ok(User {
+                    name: name.to_string(),
+                    is_admin: true,
+                    totp_enabled: true,
+                    is_service_account: false,
+                    ..User::default()
+                })
+            });
+        let mut settings = Settings::default();
+        settings.totp.required_for_admins = true;
+
+        let r = app_with_settings(Arc::new(mock_db), settings)
+            .oneshot(
+                Request::get("/admin")
+                    .header(header::COOKIE, c1234())
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert!(r.status().is_success());
+
+        Ok(())
+    }
+
     // NormalUser tests
 
     #[tokio::test]
@@ -419,14 +555,17 @@ mod auth_middleware_tests {
     use super::*;
     use crate::test_helper::encode_cookies;
     use appstate::AppStateData;
+    use axum::extract::ConnectInfo;
     use axum::middleware::from_fn_with_state;
     use axum::{Router, body::Body, routing::get};
     use axum_extra::extract::cookie::Key;
     use db::DbProvider;
-    use db::{error::DbError, mock::MockDb};
+    use db::{User, error::DbError, mock::MockDb};
+    use http_body_util::BodyExt;
     use hyper::{Request, StatusCode, header};
     use mockall::predicate::*;
     use settings::Settings;
+    use std::net::SocketAddr;
     use std::sync::Arc;
     use tower::ServiceExt;
 
@@ -497,6 +636,29 @@ mod auth_middleware_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn valid_session_cookie_is_renewed() -> Result {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .with(eq("1234"))
+            .returning(|_st| Ok(("guest".to_string(), false)));
+
+        let r = app_not_required_auth(Arc::new(mock_db))
+            .oneshot(
+                Request::get("/guarded")
+                    .header(header::COOKIE, c1234())
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(r.status(), StatusCode::OK);
+        let cookie = r.headers().get(header::SET_COOKIE).unwrap().to_str()?;
+        assert!(cookie.starts_with(constants::COOKIE_SESSION_ID));
+        assert!(cookie.contains("Max-Age=28800"));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn guarded_route_with_invalid_cookie() -> Result {
         let mut mock_db = MockDb::new();
@@ -541,6 +703,120 @@ mod auth_middleware_tests {
         Ok(())
     }
 
+    fn app_header_auth(db: Arc<dyn DbProvider>) -> Router {
+        let settings = Settings::default();
+        let state = AppStateData {
+            db,
+            signing_key: Key::from(crate::test_helper::TEST_KEY),
+            settings: Arc::new(Settings {
+                registry: settings::Registry {
+                    auth_required: true,
+                    ..settings::Registry::default()
+                },
+                header_auth: settings::HeaderAuth {
+                    enabled: true,
+                    trusted_proxies: vec!["10.0.0.0/8".to_string()],
+                    ..settings::HeaderAuth::default()
+                },
+                ..settings
+            }),
+            ..appstate::test_state()
+        };
+        Router::new()
+            .route(
+                "/guarded",
+                get(|user: MaybeUser| async move { user.name().to_string() }),
+            )
+            .route_layer(from_fn_with_state(
+                state.clone(),
+                session_auth_when_required,
+            ))
+            .with_state(state)
+    }
+
+    fn proxy_request(peer: &str, user: &str) -> Request<Body> {
+        let peer: SocketAddr = peer.parse().unwrap();
+        Request::get("/guarded")
+            .header("X-Forwarded-User", user)
+            .extension(ConnectInfo(peer))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn user(name: &str) -> User {
+        User {
+            id: 1,
+            name: name.to_string(),
+            pwd: String::new(),
+            salt: String::new(),
+            is_admin: false,
+            is_read_only: false,
+            totp_enabled: false,
+            is_service_account: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn header_auth_from_trusted_proxy() -> Result {
+        let mut mock_db = MockDb::new();
+        // Once for the sync and once for the login, although both
+        // the middleware and the extractor need the user
+        mock_db
+            .expect_get_user()
+            .with(eq("proxy_user"))
+            .times(2)
+            .returning(|name| Ok(user(name)));
+
+        let r = app_header_auth(Arc::new(mock_db))
+            .oneshot(proxy_request("10.0.0.1:1234", "proxy_user"))
+            .await?;
+        assert_eq!(r.status(), StatusCode::OK);
+        let body = r.into_body().collect().await?.to_bytes();
+        assert_eq!(&body[..], b"proxy_user");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn header_auth_creates_unknown_user() -> Result {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_user()
+            .with(eq("new_proxy_user"))
+            .times(1)
+            .returning(|name| Err(DbError::UserNotFound(name.to_string())));
+        mock_db
+            .expect_add_user()
+            .withf(|name, _, is_admin, is_read_only| {
+                name == "new_proxy_user" && !is_admin && !is_read_only
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        mock_db
+            .expect_get_user()
+            .with(eq("new_proxy_user"))
+            .returning(|name| Ok(user(name)));
+
+        let r = app_header_auth(Arc::new(mock_db))
+            .oneshot(proxy_request("10.0.0.1:1234", "new_proxy_user"))
+            .await?;
+        assert_eq!(r.status(), StatusCode::OK);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn header_auth_from_untrusted_peer_is_rejected() -> Result {
+        let mock_db = MockDb::new();
+
+        let r = app_header_auth(Arc::new(mock_db))
+            .oneshot(proxy_request("192.168.0.1:1234", "admin"))
+            .await?;
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn app_not_required_auth_with_guarded_route() -> Result {
         let mock_db = MockDb::new();
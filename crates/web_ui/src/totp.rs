@@ -0,0 +1,262 @@
+use crate::audit::AuditContext;
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::{AppState, AppStateData, DbState};
+use auth::{audit, totp};
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use db::AuditAction;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+/// Time to enter the TOTP code after the password was accepted.
+pub const PENDING_LOGIN_MINUTES: i64 = 5;
+/// Number of consecutive wrong codes, after which no codes of the user are checked
+/// for `LOCKOUT_MINUTES`. This limits guessing, as the password alone is not enough.
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+const LOCKOUT_MINUTES: i64 = 15;
+
+/// Login of a user whose password was accepted, but who still has to enter a TOTP code.
+/// It is kept in a private cookie, such that it cannot be changed by the client.
+#[derive(Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub user: String,
+    pub expires: i64,
+}
+
+impl PendingLogin {
+    pub fn new(user: String) -> Self {
+        Self {
+            user,
+            expires: (Utc::now() + Duration::minutes(PENDING_LOGIN_MINUTES)).timestamp(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires < Utc::now().timestamp()
+    }
+}
+
+#[derive(Serialize)]
+pub struct TotpStatus {
+    enabled: bool,
+    /// TOTP can only be enabled if an encryption key is configured
+    available: bool,
+    required: bool,
+}
+
+pub async fn status(
+    user: MaybeUser,
+    State(state): AppState,
+) -> Result<Json<TotpStatus>, RouteError> {
+    let settings = &state.settings.totp;
+    // The user is no admin in the session, as long as TOTP is missing but required
+    let db_user = state.db.get_user(user.name()).await?;
+
+    Ok(Json(TotpStatus {
+        enabled: db_user.totp_enabled,
+        available: !settings.encryption_key.is_empty(),
+        required: settings.required_for_admins && db_user.is_admin,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct TotpSetup {
+    secret: String,
+    url: String,
+}
+
+/// Creates a new secret for the user. TOTP is enabled after a code for the secret was entered.
+pub async fn setup(user: MaybeUser, State(state): AppState) -> Result<Json<TotpSetup>, RouteError> {
+    let settings = &state.settings.totp;
+    if settings.encryption_key.is_empty() {
+        warn!("TOTP setup failed: no encryption key configured");
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+    if state.db.get_user(user.name()).await?.totp_enabled {
+        return Err(RouteError::Status(StatusCode::CONFLICT));
+    }
+
+    let secret = totp::generate_secret();
+    let url = totp::setup_url(&secret, &settings.issuer, user.name()).map_err(totp_failure)?;
+    let encrypted =
+        totp::encrypt_secret(&secret, &settings.encryption_key).map_err(totp_failure)?;
+    state.db.set_totp_secret(user.name(), &encrypted).await?;
+
+    Ok(Json(TotpSetup { secret, url }))
+}
+
+#[derive(Deserialize)]
+pub struct TotpCode {
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+/// Enables TOTP, if the code matches the secret created in the setup.
+/// The recovery codes are only shown once, as only their hashes are stored.
+pub async fn enable(
+    user: MaybeUser,
+    State(state): AppState,
+    Json(code): Json<TotpCode>,
+) -> Result<Json<RecoveryCodes>, RouteError> {
+    if state.db.get_user(user.name()).await?.totp_enabled {
+        return Err(RouteError::Status(StatusCode::CONFLICT));
+    }
+    let Some(secret) = secret(&state, user.name()).await? else {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    };
+    let step = totp::verify_code(&secret, &code.code);
+    if !matches!(step, Some(step) if use_step(&state, user.name(), step).await?) {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+
+    let recovery_codes = totp::generate_recovery_codes();
+    state.db.enable_totp(user.name(), &recovery_codes).await?;
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+/// Disables TOTP. A valid code or recovery code is needed, such that
+/// a stolen session is not enough to remove the second factor.
+pub async fn disable(
+    user: MaybeUser,
+    State(state): AppState,
+    Json(code): Json<TotpCode>,
+) -> Result<(), RouteError> {
+    if !verify(&state, user.name(), &code.code).await? {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+
+    state.db.disable_totp(user.name()).await?;
+    Ok(())
+}
+
+/// Disables TOTP for a user who lost the authenticator and the recovery codes.
+pub async fn reset(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<String>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    db.disable_totp(&name).await?;
+    let event = audit_ctx.event(&user, AuditAction::ResetTotp, &name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+/// Checks a TOTP code or a recovery code of a user with enabled TOTP.
+/// A recovery code is deleted when it is used. After too many wrong codes,
+/// the user is locked out of the check for a while.
+pub async fn verify(state: &AppStateData, user: &str, code: &str) -> Result<bool, RouteError> {
+    let since = Utc::now() - Duration::minutes(LOCKOUT_MINUTES);
+    if state.db.get_totp_failures(user, &since).await? >= MAX_FAILED_ATTEMPTS {
+        warn!("TOTP: too many wrong codes for user {user}");
+        return Err(RouteError::Status(StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    if check(state, user, code).await? {
+        state.db.reset_totp_failures(user).await?;
+        Ok(true)
+    } else {
+        state.db.add_totp_failure(user, &since).await?;
+        Ok(false)
+    }
+}
+
+async fn check(state: &AppStateData, user: &str, code: &str) -> Result<bool, RouteError> {
+    if !state.db.get_user(user).await?.totp_enabled {
+        return Ok(false);
+    }
+    // Recovery codes still work, if the secret cannot be decrypted
+    if let Ok(Some(secret)) = secret(state, user).await
+        && let Some(step) = totp::verify_code(&secret, code)
+    {
+        return use_step(state, user, step).await;
+    }
+
+    Ok(state.db.use_recovery_code(user, code.trim()).await?)
+}
+
+/// Marks the time step of the code as used. Returns `false`, if a code of the
+/// step or a later step was used already, such that an observed code cannot be replayed.
+async fn use_step(state: &AppStateData, user: &str, step: u64) -> Result<bool, RouteError> {
+    let step = i64::try_from(step).map_err(totp_failure)?;
+    Ok(state.db.use_totp_step(user, step).await?)
+}
+
+async fn secret(state: &AppStateData, user: &str) -> Result<Option<String>, RouteError> {
+    state
+        .db
+        .get_totp_secret(user)
+        .await?
+        .map(|encrypted| totp::decrypt_secret(&encrypted, &state.settings.totp.encryption_key))
+        .transpose()
+        .map_err(totp_failure)
+}
+
+fn totp_failure(reason: impl std::fmt::Display) -> RouteError {
+    error!("TOTP: {reason}");
+    RouteError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::User;
+    use db::mock::MockDb;
+    use std::sync::Arc;
+
+    fn state(mock_db: MockDb) -> AppStateData {
+        AppStateData {
+            db: Arc::new(mock_db),
+            ..appstate::test_state()
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_code_is_counted_as_failure() {
+        let mut mock_db = MockDb::new();
+        mock_db.expect_get_totp_failures().returning(|_, _| Ok(0));
+        mock_db.expect_get_user().returning(|name| {
+            Ok(User {
+                name: name.to_string(),
+                totp_enabled: true,
+                ..User::default()
+            })
+        });
+        mock_db.expect_get_totp_secret().returning(|_| Ok(None));
+        mock_db
+            .expect_use_recovery_code()
+            .returning(|_, _| Ok(false));
+        mock_db
+            .expect_add_totp_failure()
+            .times(1)
+            .returning(|_, _| Ok(1));
+
+        let valid = verify(&state(mock_db), "user", "123456").await.unwrap();
+
+        assert!(!valid);
+    }
+
+    #[tokio::test]
+    async fn codes_are_not_checked_after_too_many_failures() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_totp_failures()
+            .returning(|_, _| Ok(MAX_FAILED_ATTEMPTS));
+
+        let result = verify(&state(mock_db), "user", "123456").await;
+
+        assert!(matches!(
+            result,
+            Err(RouteError::Status(StatusCode::TOO_MANY_REQUESTS))
+        ));
+    }
+}
@@ -1,6 +1,8 @@
+use crate::audit::AuditContext;
 use crate::error::RouteError;
 use crate::session::MaybeUser;
 use appstate::{AppState, DbState, SettingsState};
+use auth::audit;
 use axum::{
     Json,
     extract::{Query, State},
@@ -12,6 +14,11 @@ use common::normalized_name::NormalizedName;
 use common::original_name::OriginalName;
 use common::version::Version;
 use db::error::DbError;
+use db::{AuditAction, WebhookEvent};
+use registry::cratesio_verify;
+use registry::license_policy::check_license;
+use registry::webhook;
+use serde_json::json;
 use settings::Settings;
 use tracing::error;
 
@@ -111,8 +118,14 @@ pub struct CratesIoDataParams {
     name: OriginalName,
 }
 
-pub async fn cratesio_data(Query(params): Query<CratesIoDataParams>) -> Result<String, StatusCode> {
-    let url = format!("https://crates.io/api/v1/crates/{}", params.name);
+pub async fn cratesio_data(
+    Query(params): Query<CratesIoDataParams>,
+    State(settings): SettingsState,
+) -> Result<String, StatusCode> {
+    if settings.proxy.offline {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let url = settings.proxy.crate_api_url(&params.name);
 
     let client = reqwest::Client::new();
     let req = client
@@ -155,6 +168,7 @@ pub struct DeleteCrateVersionParams {
 pub async fn delete_version(
     Query(params): Query<DeleteCrateVersionParams>,
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(state): AppState,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
@@ -176,6 +190,17 @@ pub async fn delete_version(
         return Err(RouteError::Status(StatusCode::INTERNAL_SERVER_ERROR));
     }
 
+    let event = audit_ctx
+        .event(&user, AuditAction::DeleteVersion, name.to_normalized())
+        .before(json!({ "version": version }));
+    audit::record(state.db.as_ref(), event).await;
+    webhook::notify(
+        state.db.as_ref(),
+        WebhookEvent::CrateDeleted,
+        &name.to_normalized(),
+        Some(&version),
+    )
+    .await;
     Ok(())
 }
 
@@ -187,6 +212,7 @@ pub struct DeleteCrateParams {
 pub async fn delete_crate(
     Query(params): Query<DeleteCrateParams>,
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(state): AppState,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
@@ -212,6 +238,18 @@ pub async fn delete_crate(
         }
     }
 
+    let versions: Vec<&str> = crate_meta.iter().map(|cm| cm.version.as_str()).collect();
+    let event = audit_ctx
+        .event(&user, AuditAction::DeleteCrate, name.to_normalized())
+        .before(json!({ "versions": versions }));
+    audit::record(state.db.as_ref(), event).await;
+    webhook::notify(
+        state.db.as_ref(),
+        WebhookEvent::CrateDeleted,
+        &name.to_normalized(),
+        None,
+    )
+    .await;
     Ok(())
 }
 
@@ -226,6 +264,8 @@ pub struct Statistic {
     pub top_crates: TopCrates,
     pub last_updated_crate: Option<(OriginalName, Version)>,
     pub proxy_enabled: bool,
+    /// Only cached crates are served by the proxy
+    pub proxy_offline: bool,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
@@ -273,9 +313,72 @@ pub async fn statistic(State(db): DbState, State(settings): SettingsState) -> Js
         },
         last_updated_crate,
         proxy_enabled: settings.proxy.enabled,
+        proxy_offline: settings.proxy.offline,
     })
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LicenseViolation {
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    pub reason: String,
+}
+
+/// Lists all crate versions which would be rejected by the current license policy.
+pub async fn license_violations(
+    user: MaybeUser,
+    State(db): DbState,
+    State(settings): SettingsState,
+) -> Result<Json<Vec<LicenseViolation>>, RouteError> {
+    user.assert_admin()?;
+
+    if !settings.policy.license_policy_enabled() {
+        return Ok(Json(Vec::new()));
+    }
+
+    let violations = db
+        .get_crate_licenses()
+        .await?
+        .into_iter()
+        .filter_map(|c| {
+            check_license(c.license.as_deref(), &settings.policy)
+                .err()
+                .map(|e| LicenseViolation {
+                    name: c.name,
+                    version: c.version,
+                    license: c.license,
+                    reason: e.to_string(),
+                })
+        })
+        .collect();
+
+    Ok(Json(violations))
+}
+
+/// Starts a job which verifies all cached crates.io crates against the index and
+/// removes mismatching ones. The job runs in the background, as it reads every
+/// crate file, and logs its result.
+pub async fn verify_cratesio_cache(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(state): AppState,
+) -> Result<StatusCode, RouteError> {
+    user.assert_admin()?;
+
+    let event = audit_ctx.event(&user, AuditAction::VerifyCratesioCache, "crates.io");
+    audit::record(state.db.as_ref(), event).await;
+
+    tokio::spawn(async move {
+        if let Err(e) =
+            cratesio_verify::verify_cached_crates(state.db.as_ref(), &state.cratesio_storage).await
+        {
+            error!("Failed to verify cached crates.io crates: {e}");
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct BuildParams {
     package: OriginalName,
@@ -286,6 +389,7 @@ pub async fn build_rustdoc(
     Query(params): Query<BuildParams>,
     State(state): AppState,
     user: MaybeUser,
+    audit_ctx: AuditContext,
 ) -> Result<(), StatusCode> {
     let normalized_name = NormalizedName::from(params.package);
     let db = state.db;
@@ -310,9 +414,9 @@ pub async fn build_rustdoc(
 
     // If the user is the owner of the crate or any admin user,
     // the build operation is allowed.
-    let is_allowed = match user {
+    let is_allowed = match &user {
         MaybeUser::Normal(user) => db
-            .is_owner(&normalized_name, &user)
+            .is_owner(&normalized_name, user)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
         MaybeUser::Admin(_) => true,
@@ -335,6 +439,10 @@ pub async fn build_rustdoc(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let event = audit_ctx
+        .event(&user, AuditAction::BuildDocs, &normalized_name)
+        .after(json!({ "version": version }));
+    audit::record(db.as_ref(), event).await;
     Ok(())
 }
 
@@ -429,6 +537,113 @@ mod tests {
         assert_eq!(result_state, expected_state);
     }
 
+    #[tokio::test]
+    async fn license_violations_returns_violating_crates() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .returning(|_| Ok(("admin".to_string(), true)));
+        mock_db.expect_get_crate_licenses().returning(|| {
+            Ok(vec![
+                db::CrateLicense {
+                    name: "allowed".to_string(),
+                    version: "1.0.0".to_string(),
+                    license: Some("MIT OR Apache-2.0".to_string()),
+                },
+                db::CrateLicense {
+                    name: "denied".to_string(),
+                    version: "1.0.0".to_string(),
+                    license: Some("MIT AND GPL-3.0-only".to_string()),
+                },
+            ])
+        });
+
+        let (mut settings, storage) = test_deps();
+        settings.policy.allowed_licenses = vec!["MIT".to_string()];
+        let r = app(
+            mock_db,
+            KellnrCrateStorage::new(&settings, storage),
+            settings,
+        )
+        .oneshot(
+            Request::get("/license_violations")
+                .header(
+                    header::COOKIE,
+                    encode_cookies([(constants::COOKIE_SESSION_ID, "cookie")]),
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let result_status = r.status();
+        let result_msg = r.into_body().collect().await.unwrap().to_bytes();
+        let violations = serde_json::from_slice::<Vec<LicenseViolation>>(&result_msg).unwrap();
+
+        assert_eq!(result_status, StatusCode::OK);
+        assert_eq!(1, violations.len());
+        assert_eq!("denied", violations[0].name);
+        assert!(violations[0].reason.contains("GPL-3.0-only"));
+    }
+
+    #[tokio::test]
+    async fn verify_cratesio_cache_no_admin_returns_forbidden() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .returning(|_| Ok(("user".to_string(), false)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
+
+        let (settings, storage) = test_deps();
+        let r = app(
+            mock_db,
+            KellnrCrateStorage::new(&settings, storage),
+            settings,
+        )
+        .oneshot(
+            Request::post("/verify_cratesio_cache")
+                .header(
+                    header::COOKIE,
+                    encode_cookies([(constants::COOKIE_SESSION_ID, "cookie")]),
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+
+    #[tokio::test]
+    async fn license_violations_no_admin_returns_forbidden() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_validate_session()
+            .returning(|_| Ok(("user".to_string(), false)));
+
+        let (settings, storage) = test_deps();
+        let r = app(
+            mock_db,
+            KellnrCrateStorage::new(&settings, storage),
+            settings,
+        )
+        .oneshot(
+            Request::get("/license_violations")
+                .header(
+                    header::COOKIE,
+                    encode_cookies([(constants::COOKIE_SESSION_ID, "cookie")]),
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+    }
+
     #[tokio::test]
     async fn build_rust_doc_crate_not_found() {
         let mut mock_db = MockDb::new();
@@ -440,6 +655,7 @@ mod tests {
             .expect_validate_session()
             .with(eq("cookie"))
             .returning(move |_| Ok(("user".to_string(), false)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
         let (settings, storage) = test_deps();
         let r = app(
             mock_db,
@@ -474,6 +690,7 @@ mod tests {
             .expect_validate_session()
             .with(eq("cookie"))
             .returning(move |_| Ok(("user".to_string(), false)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
         mock_db
             .expect_crate_version_exists()
             .with(eq(1), eq("1.0.0"))
@@ -512,6 +729,7 @@ mod tests {
             .expect_validate_session()
             .with(eq("cookie"))
             .returning(move |_| Ok(("user".to_string(), false)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
         mock_db
             .expect_crate_version_exists()
             .with(eq(1), eq("1.0.0"))
@@ -534,6 +752,8 @@ mod tests {
                     salt: String::new(),
                     is_admin: false,
                     is_read_only: false,
+                    totp_enabled: false,
+                    is_service_account: false,
                 })
             });
         let (settings, storage) = test_deps();
@@ -570,6 +790,7 @@ mod tests {
             .expect_validate_session()
             .with(eq("cookie"))
             .returning(move |_| Ok(("user".to_string(), false)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
         mock_db
             .expect_crate_version_exists()
             .with(eq(1), eq("1.0.0"))
@@ -592,6 +813,8 @@ mod tests {
                     salt: String::new(),
                     is_admin: false,
                     is_read_only: false,
+                    totp_enabled: false,
+                    is_service_account: false,
                 })
             });
         mock_db
@@ -603,6 +826,11 @@ mod tests {
             )
             .times(1)
             .returning(move |_, _, _| Ok(()));
+        mock_db
+            .expect_add_audit_entry()
+            .withf(|event| event.action == AuditAction::BuildDocs && event.target == "foobar")
+            .times(1)
+            .returning(|_| Ok(()));
 
         let (settings, storage) = test_deps();
         let r = app(
@@ -638,6 +866,7 @@ mod tests {
             .expect_validate_session()
             .with(eq("cookie"))
             .returning(move |_| Ok(("user".to_string(), true)));
+        mock_db.expect_get_session_id().returning(|_| Ok(1));
         mock_db
             .expect_crate_version_exists()
             .with(eq(1), eq("1.0.0"))
@@ -660,6 +889,8 @@ mod tests {
                     salt: String::new(),
                     is_admin: true,
                     is_read_only: false,
+                    totp_enabled: false,
+                    is_service_account: false,
                 })
             });
         mock_db
@@ -671,6 +902,11 @@ mod tests {
             )
             .times(1)
             .returning(move |_, _, _| Ok(()));
+        mock_db
+            .expect_add_audit_entry()
+            .withf(|event| event.action == AuditAction::BuildDocs && event.target == "foobar")
+            .times(1)
+            .returning(|_| Ok(()));
 
         let (settings, storage) = test_deps();
         let r = app(
@@ -751,6 +987,7 @@ mod tests {
             },
             last_updated_crate: None,
             proxy_enabled: false,
+            proxy_offline: false,
         };
 
         assert_eq!(expect, result_stat);
@@ -812,6 +1049,7 @@ mod tests {
             },
             last_updated_crate: None,
             proxy_enabled: false,
+            proxy_offline: false,
         };
 
         assert_eq!(expect, result_stat);
@@ -885,6 +1123,7 @@ mod tests {
                 Version::try_from("1.0.0").unwrap(),
             )),
             proxy_enabled: false,
+            proxy_offline: false,
         };
         assert_eq!(expect, result_stat);
     }
@@ -1223,6 +1462,8 @@ mod tests {
             .route("/build", post(build_rustdoc))
             .route("/cratesio_data", get(cratesio_data))
             .route("/settings", get(crate::ui::settings))
+            .route("/license_violations", get(license_violations))
+            .route("/verify_cratesio_cache", post(verify_cratesio_cache))
             .with_state(AppStateData {
                 db: Arc::new(mock_db),
                 signing_key: Key::from(TEST_KEY),
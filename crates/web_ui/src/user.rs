@@ -1,18 +1,29 @@
+use crate::audit::AuditContext;
 use crate::error::RouteError;
-use crate::session::MaybeUser;
-use appstate::{AppState, DbState};
-use auth::token;
+use crate::oidc;
+use crate::session::{MaybeUser, session_cookie_with_age};
+use crate::totp;
+use appstate::rate_limit::{Lockout, LockoutKind};
+use appstate::{AppState, AppStateData, DbState, RateLimiterState, SettingsState};
+use auth::{audit, token};
 use axum::Json;
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::http::header::USER_AGENT;
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::Redirect;
 use axum_extra::extract::PrivateCookieJar;
-use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use chrono::Utc;
 use common::util::generate_rand_string;
 use cookie::time;
-use db::password::generate_salt;
-use db::{self, AuthToken, User};
+use db::error::DbError;
+use db::{self, AuditAction, AuthToken, DbProvider, ExternalIdentity, Session, User};
 use serde::{Deserialize, Serialize};
-use settings::constants::{COOKIE_SESSION_ID, COOKIE_SESSION_USER};
+use serde_json::json;
+use settings::constants::{
+    COOKIE_OIDC_STATE, COOKIE_SESSION_ID, COOKIE_SESSION_USER, COOKIE_TOTP_LOGIN,
+};
+use tracing::warn;
 
 #[derive(Serialize)]
 pub struct NewTokenResponse {
@@ -22,18 +33,44 @@ pub struct NewTokenResponse {
 
 pub async fn add_token(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(db): DbState,
     Json(auth_token): Json<token::NewTokenReqData>,
 ) -> Result<Json<NewTokenResponse>, RouteError> {
+    let token = create_token(db.as_ref(), user.name(), auth_token).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddToken, user.name())
+        .after(json!({ "token": token.name }));
+    audit::record(db.as_ref(), event).await;
+
+    Ok(token.into())
+}
+
+/// Creates a cargo token of a user or service account.
+pub(crate) async fn create_token(
+    db: &dyn DbProvider,
+    user: &str,
+    auth_token: token::NewTokenReqData,
+) -> Result<NewTokenResponse, RouteError> {
+    let expires_in_past = auth_token.expires.is_some_and(|e| e <= Utc::now());
+    if !auth_token.scopes.is_valid() || expires_in_past {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+
     let token = token::generate_token();
-    db.add_auth_token(&auth_token.name, &token, user.name())
-        .await?;
+    db.add_auth_token(
+        &auth_token.name,
+        &token,
+        user,
+        &auth_token.scopes,
+        auth_token.expires,
+    )
+    .await?;
 
     Ok(NewTokenResponse {
-        name: auth_token.name.clone(),
+        name: auth_token.name,
         token,
-    }
-    .into())
+    })
 }
 
 pub async fn list_tokens(
@@ -54,10 +91,25 @@ pub async fn list_users(
 
 pub async fn delete_token(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path(id): Path<i32>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
-    db.get_auth_tokens(user.name())
+    remove_token(db.as_ref(), user.name(), id).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::DeleteToken, user.name())
+        .before(json!({ "token_id": id }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+/// Deletes a cargo token, if it belongs to the user or service account.
+pub(crate) async fn remove_token(
+    db: &dyn DbProvider,
+    user: &str,
+    id: i32,
+) -> Result<(), RouteError> {
+    db.get_auth_tokens(user)
         .await?
         .iter()
         .find(|t| t.id == id)
@@ -74,13 +126,19 @@ pub struct ResetPwd {
 
 pub async fn reset_pwd(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path(name): Path<String>,
     State(db): DbState,
 ) -> Result<Json<ResetPwd>, RouteError> {
     user.assert_admin()?;
+    if db.get_user(&name).await?.is_service_account {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
 
     let new_pwd = generate_rand_string(12);
     db.change_pwd(&name, &new_pwd).await?;
+    let event = audit_ctx.event(&user, AuditAction::ResetPassword, &name);
+    audit::record(db.as_ref(), event).await;
 
     Ok(ResetPwd {
         user: user.name().to_owned(),
@@ -96,23 +154,92 @@ pub struct ReadOnlyState {
 
 pub async fn read_only(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path(name): Path<String>,
     State(db): DbState,
     Json(ro_state): Json<ReadOnlyState>,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
 
-    Ok(db.change_read_only_state(&name, ro_state.state).await?)
+    let before = db.get_user(&name).await?.is_read_only;
+    db.change_read_only_state(&name, ro_state.state).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::ChangeReadOnly, &name)
+        .before(json!({ "read_only": before }))
+        .after(json!({ "read_only": ro_state.state }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+/// Identities of external providers, e.g. OIDC, which can log in as the user.
+pub async fn list_identities(
+    user: MaybeUser,
+    Path(name): Path<String>,
+    State(db): DbState,
+) -> Result<Json<Vec<ExternalIdentity>>, RouteError> {
+    user.assert_admin()?;
+
+    Ok(Json(db.get_external_identities(&name).await?))
+}
+
+/// Links an identity of an external provider to an existing user. Without
+/// the link, the provider cannot log in as a user which already exists.
+pub async fn add_identity(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<String>,
+    State(db): DbState,
+    Json(identity): Json<ExternalIdentity>,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+    if identity.issuer.is_empty() || identity.subject.is_empty() {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+    if db
+        .get_external_identity_user(&identity.issuer, &identity.subject)
+        .await?
+        .is_some()
+    {
+        return Err(RouteError::Status(StatusCode::CONFLICT));
+    }
+
+    db.add_external_identity(&name, &identity).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddExternalIdentity, &name)
+        .after(json!(identity));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+pub async fn delete_identity(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<String>,
+    State(db): DbState,
+    Json(identity): Json<ExternalIdentity>,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    db.delete_external_identity(&name, &identity).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::DeleteExternalIdentity, &name)
+        .before(json!(identity));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
 
 pub async fn delete(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     Path(name): Path<String>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     user.assert_admin()?;
 
-    Ok(db.delete_user(&name).await?)
+    db.delete_user(&name).await?;
+    let event = audit_ctx.event(&user, AuditAction::DeleteUser, &name);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
 
 #[derive(Serialize)]
@@ -120,6 +247,8 @@ pub struct LoggedInUser {
     user: String,
     is_admin: bool,
     is_logged_in: bool,
+    /// The password was accepted, but the TOTP code is still missing
+    totp_required: bool,
 }
 
 #[derive(Deserialize)]
@@ -142,43 +271,245 @@ impl Credentials {
 
 pub async fn login(
     cookies: PrivateCookieJar,
+    headers: HeaderMap,
+    extensions: Extensions,
     State(state): AppState,
     Json(credentials): Json<Credentials>,
 ) -> Result<(PrivateCookieJar, Json<LoggedInUser>), RouteError> {
     credentials.validate()?;
+    let client_ip = token::client_ip(&state, &headers, &extensions);
+    let rate_limiter = &state.rate_limiter;
+    rate_limiter.check_login(client_ip, Some(&credentials.user))?;
 
     let user = state
         .db
         .authenticate_user(&credentials.user, &credentials.pwd)
         .await
-        .map_err(|_| RouteError::AuthenticationFailure)?;
+        .map_err(|_| {
+            rate_limiter.login_failed(client_ip, Some(&credentials.user));
+            RouteError::AuthenticationFailure
+        })?;
+
+    if user.totp_enabled {
+        let jar = start_totp_login(cookies, &user.name)?;
+        return Ok((
+            jar,
+            LoggedInUser {
+                user: user.name,
+                is_admin: false,
+                is_logged_in: false,
+                totp_required: true,
+            }
+            .into(),
+        ));
+    }
 
-    let session_token = generate_rand_string(12);
-    state
-        .db
-        .add_session_token(&credentials.user, &session_token)
-        .await?;
+    rate_limiter.login_succeeded(client_ip, &user.name);
+    let jar = start_session(&state, cookies, &user.name, &headers).await?;
+    Ok((
+        jar,
+        LoggedInUser {
+            user: user.name,
+            is_admin: user.is_admin,
+            is_logged_in: true,
+            totp_required: false,
+        }
+        .into(),
+    ))
+}
 
-    let jar = cookies.add(
-        Cookie::build((COOKIE_SESSION_ID, session_token))
-            .max_age(time::Duration::seconds(
-                state.settings.registry.session_age_seconds as i64,
-            ))
-            .same_site(axum_extra::extract::cookie::SameSite::Strict)
-            .path("/"),
-    );
+/// Second step of the login for users with TOTP, after the password was accepted.
+pub async fn login_totp(
+    cookies: PrivateCookieJar,
+    headers: HeaderMap,
+    extensions: Extensions,
+    State(state): AppState,
+    Json(code): Json<totp::TotpCode>,
+) -> Result<(PrivateCookieJar, Json<LoggedInUser>), RouteError> {
+    let pending: totp::PendingLogin = cookies
+        .get(COOKIE_TOTP_LOGIN)
+        .and_then(|c| serde_json::from_str(c.value()).ok())
+        .filter(|p: &totp::PendingLogin| !p.is_expired())
+        .ok_or(RouteError::AuthenticationFailure)?;
+
+    let client_ip = token::client_ip(&state, &headers, &extensions);
+    let rate_limiter = &state.rate_limiter;
+    rate_limiter.check_login(client_ip, Some(&pending.user))?;
+    if !totp::verify(&state, &pending.user, &code.code).await? {
+        rate_limiter.login_failed(client_ip, Some(&pending.user));
+        return Err(RouteError::AuthenticationFailure);
+    }
 
+    rate_limiter.login_succeeded(client_ip, &pending.user);
+    let user = state.db.get_user(&pending.user).await?;
+    let jar = cookies.remove(Cookie::build(COOKIE_TOTP_LOGIN).path("/"));
+    let jar = start_session(&state, jar, &user.name, &headers).await?;
     Ok((
         jar,
         LoggedInUser {
-            user: credentials.user.clone(),
+            user: user.name,
             is_admin: user.is_admin,
             is_logged_in: true,
+            totp_required: false,
         }
         .into(),
     ))
 }
 
+/// Remembers the user whose first factor was accepted, until the TOTP code is entered.
+fn start_totp_login(cookies: PrivateCookieJar, name: &str) -> Result<PrivateCookieJar, RouteError> {
+    let pending = serde_json::to_string(&totp::PendingLogin::new(name.to_string()))
+        .map_err(|_| RouteError::Status(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok(cookies.add(
+        Cookie::build((COOKIE_TOTP_LOGIN, pending))
+            .max_age(time::Duration::minutes(totp::PENDING_LOGIN_MINUTES))
+            .same_site(SameSite::Strict)
+            .http_only(true)
+            .path("/"),
+    ))
+}
+
+async fn start_session(
+    state: &AppStateData,
+    jar: PrivateCookieJar,
+    name: &str,
+    headers: &HeaderMap,
+) -> Result<PrivateCookieJar, RouteError> {
+    let session_token = generate_rand_string(12);
+    state
+        .db
+        .add_session_token(name, &session_token, user_agent(headers))
+        .await?;
+
+    Ok(jar.add(session_cookie_with_age(
+        session_token,
+        state.settings.registry.session_age_seconds,
+    )))
+}
+
+#[derive(Serialize)]
+pub struct OidcInfo {
+    enabled: bool,
+}
+
+#[expect(clippy::unused_async)] // part of the router
+pub async fn oidc_info(State(settings): SettingsState) -> Json<OidcInfo> {
+    Json(OidcInfo {
+        enabled: settings.oidc.enabled,
+    })
+}
+
+/// Starts the login with the OIDC provider.
+pub async fn oidc_login(
+    jar: PrivateCookieJar,
+    State(settings): SettingsState,
+) -> Result<(PrivateCookieJar, Redirect), RouteError> {
+    if !settings.oidc.enabled {
+        return Err(RouteError::Status(StatusCode::NOT_FOUND));
+    }
+
+    let metadata = oidc::discover(&settings.oidc.issuer_url)
+        .await
+        .map_err(oidc_failure)?;
+    let login = oidc::LoginState::new();
+    let url = oidc::authorization_url(&metadata, &settings.oidc, &login).map_err(oidc_failure)?;
+    let login = serde_json::to_string(&login).map_err(oidc_failure)?;
+
+    // Lax, as the provider redirects back to Kellnr from another site
+    let jar = jar.add(
+        Cookie::build((COOKIE_OIDC_STATE, login))
+            .max_age(time::Duration::minutes(10))
+            .same_site(SameSite::Lax)
+            .http_only(true)
+            .path("/"),
+    );
+    Ok((jar, Redirect::to(&url)))
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Finishes the login with the OIDC provider. The user is created
+/// on the first login and logged in with a normal Kellnr session.
+pub async fn oidc_callback(
+    jar: PrivateCookieJar,
+    headers: HeaderMap,
+    State(state): AppState,
+    Query(callback): Query<OidcCallback>,
+) -> Result<(PrivateCookieJar, Redirect), RouteError> {
+    let settings = &state.settings.oidc;
+    if !settings.enabled {
+        return Err(RouteError::Status(StatusCode::NOT_FOUND));
+    }
+
+    let login: oidc::LoginState = jar
+        .get(COOKIE_OIDC_STATE)
+        .and_then(|c| serde_json::from_str(c.value()).ok())
+        .ok_or_else(|| oidc_failure("No login in progress"))?;
+    let jar = jar.remove(Cookie::build(COOKIE_OIDC_STATE).path("/"));
+
+    if let Some(error) = callback.error {
+        return Err(oidc_failure(format!("Provider returned error: {error}")));
+    }
+    if callback.state.as_deref() != Some(login.state.as_str()) {
+        return Err(oidc_failure("State does not match"));
+    }
+    let code = callback
+        .code
+        .ok_or_else(|| oidc_failure("No authorization code"))?;
+
+    let metadata = oidc::discover(&settings.issuer_url)
+        .await
+        .map_err(oidc_failure)?;
+    let id_token = oidc::exchange_code(&metadata, settings, &code, &login.verifier)
+        .await
+        .map_err(oidc_failure)?;
+    let claims = oidc::verify_id_token(&id_token, &metadata, settings, &login.nonce)
+        .await
+        .map_err(oidc_failure)?;
+    let name = oidc::username(&claims, settings)
+        .ok_or_else(|| oidc_failure(format!("Claim {} not found", settings.username_claim)))?;
+    let identity =
+        oidc::identity(&claims, &metadata).ok_or_else(|| oidc_failure("Claim sub not found"))?;
+
+    let groups = oidc::groups(&claims, settings);
+    let name = oidc::external_user(name, identity, &groups, settings)
+        .sync(state.db.as_ref())
+        .await
+        .map_err(|e| match e {
+            DbError::ExternalIdentityNotLinked(_) | DbError::ServiceAccountLogin(_) => {
+                oidc_failure(e)
+            }
+            e => e.into(),
+        })?;
+
+    // The provider replaces the password, but not the second factor
+    let user = state.db.get_user(&name).await?;
+    if user.totp_enabled {
+        let jar = start_totp_login(jar, &user.name)?;
+        return Ok((jar, Redirect::to("/login?totp=true")));
+    }
+
+    let jar = start_session(&state, jar, &name, &headers).await?;
+    Ok((jar, Redirect::to("/")))
+}
+
+fn oidc_failure(reason: impl std::fmt::Display) -> RouteError {
+    warn!("OIDC login failed: {reason}");
+    RouteError::Status(StatusCode::UNAUTHORIZED)
+}
+
+fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(USER_AGENT)
+        .and_then(|ua| ua.to_str().ok())
+        .map(ToString::to_string)
+}
+
 #[expect(clippy::unused_async)] // part of the router
 pub async fn login_state(user: Option<MaybeUser>) -> Json<LoggedInUser> {
     match user {
@@ -186,16 +517,19 @@ pub async fn login_state(user: Option<MaybeUser>) -> Json<LoggedInUser> {
             user,
             is_admin: false,
             is_logged_in: true,
+            totp_required: false,
         },
         Some(MaybeUser::Admin(user)) => LoggedInUser {
             user,
             is_admin: true,
             is_logged_in: true,
+            totp_required: false,
         },
         None => LoggedInUser {
             user: String::new(),
             is_admin: false,
             is_logged_in: false,
+            totp_required: false,
         },
     }
     .into()
@@ -217,6 +551,76 @@ pub async fn logout(
     Ok(jar)
 }
 
+pub async fn list_sessions(
+    user: MaybeUser,
+    jar: PrivateCookieJar,
+    State(db): DbState,
+) -> Result<Json<Vec<Session>>, RouteError> {
+    let current = jar
+        .get(COOKIE_SESSION_ID)
+        .map(|c| c.value().to_owned())
+        .unwrap_or_default();
+
+    Ok(Json(db.get_sessions(user.name(), &current).await?))
+}
+
+pub async fn delete_session(
+    user: MaybeUser,
+    Path(id): Path<i64>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    db.delete_session(user.name(), id)
+        .await
+        .map_err(|_| RouteError::Status(StatusCode::BAD_REQUEST))
+}
+
+/// Logs out the user on all devices
+pub async fn logout_user(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(name): Path<String>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    let sessions = db.delete_user_sessions(&name).await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::LogoutUser, &name)
+        .before(json!({ "sessions": sessions }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+/// Lists the IP addresses and accounts, which are locked out after too many failed logins
+#[expect(clippy::unused_async)] // part of the router
+pub async fn list_lockouts(
+    user: MaybeUser,
+    State(rate_limiter): RateLimiterState,
+) -> Result<Json<Vec<Lockout>>, RouteError> {
+    user.assert_admin()?;
+
+    Ok(Json(rate_limiter.lockouts()))
+}
+
+pub async fn clear_lockout(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path((kind, key)): Path<(LockoutKind, String)>,
+    State(rate_limiter): RateLimiterState,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    if !rate_limiter.clear_lockout(kind, &key) {
+        return Err(RouteError::Status(StatusCode::NOT_FOUND));
+    }
+    let event = audit_ctx
+        .event(&user, AuditAction::ClearLockout, &key)
+        .before(json!({ "kind": kind }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
 #[derive(Deserialize)]
 pub struct PwdChange {
     pub old_pwd: String,
@@ -241,16 +645,23 @@ impl PwdChange {
 
 pub async fn change_pwd(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(db): DbState,
     Json(pwd_change): Json<PwdChange>,
 ) -> Result<(), RouteError> {
     pwd_change.validate()?;
 
-    let Ok(user) = db.authenticate_user(user.name(), &pwd_change.old_pwd).await else {
+    if db
+        .authenticate_user(user.name(), &pwd_change.old_pwd)
+        .await
+        .is_err()
+    {
         return Err(RouteError::Status(StatusCode::BAD_REQUEST));
-    };
+    }
 
-    db.change_pwd(&user.name, &pwd_change.new_pwd1).await?;
+    db.change_pwd(user.name(), &pwd_change.new_pwd1).await?;
+    let event = audit_ctx.event(&user, AuditAction::ChangePassword, user.name());
+    audit::record(db.as_ref(), event).await;
     Ok(())
 }
 
@@ -282,6 +693,7 @@ impl NewUser {
 
 pub async fn add(
     user: MaybeUser,
+    audit_ctx: AuditContext,
     State(db): DbState,
     Json(new_user): Json<NewUser>,
 ) -> Result<(), RouteError> {
@@ -289,14 +701,16 @@ pub async fn add(
 
     new_user.validate()?;
 
-    let salt = generate_salt();
-    Ok(db
-        .add_user(
-            &new_user.name,
-            &new_user.pwd1,
-            &salt,
-            new_user.is_admin,
-            new_user.is_read_only,
-        )
-        .await?)
+    db.add_user(
+        &new_user.name,
+        &new_user.pwd1,
+        new_user.is_admin,
+        new_user.is_read_only,
+    )
+    .await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddUser, &new_user.name)
+        .after(json!({ "admin": new_user.is_admin, "read_only": new_user.is_read_only }));
+    audit::record(db.as_ref(), event).await;
+    Ok(())
 }
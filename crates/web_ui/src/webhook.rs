@@ -0,0 +1,251 @@
+use crate::audit::AuditContext;
+use crate::error::RouteError;
+use crate::session::MaybeUser;
+use appstate::DbState;
+use auth::{audit, token};
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use db::{AuditAction, DeliveryStatus, Webhook, WebhookDelivery, WebhookEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const MAX_PAGE_SIZE: u64 = 100;
+
+pub async fn list(user: MaybeUser, State(db): DbState) -> Result<Json<Vec<Webhook>>, RouteError> {
+    user.assert_admin()?;
+    Ok(Json(db.get_webhooks().await?))
+}
+
+#[derive(Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Generated if not set
+    #[serde(default)]
+    pub secret: Option<String>,
+    pub events: Vec<WebhookEvent>,
+    #[serde(default = "all_crates")]
+    pub crate_pattern: String,
+}
+
+fn all_crates() -> String {
+    "*".to_string()
+}
+
+impl NewWebhook {
+    pub fn validate(&self) -> Result<(), RouteError> {
+        let valid_url = reqwest::Url::parse(&self.url)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !valid_url || self.events.is_empty() || self.crate_pattern.is_empty() {
+            return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+        }
+        Ok(())
+    }
+}
+
+/// The secret is only returned once, as it is needed to verify the signatures.
+#[derive(Serialize)]
+pub struct NewWebhookResponse {
+    id: i64,
+    secret: String,
+}
+
+pub async fn add(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    State(db): DbState,
+    Json(new_webhook): Json<NewWebhook>,
+) -> Result<Json<NewWebhookResponse>, RouteError> {
+    user.assert_admin()?;
+    new_webhook.validate()?;
+
+    let secret = new_webhook
+        .secret
+        .filter(|secret| !secret.is_empty())
+        .unwrap_or_else(token::generate_token);
+    let id = db
+        .add_webhook(
+            &new_webhook.url,
+            &secret,
+            &new_webhook.events,
+            &new_webhook.crate_pattern,
+        )
+        .await?;
+    let event = audit_ctx
+        .event(&user, AuditAction::AddWebhook, &new_webhook.url)
+        .after(json!({ "events": new_webhook.events, "crate_pattern": new_webhook.crate_pattern }));
+    audit::record(db.as_ref(), event).await;
+
+    Ok(Json(NewWebhookResponse { id, secret }))
+}
+
+pub async fn delete(
+    user: MaybeUser,
+    audit_ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    db.delete_webhook(id).await?;
+    let event = audit_ctx.event(&user, AuditAction::DeleteWebhook, id);
+    audit::record(db.as_ref(), event).await;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct DeliveryParams {
+    status: Option<DeliveryStatus>,
+    page: Option<u64>,
+    page_size: Option<u64>,
+}
+
+/// Returns the deliveries of the webhook, newest first.
+pub async fn deliveries(
+    user: MaybeUser,
+    Path(id): Path<i64>,
+    Query(params): Query<DeliveryParams>,
+    State(db): DbState,
+) -> Result<Json<Vec<WebhookDelivery>>, RouteError> {
+    user.assert_admin()?;
+
+    let page_size = params.page_size.unwrap_or(20).clamp(1, MAX_PAGE_SIZE);
+    let offset = params
+        .page
+        .unwrap_or(0)
+        .checked_mul(page_size)
+        .ok_or(RouteError::Status(StatusCode::BAD_REQUEST))?;
+    Ok(Json(
+        db.get_webhook_deliveries(id, params.status, page_size, offset)
+            .await?,
+    ))
+}
+
+/// Queues the delivery to be sent again, e.g. after a failed delivery.
+pub async fn retry(
+    user: MaybeUser,
+    Path(id): Path<i64>,
+    State(db): DbState,
+) -> Result<(), RouteError> {
+    user.assert_admin()?;
+
+    db.retry_webhook_delivery(id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::{admin_db, encode_cookies, test_app};
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use axum::routing::{get, post};
+    use db::mock::MockDb;
+    use http_body_util::BodyExt;
+    use mockall::predicate::*;
+    use settings::constants::COOKIE_SESSION_ID;
+    use tower::ServiceExt;
+
+    fn app(mock_db: MockDb) -> Router {
+        test_app(
+            mock_db,
+            Router::new()
+                .route("/", post(add))
+                .route("/{id}/deliveries", get(deliveries)),
+        )
+    }
+
+    fn add_request(body: &str) -> Request<Body> {
+        Request::post("/")
+            .header(
+                header::COOKIE,
+                encode_cookies([(COOKIE_SESSION_ID, "token")]),
+            )
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn add_generates_secret() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_add_webhook()
+            .with(
+                eq("https://ci.example.com/hook"),
+                always(),
+                eq(vec![WebhookEvent::CratePublished]),
+                eq("*"),
+            )
+            .returning(|_, _, _, _| Ok(1));
+        mock_db.expect_add_audit_entry().returning(|_| Ok(()));
+
+        let r = app(mock_db)
+            .oneshot(add_request(
+                r#"{"url":"https://ci.example.com/hook","events":["crate_published"]}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(1, response["id"]);
+        assert!(!response["secret"].as_str().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_rejects_invalid_url() {
+        let r = app(admin_db())
+            .oneshot(add_request(
+                r#"{"url":"ftp://ci.example.com","events":["crate_published"]}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+
+    #[tokio::test]
+    async fn deliveries_are_filtered_by_status() {
+        let mut mock_db = admin_db();
+        mock_db
+            .expect_get_webhook_deliveries()
+            .with(eq(3), eq(Some(DeliveryStatus::Failed)), eq(10), eq(20))
+            .returning(|_, _, _, _| Ok(vec![]));
+
+        let r = app(mock_db)
+            .oneshot(
+                Request::get("/3/deliveries?status=failed&page=2&page_size=10")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
+    #[tokio::test]
+    async fn deliveries_reject_page_overflow() {
+        let r = app(admin_db())
+            .oneshot(
+                Request::get(format!("/3/deliveries?page={}&page_size=10", u64::MAX))
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "token")]),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, r.status());
+    }
+}
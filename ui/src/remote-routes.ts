@@ -8,8 +8,45 @@ export const ADD_USER = "/api/v1/user/add";
 export const DELETE_USER = (name: string) => `/api/v1/user/delete/${encodeURIComponent(name)}`;
 export const LIST_USERS = "/api/v1/user/list_users";
 export const RESET_PWD = (name: string) => `/api/v1/user/reset_pwd/${encodeURIComponent(name)}`;
+export const LOGOUT_USER = (name: string) => `/api/v1/user/logout_user/${encodeURIComponent(name)}`;
+export const LIST_SESSIONS = "/api/v1/user/list_sessions";
+export const DELETE_SESSION = (id: number) => `/api/v1/user/delete_session/${id}`;
+export const LIST_SERVICE_ACCOUNTS = "/api/v1/service_accounts";
+export const ADD_SERVICE_ACCOUNT = "/api/v1/service_accounts/add";
+export const DELETE_SERVICE_ACCOUNT = (name: string) =>
+  `/api/v1/service_accounts/delete/${encodeURIComponent(name)}`;
+export const SERVICE_ACCOUNT_TOKENS = (name: string) =>
+  `/api/v1/service_accounts/${encodeURIComponent(name)}/tokens`;
+export const DELETE_SERVICE_ACCOUNT_TOKEN = (name: string, id: number) =>
+  `/api/v1/service_accounts/${encodeURIComponent(name)}/tokens/${id}`;
+export const LIST_LOCKOUTS = "/api/v1/user/lockouts";
+export const CLEAR_LOCKOUT = (kind: string, key: string) =>
+  `/api/v1/user/lockouts/${kind}/${encodeURIComponent(key)}`;
+export const AUDIT_LOG = "/api/v1/audit";
+export const AUDIT_LOG_EXPORT = "/api/v1/audit/export";
+export const WEBHOOKS = "/api/v1/webhooks";
+export const DELETE_WEBHOOK = (id: number) => `/api/v1/webhooks/${id}`;
+export const WEBHOOK_DELIVERIES = (id: number) => `/api/v1/webhooks/${id}/deliveries`;
+export const RETRY_WEBHOOK_DELIVERY = (id: number) => `/api/v1/webhooks/deliveries/${id}/retry`;
+export const QUARANTINE_EXEMPTIONS = "/api/v1/quarantine/exemptions";
+export const DELETE_QUARANTINE_EXEMPTION = (name: string) => `/api/v1/quarantine/exemptions/${name}`;
+export const PROXY_POLICY_RULES = "/api/v1/proxy_policy/rules";
+export const DELETE_PROXY_POLICY_RULE = (id: number) => `/api/v1/proxy_policy/rules/${id}`;
+export const PROXY_POLICY_BLOCKED = "/api/v1/proxy_policy/blocked";
+export const CRATESIO_PINS = "/api/v1/cratesio_cache/pins";
+export const DELETE_CRATESIO_PIN = (name: string, version: string) => `/api/v1/cratesio_cache/pins/${name}/${version}`;
+export const CRATESIO_EVICTION_REPORTS = "/api/v1/cratesio_cache/reports";
+export const CRATESIO_EVICT = "/api/v1/cratesio_cache/evict";
 export const USER_READ_ONLY = (name: string) => `/api/v1/user/read_only/${encodeURIComponent(name)}`;
 export const LOGIN = "/api/v1/user/login";
+export const LOGIN_TOTP = "/api/v1/user/login/totp";
+export const TOTP_STATUS = "/api/v1/user/totp";
+export const TOTP_SETUP = "/api/v1/user/totp/setup";
+export const TOTP_ENABLE = "/api/v1/user/totp/enable";
+export const TOTP_DISABLE = "/api/v1/user/totp/disable";
+export const TOTP_RESET = (name: string) => `/api/v1/user/totp/reset/${encodeURIComponent(name)}`;
+export const OIDC_INFO = "/api/v1/user/oidc";
+export const OIDC_LOGIN = "/api/v1/user/oidc/login";
 
 export const ADD_GROUP = "/api/v1/group/add";
 export const DELETE_GROUP = (name: string) => `/api/v1/group/delete/${encodeURIComponent(name)}`;
@@ -5,4 +5,5 @@ export type OwnerList = {
 export type Owner = {
     name?: string
     login: string
+    kind?: "user" | "service_account"
 }
\ No newline at end of file
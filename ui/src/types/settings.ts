@@ -3,10 +3,20 @@ export type Settings = {
     local: Local
     log: Log
     origin: Origin
+    policy: Policy
     postgresql: Postgresql
     proxy: Proxy
     registry: Registry
     s3: S3
+    trusted_publishing: TrustedPublishing
+    tokens: Tokens
+    password_hashing: PasswordHashing
+    oidc: Oidc
+    ldap: Ldap
+    header_auth: HeaderAuth
+    totp: Totp
+    rate_limit: RateLimit
+    webhooks: Webhooks
 }
 
 export type Docs = {
@@ -31,6 +41,11 @@ export type Origin = {
     protocol: string
 }
 
+export type Policy = {
+    allowed_licenses: string[]
+    denied_licenses: string[]
+}
+
 export type Postgresql = {
     enabled: boolean
     address: string
@@ -41,7 +56,14 @@ export type Postgresql = {
 
 export type Proxy = {
     enabled: boolean
+    offline: boolean
     num_threads: number
+    index_url: string
+    download_url: string
+    api_url: string
+    quarantine_days: number
+    max_cache_size: number
+    max_cache_age_days: number
 }
 
 export type Registry = {
@@ -65,6 +87,84 @@ export type S3 = {
     cratesio_bucket: string
 }
 
+export type TrustedPublishing = {
+    enabled: boolean
+    token_ttl_seconds: number
+}
+
+export type Tokens = {
+    usage_update_interval_seconds: number
+    max_unused_days: number
+    delete_unused: boolean
+}
+
+export type PasswordHashing = {
+    memory_kib: number
+    iterations: number
+    parallelism: number
+}
+
+export type Oidc = {
+    enabled: boolean
+    issuer_url: string
+    client_id: string
+    redirect_url: string
+    scopes: string[]
+    username_claim: string
+    groups_claim: string
+    admin_groups: string[]
+    read_only_groups: string[]
+    sync_groups: boolean
+}
+
+export type Ldap = {
+    enabled: boolean
+    url: string
+    start_tls: boolean
+    user_dn_template: string
+    bind_dn: string
+    search_base: string
+    search_filter: string
+    group_attribute: string
+    admin_groups: string[]
+    read_only_groups: string[]
+    sync_groups: boolean
+}
+
+export type HeaderAuth = {
+    enabled: boolean
+    trusted_proxies: string[]
+    user_header: string
+    groups_header: string
+    admin_groups: string[]
+    read_only_groups: string[]
+    sync_groups: boolean
+}
+
+export type Totp = {
+    required_for_admins: boolean
+    issuer: string
+}
+
+export type RateLimit = {
+    enabled: boolean
+    max_failed_logins: number
+    lockout_threshold: number
+    lockout_minutes: number
+    publish_per_minute: number
+    download_per_minute: number
+    trusted_proxies: string[]
+    client_ip_header: string
+}
+
+export type Webhooks = {
+    max_attempts: number
+    initial_retry_seconds: number
+    max_retry_seconds: number
+    timeout_seconds: number
+    poll_interval_seconds: number
+}
+
 export const emptySettings: Settings = {
     docs: {
         enabled: true,
@@ -84,6 +184,10 @@ export const emptySettings: Settings = {
         port: 0,
         protocol: "0"
     },
+    policy: {
+        allowed_licenses: [],
+        denied_licenses: []
+    },
     postgresql: {
         enabled: false,
         address: "",
@@ -93,7 +197,14 @@ export const emptySettings: Settings = {
     },
     proxy: {
         enabled: false,
-        num_threads: 0
+        offline: false,
+        num_threads: 0,
+        index_url: "",
+        download_url: "",
+        api_url: "",
+        quarantine_days: 0,
+        max_cache_size: 0,
+        max_cache_age_days: 0
     },
     registry: {
         data_dir: "",
@@ -113,5 +224,74 @@ export const emptySettings: Settings = {
         allow_http: false,
         crates_bucket: "",
         cratesio_bucket: ""
+    },
+    trusted_publishing: {
+        enabled: false,
+        token_ttl_seconds: 0
+    },
+    tokens: {
+        usage_update_interval_seconds: 0,
+        max_unused_days: 0,
+        delete_unused: false
+    },
+    password_hashing: {
+        memory_kib: 0,
+        iterations: 0,
+        parallelism: 0
+    },
+    oidc: {
+        enabled: false,
+        issuer_url: "",
+        client_id: "",
+        redirect_url: "",
+        scopes: [],
+        username_claim: "",
+        groups_claim: "",
+        admin_groups: [],
+        read_only_groups: [],
+        sync_groups: false
+    },
+    ldap: {
+        enabled: false,
+        url: "",
+        start_tls: false,
+        user_dn_template: "",
+        bind_dn: "",
+        search_base: "",
+        search_filter: "",
+        group_attribute: "",
+        admin_groups: [],
+        read_only_groups: [],
+        sync_groups: false
+    },
+    header_auth: {
+        enabled: false,
+        trusted_proxies: [],
+        user_header: "",
+        groups_header: "",
+        admin_groups: [],
+        read_only_groups: [],
+        sync_groups: false
+    },
+    totp: {
+        required_for_admins: false,
+        issuer: ""
+    },
+    rate_limit: {
+        enabled: false,
+        max_failed_logins: 0,
+        lockout_threshold: 0,
+        lockout_minutes: 0,
+        publish_per_minute: 0,
+        download_per_minute: 0,
+        trusted_proxies: [],
+        client_ip_header: ""
+    },
+    webhooks: {
+        max_attempts: 0,
+        initial_retry_seconds: 0,
+        max_retry_seconds: 0,
+        timeout_seconds: 0,
+        poll_interval_seconds: 0
     }
 }
@@ -12,4 +12,5 @@ export type Statistics = {
     },
     last_updated_crate: [string, string], 
     proxy_enabled: boolean,
+    proxy_offline: boolean,
 }